@@ -0,0 +1,491 @@
+//! The two blacklists in the Mods directory, which are easily confused.
+//!
+//! - `blacklist.txt` is Everest's: mods listed there are installed but not loaded, i.e. disabled.
+//! - `updaterblacklist.txt` is the updater's: mods listed there are loaded but never updated.
+//!
+//! Neither implies the other, e.g. a disabled mod is still updated unless `--skip-disabled`.
+use std::{
+    collections::HashSet,
+    fmt, fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use tempfile::NamedTempFile;
+use tracing::instrument;
+
+use crate::{log::anonymize, utils};
+
+/// File name of the updater's blacklist in the Mods directory.
+pub const UPDATER_BLACKLIST_FILE: &str = "updaterblacklist.txt";
+
+/// File name of Everest's blacklist in the Mods directory, mods listed there are not loaded.
+pub const EVEREST_BLACKLIST_FILE: &str = "blacklist.txt";
+
+/// Returns the file names listed in Everest's blacklist, i.e. the disabled mods.
+pub fn read_everest_blacklist(mods_dir: &Path) -> io::Result<HashSet<String>> {
+    let content = read_or_empty(&mods_dir.join(EVEREST_BLACKLIST_FILE))?;
+    Ok(everest_entries(&content).map(String::from).collect())
+}
+
+/// Removes the lines of the given file names from Everest's blacklist, keeping everything else.
+pub fn remove_from_everest_blacklist(mods_dir: &Path, names: &HashSet<String>) -> io::Result<()> {
+    remove_lines(&mods_dir.join(EVEREST_BLACKLIST_FILE), names).map(|_| ())
+}
+
+/// Appends the file names to Everest's blacklist, so the game does not load them.
+pub fn append_to_everest_blacklist(mods_dir: &Path, names: &[String]) -> io::Result<()> {
+    append_lines(&mods_dir.join(EVEREST_BLACKLIST_FILE), names, "").map(|_| ())
+}
+
+/// Header written by Everest when it creates the updater's blacklist.
+const UPDATER_BLACKLIST_HEADER: &str = "# This is the Updater Blacklist. Lines starting with # are ignored.
+# If you put the name of a mod zip in this file, it won't be auto-updated and it won't show update notifications on the title screen.
+";
+
+/// Appends the file names to the updater's blacklist, and returns the ones not listed before.
+///
+/// The file is created with the header of Everest if it does not exist.
+pub fn append_to_updater_blacklist(mods_dir: &Path, names: &[String]) -> io::Result<Vec<String>> {
+    append_lines(
+        &mods_dir.join(UPDATER_BLACKLIST_FILE),
+        names,
+        UPDATER_BLACKLIST_HEADER,
+    )
+}
+
+/// Removes the lines of the given file names from the updater's blacklist, keeping comments and
+/// the other lines. Returns `false` if none of them was listed.
+pub fn remove_from_updater_blacklist(mods_dir: &Path, names: &HashSet<String>) -> io::Result<bool> {
+    remove_lines(&mods_dir.join(UPDATER_BLACKLIST_FILE), names)
+}
+
+fn read_or_empty(path: &Path) -> io::Result<String> {
+    match fs::read_to_string(path) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+        result => result,
+    }
+}
+
+fn remove_lines(path: &Path, names: &HashSet<String>) -> io::Result<bool> {
+    let content = read_or_empty(path)?;
+    let kept: String = content
+        .split_inclusive('\n')
+        .filter(|line| !names.contains(line.trim()))
+        .collect();
+    let changed = kept != content;
+    if changed {
+        write_atomically(path, &kept)?;
+    }
+    Ok(changed)
+}
+
+fn append_lines(path: &Path, names: &[String], header: &str) -> io::Result<Vec<String>> {
+    let mut content = read_or_empty(path)?;
+    if content.is_empty() {
+        content.push_str(header);
+    }
+    let mut listed: HashSet<String> = everest_entries(&content).map(String::from).collect();
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    let mut added = Vec::new();
+    for name in names {
+        if listed.insert(name.clone()) {
+            content.push_str(name);
+            content.push('\n');
+            added.push(name.clone());
+        }
+    }
+    if !added.is_empty() {
+        write_atomically(path, &content)?;
+    }
+    Ok(added)
+}
+
+/// Replaces the file with a temporary one, so the game never reads a half-written list.
+///
+/// Permissions of the old file are kept, since the temporary file is only readable by the user.
+fn write_atomically(path: &Path, content: &str) -> io::Result<()> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let mut tmp = NamedTempFile::new_in(dir)?;
+    tmp.write_all(content.as_bytes())?;
+    tmp.as_file().sync_all()?;
+    if let Ok(old) = fs::metadata(path) {
+        fs::set_permissions(tmp.path(), old.permissions())?;
+    }
+    tmp.persist(path).map_err(|e| e.error)?;
+    Ok(())
+}
+
+fn everest_entries(content: &str) -> impl Iterator<Item = &str> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+}
+
+#[instrument(skip_all)]
+pub fn fetch(source: &impl UpdaterBlacklistSource) -> io::Result<UpdaterBlacklist> {
+    let content = source.fetch_content()?;
+    let blacklist: UpdaterBlacklist = content
+        .parse()
+        .expect("should be parsed since this is an infallible operation");
+    Ok(blacklist)
+}
+
+/// Represents `updaterblacklist.txt` which is used to ignore specific mods from updates.
+#[derive(Debug, Clone, Default)]
+pub struct UpdaterBlacklist {
+    /// A list of unique mod filenames.
+    filenames: HashSet<String>,
+    /// Glob patterns and mod names, in the order of the lines.
+    patterns: Vec<PatternLine>,
+}
+
+impl UpdaterBlacklist {
+    pub fn filenames(&self) -> &HashSet<String> {
+        &self.filenames
+    }
+
+    pub fn patterns(&self) -> &[PatternLine] {
+        &self.patterns
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.filenames.is_empty() && self.patterns.is_empty()
+    }
+
+    /// Checks if the mod is listed by its file name, a glob pattern, or its name.
+    pub fn matches(&self, file_name: &str, mod_name: &str) -> bool {
+        self.filenames.contains(file_name)
+            || self.patterns.iter().any(|p| p.matches(file_name, mod_name))
+    }
+}
+
+/// Entry of the updater's blacklist other than a plain file name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pattern {
+    /// Glob pattern of the file names, e.g. `SpringCollab*.zip`.
+    Glob(String),
+    /// Name of the mod in `everest.yaml`, written as `name: SpeedrunTool`.
+    Name(String),
+}
+
+/// Pattern with its line number in the file, for the diagnostics.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternLine {
+    /// 1-based line number.
+    pub line: usize,
+    /// Trimmed text of the line, which identifies it on removal.
+    pub text: String,
+    pub pattern: Pattern,
+}
+
+impl PatternLine {
+    /// Matches the file name by the glob, or the mod name ignoring ASCII case.
+    pub fn matches(&self, file_name: &str, mod_name: &str) -> bool {
+        match &self.pattern {
+            Pattern::Glob(glob) => utils::glob_match(glob, file_name),
+            Pattern::Name(name) => name.eq_ignore_ascii_case(mod_name),
+        }
+    }
+}
+
+impl fmt::Display for PatternLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+/// Prefix of the lines listing a mod by its name instead of the file name.
+const NAME_PREFIX: &str = "name:";
+
+impl FromStr for UpdaterBlacklist {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut blacklist = UpdaterBlacklist::default();
+        for (i, line) in s.lines().enumerate() {
+            let line = line.trim();
+            // NOTE "SomeMod.zip" is an example entry, so we should ignore it
+            if line.is_empty() || line.starts_with('#') || line == "SomeMod.zip" {
+                continue;
+            }
+            let pattern = if let Some(name) = line.strip_prefix(NAME_PREFIX) {
+                Pattern::Name(name.trim().to_string())
+            } else if line.contains(['*', '?']) {
+                Pattern::Glob(line.to_string())
+            } else {
+                blacklist.filenames.insert(line.to_string());
+                continue;
+            };
+            blacklist.patterns.push(PatternLine {
+                line: i + 1,
+                text: line.to_string(),
+                pattern,
+            });
+        }
+        Ok(blacklist)
+    }
+}
+
+/// A source that provides content for the updater's blacklist.
+pub trait UpdaterBlacklistSource {
+    fn fetch_content(&self) -> io::Result<String>;
+}
+
+/// A blacklist source that reads from a local file.
+#[derive(Debug, Clone)]
+pub struct LocalUpdaterBlacklistSource {
+    /// A path to the updater's blacklist.
+    path: PathBuf,
+}
+
+impl LocalUpdaterBlacklistSource {
+    pub fn new(mods_dir: &Path) -> Self {
+        Self {
+            path: mods_dir.join(UPDATER_BLACKLIST_FILE),
+        }
+    }
+}
+
+impl UpdaterBlacklistSource for LocalUpdaterBlacklistSource {
+    #[instrument(skip_all, fields(path = %anonymize(&self.path)))]
+    fn fetch_content(&self) -> io::Result<String> {
+        let content = fs::read_to_string(&self.path).or_else(|e| {
+            if e.kind() == io::ErrorKind::NotFound {
+                Ok(String::new())
+            } else {
+                Err(e)
+            }
+        })?;
+        Ok(content)
+    }
+}
+
+#[cfg(test)]
+mod parse_tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_default_blacklist() {
+        let default = r#"
+# This is the Updater Blacklist. Lines starting with # are ignored.
+# If you put the name of a mod zip in this file, it won't be auto-updated and it won't show update notifications on the title screen.
+SomeMod.zip
+"#;
+
+        let blacklist: UpdaterBlacklist = default.parse().expect("should be parsed");
+        assert!(blacklist.filenames().is_empty())
+    }
+
+    #[test]
+    fn test_parse_blacklist_with_actual_names() {
+        let content = r#"
+SpeedrunTool.zip
+Another Farewell Map CC-Side.zip
+GravityHelper.zip
+"#;
+
+        let blacklist: UpdaterBlacklist = content.parse().expect("should be parsed");
+        assert_eq!(blacklist.filenames().len(), 3)
+    }
+
+    #[test]
+    fn test_parse_patterns() {
+        let content = "# pinned\nSpringCollab*.zip\nname: SpeedrunTool\nGravityHelper.zip\n";
+
+        let blacklist: UpdaterBlacklist = content.parse().expect("should be parsed");
+        assert_eq!(blacklist.filenames().len(), 1);
+        assert_eq!(
+            blacklist.patterns(),
+            [
+                PatternLine {
+                    line: 2,
+                    text: "SpringCollab*.zip".into(),
+                    pattern: Pattern::Glob("SpringCollab*.zip".into())
+                },
+                PatternLine {
+                    line: 3,
+                    text: "name: SpeedrunTool".into(),
+                    pattern: Pattern::Name("SpeedrunTool".into())
+                },
+            ]
+        );
+
+        assert!(blacklist.matches("SpringCollab2020.zip", "SpringCollab2020"));
+        assert!(blacklist.matches("other-name.zip", "speedruntool"));
+        assert!(blacklist.matches("GravityHelper.zip", "GravityHelper"));
+        assert!(!blacklist.matches("springcollab2020.zip", "Other"));
+    }
+}
+
+#[cfg(test)]
+mod everest_blacklist_tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_remove_from_everest_blacklist() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join(EVEREST_BLACKLIST_FILE);
+        fs::write(&path, "# Disabled mods\nFoo.zip\n# Bar.zip\nBaz.zip\n")?;
+        assert_eq!(
+            read_everest_blacklist(dir.path())?,
+            HashSet::from(["Foo.zip".to_string(), "Baz.zip".to_string()])
+        );
+
+        remove_from_everest_blacklist(dir.path(), &HashSet::from(["Foo.zip".to_string()]))?;
+        assert_eq!(
+            fs::read_to_string(&path)?,
+            "# Disabled mods\n# Bar.zip\nBaz.zip\n"
+        );
+
+        append_to_everest_blacklist(dir.path(), &["Baz.zip".into(), "Qux.zip".into()])?;
+        assert_eq!(
+            fs::read_to_string(&path)?,
+            "# Disabled mods\n# Bar.zip\nBaz.zip\nQux.zip\n"
+        );
+
+        fs::remove_file(&path)?;
+        assert!(read_everest_blacklist(dir.path())?.is_empty());
+        assert!(remove_from_everest_blacklist(dir.path(), &HashSet::new()).is_ok());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod updater_blacklist_tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_edit_updater_blacklist() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join(UPDATER_BLACKLIST_FILE);
+
+        // created with the header
+        let added = append_to_updater_blacklist(dir.path(), &["Foo.zip".into()])?;
+        assert_eq!(added, ["Foo.zip"]);
+        let content = fs::read_to_string(&path)?;
+        assert!(content.starts_with(UPDATER_BLACKLIST_HEADER));
+        assert!(content.ends_with("\nFoo.zip\n"));
+
+        // comments and unknown lines are kept
+        fs::write(&path, "# pinned\nFoo.zip\n  some note\nBar.zip")?;
+        let added = append_to_updater_blacklist(dir.path(), &["Bar.zip".into(), "Baz.zip".into()])?;
+        assert_eq!(added, ["Baz.zip"]);
+        assert_eq!(
+            fs::read_to_string(&path)?,
+            "# pinned\nFoo.zip\n  some note\nBar.zip\nBaz.zip\n"
+        );
+
+        let names = HashSet::from(["Foo.zip".to_string(), "Baz.zip".to_string()]);
+        assert!(remove_from_updater_blacklist(dir.path(), &names)?);
+        assert_eq!(
+            fs::read_to_string(&path)?,
+            "# pinned\n  some note\nBar.zip\n"
+        );
+        assert!(!remove_from_updater_blacklist(dir.path(), &names)?);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod fetch_tests {
+    use super::*;
+
+    /// Mock source for controlled input
+    struct MockSource {
+        content: Option<String>,
+        error: Option<io::ErrorKind>,
+    }
+
+    impl UpdaterBlacklistSource for MockSource {
+        fn fetch_content(&self) -> io::Result<String> {
+            if let Some(kind) = self.error {
+                Err(io::Error::new(kind, "mock error"))
+            } else {
+                Ok(self.content.clone().unwrap_or_default())
+            }
+        }
+    }
+
+    #[test]
+    fn test_fetch_empty_content() {
+        // Empty content should result in empty blacklist
+        let source = MockSource {
+            content: Some(String::new()),
+            error: None,
+        };
+
+        let blacklist = fetch(&source).expect("fetch should succeed");
+
+        assert!(blacklist.filenames().is_empty());
+    }
+
+    #[test]
+    fn test_fetch_valid_content() {
+        // Valid blacklist entries
+        let source = MockSource {
+            content: Some(
+                r#"
+SpeedrunTool.zip
+Another Farewell Map CC-Side.zip
+GravityHelper.zip
+"#
+                .to_string(),
+            ),
+            error: None,
+        };
+
+        let blacklist = fetch(&source).expect("fetch should succeed");
+
+        assert_eq!(blacklist.filenames().len(), 3);
+        assert!(blacklist.filenames().contains("SpeedrunTool.zip"));
+        assert!(
+            blacklist
+                .filenames()
+                .contains("Another Farewell Map CC-Side.zip")
+        );
+        assert!(blacklist.filenames().contains("GravityHelper.zip"));
+    }
+
+    #[test]
+    fn test_fetch_ignores_comments_and_default_entry() {
+        // Should ignore comments and "SomeMod.zip"
+        let source = MockSource {
+            content: Some(
+                r#"
+# comment
+SomeMod.zip
+ValidMod.zip
+"#
+                .to_string(),
+            ),
+            error: None,
+        };
+
+        let blacklist = fetch(&source).expect("fetch should succeed");
+
+        assert_eq!(blacklist.filenames().len(), 1);
+        assert!(blacklist.filenames().contains("ValidMod.zip"));
+    }
+
+    #[test]
+    fn test_fetch_propagates_error() {
+        // Simulate IO error
+        let source = MockSource {
+            content: None,
+            error: Some(io::ErrorKind::Other),
+        };
+
+        let result = fetch(&source);
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,1001 @@
+//! Checksums and manifests of the installed archives, recomputed only when the files are modified.
+//!
+//! The database is serialized with rkyv behind a header of the magic bytes, the format version,
+//! and the checksum of the payload. A cache of another version is rebuilt, so a truncated or
+//! outdated file is never misread.
+//!
+//! Writers hold an advisory lock of `{cache}.lock`, and replace the file by rename, so readers
+//! never see a partial write. When another process holds the lock, the cache is used read-only.
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs::{self, File, TryLockError},
+    io::{self, Read, Seek, SeekFrom, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use rayon::prelude::*;
+use rkyv::{
+    Archive, Deserialize, Serialize,
+    api::high::HighValidator,
+    bytecheck::CheckBytes,
+    de::Pool,
+    rancor::{self, Strategy},
+    util::AlignedVec,
+};
+use tempfile::NamedTempFile;
+use tracing::{Span, debug, instrument, warn};
+use xxhash_rust::{
+    xxh3::xxh3_64,
+    xxh64::{Xxh64, xxh64},
+};
+
+use crate::{
+    Checksums,
+    config::ModsConfig,
+    dependency::Dependency,
+    local::{DeclaredMod, ExcludePatterns, ModType, is_mod_archive},
+    log::anonymize,
+    progress::{self, Task},
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum CacheError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Archive(#[from] rancor::Error),
+    #[error("cache format version {0} is not supported")]
+    UnsupportedVersion(u32),
+    #[error("checksum of the cache does not match, the file may be truncated")]
+    Corrupted,
+    #[error("cache is locked by another hultra process")]
+    Locked,
+}
+
+/// Magic bytes at the beginning of the cache file.
+const MAGIC: [u8; 8] = *b"HULTRAHC";
+
+/// Version of the cache format, bumped whenever the archived structs change.
+///
+/// Version 1 had no header, and was keyed by inode. Version 2 had no manifests.
+/// Version 3 recorded the mtime only in seconds. Version 4 had no types of the mods.
+/// Version 5 had only the first mod of the manifests. Version 6 had the unused XXH3 of the
+/// whole archives.
+const VERSION: u32 = 7;
+
+/// Length of each byte range hashed by [`sample_file`].
+const SAMPLE_LEN: u64 = 4096;
+
+/// Length of the header: magic bytes, version, reserved, and XXH64 of the payload.
+const HEADER_LEN: usize = MAGIC.len() + 4 + 4 + 8;
+
+/// How long to wait for another process to release the lock of the cache.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Represents database of file cache, keyed by [`cache_key`].
+#[derive(Archive, Deserialize, Serialize, Debug, Default)]
+pub struct FileCacheDb {
+    entries: BTreeMap<String, CacheEntry>,
+    /// Manifests are cached separately, since hashing is only needed to check updates.
+    manifests: BTreeMap<String, ManifestEntry>,
+}
+
+impl FileCacheDb {
+    /// Returns the cached manifest if the archive is unchanged since it was read.
+    pub fn manifest(&self, key: &str, snapshot: &FileSnapshot) -> Option<&CachedManifest> {
+        self.manifests
+            .get(key)
+            .filter(|entry| entry.snapshot == *snapshot)
+            .map(|entry| &entry.manifest)
+    }
+
+    /// Returns the names of the mods in the cached manifests, i.e. installed at the last scan.
+    pub fn mod_names(&self) -> impl Iterator<Item = &str> {
+        self.manifests.values().flat_map(|entry| {
+            let manifest = &entry.manifest;
+            std::iter::once(manifest.name.as_str()).chain(manifest.others.iter().map(|m| m.name()))
+        })
+    }
+
+    /// Checks if any manifest is of an archive not in `keys`, i.e. no longer installed.
+    pub fn has_stale_manifests(&self, keys: &HashSet<String>) -> bool {
+        self.manifests.keys().any(|key| !keys.contains(key))
+    }
+
+    /// Records the manifests read from the archives, and removes the ones not in `keys`.
+    ///
+    /// Returns `true` if anything is changed.
+    fn update_manifests(
+        &mut self,
+        read: Vec<(String, FileSnapshot, CachedManifest)>,
+        keys: &HashSet<String>,
+    ) -> bool {
+        let mut updated = !read.is_empty();
+        for (key, snapshot, manifest) in read {
+            let entry = ManifestEntry { snapshot, manifest };
+            self.manifests.insert(key, entry);
+        }
+        let count = self.manifests.len();
+        self.manifests.retain(|key, _| keys.contains(key));
+        updated |= self.manifests.len() != count;
+        updated
+    }
+
+    /// Returns the number of the cached archives.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns XXH64 of the archive at the last sync.
+    pub fn hash(&self, key: &str) -> Option<u64> {
+        self.entries.get(key).map(|entry| entry.hash)
+    }
+
+    /// Checks if the given key is exist and the value contains given value.
+    pub fn is_cache_valid(&self, key: &str, checksums: &Checksums) -> bool {
+        self.entries
+            .get(key)
+            .map(|entry| checksums.contains(entry.hash()))
+            .unwrap_or(false)
+    }
+
+    /// Checks if cache entry exists and is still valid.
+    ///
+    /// ### Returns
+    /// * `true`: It means no cache (new record), or contents are modified.
+    /// * `false`: It means the entry is still valid, no need to rehash them.
+    pub fn should_rehash(&self, key: &str, snapshot: &FileSnapshot) -> bool {
+        self.entries
+            .get(key)
+            .map(|entry| !entry.is_unchanged(snapshot))
+            .unwrap_or(true)
+    }
+}
+
+/// Metadata of the file to tell if it is modified without reading it.
+#[derive(Archive, Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileSnapshot {
+    /// Modification time in UNIX time.
+    mtime: i64,
+    mtime_nsec: u32,
+    /// Status change time in UNIX time, zero where it is not available.
+    ///
+    /// NOTE updated on every write even if the mtime is restored, e.g. by archivers
+    ctime: i64,
+    ctime_nsec: u32,
+    size: u64,
+}
+
+impl FileSnapshot {
+    pub fn new(meta: &fs::Metadata) -> io::Result<Self> {
+        let (mtime, mtime_nsec) = unix_time(meta.modified()?);
+        #[cfg(unix)]
+        let (ctime, ctime_nsec) = {
+            use std::os::unix::fs::MetadataExt;
+            (
+                meta.ctime(),
+                u32::try_from(meta.ctime_nsec()).unwrap_or_default(),
+            )
+        };
+        #[cfg(not(unix))]
+        let (ctime, ctime_nsec) = (0, 0);
+        Ok(Self {
+            mtime,
+            mtime_nsec,
+            ctime,
+            ctime_nsec,
+            size: meta.len(),
+        })
+    }
+}
+
+/// Parsed `everest.yaml` of the archive.
+#[derive(Archive, Deserialize, Serialize, Debug, Clone)]
+pub struct CachedManifest {
+    name: String,
+    version: String,
+    dependencies: Vec<Dependency>,
+    /// Told from the entries, so the archive is not opened again.
+    mod_type: ModType,
+    /// The other mods declared by the manifest.
+    others: Vec<DeclaredMod>,
+}
+
+impl CachedManifest {
+    pub fn new(
+        name: String,
+        version: String,
+        dependencies: Vec<Dependency>,
+        mod_type: ModType,
+        others: Vec<DeclaredMod>,
+    ) -> Self {
+        Self {
+            name,
+            version,
+            dependencies,
+            mod_type,
+            others,
+        }
+    }
+
+    pub fn into_parts(self) -> (String, String, Vec<Dependency>, ModType, Vec<DeclaredMod>) {
+        (
+            self.name,
+            self.version,
+            self.dependencies,
+            self.mod_type,
+            self.others,
+        )
+    }
+}
+
+/// Manifest with the snapshot of the file when it was read.
+#[derive(Archive, Deserialize, Serialize, Debug)]
+struct ManifestEntry {
+    snapshot: FileSnapshot,
+    manifest: CachedManifest,
+}
+
+/// Digests with the snapshot of the file when it was last hashed.
+#[derive(Archive, Deserialize, Serialize, Debug)]
+pub struct CacheEntry {
+    snapshot: FileSnapshot,
+    hash: u64,   // XXH64, compared with the registry
+    sample: u64, // XXH3 of the sampled ranges, see `sample_file`
+}
+
+impl CacheEntry {
+    pub fn new(snapshot: FileSnapshot, digests: FileDigests) -> Self {
+        Self {
+            snapshot,
+            hash: digests.xxh64,
+            sample: digests.sample,
+        }
+    }
+
+    pub fn hash(&self) -> &u64 {
+        &self.hash
+    }
+}
+
+impl CacheEntry {
+    /// Checks if the metadata is unchanged.
+    pub fn is_unchanged(&self, snapshot: &FileSnapshot) -> bool {
+        self.snapshot == *snapshot
+    }
+}
+
+/// Summary of the cache compared with the installed archives.
+#[derive(Debug)]
+pub struct CacheStatus {
+    /// Number of the cached archives.
+    pub entries: usize,
+    /// Archives to be hashed on the next run, i.e. new or modified ones.
+    pub pending: usize,
+    /// Entries of the archives which are no longer installed.
+    pub stale: usize,
+}
+
+/// Compares the cache with the installed archives, without hashing nor saving anything.
+pub fn status(config: &impl ModsConfig) -> Result<CacheStatus, CacheError> {
+    let cache = load_cache_db(config.cache_db_path())?;
+    let (current_keys, pending) = scan(&cache, &config.mods_dir(), config.exclude(), false)?;
+    let stale = cache
+        .entries
+        .keys()
+        .filter(|key| !current_keys.contains(*key))
+        .count();
+    Ok(CacheStatus {
+        entries: cache.len(),
+        pending: pending.len(),
+        stale,
+    })
+}
+
+/// Removes the cache file, returns `false` if it does not exist.
+pub fn clear(config: &impl ModsConfig) -> Result<bool, CacheError> {
+    let _lock = CacheLock::acquire(config.cache_db_path()).ok_or(CacheError::Locked)?;
+    match fs::remove_file(config.cache_db_path()) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Advisory lock of the cache database, released when dropped.
+///
+/// NOTE a separate file is locked, since the database itself is replaced by rename
+#[derive(Debug)]
+struct CacheLock {
+    _file: File,
+}
+
+impl CacheLock {
+    /// Waits for the lock up to [`LOCK_TIMEOUT`], returns `None` if it cannot be acquired.
+    fn acquire(cache_path: &Path) -> Option<Self> {
+        match Self::try_acquire(cache_path, LOCK_TIMEOUT) {
+            Ok(Some(lock)) => Some(lock),
+            Ok(None) => {
+                warn!("another hultra process is using the checksum cache");
+                None
+            }
+            Err(e) => {
+                warn!(?e, "failed to lock the checksum cache");
+                None
+            }
+        }
+    }
+
+    fn try_acquire(cache_path: &Path, timeout: Duration) -> io::Result<Option<Self>> {
+        let mut lock_path = cache_path.as_os_str().to_owned();
+        lock_path.push(".lock");
+        if let Some(parent) = cache_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(lock_path)?;
+
+        let started = Instant::now();
+        loop {
+            match file.try_lock() {
+                Ok(()) => return Ok(Some(Self { _file: file })),
+                Err(TryLockError::WouldBlock) if started.elapsed() < timeout => {
+                    thread::sleep(Duration::from_millis(100));
+                }
+                Err(TryLockError::WouldBlock) => return Ok(None),
+                Err(TryLockError::Error(e)) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Returns the key of the archive in the cache, i.e. the file name in the Mods directory.
+///
+/// NOTE inodes are not available on every platform, and a renamed archive is just rehashed
+pub fn cache_key(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_else(|| path.to_string_lossy())
+        .into_owned()
+}
+
+/// Returns the modification time in UNIX time (seconds), negative if before the epoch.
+pub fn unix_mtime(meta: &fs::Metadata) -> io::Result<i64> {
+    Ok(unix_time(meta.modified()?).0)
+}
+
+/// Returns seconds and nanoseconds since the epoch, the seconds are negative if before it.
+fn unix_time(time: SystemTime) -> (i64, u32) {
+    let (duration, sign) = match time.duration_since(UNIX_EPOCH) {
+        Ok(elapsed) => (elapsed, 1),
+        Err(e) => (e.duration(), -1),
+    };
+    let secs = i64::try_from(duration.as_secs()).unwrap_or(i64::MAX);
+    (sign * secs, duration.subsec_nanos())
+}
+
+/// Gets up-to-date file cache.
+///
+/// * `paranoid`: also compares the sampled ranges of the archives whose metadata is unchanged.
+#[instrument(skip(config), fields(path = %anonymize(config.cache_db_path())))]
+pub fn sync(config: &impl ModsConfig, paranoid: bool) -> Result<FileCacheDb, CacheError> {
+    // NOTE loaded after locking, so the entries saved by another process are not discarded
+    let lock = CacheLock::acquire(config.cache_db_path());
+    let (mut cache, outdated) = load_or_default(config.cache_db_path());
+
+    if update_cache(&mut cache, &config.mods_dir(), config.exclude(), paranoid)? || outdated {
+        match lock {
+            Some(_) => save_cache_db(&cache, config.cache_db_path())?,
+            None => warn!("the checksum cache is not saved, they are hashed again on the next run"),
+        }
+    }
+
+    Ok(cache)
+}
+
+/// Loads the cache database, or an empty one if it cannot be read.
+pub fn load(config: &impl ModsConfig) -> FileCacheDb {
+    load_or_default(config.cache_db_path()).0
+}
+
+/// Records the manifests read from the archives, and removes the ones not in `keys`.
+///
+/// The database is loaded again under the lock, so the entries saved by another process since
+/// it was loaded are kept. Nothing is saved if the lock cannot be acquired.
+pub fn save_manifests(
+    config: &impl ModsConfig,
+    read: Vec<(String, FileSnapshot, CachedManifest)>,
+    keys: &HashSet<String>,
+) -> Result<(), CacheError> {
+    let Some(_lock) = CacheLock::acquire(config.cache_db_path()) else {
+        return Ok(());
+    };
+    let (mut cache, outdated) = load_or_default(config.cache_db_path());
+    if cache.update_manifests(read, keys) || outdated {
+        save_cache_db(&cache, config.cache_db_path())?;
+    }
+    Ok(())
+}
+
+/// Loads the cache database, returns `true` with it if it should be saved even if unchanged,
+/// i.e. rebuilt.
+fn load_or_default(cache_path: &Path) -> (FileCacheDb, bool) {
+    match load_cache_db(cache_path) {
+        Ok(cache) => (cache, false),
+        Err(CacheError::Io(e)) if e.kind() == io::ErrorKind::NotFound => Default::default(),
+        Err(e) => {
+            warn!(?e, "rebuilding the checksum cache");
+            (FileCacheDb::default(), true)
+        }
+    }
+}
+
+/// Discards the cache and hashes all of the archives again.
+#[instrument(skip(config), fields(path = %anonymize(config.cache_db_path())))]
+pub fn rebuild(config: &impl ModsConfig) -> Result<FileCacheDb, CacheError> {
+    let _lock = CacheLock::acquire(config.cache_db_path()).ok_or(CacheError::Locked)?;
+    let mut cache = FileCacheDb::default();
+    update_cache(&mut cache, &config.mods_dir(), config.exclude(), false)?;
+    save_cache_db(&cache, config.cache_db_path())?;
+    Ok(cache)
+}
+
+/// Replaces the cache database with given entries, keyed by [`cache_key`].
+///
+/// Used after the Mods directory is migrated, since the mtime of the copied files may differ.
+pub fn replace(
+    cache_path: &Path,
+    entries: impl IntoIterator<Item = (String, CacheEntry)>,
+) -> Result<(), CacheError> {
+    let cache = FileCacheDb {
+        entries: entries.into_iter().collect(),
+        ..Default::default()
+    };
+    let _lock = CacheLock::acquire(cache_path).ok_or(CacheError::Locked)?;
+    save_cache_db(&cache, cache_path)
+}
+
+/// File to be hashed, with the metadata at the time of scanning.
+struct PendingFile {
+    key: String,
+    path: PathBuf,
+    snapshot: FileSnapshot,
+}
+
+/// Lists the keys of the installed archives, and the ones to be hashed since they are new or
+/// modified.
+///
+/// * `paranoid`: the archives whose sampled ranges differ are also hashed, e.g. rewritten with
+///   the same size by a tool which restores the mtime.
+fn scan(
+    cache: &FileCacheDb,
+    mods_dir: &Path,
+    exclude: &ExcludePatterns,
+    paranoid: bool,
+) -> io::Result<(HashSet<String>, Vec<PendingFile>)> {
+    let mut current_keys = HashSet::new();
+    let mut pending = Vec::new();
+
+    for entry in (mods_dir.read_dir()?).flatten() {
+        // Skip anything that isn't a regular file *or* isn't a `.zip`, and excluded ones
+        if !is_mod_archive(&entry, exclude) {
+            continue;
+        }
+
+        // Get file metadata
+        let Ok(snapshot) = entry.metadata().and_then(|meta| FileSnapshot::new(&meta)) else {
+            continue;
+        };
+        let path = entry.path();
+        let key = cache_key(&path);
+        let modified = match cache.entries.get(&key) {
+            Some(cached) if cached.is_unchanged(&snapshot) => {
+                paranoid && sample_file(&path).ok() != Some(cached.sample)
+            }
+            _ => true,
+        };
+        if modified {
+            pending.push(PendingFile {
+                key: key.clone(),
+                path,
+                snapshot,
+            });
+        }
+        current_keys.insert(key);
+    }
+    Ok((current_keys, pending))
+}
+
+/// Updates cache entries based on current filesystem state.
+///
+/// The directory is scanned first, then the new or modified files are hashed in parallel,
+/// since hashing hundreds of archives one by one takes most of the time on a cold cache.
+#[instrument(level = "debug", skip_all, fields(mods_dir = %anonymize(mods_dir), pending))]
+fn update_cache(
+    cache: &mut FileCacheDb,
+    mods_dir: &Path,
+    exclude: &ExcludePatterns,
+    paranoid: bool,
+) -> io::Result<bool> {
+    let (current_keys, pending) = scan(cache, mods_dir, exclude, paranoid)?;
+    Span::current().record("pending", pending.len());
+
+    let mut updated = !pending.is_empty();
+    if updated {
+        let pb = progress::start(Task::Hashing, pending.len() as u64);
+        let hashed: io::Result<Vec<_>> = pending
+            .into_par_iter()
+            .map(|file| {
+                let digests = hash_file(&file.path)?;
+                pb.inc(1);
+                Ok((file, digests))
+            })
+            .collect();
+        pb.finish();
+
+        for (file, digests) in hashed? {
+            // Create new cache entry
+            let cache_entry = CacheEntry::new(file.snapshot, digests);
+            debug!(key = file.key, ?cache_entry, "new entry created");
+            cache.entries.insert(file.key, cache_entry);
+        }
+    }
+
+    // Remove stale cache entries (files that no longer exist)
+    let stale_count = cache.entries.len() + cache.manifests.len();
+    cache.entries.retain(|key, _| current_keys.contains(key));
+    cache.manifests.retain(|key, _| current_keys.contains(key));
+    updated |= cache.entries.len() + cache.manifests.len() != stale_count;
+
+    Ok(updated)
+}
+
+/// Loads cache database from disk using rkyv.
+fn load_cache_db(cache_path: &Path) -> Result<FileCacheDb, CacheError> {
+    let bytes = fs::read(cache_path)?;
+    decode(&bytes)
+}
+
+fn decode(bytes: &[u8]) -> Result<FileCacheDb, CacheError> {
+    if bytes.len() < HEADER_LEN || bytes[..MAGIC.len()] != MAGIC {
+        // NOTE the version 1 had no header
+        return Err(CacheError::UnsupportedVersion(1));
+    }
+
+    let (header, payload) = bytes.split_at(HEADER_LEN);
+    let mut version = [0; 4];
+    version.copy_from_slice(&header[8..12]);
+    let version = u32::from_le_bytes(version);
+    if version != VERSION {
+        return Err(CacheError::UnsupportedVersion(version));
+    }
+    let mut checksum = [0; 8];
+    checksum.copy_from_slice(&header[16..24]);
+    if u64::from_le_bytes(checksum) != xxh64(payload, 0) {
+        return Err(CacheError::Corrupted);
+    }
+
+    from_archived_bytes(payload)
+}
+
+/// Reads the value archived with rkyv from the file, e.g. the records kept in the cache directory.
+pub fn read_archived<T>(path: &Path) -> Result<T, CacheError>
+where
+    T: Archive,
+    T::Archived: for<'a> CheckBytes<HighValidator<'a, rancor::Error>>
+        + Deserialize<T, Strategy<Pool, rancor::Error>>,
+{
+    from_archived_bytes(&fs::read(path)?)
+}
+
+/// Deserializes the archived value through an aligned copy of the bytes.
+///
+/// NOTE rkyv reads the archived data in place, and neither the `Vec` from `fs::read` nor the
+/// payload after the header of the database is aligned for it
+fn from_archived_bytes<T>(bytes: &[u8]) -> Result<T, CacheError>
+where
+    T: Archive,
+    T::Archived: for<'a> CheckBytes<HighValidator<'a, rancor::Error>>
+        + Deserialize<T, Strategy<Pool, rancor::Error>>,
+{
+    let mut aligned = AlignedVec::<16>::with_capacity(bytes.len());
+    aligned.extend_from_slice(bytes);
+    Ok(rkyv::from_bytes::<T, rancor::Error>(&aligned)?)
+}
+
+fn encode(cache: &FileCacheDb) -> Result<Vec<u8>, CacheError> {
+    let payload = rkyv::to_bytes::<rancor::Error>(cache)?;
+    Ok(frame(VERSION, &payload))
+}
+
+/// Prepends the header to the payload.
+fn frame(version: u32, payload: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(HEADER_LEN + payload.len());
+    bytes.extend_from_slice(&MAGIC);
+    bytes.extend_from_slice(&version.to_le_bytes());
+    bytes.extend_from_slice(&[0; 4]);
+    bytes.extend_from_slice(&xxh64(payload, 0).to_le_bytes());
+    bytes.extend_from_slice(payload);
+    bytes
+}
+
+/// Saves cache database to disk using rkyv.
+///
+/// NOTE written to a temporary file and renamed, since multiple commands may save it at once.
+/// The temporary file is readable only by the owner.
+fn save_cache_db(cache: &FileCacheDb, cache_path: &Path) -> Result<(), CacheError> {
+    let bytes = encode(cache)?;
+    let dir = cache_path.parent().unwrap_or(Path::new("."));
+    let mut temp = NamedTempFile::new_in(dir)?;
+    temp.write_all(&bytes)?;
+    temp.as_file().sync_all()?;
+    temp.persist(cache_path).map_err(|e| e.error)?;
+    Ok(())
+}
+
+/// Digests of the file content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileDigests {
+    /// XXH64, the only algorithm the registry provides.
+    xxh64: u64,
+    /// XXH3 of the sampled ranges, see [`sample_file`]. XXH3 is much faster on modern CPUs, so
+    /// the local checks of unchanged archives use it.
+    sample: u64,
+}
+
+impl FileDigests {
+    pub fn xxh64(&self) -> u64 {
+        self.xxh64
+    }
+}
+
+/// Returns digests of xxhash by calculating given file.
+///
+/// NOTE the file is read once, the sampled ranges are collected while hashing the whole file.
+/// Only XXH64 covers the whole file, since the registry has nothing else to compare with.
+#[instrument(level = "debug", skip_all, fields(path = %anonymize(file_path)))]
+pub fn hash_file(file_path: &Path) -> io::Result<FileDigests> {
+    let mut reader = File::open(file_path)?;
+    let ranges = sample_ranges(reader.metadata()?.len());
+    let mut sampled: [Vec<u8>; 3] = Default::default();
+
+    // NOTE Use Box<[T]> to avoid stack overflow
+    let mut buffer = vec![0u8; 64 * 1024].into_boxed_slice();
+    let mut xxh64 = Xxh64::new(0);
+    let mut position = 0;
+
+    loop {
+        let bytes_read = reader.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        let chunk = &buffer[..bytes_read];
+        xxh64.update(chunk);
+
+        let end = position + bytes_read as u64;
+        for (range, bytes) in ranges.iter().zip(&mut sampled) {
+            let start = range.start.max(position);
+            let stop = range.end.min(end);
+            if start < stop {
+                bytes.extend_from_slice(
+                    &chunk[(start - position) as usize..(stop - position) as usize],
+                );
+            }
+        }
+        position = end;
+    }
+
+    Ok(FileDigests {
+        xxh64: xxh64.digest(),
+        sample: xxh3_64(&sampled.concat()),
+    })
+}
+
+/// Returns XXH3 of the first, the middle, and the last ranges of the file.
+///
+/// Far cheaper than hashing the whole file, and the last range covers the central directory
+/// of the ZIP archive, which changes whenever any entry is changed.
+pub fn sample_file(file_path: &Path) -> io::Result<u64> {
+    let mut reader = File::open(file_path)?;
+    let mut bytes = Vec::with_capacity(3 * SAMPLE_LEN as usize);
+    for range in sample_ranges(reader.metadata()?.len()) {
+        reader.seek(SeekFrom::Start(range.start))?;
+        Read::by_ref(&mut reader)
+            .take(range.end - range.start)
+            .read_to_end(&mut bytes)?;
+    }
+    Ok(xxh3_64(&bytes))
+}
+
+/// Returns the ranges hashed by [`sample_file`], which overlap in small files.
+fn sample_ranges(size: u64) -> [Range<u64>; 3] {
+    let last = size.saturating_sub(SAMPLE_LEN);
+    [0, last / 2, last].map(|offset| offset..(offset + SAMPLE_LEN).min(size))
+}
+
+#[cfg(test)]
+mod tests_cache {
+    use std::time::Instant;
+
+    use tempfile::tempdir;
+    use xxhash_rust::{xxh3::xxh3_64, xxh64::xxh64};
+
+    use super::*;
+    use crate::Checksum;
+
+    #[test]
+    fn test_hash_file() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("mod.zip");
+        // larger than the buffer to cover multiple reads, and the middle range across two reads
+        for len in [200_000u32, 2 * 64 * 1024 + 2000] {
+            let content: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+            fs::write(&path, &content)?;
+
+            let digests = hash_file(&path)?;
+            assert_eq!(digests.xxh64, xxh64(&content, 0));
+            assert_eq!(digests.sample, sample_file(&path)?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_cache_skips_unchanged_files() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        fs::write(dir.path().join("a.zip"), b"a")?;
+        fs::write(dir.path().join("b.zip"), b"b")?;
+
+        let mut cache = FileCacheDb::default();
+        let exclude = ExcludePatterns::default();
+        assert!(update_cache(&mut cache, dir.path(), &exclude, false)?);
+        assert_eq!(cache.entries.len(), 2);
+
+        // nothing changed
+        assert!(!update_cache(&mut cache, dir.path(), &exclude, false)?);
+        assert!(!update_cache(&mut cache, dir.path(), &exclude, true)?);
+
+        // stale entry is removed
+        fs::remove_file(dir.path().join("b.zip"))?;
+        assert!(update_cache(&mut cache, dir.path(), &exclude, false)?);
+        assert_eq!(cache.entries.len(), 1);
+
+        // keyed by the file name, so a renamed archive is hashed again
+        fs::rename(dir.path().join("a.zip"), dir.path().join("c.zip"))?;
+        assert!(update_cache(&mut cache, dir.path(), &exclude, false)?);
+        assert_eq!(cache.entries.keys().collect::<Vec<_>>(), ["c.zip"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_paranoid_detects_rewrite_with_same_metadata() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("a.zip");
+        fs::write(&path, b"before")?;
+        let mtime = fs::metadata(&path)?.modified()?;
+
+        let mut cache = FileCacheDb::default();
+        let exclude = ExcludePatterns::default();
+        update_cache(&mut cache, dir.path(), &exclude, false)?;
+
+        // same size with the mtime restored, and the ctime is not available or also unchanged
+        fs::write(&path, b"after!")?;
+        File::options()
+            .write(true)
+            .open(&path)?
+            .set_modified(mtime)?;
+        let entry = cache.entries.get_mut("a.zip").expect("should be hashed");
+        entry.snapshot = FileSnapshot::new(&fs::metadata(&path)?)?;
+
+        assert!(!update_cache(&mut cache, dir.path(), &exclude, false)?);
+        assert_eq!(cache.entries["a.zip"].hash, xxh64(b"before", 0));
+        assert!(update_cache(&mut cache, dir.path(), &exclude, true)?);
+        assert_eq!(cache.entries["a.zip"].hash, xxh64(b"after!", 0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_sample_file() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("mod.zip");
+        let mut content = vec![0u8; 3 * SAMPLE_LEN as usize];
+        fs::write(&path, &content)?;
+        let sampled = sample_file(&path)?;
+        assert_eq!(sampled, hash_file(&path)?.sample);
+
+        // the middle range
+        content[SAMPLE_LEN as usize + 1] = 1;
+        fs::write(&path, &content)?;
+        assert_ne!(sample_file(&path)?, sampled);
+
+        // smaller than a range
+        fs::write(&path, b"abc")?;
+        assert_eq!(sample_file(&path)?, xxh3_64(b"abcabcabc"));
+        assert_eq!(hash_file(&path)?.sample, xxh3_64(b"abcabcabc"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_cache_lock() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let cache_path = dir.path().join("state").join("checksum.cache");
+        let lock = CacheLock::try_acquire(&cache_path, Duration::ZERO)?;
+        assert!(lock.is_some());
+        assert!(
+            dir.path()
+                .join("state")
+                .join("checksum.cache.lock")
+                .exists()
+        );
+
+        // held by another writer
+        assert!(CacheLock::try_acquire(&cache_path, Duration::ZERO)?.is_none());
+        drop(lock);
+        assert!(CacheLock::try_acquire(&cache_path, Duration::ZERO)?.is_some());
+        Ok(())
+    }
+
+    fn snapshot(mtime: i64) -> FileSnapshot {
+        FileSnapshot {
+            mtime,
+            mtime_nsec: 500,
+            ctime: mtime,
+            ctime_nsec: 500,
+            size: 20,
+        }
+    }
+
+    fn sample_db() -> FileCacheDb {
+        let digests = FileDigests {
+            xxh64: 1,
+            sample: 3,
+        };
+        FileCacheDb {
+            entries: BTreeMap::from([("Foo.zip".into(), CacheEntry::new(snapshot(10), digests))]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_encode_and_decode() -> anyhow::Result<()> {
+        let bytes = encode(&sample_db())?;
+        assert!(bytes.starts_with(&MAGIC));
+        let cache = decode(&bytes)?;
+        assert!(cache.is_cache_valid("Foo.zip", &Checksums::from_iter([Checksum(1)])));
+        assert!(!cache.should_rehash("Foo.zip", &snapshot(10)));
+        // modified within the same second
+        let modified = FileSnapshot {
+            mtime_nsec: 501,
+            ..snapshot(10)
+        };
+        assert!(cache.should_rehash("Foo.zip", &modified));
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_rejects_broken_cache() -> anyhow::Result<()> {
+        let bytes = encode(&sample_db())?;
+        let truncated = &bytes[..bytes.len() - 1];
+        assert!(matches!(decode(truncated), Err(CacheError::Corrupted)));
+
+        let mut future = bytes.clone();
+        future[8..12].copy_from_slice(&(VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            decode(&future),
+            Err(CacheError::UnsupportedVersion(v)) if v == VERSION + 1
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_archived() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("Foo.cache");
+        let entries = BTreeMap::from([("Foo.zip".to_string(), 42u64)]);
+        fs::write(&path, rkyv::to_bytes::<rancor::Error>(&entries)?)?;
+        assert_eq!(read_archived::<BTreeMap<String, u64>>(&path)?, entries);
+
+        // misaligned on purpose, like the bytes read into a `Vec`
+        let mut bytes = vec![0u8];
+        bytes.extend_from_slice(&rkyv::to_bytes::<rancor::Error>(&entries)?);
+        assert_eq!(
+            from_archived_bytes::<BTreeMap<String, u64>>(&bytes[1..])?,
+            entries
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_rejects_older_versions() -> anyhow::Result<()> {
+        // headerless
+        let payload = rkyv::to_bytes::<rancor::Error>(&sample_db())?;
+        assert!(matches!(
+            decode(&payload),
+            Err(CacheError::UnsupportedVersion(1))
+        ));
+        // recorded the mtime only in seconds
+        assert!(matches!(
+            decode(&frame(3, &payload)),
+            Err(CacheError::UnsupportedVersion(3))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_manifests() -> anyhow::Result<()> {
+        let mut cache = sample_db();
+        let others = vec![DeclaredMod::new_unchecked("FooMaps", "1.0.0", Vec::new())];
+        let manifest = CachedManifest::new(
+            "Foo".into(),
+            "1.0.0".into(),
+            Vec::new(),
+            ModType::Helper,
+            others,
+        );
+        let read = vec![("Foo.zip".to_string(), snapshot(10), manifest)];
+        let keys = HashSet::from(["Foo.zip".to_string()]);
+        assert!(cache.update_manifests(read, &keys));
+        assert!(!cache.update_manifests(Vec::new(), &keys));
+
+        // survives the roundtrip
+        let cache = decode(&encode(&cache)?)?;
+        let found = cache
+            .manifest("Foo.zip", &snapshot(10))
+            .map(|m| m.name.as_str());
+        assert_eq!(found, Some("Foo"));
+        assert_eq!(cache.mod_names().collect::<Vec<_>>(), ["Foo", "FooMaps"]);
+        // modified since it was read
+        assert!(cache.manifest("Foo.zip", &snapshot(11)).is_none());
+
+        // removed with the archive
+        let mut cache = cache;
+        assert!(cache.update_manifests(Vec::new(), &HashSet::new()));
+        assert!(cache.manifest("Foo.zip", &snapshot(10)).is_none());
+        Ok(())
+    }
+
+    /// Compares the throughput of XXH64 and XXH3, and measures the cache-warm path.
+    ///
+    /// Run with `cargo test --release -- --ignored bench_ --nocapture`.
+    ///
+    /// NOTE on one core of a Linux VM: 8 MiB in 1.6 ms by XXH64 and 1.2 ms by XXH3, 161 ms cold
+    /// and 0.3 ms warm for the 64 files. XXH3 of the whole archives is not worth a second hasher,
+    /// since the registry only compares XXH64, and the warm path hashes at most the samples.
+    #[test]
+    #[ignore = "benchmark"]
+    fn bench_hash_and_cache_warm_path() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let content = vec![0x5au8; 8 * 1024 * 1024];
+        for i in 0..64 {
+            fs::write(dir.path().join(format!("{}.zip", i)), &content)?;
+        }
+
+        let start = Instant::now();
+        let _ = xxh64(&content, 0);
+        let t_xxh64 = start.elapsed();
+        let start = Instant::now();
+        let _ = xxh3_64(&content);
+        let t_xxh3 = start.elapsed();
+        println!("8 MiB: xxh64 {:?}, xxh3 {:?}", t_xxh64, t_xxh3);
+
+        let mut cache = FileCacheDb::default();
+        let exclude = ExcludePatterns::default();
+        let start = Instant::now();
+        update_cache(&mut cache, dir.path(), &exclude, false)?;
+        println!("cold: {:?} for 64 files (512 MiB)", start.elapsed());
+
+        let start = Instant::now();
+        update_cache(&mut cache, dir.path(), &exclude, false)?;
+        println!("warm: {:?} for 64 files", start.elapsed());
+        Ok(())
+    }
+}
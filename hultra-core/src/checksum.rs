@@ -1,5 +1,7 @@
 use std::{collections::HashSet, fmt, str::FromStr};
 
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+
 use crate::utils;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -10,6 +12,18 @@ impl Checksums {
     pub fn contains(&self, hash: &u64) -> bool {
         self.0.contains(&Checksum(*hash))
     }
+
+    /// Checks if the registry has no hash of the file, which is then verified by weaker means.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the values in ascending order, stable to compare or store.
+    pub fn sorted(&self) -> Vec<u64> {
+        let mut values: Vec<u64> = self.0.iter().map(|checksum| checksum.0).collect();
+        values.sort_unstable();
+        values
+    }
 }
 
 impl FromIterator<Checksum> for Checksums {
@@ -80,6 +94,20 @@ impl FromStr for Checksum {
     }
 }
 
+/// Serialized in the same form as displayed, e.g. `0x0123456789abcdef`.
+impl Serialize for Checksum {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Checksum {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
 #[cfg(test)]
 mod tests_checksum_verification {
     use super::*;
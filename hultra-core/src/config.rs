@@ -0,0 +1,16 @@
+//! Locations of the installed mods, provided by the frontend.
+use std::path::{Path, PathBuf};
+
+use crate::local::ExcludePatterns;
+
+/// Where the installed mods and their cache are.
+pub trait ModsConfig {
+    /// Directory of the installed mods.
+    fn mods_dir(&self) -> PathBuf;
+
+    /// Path to the cache of the checksums and the manifests of the installed mods.
+    fn cache_db_path(&self) -> &Path;
+
+    /// Patterns of the entries in the Mods directory to be ignored.
+    fn exclude(&self) -> &ExcludePatterns;
+}
@@ -0,0 +1,612 @@
+//! Domain model of dependency graph to resolve missing dependency of mods.
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use rkyv::Archive;
+use serde::Deserialize;
+use tracing::{debug, instrument};
+
+use crate::{
+    LocalMod,
+    version::{ModVersion, is_older},
+};
+
+/// Helper of the collab lobbies, which collabs and contests depend on.
+const COLLAB_HELPER: &str = "CollabUtils2";
+
+/// Represents `mod_dependency_graph.yaml`.
+#[derive(Debug, Default, Deserialize, Archive, rkyv::Serialize, rkyv::Deserialize)]
+#[serde(transparent)]
+pub struct DependencyGraph {
+    /// Detail of nodes
+    nodes: HashMap<String, DependencyNode>,
+}
+
+impl DependencyGraph {
+    /// Adds the mods declared by a local archive, replacing the nodes of the same names,
+    /// since the archive may be newer than the graph.
+    pub fn insert(&mut self, m: &LocalMod) {
+        for (name, dependencies) in m.declared_dependencies() {
+            self.insert_dependencies(name, dependencies.to_vec());
+        }
+    }
+
+    /// Adds the dependencies of the mod, replacing the node of the same name.
+    pub fn insert_dependencies(&mut self, name: &str, dependencies: Vec<Dependency>) {
+        self.nodes
+            .insert(name.to_string(), DependencyNode { dependencies });
+    }
+
+    /// Resolves which mods need to be downloaded by traversing the dependencies of the targets,
+    /// and filtering out already installed mods.
+    ///
+    /// Installed dependencies older than the version required by any of the mods are
+    /// reported as outdated, since Everest refuses to load the mods which require them.
+    ///
+    /// * `installed`: mod name to installed version
+    #[instrument(level = "debug", skip_all, fields(targets = target_names.len(), installed = installed.len()))]
+    pub fn check_names(
+        &self,
+        target_names: HashSet<String>,
+        installed: &HashMap<String, String>,
+    ) -> Resolution {
+        // 1. Traverse the dependency graph to list all required mods (BFS)
+        let (required, unresolved) = self.bfs_traversal(target_names.clone());
+
+        // 2. Compare the installed versions with the highest required ones
+        let mut outdated: Vec<OutdatedDependency> = self
+            .required_versions(&required)
+            .into_iter()
+            .filter_map(|(name, version)| {
+                let installed = installed.get(name)?;
+                is_older(installed, version).then(|| OutdatedDependency {
+                    name: name.to_string(),
+                    installed: installed.clone(),
+                    required: version.to_string(),
+                })
+            })
+            .collect();
+        outdated.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let everest = self.required_everest(&required);
+        let missing: HashSet<String> = required
+            .into_iter()
+            .filter(|name| !installed.contains_key(name))
+            .collect();
+        let unresolved = unresolved
+            .into_iter()
+            .filter(|name| missing.contains(name))
+            .collect();
+        let groups = self.group_by_target(target_names, &missing);
+        Resolution {
+            missing,
+            unresolved,
+            groups,
+            outdated,
+            everest,
+        }
+    }
+
+    /// Groups the missing mods by the target which requires them.
+    fn group_by_target(
+        &self,
+        target_names: HashSet<String>,
+        missing: &HashSet<String>,
+    ) -> Vec<InstallGroup> {
+        let mut targets: Vec<String> = target_names.into_iter().collect();
+        targets.sort();
+        let mut assigned = HashSet::new();
+        targets
+            .into_iter()
+            .map(|target| {
+                let (required, _) = self.bfs_traversal(HashSet::from([target.clone()]));
+                let mut dependencies: Vec<String> = required
+                    .into_iter()
+                    .filter(|name| *name != target && missing.contains(name))
+                    .filter(|name| assigned.insert(name.clone()))
+                    .collect();
+                dependencies.sort();
+                let is_collab = self.get_node_by_key(&target).is_some_and(|node| {
+                    node.dependencies
+                        .iter()
+                        .any(|dep| dep.name() == COLLAB_HELPER)
+                });
+                InstallGroup {
+                    target,
+                    is_collab,
+                    dependencies,
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the highest version of Everest required by the given mods.
+    fn required_everest(&self, names: &HashSet<String>) -> Option<EverestRequirement> {
+        let mut sorted: Vec<&String> = names.iter().collect();
+        sorted.sort();
+        let mut highest: Option<EverestRequirement> = None;
+        for name in sorted {
+            let Some(node) = self.get_node_by_key(name) else {
+                continue;
+            };
+            let deps = node
+                .dependencies
+                .iter()
+                .filter(|dep| matches!(dep.name(), "Everest" | "EverestCore"));
+            for dep in deps {
+                let Some(build) = dep
+                    .version()
+                    .parse::<ModVersion>()
+                    .ok()
+                    .and_then(|version| version.everest_build())
+                else {
+                    continue;
+                };
+                if highest.as_ref().is_none_or(|h| h.build < build) {
+                    highest = Some(EverestRequirement {
+                        required_by: name.clone(),
+                        build,
+                    });
+                }
+            }
+        }
+        highest
+    }
+
+    /// Returns the highest version of each dependency required by the given mods.
+    fn required_versions<'a>(&'a self, names: &HashSet<String>) -> HashMap<&'a str, &'a str> {
+        let mut versions: HashMap<&str, &str> = HashMap::new();
+        let deps = names
+            .iter()
+            .filter_map(|name| self.get_node_by_key(name))
+            .flat_map(|node| &node.dependencies);
+        for dep in deps {
+            let version = versions.entry(dep.name()).or_insert(dep.version());
+            if is_older(version, dep.version()) {
+                *version = dep.version();
+            }
+        }
+        versions
+    }
+
+    /// Traverses the dependency graph using BFS from multiple starting mods.
+    ///
+    /// # Returns
+    ///
+    /// A `HashSet` containing all required mods, including:
+    /// - The starting mods themselves
+    /// - All direct and transitive dependencies
+    ///
+    /// and another one of the mods not found in the graph, whose dependencies are unknown.
+    #[instrument(skip(self))]
+    fn bfs_traversal(&self, start_mods: HashSet<String>) -> (HashSet<String>, HashSet<String>) {
+        let mut visited = HashSet::new();
+        let mut not_found = HashSet::new();
+        let mut queue = VecDeque::new();
+
+        // Adds starting mods to queue
+        for start_mod in start_mods {
+            queue.push_back(start_mod);
+        }
+
+        while let Some(current) = queue.pop_front() {
+            if !visited.insert(current.clone()) {
+                continue; // Already visited
+            }
+            if let Some(node) = self.get_node_by_key(&current) {
+                for dep in &node.dependencies {
+                    if !dep.is_core() {
+                        queue.push_back(dep.name().to_string());
+                    }
+                }
+            } else {
+                debug!(?current, "not found in dep graph");
+                not_found.insert(current);
+            }
+        }
+
+        debug!("found dependencies: {:?}", visited);
+
+        (visited, not_found)
+    }
+
+    /// Gets the node information for a given mod name.
+    fn get_node_by_key(&self, key: &str) -> Option<&DependencyNode> {
+        self.nodes.get(key)
+    }
+}
+
+/// Reverse index of the dependencies declared by the installed mods.
+#[derive(Debug, Default)]
+pub struct ReverseDependencies {
+    /// Mod name to the mods which require it, with the required version.
+    dependents: HashMap<String, Vec<(String, String)>>,
+}
+
+/// Installed mod which requires the mod.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependent {
+    pub name: String,
+    /// Required version, empty if not specified.
+    pub version: String,
+    /// Mod in between, `None` if this mod requires it directly.
+    pub via: Option<String>,
+}
+
+impl ReverseDependencies {
+    pub fn new(mods: &[LocalMod]) -> Self {
+        let mut dependents: HashMap<String, Vec<(String, String)>> = HashMap::new();
+        for (name, dependencies) in mods.iter().flat_map(LocalMod::declared_dependencies) {
+            for dep in dependencies {
+                dependents
+                    .entry(dep.name().to_string())
+                    .or_default()
+                    .push((name.to_string(), dep.version().to_string()));
+            }
+        }
+        for list in dependents.values_mut() {
+            list.sort();
+            list.dedup();
+        }
+        Self { dependents }
+    }
+
+    /// Lists the mods which require the mod directly or transitively, nearest first.
+    ///
+    /// The name is matched case-insensitively if there is no exact match.
+    pub fn dependents_of(&self, name: &str) -> Vec<Dependent> {
+        let name = if self.dependents.contains_key(name) {
+            name
+        } else {
+            match self
+                .dependents
+                .keys()
+                .find(|key| key.eq_ignore_ascii_case(name))
+            {
+                Some(key) => key,
+                None => return Vec::new(),
+            }
+        };
+
+        let mut result = Vec::new();
+        let mut visited = HashSet::from([name.to_string()]);
+        let mut queue = VecDeque::from([(name, None::<&str>)]);
+        while let Some((current, via)) = queue.pop_front() {
+            let Some(list) = self.dependents.get(current) else {
+                continue;
+            };
+            for (dependent, version) in list {
+                if !visited.insert(dependent.clone()) {
+                    continue;
+                }
+                // NOTE report the version required from the mod in between
+                let via = via.or((current != name).then_some(current));
+                result.push(Dependent {
+                    name: dependent.clone(),
+                    version: version.clone(),
+                    via: via.map(String::from),
+                });
+                queue.push_back((dependent, via));
+            }
+        }
+        result
+    }
+}
+
+/// Result of the dependency check.
+#[derive(Debug, Default)]
+pub struct Resolution {
+    /// Names of the mods which are not installed, including the targets.
+    pub missing: HashSet<String>,
+    /// Missing mods not found in the graph yet, e.g. brand-new uploads.
+    ///
+    /// Their dependencies can only be read from `everest.yaml` after downloading them.
+    pub unresolved: HashSet<String>,
+    /// Missing mods grouped by the target, sorted by the target name.
+    pub groups: Vec<InstallGroup>,
+    /// Installed dependencies older than the required version, sorted by name.
+    pub outdated: Vec<OutdatedDependency>,
+    /// The highest build of Everest required by the mods.
+    pub everest: Option<EverestRequirement>,
+}
+
+/// Missing dependencies of the target.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstallGroup {
+    pub target: String,
+    /// The target is a collab or a contest, which usually pulls in dozens of helpers.
+    pub is_collab: bool,
+    /// Sorted by name. Dependencies shared with other targets belong to the first one.
+    pub dependencies: Vec<String>,
+}
+
+/// Build of Everest required by the mod.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EverestRequirement {
+    pub required_by: String,
+    pub build: u32,
+}
+
+/// Installed dependency older than the required version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutdatedDependency {
+    pub name: String,
+    pub installed: String,
+    pub required: String,
+}
+
+/// Each entry of the `mod_dependency_graph.yaml`.
+#[derive(Debug, Default, Deserialize, Archive, rkyv::Serialize, rkyv::Deserialize)]
+struct DependencyNode {
+    /// List of dependencies.
+    #[serde(rename = "Dependencies")]
+    dependencies: Vec<Dependency>,
+}
+
+/// Dependency of the mod.
+#[derive(Debug, Clone, Default, Deserialize, Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct Dependency {
+    #[serde(rename = "Name")]
+    name: String,
+    /// Minimum version of the dependency. Some mods do not specify it.
+    #[serde(rename = "Version", default)]
+    version: String,
+}
+
+impl Dependency {
+    /// Returns the name of the dependency.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the required version of the dependency.
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Checks if this is the game or Everest itself, which cannot be installed as a mod.
+    pub fn is_core(&self) -> bool {
+        matches!(self.name(), "Celeste" | "Everest" | "EverestCore")
+    }
+}
+
+#[cfg(test)]
+mod tests_graph {
+    use crate::registry::ModRegistry;
+
+    use super::*;
+
+    #[test]
+    fn test_bfs_traversal() {
+        let yaml_data = r#"
+DarkMatterJourney:
+  Dependencies:
+    - Name: "MoreLockBlocks"
+      Version: "1.0.0"
+MoreLockBlocks:
+  Dependencies: []
+darkmoonruins:
+  Dependencies:
+    - Name: "AvBdayHelper2021"
+      Version: "1.0.0"
+AvBdayHelper2021:
+  Dependencies:
+    - Name: "ExtendedVariantMode"
+      Version: "1.0.0"
+ExtendedVariantMode:
+  Dependencies: []
+"#;
+        let graph: DependencyGraph = serde_yaml_ng::from_slice(yaml_data.as_bytes()).unwrap();
+        let mut start_mods = HashSet::new();
+        start_mods.insert("DarkMatterJourney".to_string());
+        start_mods.insert("darkmoonruins".to_string());
+        let (all_required, not_found) = graph.bfs_traversal(start_mods);
+
+        let expected_mods: HashSet<String> = [
+            "DarkMatterJourney",
+            "MoreLockBlocks",
+            "darkmoonruins",
+            "AvBdayHelper2021",
+            "ExtendedVariantMode",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+        assert_eq!(all_required, expected_mods);
+        assert!(not_found.is_empty());
+    }
+
+    #[test]
+    fn test_group_by_target() {
+        let graph: DependencyGraph = serde_yaml_ng::from_str(
+            r#"
+StrawberryJam:
+  Dependencies:
+    - Name: CollabUtils2
+    - Name: Helper
+    - Name: Shared
+Map:
+  Dependencies:
+    - Name: Shared
+CollabUtils2:
+  Dependencies: []
+Helper:
+  Dependencies: []
+Shared:
+  Dependencies: []
+"#,
+        )
+        .unwrap();
+        let installed = HashMap::from([("Helper".to_string(), "1.0.0".to_string())]);
+        let targets = HashSet::from(["StrawberryJam".to_string(), "Map".to_string()]);
+
+        let resolution = graph.check_names(targets, &installed);
+        assert_eq!(
+            resolution.groups,
+            vec![
+                InstallGroup {
+                    target: "Map".into(),
+                    is_collab: false,
+                    dependencies: vec!["Shared".into()],
+                },
+                InstallGroup {
+                    target: "StrawberryJam".into(),
+                    is_collab: true,
+                    dependencies: vec!["CollabUtils2".into()],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_insert_local_mod() {
+        use std::path::PathBuf;
+
+        use crate::local::ModFile;
+
+        let mut graph: DependencyGraph =
+            serde_yaml_ng::from_str("Helper:\n  Dependencies: []\n").unwrap();
+        let deps = vec![Dependency {
+            name: "Helper".into(),
+            version: "1.0.0".into(),
+        }];
+        let file = ModFile::new_unchecked(PathBuf::from("/Downloads/NewMap.zip"));
+        let m = LocalMod::new(file, "NewMap".into(), "1.0.0".into()).with_dependencies(deps);
+        graph.insert(&m);
+
+        // the local archive is installed, its dependencies are not
+        let installed = HashMap::from([("NewMap".to_string(), "1.0.0".to_string())]);
+        let resolution = graph.check_names(HashSet::from(["NewMap".to_string()]), &installed);
+        assert_eq!(resolution.missing, HashSet::from(["Helper".to_string()]));
+        assert!(resolution.unresolved.is_empty());
+        assert_eq!(resolution.groups[0].dependencies, ["Helper"]);
+    }
+
+    #[test]
+    fn test_check_names_not_in_graph() {
+        let graph: DependencyGraph = serde_yaml_ng::from_str(
+            "Map:\n  Dependencies:\n    - Name: BrandNewHelper\n      Version: 1.0.0\n",
+        )
+        .unwrap();
+
+        let resolution = graph.check_names(HashSet::from(["Map".to_string()]), &HashMap::new());
+        assert_eq!(
+            resolution.unresolved,
+            HashSet::from(["BrandNewHelper".into()])
+        );
+
+        // installed ones do not need to be resolved
+        let installed = HashMap::from([("BrandNewHelper".to_string(), "1.0.0".to_string())]);
+        let resolution = graph.check_names(HashSet::from(["Map".to_string()]), &installed);
+        assert!(resolution.unresolved.is_empty());
+    }
+
+    #[test]
+    fn test_check_dependencies() {
+        let graph: DependencyGraph = serde_yaml_ng::from_str(
+            r#"
+Map:
+  Dependencies:
+    - Name: Helper
+      Version: 1.2.0
+    - Name: Library
+      Version: 1.0.0
+    - Name: Everest
+      Version: 1.4000.0
+Helper:
+  Dependencies:
+    - Name: Library
+      Version: 2.1.0
+    - Name: EverestCore
+      Version: 1.4465.0
+Library:
+  Dependencies: []
+"#,
+        )
+        .unwrap();
+        let registry: ModRegistry = serde_yaml_ng::from_str(
+            "Map:\n  GameBananaId: 1\n  Version: 1.0.0\n  URL: https://gamebanana.com/mmdl/1\n  Size: 1\n  xxHash: []\n",
+        )
+        .unwrap();
+        let installed: HashMap<String, String> = [("Helper", "1.2.0-beta"), ("Library", "2.0.3")]
+            .map(|(name, version)| (name.to_string(), version.to_string()))
+            .into();
+
+        let targets = registry.names_by_gbids(&HashSet::from([1]));
+        let resolution = graph.check_names(targets, &installed);
+        assert_eq!(resolution.missing, HashSet::from(["Map".to_string()]));
+        // the highest requirement of Library wins, pre-release of Helper satisfies 1.2.0
+        assert_eq!(
+            resolution.outdated,
+            vec![OutdatedDependency {
+                name: "Library".into(),
+                installed: "2.0.3".into(),
+                required: "2.1.0".into(),
+            }]
+        );
+        assert_eq!(
+            resolution.everest,
+            Some(EverestRequirement {
+                required_by: "Helper".into(),
+                build: 4465,
+            })
+        );
+    }
+
+    #[test]
+    fn test_reverse_dependencies() {
+        use std::path::PathBuf;
+
+        use crate::local::{DeclaredMod, ModFile};
+
+        let local_mod = |name: &str, deps: &[(&str, &str)]| {
+            let file = ModFile::new_unchecked(PathBuf::from(format!("/Mods/{}.zip", name)));
+            let deps = deps
+                .iter()
+                .map(|(name, version)| Dependency {
+                    name: name.to_string(),
+                    version: version.to_string(),
+                })
+                .collect();
+            LocalMod::new(file, name.to_string(), "1.0.0".into()).with_dependencies(deps)
+        };
+        let mods = [
+            local_mod(
+                "MapA",
+                &[("MapHelper", "1.0.0"), ("GravityHelper", "1.2.0")],
+            ),
+            local_mod("MapB", &[("MapHelper", "1.1.0")]),
+            local_mod("MapHelper", &[("GravityHelper", "")]),
+            local_mod("GravityHelper", &[]),
+            // the maps shipped with their helper in one archive
+            local_mod("Bundle", &[]).with_others(vec![DeclaredMod::new_unchecked(
+                "BundleMaps",
+                "1.0.0",
+                vec![Dependency {
+                    name: "CollabUtils2".into(),
+                    version: "1.6.0".into(),
+                }],
+            )]),
+        ];
+        let index = ReverseDependencies::new(&mods);
+
+        let dependent = |name: &str, version: &str, via: Option<&str>| Dependent {
+            name: name.into(),
+            version: version.into(),
+            via: via.map(String::from),
+        };
+        assert_eq!(
+            index.dependents_of("gravityhelper"),
+            vec![
+                dependent("MapA", "1.2.0", None),
+                dependent("MapHelper", "", None),
+                dependent("MapB", "1.1.0", Some("MapHelper")),
+            ]
+        );
+        assert!(index.dependents_of("MapA").is_empty());
+        assert_eq!(
+            index.dependents_of("CollabUtils2"),
+            vec![dependent("BundleMaps", "1.6.0", None)]
+        );
+    }
+}
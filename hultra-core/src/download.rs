@@ -0,0 +1,223 @@
+//! Files to download, planned from the registry and the update checks.
+use std::{fmt::Display, str::FromStr};
+
+use tracing::debug;
+
+use crate::{
+    Checksums, ParseChecksumError,
+    gamebanana::{self, GamebananaIdError},
+    lockfile::LockedMod,
+    registry::Entry,
+    update::UpdateContext,
+    utils,
+};
+
+/// Metadata of target mod to be downloaded.
+#[derive(Debug, Clone)]
+pub struct DownloadFile {
+    /// Original download URL for the mod.
+    url: DownloadUrl,
+    /// A name of the mod.
+    name: String,
+    /// Version of the mod expected in `everest.yaml`.
+    version: String,
+    /// File name of the mod, `None` if the name cannot be a file name.
+    stem: Option<FileStem>,
+    /// File size used for the progress bar.
+    size: u64,
+    /// A expected list of XxHash64.
+    checksums: Checksums,
+}
+
+impl DownloadFile {
+    pub fn url(&self) -> &DownloadUrl {
+        &self.url
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+    pub fn file_stem(&self) -> Option<&str> {
+        self.stem.as_ref().map(|stem| stem.0.as_str())
+    }
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+    pub fn checksums(&self) -> &Checksums {
+        &self.checksums
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ParseDownloadFileError {
+    #[error(transparent)]
+    Url(#[from] GamebananaIdError),
+    #[error(transparent)]
+    Checksum(#[from] ParseChecksumError),
+    #[error("no download URL is known for {0}")]
+    NoUrl(String),
+}
+
+impl TryFrom<&UpdateContext> for DownloadFile {
+    type Error = ParseDownloadFileError;
+
+    fn try_from(value: &UpdateContext) -> Result<Self, Self::Error> {
+        let url = DownloadUrl::from_str(value.url())?;
+
+        Ok(Self {
+            url,
+            name: value.name().to_string(),
+            version: value.available_version().to_string(),
+            stem: FileStem::parse(value.name()),
+            size: value.size(),
+            checksums: value.checksums().to_owned(),
+        })
+    }
+}
+
+impl TryFrom<(&str, &Entry)> for DownloadFile {
+    type Error = ParseDownloadFileError;
+
+    fn try_from((name, entry): (&str, &Entry)) -> Result<Self, Self::Error> {
+        let url = DownloadUrl::from_str(entry.url())?;
+        let checksums = entry.checksums()?;
+
+        Ok(Self {
+            url,
+            name: name.to_string(),
+            version: entry.version().to_string(),
+            stem: FileStem::parse(name),
+            size: entry.file_size(),
+            checksums,
+        })
+    }
+}
+
+impl TryFrom<&LockedMod> for DownloadFile {
+    type Error = ParseDownloadFileError;
+
+    fn try_from(value: &LockedMod) -> Result<Self, Self::Error> {
+        let url = value
+            .url
+            .as_deref()
+            .ok_or_else(|| ParseDownloadFileError::NoUrl(value.name.clone()))?;
+
+        Ok(Self {
+            url: DownloadUrl::from_str(url)?,
+            name: value.name.clone(),
+            version: value.version.clone(),
+            stem: FileStem::parse(&value.name),
+            size: value.size,
+            checksums: Checksums::from_iter([value.xxhash.clone()]),
+        })
+    }
+}
+
+/// Download URL of the mod. This is the original form used in the GameBanana.
+///
+/// Valid form:
+/// `https://gamebanana.com/mmdl/{ID}`, or `https://gamebanana.com/dl/{ID}`.
+/// See [`gamebanana`] for the accepted variations.
+#[derive(Debug, Clone)]
+pub struct DownloadUrl {
+    raw: String,
+    id: u32,
+}
+
+impl DownloadUrl {
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+
+    pub fn gbid(&self) -> u32 {
+        self.id
+    }
+}
+
+impl FromStr for DownloadUrl {
+    type Err = GamebananaIdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let id = gamebanana::extract_file_id(s)?;
+        Ok(DownloadUrl {
+            raw: s.to_string(),
+            id,
+        })
+    }
+}
+
+impl Display for DownloadUrl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.raw())
+    }
+}
+
+#[cfg(test)]
+mod tests_download_url {
+    use super::*;
+
+    #[test]
+    fn test_parse_valid_url() {
+        let input = "https://gamebanana.com/mmdl/12345";
+        let result = DownloadUrl::from_str(input);
+
+        assert!(result.is_ok());
+        let download_url = result.unwrap();
+        assert_eq!(download_url.gbid(), 12345);
+        assert_eq!(download_url.raw(), input);
+    }
+
+    #[test]
+    fn test_parse_invalid_prefix() {
+        let input = "https://google.com/12345";
+        let result = DownloadUrl::from_str(input);
+
+        assert!(matches!(result, Err(GamebananaIdError::WrongHost(_))));
+    }
+
+    #[test]
+    fn test_parse_invalid_id() {
+        assert!(matches!(
+            DownloadUrl::from_str("https://gamebanana.com/mmdl/abc"),
+            Err(GamebananaIdError::InvalidId(_))
+        ));
+
+        assert!(matches!(
+            DownloadUrl::from_str("https://gamebanana.com/mmdl/4294967296"),
+            Err(GamebananaIdError::InvalidId(_))
+        ));
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ParseNameError {
+    #[error(transparent)]
+    NonAscii(#[from] utils::NonAsciiError),
+}
+
+#[derive(Debug, Clone)]
+struct FileStem(String);
+
+impl FromStr for FileStem {
+    type Err = ParseNameError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let clean_s = utils::sanitize_stem(s)?;
+        Ok(Self(clean_s))
+    }
+}
+
+impl FileStem {
+    /// Returns `None` if the name cannot be a file name, e.g. non-ASCII or blank.
+    fn parse(name: &str) -> Option<Self> {
+        match Self::from_str(name) {
+            Ok(stem) if !stem.0.is_empty() => Some(stem),
+            _ => {
+                debug!(name, "name of the mod cannot be a file name");
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,174 @@
+//! Canonical parser of the GameBanana URLs, shared by the commands and the downloader.
+//!
+//! Accepted forms, with or without `www.`, a trailing slash, query string, and fragment:
+//! * `https://gamebanana.com/mods/{ID}`: mod page
+//! * `https://gamebanana.com/dl/{ID}`, `https://gamebanana.com/mmdl/{ID}`: file download
+use url::Url;
+
+const HOST: &str = "gamebanana.com";
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum GamebananaIdError {
+    #[error("invalid URL: {0}")]
+    InvalidUrl(#[from] url::ParseError),
+    #[error("URL must be on {HOST}, but the host is '{0}'")]
+    WrongHost(String),
+    #[error("URL must be {expected}, but the path is '{path}'")]
+    WrongPath {
+        expected: &'static str,
+        path: String,
+    },
+    #[error("ID must be a positive integer up to {max}, but got '{0}'", max = u32::MAX)]
+    InvalidId(String),
+}
+
+/// Kind of the GameBanana URL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UrlKind {
+    /// `/mods/{ID}`, the ID of the mod.
+    ModPage,
+    /// `/dl/{ID}` or `/mmdl/{ID}`, the ID of the file.
+    File,
+}
+
+impl UrlKind {
+    fn expected(self) -> &'static str {
+        match self {
+            Self::ModPage => "https://gamebanana.com/mods/{ID}",
+            Self::File => "https://gamebanana.com/mmdl/{ID} or https://gamebanana.com/dl/{ID}",
+        }
+    }
+}
+
+/// Parses the GameBanana URL into its kind and ID.
+pub fn parse(input: &str) -> Result<(UrlKind, u32), GamebananaIdError> {
+    let url = Url::parse(input)?;
+
+    let host = url.host_str().unwrap_or_default();
+    if !matches!(url.scheme(), "https" | "http")
+        || host.strip_prefix("www.").unwrap_or(host) != HOST
+    {
+        return Err(GamebananaIdError::WrongHost(host.to_string()));
+    }
+
+    let wrong_path = || GamebananaIdError::WrongPath {
+        expected: "a mod page or download URL",
+        path: url.path().to_string(),
+    };
+    let mut segments = url.path_segments().ok_or_else(wrong_path)?;
+    let kind = match segments.next() {
+        Some("mods") => UrlKind::ModPage,
+        Some("dl" | "mmdl") => UrlKind::File,
+        _ => return Err(wrong_path()),
+    };
+    let id = segments.next().ok_or_else(wrong_path)?;
+    // NOTE a trailing slash yields an empty segment
+    if segments.any(|segment| !segment.is_empty()) {
+        return Err(wrong_path());
+    }
+
+    match id.parse::<u32>() {
+        Ok(id) if id > 0 => Ok((kind, id)),
+        _ => Err(GamebananaIdError::InvalidId(id.to_string())),
+    }
+}
+
+/// Extracts the mod ID from the mod page URL.
+pub fn extract_mod_id(input: &str) -> Result<u32, GamebananaIdError> {
+    extract(input, UrlKind::ModPage)
+}
+
+/// Extracts the file ID from the download URL.
+pub fn extract_file_id(input: &str) -> Result<u32, GamebananaIdError> {
+    extract(input, UrlKind::File)
+}
+
+fn extract(input: &str, expected: UrlKind) -> Result<u32, GamebananaIdError> {
+    match parse(input)? {
+        (kind, id) if kind == expected => Ok(id),
+        _ => Err(GamebananaIdError::WrongPath {
+            expected: expected.expected(),
+            path: Url::parse(input)?.path().to_string(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests_gamebanana {
+    use super::*;
+
+    #[test]
+    fn test_parse_accepted_forms() {
+        for (input, expected) in [
+            ("https://gamebanana.com/mods/123", (UrlKind::ModPage, 123)),
+            (
+                "https://www.gamebanana.com/mods/123/",
+                (UrlKind::ModPage, 123),
+            ),
+            (
+                "https://gamebanana.com/mods/123?tab=files#top",
+                (UrlKind::ModPage, 123),
+            ),
+            (
+                "https://gamebanana.com/mmdl/1298450",
+                (UrlKind::File, 1298450),
+            ),
+            (
+                "https://gamebanana.com/dl/1298450",
+                (UrlKind::File, 1298450),
+            ),
+            (
+                "http://gamebanana.com/dl/1298450?version=2",
+                (UrlKind::File, 1298450),
+            ),
+        ] {
+            assert_eq!(parse(input), Ok(expected), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!(
+            parse("not a url"),
+            Err(GamebananaIdError::InvalidUrl(_))
+        ));
+        assert_eq!(
+            parse("https://google.com/mods/123"),
+            Err(GamebananaIdError::WrongHost("google.com".into()))
+        );
+        assert_eq!(
+            parse("https://notgamebanana.com/mods/123"),
+            Err(GamebananaIdError::WrongHost("notgamebanana.com".into()))
+        );
+        assert!(matches!(
+            parse("https://gamebanana.com/tools/123"),
+            Err(GamebananaIdError::WrongPath { .. })
+        ));
+        assert!(matches!(
+            parse("https://gamebanana.com/mods/123/extra"),
+            Err(GamebananaIdError::WrongPath { .. })
+        ));
+        for id in ["abc", "0", "-1", "4294967296", ""] {
+            assert_eq!(
+                parse(&format!("https://gamebanana.com/mods/{}", id)),
+                Err(GamebananaIdError::InvalidId(id.into())),
+                "{}",
+                id
+            );
+        }
+    }
+
+    #[test]
+    fn test_extract_by_kind() {
+        assert_eq!(extract_mod_id("https://gamebanana.com/mods/42"), Ok(42));
+        assert_eq!(extract_file_id("https://gamebanana.com/dl/42"), Ok(42));
+        assert!(matches!(
+            extract_mod_id("https://gamebanana.com/mmdl/42"),
+            Err(GamebananaIdError::WrongPath { .. })
+        ));
+        assert!(matches!(
+            extract_file_id("https://gamebanana.com/mods/42"),
+            Err(GamebananaIdError::WrongPath { .. })
+        ));
+    }
+}
@@ -0,0 +1,40 @@
+//! Mod management logic of hultra, without the command line interface.
+//!
+//! Frontends provide the locations of the installed mods by [`config::ModsConfig`], and may
+//! draw the progress of the long tasks with [`progress::set_reporter`].
+//!
+//! --- Raw Data From YAML File ---
+//! * local/manifest.rs: `everest.yaml`, metadata file in ZIP archive
+//! * registry.rs: `everest_update.yaml`, database for check updates, also used for installing mods
+//! * dependency.rs: `dependency_graph.yaml`, database for resolving mod dependencies
+//!
+//! --- Core Domain Logic ---
+//! * checksum.rs: xxhash64 of mod file, used for checking updates
+//! * version.rs: lenient SemVer of mods, used for checking dependencies
+//! * cache.rs: cache the file checksum to avoid re-hash
+//! * gamebanana.rs: parse IDs from the GameBanana URLs
+//! * blacklist.rs: Everest's blacklist and the updater's blacklist
+//! * update.rs: find the installed mods to be updated
+//! * download.rs: files to download, planned from the registry
+//! * lockfile.rs: `hultra.lock`, the exact set of the installed mods
+//!
+//! --- Local File ---
+//! * local.rs: represents installed mod
+pub mod blacklist;
+pub mod cache;
+pub mod checksum;
+pub mod config;
+pub mod dependency;
+pub mod download;
+pub mod gamebanana;
+pub mod local;
+pub mod lockfile;
+pub mod log;
+pub mod progress;
+pub mod registry;
+pub mod update;
+pub mod utils;
+pub mod version;
+
+pub use checksum::{Checksum, ChecksumVerificationError, Checksums, ParseChecksumError};
+pub use local::{LocalMod, ModFile};
@@ -0,0 +1,434 @@
+use std::{
+    borrow::Cow,
+    collections::HashSet,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use rkyv::{Archive, Deserialize, Serialize};
+use tracing::{info, warn};
+
+pub use exclude::ExcludePatterns;
+pub use manifest::MetadataReadError;
+pub use mod_type::ModType;
+pub use resolver::{read_mod, read_mod_names, scan_mods};
+
+use crate::{
+    blacklist::{PatternLine, UPDATER_BLACKLIST_FILE, UpdaterBlacklist},
+    cache,
+    dependency::Dependency,
+};
+
+mod contents;
+pub mod dialog;
+pub mod doctor;
+pub mod duplicate;
+mod exclude;
+mod manifest;
+pub mod maps;
+mod mod_type;
+pub mod readme;
+mod resolver;
+pub mod usage;
+
+/// Information of installed mod.
+#[derive(Debug, Clone)]
+pub struct LocalMod {
+    /// Full path to the ZIP archive of the mod.
+    file: ModFile,
+    /// Mod name.
+    name: String,
+    /// Version label of the mod to display.
+    version: DisplayVersion,
+    /// Dependencies declared in the manifest.
+    dependencies: Vec<Dependency>,
+    /// Type told by the scan, `None` for the archives read without it.
+    mod_type: Option<ModType>,
+    /// The other mods declared by the manifest, this one is the first.
+    others: Vec<DeclaredMod>,
+}
+
+/// Another mod declared by the same manifest, e.g. the maps shipped with their helper.
+#[derive(Archive, Deserialize, Serialize, Debug, Clone)]
+pub struct DeclaredMod {
+    name: String,
+    version: String,
+    dependencies: Vec<Dependency>,
+}
+
+impl DeclaredMod {
+    #[cfg(test)]
+    pub fn new_unchecked(name: &str, version: &str, dependencies: Vec<Dependency>) -> Self {
+        Self {
+            name: name.to_string(),
+            version: version.to_string(),
+            dependencies,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    pub fn dependencies(&self) -> &[Dependency] {
+        &self.dependencies
+    }
+}
+
+#[derive(Debug, Clone)]
+struct DisplayVersion(String);
+
+impl std::fmt::Display for DisplayVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "v{}", self.0)
+    }
+}
+
+impl LocalMod {
+    pub fn new(file: ModFile, name: String, version: String) -> Self {
+        Self {
+            file,
+            name,
+            version: DisplayVersion(version),
+            dependencies: Vec::new(),
+            mod_type: None,
+            others: Vec::new(),
+        }
+    }
+
+    pub fn with_dependencies(mut self, dependencies: Vec<Dependency>) -> Self {
+        self.dependencies = dependencies;
+        self
+    }
+
+    pub fn with_mod_type(mut self, mod_type: Option<ModType>) -> Self {
+        self.mod_type = mod_type;
+        self
+    }
+
+    pub fn with_others(mut self, others: Vec<DeclaredMod>) -> Self {
+        self.others = others;
+        self
+    }
+
+    pub fn file(&self) -> &ModFile {
+        &self.file
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn version(&self) -> &str {
+        &self.version.0
+    }
+
+    pub fn dependencies(&self) -> &[Dependency] {
+        &self.dependencies
+    }
+
+    pub fn mod_type(&self) -> Option<ModType> {
+        self.mod_type
+    }
+
+    pub fn others(&self) -> &[DeclaredMod] {
+        &self.others
+    }
+
+    /// Returns the names and versions of all mods declared by the manifest, this one first.
+    ///
+    /// NOTE Everest loads each of them, so they all satisfy the dependencies of the other mods
+    pub fn declared(&self) -> impl Iterator<Item = (&str, &str)> {
+        std::iter::once((self.name(), self.version()))
+            .chain(self.others.iter().map(|m| (m.name(), m.version())))
+    }
+
+    /// Returns the dependencies of each mod declared by the manifest, this one first.
+    pub fn declared_dependencies(&self) -> impl Iterator<Item = (&str, &[Dependency])> {
+        std::iter::once((self.name(), self.dependencies()))
+            .chain(self.others.iter().map(|m| (m.name(), m.dependencies())))
+    }
+
+    fn file_name(&self) -> &str {
+        self.file
+            .path()
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or_default()
+    }
+
+    /// Checks if the mod is listed in the updater's blacklist, by file name or by a pattern.
+    pub fn is_blacklisted(&self, blacklist: &UpdaterBlacklist) -> bool {
+        blacklist.matches(self.file_name(), &self.name)
+    }
+
+    fn is_blacklisted_by(&self, pattern: &PatternLine) -> bool {
+        pattern.matches(self.file_name(), &self.name)
+    }
+}
+
+impl fmt::Display for LocalMod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self
+            .file()
+            .path()
+            .file_stem()
+            .is_some_and(|name| name.eq_ignore_ascii_case(self.name()))
+        {
+            write!(f, "{} (v{})", self.name(), self.version())?;
+        } else {
+            let filename = self
+                .file()
+                .path()
+                .file_name()
+                .map(|name| name.to_string_lossy())
+                .unwrap_or(Cow::Borrowed("unknown"));
+
+            write!(f, "*{} (v{}) [{}]", self.name(), self.version(), filename)?;
+        }
+        Ok(())
+    }
+}
+
+/// Represents a validated path to a mod file, a `.zip` archive or an unpacked directory.
+#[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+pub struct ModFile {
+    path: PathBuf,
+    /// Size of the archive read by the directory scan, `None` for the unpacked mods.
+    size: Option<u64>,
+}
+
+impl ModFile {
+    fn from(path: PathBuf) -> Self {
+        Self { path, size: None }
+    }
+
+    fn with_size(mut self, size: Option<u64>) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the size of the archive, recorded by the scan without reading the file again.
+    pub fn size(&self) -> Option<u64> {
+        self.size
+    }
+
+    /// Checks if the mod is unpacked, which cannot be updated since it may be edited by hand.
+    pub fn is_dir(&self) -> bool {
+        self.path.is_dir()
+    }
+
+    /// Wraps the path without checking that it is a mod, e.g. in the tests of the frontends.
+    pub fn new_unchecked(path: PathBuf) -> Self {
+        Self::from(path)
+    }
+}
+
+impl ModFile {
+    /// Checks if the file is listed in Everest's blacklist, i.e. not loaded by the game.
+    pub fn is_disabled(&self, disabled: &HashSet<String>) -> bool {
+        self.path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|name| disabled.contains(name))
+    }
+}
+
+pub trait ModIdentityService {
+    /// Fetches the cache key and modification time (UNIX time in seconds) of the file.
+    fn fetch_id(&self, path: &Path) -> io::Result<(String, i64)>;
+}
+
+pub struct LocalFileSystemService;
+
+impl ModIdentityService for LocalFileSystemService {
+    fn fetch_id(&self, path: &Path) -> io::Result<(String, i64)> {
+        let mtime = cache::unix_mtime(&path.metadata()?)?;
+        Ok((cache::cache_key(path), mtime))
+    }
+}
+
+#[cfg(test)]
+pub struct MockFileSystemService {
+    pub should_fail: bool,
+}
+
+#[cfg(test)]
+impl ModIdentityService for MockFileSystemService {
+    fn fetch_id(&self, path: &Path) -> io::Result<(String, i64)> {
+        if self.should_fail {
+            Err(io::Error::other("intentional error"))
+        } else {
+            Ok((cache::cache_key(path), 1_700_000_000))
+        }
+    }
+}
+
+/// Writes the archive of the entries, for the tests reading the archives.
+#[cfg(test)]
+pub(crate) fn write_zip(path: &Path, entries: &[(&str, &str)]) -> anyhow::Result<()> {
+    use std::io::Write;
+
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(path)?);
+    for (name, content) in entries {
+        zip.start_file(*name, zip::write::SimpleFileOptions::default())?;
+        zip.write_all(content.as_bytes())?;
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+/// A service for discovering mod files within a directory.
+trait ModFileSource {
+    /// Returns a list of valid mod files.
+    fn fetch_all(&self) -> io::Result<Vec<ModFile>>;
+}
+
+/// A standard implementation of [`ModFileSource`] that interacts with the local file system.
+#[derive(Debug)]
+struct LocalModFileSource<'a> {
+    mods_dir: PathBuf,
+    exclude: &'a ExcludePatterns,
+}
+
+impl<'a> LocalModFileSource<'a> {
+    fn new(mods_dir: impl Into<PathBuf>, exclude: &'a ExcludePatterns) -> Self {
+        Self {
+            mods_dir: mods_dir.into(),
+            exclude,
+        }
+    }
+}
+
+impl ModFileSource for LocalModFileSource<'_> {
+    fn fetch_all(&self) -> io::Result<Vec<ModFile>> {
+        let found_paths = fs::read_dir(&self.mods_dir)?
+            .flatten()
+            .filter(|e| is_mod_archive(e, self.exclude) || is_mod_directory(e, self.exclude))
+            .map(|e| {
+                // NOTE read with the entry, so `list` and `du` do not read the archives again
+                let size = e.metadata().ok().filter(|m| m.is_file()).map(|m| m.len());
+                ModFile::from(e.path()).with_size(size)
+            })
+            .collect();
+        Ok(found_paths)
+    }
+}
+
+/// Checks if the entry in the Mods directory is a ZIP archive which is not excluded.
+pub fn is_mod_archive(entry: &fs::DirEntry, exclude: &ExcludePatterns) -> bool {
+    let is_zip = entry.file_type().is_ok_and(|ft| ft.is_file())
+        && entry
+            .path()
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"));
+
+    is_zip && !exclude.is_excluded(&entry.file_name().to_string_lossy(), false)
+}
+
+/// Checks if the entry in the Mods directory is an unpacked mod which is not excluded.
+///
+/// NOTE Everest loads the folders with `everest.yaml` at their root, e.g. mods under development
+fn is_mod_directory(entry: &fs::DirEntry, exclude: &ExcludePatterns) -> bool {
+    let is_mod = entry.file_type().is_ok_and(|ft| ft.is_dir())
+        && manifest::MANIFEST_NAMES
+            .iter()
+            .any(|name| entry.path().join(name).is_file());
+
+    is_mod && !exclude.is_excluded(&entry.file_name().to_string_lossy(), true)
+}
+
+pub trait LocalModExt {
+    fn apply_blacklist(&mut self, ublist: &UpdaterBlacklist) -> io::Result<()>;
+
+    /// Removes the mods disabled in Everest's blacklist, returns how many were removed.
+    fn skip_disabled(&mut self, disabled: &HashSet<String>) -> usize;
+}
+
+impl LocalModExt for Vec<LocalMod> {
+    fn apply_blacklist(&mut self, ublist: &UpdaterBlacklist) -> io::Result<()> {
+        let initial_count = self.len();
+
+        if ublist.is_empty() {
+            return Ok(());
+        }
+
+        for pattern in ublist.patterns() {
+            if !self.iter().any(|m| m.is_blacklisted_by(pattern)) {
+                warn!(
+                    "{}:{}: '{}' matches none of the installed mods",
+                    UPDATER_BLACKLIST_FILE, pattern.line, pattern
+                );
+            }
+        }
+
+        self.retain(|local_mod| !local_mod.is_blacklisted(ublist));
+
+        let ignored_count = initial_count - self.len();
+        if ignored_count > 0 {
+            info!("{} mods were ignored due to blacklist", ignored_count)
+        }
+
+        Ok(())
+    }
+
+    fn skip_disabled(&mut self, disabled: &HashSet<String>) -> usize {
+        let initial_count = self.len();
+        self.retain(|local_mod| !local_mod.file().is_disabled(disabled));
+        initial_count - self.len()
+    }
+}
+
+#[cfg(test)]
+mod tests_local {
+    use super::*;
+
+    #[test]
+    fn test_apply_blacklist() -> anyhow::Result<()> {
+        let local = |file: &str, name: &str| {
+            LocalMod::new(
+                ModFile::from(PathBuf::from("/Mods").join(file)),
+                name.into(),
+                "1.0.0".into(),
+            )
+        };
+        let mut mods = vec![
+            local("SpringCollab2020.zip", "SpringCollab2020"),
+            local("other-name.zip", "SpeedrunTool"),
+            local("Foo.zip", "Foo"),
+            local("Bar.zip", "Bar"),
+        ];
+        let ublist: UpdaterBlacklist =
+            "SpringCollab*.zip\nname: speedruntool\nFoo.zip\nname: Gone\n"
+                .parse()
+                .expect("should be parsed");
+        mods.apply_blacklist(&ublist)?;
+        assert_eq!(mods.len(), 1);
+        assert_eq!(mods[0].name(), "Bar");
+        Ok(())
+    }
+
+    #[test]
+    fn test_skip_disabled() {
+        let local = |file: &str| {
+            LocalMod::new(
+                ModFile::from(PathBuf::from("/Mods").join(file)),
+                file.trim_end_matches(".zip").into(),
+                "1.0.0".into(),
+            )
+        };
+        let mut mods = vec![local("Foo.zip"), local("Bar.zip")];
+        let disabled = HashSet::from(["Bar.zip".to_string(), "Gone.zip".to_string()]);
+        assert_eq!(mods.skip_disabled(&disabled), 1);
+        assert_eq!(mods.len(), 1);
+        assert_eq!(mods[0].name(), "Foo");
+    }
+}
@@ -0,0 +1,86 @@
+//! Entries of the installed mods, in the archives or the unpacked directories.
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use zip_finder::ZipArchiveIndex;
+
+/// Entries of the mod, named relative to its root with `/` like the ones in the archives.
+#[derive(Debug)]
+pub enum ModContents {
+    Archive(ZipArchiveIndex),
+    Directory { root: PathBuf, names: Vec<Vec<u8>> },
+}
+
+impl ModContents {
+    /// Opens the archive, or lists the files in the directory recursively.
+    pub fn open(path: &Path) -> Result<Self, zip_finder::Error> {
+        if !path.is_dir() {
+            return Ok(Self::Archive(ZipArchiveIndex::open(path)?));
+        }
+        let mut names = Vec::new();
+        list_files(path, "", &mut names)?;
+        names.sort();
+        Ok(Self::Directory {
+            root: path.to_path_buf(),
+            names,
+        })
+    }
+
+    /// Returns the names of the files.
+    pub fn names(&self) -> Vec<&[u8]> {
+        match self {
+            Self::Archive(index) => index.names().collect(),
+            Self::Directory { names, .. } => names.iter().map(Vec::as_slice).collect(),
+        }
+    }
+
+    /// Reads the file, decoding it if it is compressed in the archive.
+    pub fn extract(&mut self, name: &[u8]) -> Result<Vec<u8>, zip_finder::Error> {
+        match self {
+            Self::Archive(index) => index.extract(name),
+            Self::Directory { root, .. } => {
+                let name = String::from_utf8_lossy(name);
+                Ok(fs::read(root.join(name.as_ref()))?)
+            }
+        }
+    }
+}
+
+fn list_files(dir: &Path, prefix: &str, names: &mut Vec<Vec<u8>>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)?.flatten() {
+        let name = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            list_files(&entry.path(), &format!("{}/", name), names)?;
+        } else if file_type.is_file() {
+            names.push(name.into_bytes());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests_contents {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_directory() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        fs::create_dir_all(dir.path().join("Maps/Me"))?;
+        fs::write(dir.path().join("everest.yaml"), "- Name: Foo\n")?;
+        fs::write(dir.path().join("Maps/Me/1-Foo.bin"), "")?;
+
+        let mut contents = ModContents::open(dir.path())?;
+        assert_eq!(
+            contents.names(),
+            [b"Maps/Me/1-Foo.bin".as_slice(), b"everest.yaml"]
+        );
+        assert_eq!(contents.extract(b"everest.yaml")?, b"- Name: Foo\n");
+        assert!(contents.extract(b"missing.txt").is_err());
+        Ok(())
+    }
+}
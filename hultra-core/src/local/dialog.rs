@@ -0,0 +1,145 @@
+//! Dialog files shipped inside mod archives, e.g. `Dialog/English.txt`.
+//!
+//! The format is the one Celeste reads: `KEY= value` lines, where the following lines without a
+//! key continue the value, and the lines starting with `#` are comments. Keys are compared
+//! case-insensitively like Celeste does.
+use std::collections::HashMap;
+
+use crate::local::contents::ModContents;
+
+/// Language of the game by default, used for the keys the other languages do not have.
+pub const DEFAULT_LANGUAGE: &str = "English";
+
+const DIALOG_DIR: &str = "Dialog/";
+const DIALOG_EXTENSION: &str = ".txt";
+
+/// Values of the dialog keyed by lowercase keys.
+#[derive(Debug, Default)]
+pub struct Dialog {
+    entries: HashMap<String, String>,
+}
+
+impl Dialog {
+    /// Parses the dialog file, a leading UTF-8 BOM is ignored.
+    pub fn parse(text: &str) -> Self {
+        let mut entries = HashMap::new();
+        let mut current: Option<String> = None;
+        for line in text.trim_start_matches('\u{feff}').lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some((key, value)) = line.split_once('=')
+                && is_key(key.trim())
+            {
+                let key = key.trim().to_lowercase();
+                entries.insert(key.clone(), value.trim().to_string());
+                current = Some(key);
+            } else if let Some(value) = current.as_ref().and_then(|key| entries.get_mut(key)) {
+                value.push('\n');
+                value.push_str(line);
+            }
+        }
+        Self { entries }
+    }
+
+    /// Reads the dialog of the language in the mod, with English for the missing keys.
+    ///
+    /// Returns an empty dialog if the mod has neither of them.
+    pub fn read(contents: &mut ModContents, language: &str) -> Result<Self, zip_finder::Error> {
+        let mut dialog = Self::default();
+        let mut languages = vec![DEFAULT_LANGUAGE];
+        if !language.eq_ignore_ascii_case(DEFAULT_LANGUAGE) {
+            languages.push(language);
+        }
+        for language in languages {
+            let path = format!("{}{}{}", DIALOG_DIR, language, DIALOG_EXTENSION);
+            // NOTE file names are case-insensitive on Windows, where most mods are made
+            let Some(name) = contents
+                .names()
+                .into_iter()
+                .find(|name| name.eq_ignore_ascii_case(path.as_bytes()))
+                .map(<[u8]>::to_vec)
+            else {
+                continue;
+            };
+            let bytes = contents.extract(&name)?;
+            let text = String::from_utf8_lossy(&bytes);
+            dialog.entries.extend(Self::parse(&text).entries);
+        }
+        Ok(dialog)
+    }
+
+    /// Returns the value of the key, the lines are joined with `\n`.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries
+            .get(&key.to_lowercase())
+            .map(String::as_str)
+            .filter(|value| !value.is_empty())
+    }
+}
+
+/// Converts the SID of the map to its dialog key, e.g. `Author/1-Foo` to `Author_1_Foo`.
+pub fn sid_key(sid: &str) -> String {
+    sid.replace(['/', '-', '+', ' '], "_")
+}
+
+fn is_key(key: &str) -> bool {
+    !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests_dialog {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let dialog = Dialog::parse(
+            "\u{feff}# names\nAuthor_1_Foo= Forsaken Foo\nAuthor_1_Foo_remix=\n\nPOEM= first line\n  # not a part of the poem\n  second line\nnot a key = value\n",
+        );
+        assert_eq!(dialog.get("author_1_foo"), Some("Forsaken Foo"));
+        assert_eq!(
+            dialog.get("POEM"),
+            Some("first line\nsecond line\nnot a key = value")
+        );
+        assert_eq!(dialog.get("Author_1_Foo_remix"), None);
+        assert_eq!(dialog.get("missing"), None);
+    }
+
+    #[test]
+    fn test_sid_key() {
+        assert_eq!(
+            sid_key("Author/Campaign/1-Foo+Bar"),
+            "Author_Campaign_1_Foo_Bar"
+        );
+        assert_eq!(sid_key("Author/My Map"), "Author_My_Map");
+    }
+
+    #[test]
+    fn test_read_with_fallback() -> anyhow::Result<()> {
+        // an unpacked mod, the archives are read through the same entries
+        let dir = tempdir()?;
+        fs::create_dir_all(dir.path().join("Dialog"))?;
+        fs::write(
+            dir.path().join("Dialog/English.txt"),
+            "Foo= Foo Valley\nBar= Bar Peak\n",
+        )?;
+        fs::write(
+            dir.path().join("Dialog/japanese.txt"),
+            "\u{feff}Foo= フーの谷\n",
+        )?;
+        let mut contents = ModContents::open(dir.path())?;
+
+        let dialog = Dialog::read(&mut contents, "Japanese")?;
+        assert_eq!(dialog.get("Foo"), Some("フーの谷"));
+        assert_eq!(dialog.get("Bar"), Some("Bar Peak"));
+
+        let dialog = Dialog::read(&mut contents, "French")?;
+        assert_eq!(dialog.get("Foo"), Some("Foo Valley"));
+        Ok(())
+    }
+}
@@ -0,0 +1,247 @@
+//! Checks of the installed archives which cause silent load failures in Everest.
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use rayon::prelude::*;
+
+use crate::local::{
+    ExcludePatterns,
+    contents::ModContents,
+    is_mod_archive, is_mod_directory,
+    manifest::{self, Manifest, Repair},
+};
+
+/// Problem found in the archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Issue {
+    /// The archive cannot be read.
+    Unreadable(String),
+    /// The manifest cannot be parsed.
+    InvalidManifest(String),
+    /// The manifest is parsed only after the repairs, other parsers may still reject it.
+    MalformedManifest(Vec<Repair>),
+    /// `DLL` of the manifest points at the entry which does not exist.
+    MissingDll {
+        name: String,
+        dll: String,
+        /// Entry which matches the path case-insensitively.
+        similar: Option<String>,
+    },
+}
+
+impl Issue {
+    /// Stable identifier of the problem, for the JSON output.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Unreadable(_) => "unreadable",
+            Self::InvalidManifest(_) => "invalid_manifest",
+            Self::MalformedManifest(_) => "malformed_manifest",
+            Self::MissingDll { .. } => "missing_dll",
+        }
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unreadable(e) => write!(f, "cannot read the archive: {}", e),
+            Self::InvalidManifest(e) => write!(f, "cannot parse everest.yaml: {}", e),
+            Self::MalformedManifest(repairs) => {
+                let repairs: Vec<String> = repairs.iter().map(Repair::to_string).collect();
+                write!(f, "everest.yaml has {}", repairs.join(", "))
+            }
+            Self::MissingDll { name, dll, similar } => {
+                write!(
+                    f,
+                    "{} declares DLL '{}' which is not in the archive",
+                    name, dll
+                )?;
+                if let Some(similar) = similar {
+                    write!(f, " (found '{}', the case differs)", similar)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Issues found in the archive.
+#[derive(Debug)]
+pub struct Diagnosis {
+    pub path: PathBuf,
+    pub issues: Vec<Issue>,
+}
+
+/// Checks all mods in the Mods directory, returns only the ones with issues.
+pub fn diagnose_mods(mods_dir: &Path, exclude: &ExcludePatterns) -> io::Result<Vec<Diagnosis>> {
+    let mut archives: Vec<PathBuf> = fs::read_dir(mods_dir)?
+        .flatten()
+        .filter(|entry| is_mod_archive(entry, exclude) || is_mod_directory(entry, exclude))
+        .map(|entry| entry.path())
+        .collect();
+    archives.sort();
+
+    let diagnoses = archives
+        .into_par_iter()
+        .map(|path| Diagnosis {
+            issues: diagnose(&path),
+            path,
+        })
+        .filter(|diagnosis| !diagnosis.issues.is_empty())
+        .collect();
+    Ok(diagnoses)
+}
+
+/// Checks the archive, or the unpacked mod.
+pub fn diagnose(path: &Path) -> Vec<Issue> {
+    let mut contents = match ModContents::open(path) {
+        Ok(contents) => contents,
+        Err(e) => return vec![Issue::Unreadable(e.to_string())],
+    };
+
+    // NOTE archives without a manifest are valid, e.g. map-only mods
+    let Some(manifest_name) = manifest::find_manifest(&contents).map(<[u8]>::to_vec) else {
+        return Vec::new();
+    };
+    let (manifests, repairs) = match contents
+        .extract(&manifest_name)
+        .map_err(|e| Issue::Unreadable(e.to_string()))
+        .and_then(|bytes| {
+            Manifest::parse_lenient(&bytes).map_err(|e| Issue::InvalidManifest(e.to_string()))
+        }) {
+        Ok(parsed) => parsed,
+        Err(issue) => return vec![issue],
+    };
+
+    let names = contents.names();
+    let malformed = (!repairs.is_empty()).then_some(Issue::MalformedManifest(repairs));
+    let missing_dlls = manifests.into_iter().filter_map(|manifest| {
+        let dll = manifest.dll?;
+        // NOTE Windows-style separator is also accepted by Everest
+        let normalized = dll.replace('\\', "/");
+        let normalized = normalized.trim_start_matches("./");
+        if names.contains(&normalized.as_bytes()) {
+            return None;
+        }
+        let similar = names
+            .iter()
+            .find(|name| name.eq_ignore_ascii_case(normalized.as_bytes()))
+            .map(|name| String::from_utf8_lossy(name).into_owned());
+        Some(Issue::MissingDll {
+            name: manifest.name,
+            dll,
+            similar,
+        })
+    });
+    malformed.into_iter().chain(missing_dlls).collect()
+}
+
+#[cfg(test)]
+mod tests_doctor {
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::local::write_zip;
+
+    #[test]
+    fn test_dll_exists() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("Code.zip");
+        write_zip(
+            &path,
+            &[
+                (
+                    "everest.yaml",
+                    "- Name: CodeMod\n  Version: 1.0.0\n  DLL: Code\\CodeMod.dll\n",
+                ),
+                ("Code/CodeMod.dll", "MZ"),
+            ],
+        )?;
+        assert!(diagnose(&path).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_missing_dll() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("Broken.zip");
+        write_zip(
+            &path,
+            &[
+                (
+                    "everest.yaml",
+                    "- Name: Broken\n  Version: 1.0.0\n  DLL: Code/Broken.dll\n- Name: NoCode\n  Version: 1.0.0\n",
+                ),
+                ("code/broken.dll", "MZ"),
+            ],
+        )?;
+        assert_eq!(
+            diagnose(&path),
+            vec![Issue::MissingDll {
+                name: "Broken".into(),
+                dll: "Code/Broken.dll".into(),
+                similar: Some("code/broken.dll".into()),
+            }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_malformed_manifest() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("Tabs.zip");
+        write_zip(
+            &path,
+            &[(
+                "everest.yaml",
+                "- Name: Tabs\n\tVersion: 1.0.0\n\tDLL: Tabs.dll\n",
+            )],
+        )?;
+        assert_eq!(
+            diagnose(&path),
+            vec![
+                Issue::MalformedManifest(vec![Repair::Tabs]),
+                Issue::MissingDll {
+                    name: "Tabs".into(),
+                    dll: "Tabs.dll".into(),
+                    similar: None,
+                },
+            ]
+        );
+
+        write_zip(&path, &[("everest.yaml", "- Name: [broken\n")])?;
+        assert!(matches!(diagnose(&path)[..], [Issue::InvalidManifest(_)]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_nested_and_unpacked() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let manifest = "- Name: Dev\n  Version: 1.0.0\n  DLL: Dev.dll\n";
+        let path = dir.path().join("Nested.zip");
+        write_zip(&path, &[("Dev/everest.yaml", manifest)])?;
+        assert!(matches!(diagnose(&path)[..], [Issue::MissingDll { .. }]));
+
+        let unpacked = dir.path().join("Dev");
+        fs::create_dir(&unpacked)?;
+        fs::write(unpacked.join("everest.yaml"), manifest)?;
+        fs::write(unpacked.join("Dev.dll"), "MZ")?;
+        assert!(diagnose(&unpacked).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_diagnose_mods() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        write_zip(&dir.path().join("Map.zip"), &[("Maps/map.bin", "")])?;
+        fs::write(dir.path().join("Corrupted.zip"), b"not a zip")?;
+
+        let diagnoses = diagnose_mods(dir.path(), &ExcludePatterns::default())?;
+        assert_eq!(diagnoses.len(), 1);
+        assert!(diagnoses[0].path.ends_with("Corrupted.zip"));
+        assert!(matches!(diagnoses[0].issues[..], [Issue::Unreadable(_)]));
+        Ok(())
+    }
+}
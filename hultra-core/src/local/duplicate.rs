@@ -0,0 +1,128 @@
+//! Detection of the archives which declare the same mod name.
+//!
+//! Everest loads only one of them and the choice is not predictable, so the mod may run
+//! an older version, or fail to load with the other mods which require the newer one.
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::{LocalMod, version::ModVersion};
+
+/// Archives which declare the same mod name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Duplicate {
+    pub name: String,
+    /// Archive of the newest version.
+    pub keep: PathBuf,
+    /// The other archives, which should be disabled or removed.
+    pub others: Vec<PathBuf>,
+}
+
+/// Finds the mod names declared in multiple archives.
+///
+/// Archives disabled in Everest's blacklist are ignored since they are not loaded.
+pub fn find_duplicates(mods: &[LocalMod], disabled: &HashSet<String>) -> Vec<Duplicate> {
+    find_duplicates_by(mods, disabled, modified)
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+fn find_duplicates_by(
+    mods: &[LocalMod],
+    disabled: &HashSet<String>,
+    modified: impl Fn(&Path) -> Option<SystemTime>,
+) -> Vec<Duplicate> {
+    // NOTE every mod declared by the manifest counts, e.g. the maps shipped with their helper
+    let mut by_name: BTreeMap<&str, Vec<(&str, &LocalMod)>> = BTreeMap::new();
+    for m in mods {
+        let file_name = m.file().path().file_name().unwrap_or_default();
+        if disabled.contains(file_name.to_string_lossy().as_ref()) {
+            continue;
+        }
+        for (name, version) in m.declared() {
+            let archives = by_name.entry(name).or_default();
+            if !archives.iter().any(|(_, other)| other.file() == m.file()) {
+                archives.push((version, m));
+            }
+        }
+    }
+
+    by_name
+        .into_iter()
+        .filter(|(_, archives)| archives.len() > 1)
+        .map(|(name, mut archives)| {
+            // NOTE newest version first, the recently modified one wins a tie or unparsable versions
+            archives.sort_by_cached_key(|(version, m)| {
+                let version = version.parse::<ModVersion>().ok();
+                std::cmp::Reverse((version, modified(m.file().path())))
+            });
+            let mut paths = archives.iter().map(|(_, m)| m.file().path().to_path_buf());
+            Duplicate {
+                name: name.to_string(),
+                keep: paths.next().unwrap_or_default(),
+                others: paths.collect(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests_duplicate {
+    use std::time::Duration;
+
+    use crate::local::{DeclaredMod, ModFile};
+
+    use super::*;
+
+    fn local_mod(file: &str, name: &str, version: &str) -> LocalMod {
+        let file = ModFile::new_unchecked(PathBuf::from(format!("/Mods/{}", file)));
+        LocalMod::new(file, name.to_string(), version.to_string())
+    }
+
+    #[test]
+    fn test_find_duplicates() {
+        let mods = [
+            local_mod("GravityHelper.zip", "GravityHelper", "1.2.20"),
+            local_mod("GravityHelper (1).zip", "GravityHelper", "1.2.3"),
+            local_mod("gh-old.zip", "GravityHelper", "1.0.0"),
+            local_mod("Map.zip", "Map", "weird"),
+            local_mod("Map-copy.zip", "Map", "weird"),
+            local_mod("Disabled.zip", "Solo", "1.0.0"),
+            local_mod("Solo.zip", "Solo", "1.0.0"),
+            local_mod("Bundle.zip", "BundleHelper", "2.0.0").with_others(vec![
+                DeclaredMod::new_unchecked("Map", "1.0.0", Vec::new()),
+                DeclaredMod::new_unchecked("Map", "1.0.0", Vec::new()),
+            ]),
+        ];
+        let disabled = HashSet::from(["Disabled.zip".to_string(), "gh-old.zip".to_string()]);
+        let now = SystemTime::now();
+        let modified = |path: &Path| {
+            Some(if path.ends_with("Map-copy.zip") {
+                now
+            } else {
+                now - Duration::from_secs(60)
+            })
+        };
+
+        assert_eq!(
+            find_duplicates_by(&mods, &disabled, modified),
+            vec![
+                Duplicate {
+                    name: "GravityHelper".into(),
+                    keep: "/Mods/GravityHelper.zip".into(),
+                    others: vec!["/Mods/GravityHelper (1).zip".into()],
+                },
+                Duplicate {
+                    name: "Map".into(),
+                    keep: "/Mods/Bundle.zip".into(),
+                    others: vec!["/Mods/Map-copy.zip".into(), "/Mods/Map.zip".into()],
+                },
+            ]
+        );
+    }
+}
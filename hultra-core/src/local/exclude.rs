@@ -0,0 +1,50 @@
+//! Patterns to ignore entries in the Mods directory.
+use serde::Deserialize;
+
+use crate::utils::glob_match;
+
+/// Glob patterns matched against the entry names directly under the Mods directory.
+///
+/// A pattern ending with `/` only matches directories (e.g. `Backups/`).
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct ExcludePatterns(Vec<String>);
+
+impl ExcludePatterns {
+    /// Checks if the entry should be ignored.
+    pub fn is_excluded(&self, name: &str, is_dir: bool) -> bool {
+        self.0
+            .iter()
+            .any(|pattern| match pattern.strip_suffix('/') {
+                Some(dir_pattern) => is_dir && glob_match(dir_pattern, name),
+                None => glob_match(pattern, name),
+            })
+    }
+}
+
+impl From<Vec<String>> for ExcludePatterns {
+    fn from(value: Vec<String>) -> Self {
+        Self(value)
+    }
+}
+
+#[cfg(test)]
+mod tests_exclude {
+    use super::*;
+
+    #[test]
+    fn test_is_excluded() {
+        let patterns = ExcludePatterns::from(vec!["*.zip.old".to_string(), "Backups/".to_string()]);
+
+        assert!(patterns.is_excluded("GravityHelper.zip.old", false));
+        assert!(patterns.is_excluded("Backups", true));
+        assert!(!patterns.is_excluded("Backups", false));
+        assert!(!patterns.is_excluded("GravityHelper.zip", false));
+    }
+
+    #[test]
+    fn test_empty() {
+        let patterns = ExcludePatterns::default();
+        assert!(!patterns.is_excluded("GravityHelper.zip", false));
+    }
+}
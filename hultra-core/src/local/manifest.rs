@@ -0,0 +1,399 @@
+//! Raw data of `everest.yaml`.
+use std::{collections::VecDeque, fs, io, path::Path};
+
+use serde::Deserialize;
+use tracing::{debug, instrument, warn};
+use zip_finder::{ExtractOptions, basename};
+
+pub use repair::Repair;
+
+use crate::{
+    dependency::Dependency,
+    local::{DeclaredMod, ModType, contents::ModContents},
+    log::anonymize,
+};
+
+mod repair;
+
+/// Represents the metadata of mod.
+#[derive(Debug, Default, Deserialize)]
+pub(super) struct Manifest {
+    #[serde(rename = "Name")]
+    pub(super) name: String,
+    #[serde(rename = "Version")]
+    pub(super) version: String,
+    #[serde(rename = "Dependencies", default)]
+    pub(super) dependencies: Vec<Dependency>,
+    /// Path to the code of the mod in the archive, e.g. `Code/MyMod.dll`.
+    #[serde(rename = "DLL", default)]
+    pub(super) dll: Option<String>,
+    /// Told from the entries of the archive, not a field of the manifest.
+    #[serde(skip)]
+    pub(super) mod_type: Option<ModType>,
+    /// The entries after this one, read by [`MetadataReader::read_metadata`].
+    #[serde(skip)]
+    pub(super) others: Vec<DeclaredMod>,
+}
+
+impl From<Manifest> for DeclaredMod {
+    fn from(value: Manifest) -> Self {
+        Self {
+            name: value.name,
+            version: value.version,
+            dependencies: value.dependencies,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ManifestParseError {
+    #[error("manifest is parsed successfully but no entries found on the file")]
+    NoEntry,
+    #[error("failed to deserialize bytes as `everest.yaml`")]
+    InvalidYamlStructure(#[from] serde_yaml_ng::Error),
+}
+
+impl TryFrom<Vec<u8>> for Manifest {
+    type Error = ManifestParseError;
+
+    fn try_from(buffer: Vec<u8>) -> Result<Self, Self::Error> {
+        let mut manifests = Manifest::parse_all(&buffer)?;
+        manifests.pop_front().ok_or(ManifestParseError::NoEntry)
+    }
+}
+
+impl Manifest {
+    /// Parses all entries of the manifest. A manifest may declare multiple mods.
+    pub(super) fn parse_all(buffer: &[u8]) -> Result<VecDeque<Manifest>, ManifestParseError> {
+        // Remove UTF-8 BOM if present
+        let clean_slice = buffer.strip_prefix(&[0xEF, 0xBB, 0xBF]).unwrap_or(buffer);
+
+        // NOTE Use `VecDeque` for efficient `pop_front` operation (`O(1)` vs `Vec::remove(0)` which is `O(n)`)
+        Ok(serde_yaml_ng::from_slice(clean_slice)?)
+    }
+
+    /// Parses all entries of the manifest, repairing it if the parser rejects it.
+    ///
+    /// Returns the repairs made, empty if the manifest is valid.
+    pub(super) fn parse_lenient(
+        buffer: &[u8],
+    ) -> Result<(VecDeque<Manifest>, Vec<Repair>), ManifestParseError> {
+        let e = match Self::parse_all(buffer) {
+            Ok(manifests) => return Ok((manifests, Vec::new())),
+            Err(e) => e,
+        };
+        let Some((repaired, repairs)) = repair::repair(&String::from_utf8_lossy(buffer)) else {
+            return Err(e);
+        };
+        // NOTE the error of the original is more helpful to fix the manifest
+        let manifests = Self::parse_all(repaired.as_bytes()).map_err(|_| e)?;
+        Ok((manifests, repairs))
+    }
+}
+
+#[cfg(test)]
+mod tests_manifest_parsing {
+    use anyhow::{Context, Result};
+
+    use super::*;
+
+    #[test]
+    fn test_parse_manifest() -> Result<()> {
+        let bytes = br#"
+- Name: darkmoonruins
+  Version: 1.1.4
+  Dependencies:
+    - Name: AvBdayHelper2021
+      Version: 1.0.2
+    - Name: CherryHelper
+      Version: 1.7.1
+    - Name: CollabUtils2
+      Version: 1.6.13
+"#;
+        let manifest = Manifest::try_from(bytes.to_vec());
+        assert!(manifest.is_ok());
+
+        let manifest = manifest.context("failed to parse manifest from YAML")?;
+        assert_eq!(manifest.name, "darkmoonruins");
+        assert_eq!(manifest.version, "1.1.4");
+        assert_eq!(manifest.dependencies.len(), 3);
+        assert_eq!(manifest.dependencies[2].name(), "CollabUtils2");
+        assert_eq!(manifest.dependencies[2].version(), "1.6.13");
+        Ok(())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum MetadataReadError {
+    #[error(transparent)]
+    Archive(#[from] zip_finder::Error),
+    #[error(transparent)]
+    Parse(#[from] ManifestParseError),
+}
+
+impl MetadataReadError {
+    /// Checks if the archive has no manifest, which is valid, e.g. map-only mods.
+    pub fn is_missing_manifest(&self) -> bool {
+        match self {
+            Self::Archive(e) => e.is_target_not_found(),
+            Self::Parse(e) => matches!(e, ManifestParseError::NoEntry),
+        }
+    }
+}
+
+pub trait MetadataReader {
+    /// Reads the first entry of the manifest, with the others in [`Manifest::others`].
+    fn read_metadata(&self, path: &Path) -> Result<Manifest, MetadataReadError>;
+}
+
+/// Names of the manifest, the first one is preferred.
+pub(super) const MANIFEST_NAMES: [&str; 2] = ["everest.yaml", "everest.yml"];
+
+/// Buffer cap for scanning the central directory.
+///
+/// NOTE Archives are scanned in parallel, so the central directory of big collabs (several MB)
+/// should not be loaded at once.
+const MAX_SCAN_BUFFER: usize = 64 * 1024;
+
+#[derive(Debug, Clone)]
+pub(super) struct LocalMetadataReader;
+
+impl MetadataReader for LocalMetadataReader {
+    #[instrument(level = "debug", skip_all, fields(path = %anonymize(path)))]
+    fn read_metadata(&self, path: &Path) -> Result<Manifest, MetadataReadError> {
+        let mut manifests = self.read_all_metadata(path)?;
+        let mut manifest = manifests.pop_front().ok_or(ManifestParseError::NoEntry)?;
+        manifest.others = manifests.into_iter().map(DeclaredMod::from).collect();
+        Ok(manifest)
+    }
+}
+
+impl LocalMetadataReader {
+    /// Reads all entries of the manifest, a manifest may declare multiple mods.
+    pub(super) fn read_all_metadata(
+        &self,
+        path: &Path,
+    ) -> Result<VecDeque<Manifest>, MetadataReadError> {
+        let bytes = if path.is_dir() {
+            read_unpacked_manifest(path)?
+        } else {
+            read_archived_manifest(path)?
+        };
+        let (manifests, repairs) = Manifest::parse_lenient(&bytes)?;
+        if !repairs.is_empty() {
+            let repairs: Vec<String> = repairs.iter().map(Repair::to_string).collect();
+            warn!(
+                "{}: everest.yaml is read after fixing {}",
+                anonymize(path),
+                repairs.join(", ")
+            );
+        }
+        Ok(manifests)
+    }
+}
+
+/// Reads the manifest in the archive.
+fn read_archived_manifest(path: &Path) -> Result<Vec<u8>, MetadataReadError> {
+    let [name, alt_name] = MANIFEST_NAMES.map(str::as_bytes);
+    let (bytes, info) = zip_finder::extract_file_from_zip_with(
+        path,
+        name,
+        Some(alt_name),
+        // NOTE Some mods ship the manifest in a nested folder, e.g. `SomeFolder/everest.yaml`
+        &ExtractOptions::new()
+            .max_buffer(MAX_SCAN_BUFFER)
+            .match_basename(true),
+    )?;
+    debug!(
+        name = %String::from_utf8_lossy(info.name()),
+        size = info.uncompressed_size(),
+        crc32 = format_args!("{:08x}", info.crc32()),
+        "extracted manifest"
+    );
+    Ok(bytes)
+}
+
+/// Finds the entry of the manifest, the same one [`LocalMetadataReader`] reads.
+///
+/// The names at the root are preferred, then the nested ones in archives.
+pub(super) fn find_manifest(contents: &ModContents) -> Option<&[u8]> {
+    let names = contents.names();
+    let candidates = MANIFEST_NAMES.map(str::as_bytes);
+    let find = |matches: fn(&[u8], &[u8]) -> bool| {
+        candidates
+            .iter()
+            .find_map(|candidate| names.iter().copied().find(|name| matches(name, candidate)))
+    };
+    find(|name, candidate| name == candidate).or_else(|| {
+        // NOTE Everest reads only the root of the unpacked mods
+        matches!(contents, ModContents::Archive(_))
+            .then(|| find(|name, candidate| basename(name) == candidate))
+            .flatten()
+    })
+}
+
+/// Reads the manifest at the root of the unpacked mod, Everest does not look into the folders.
+fn read_unpacked_manifest(dir: &Path) -> Result<Vec<u8>, MetadataReadError> {
+    for name in MANIFEST_NAMES {
+        match fs::read(dir.join(name)) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            result => return Ok(result.map_err(zip_finder::Error::from)?),
+        }
+    }
+    Err(ManifestParseError::NoEntry.into())
+}
+
+#[cfg(test)]
+mod tests_metadata_reader {
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::local::write_zip;
+
+    #[test]
+    fn test_read_nested_manifest() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("nested.zip");
+        write_zip(
+            &path,
+            &[
+                ("SomeFolder/Maps/map.bin", ""),
+                (
+                    "SomeFolder/everest.yaml",
+                    "- Name: Nested\n  Version: 1.0.0\n",
+                ),
+            ],
+        )?;
+
+        let manifest = LocalMetadataReader.read_metadata(&path)?;
+        assert_eq!(manifest.name, "Nested");
+        Ok(())
+    }
+
+    #[test]
+    fn test_root_manifest_is_preferred() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("both.zip");
+        write_zip(
+            &path,
+            &[
+                ("Old/everest.yaml", "- Name: Old\n  Version: 0.1.0\n"),
+                ("everest.yml", "- Name: Root\n  Version: 1.0.0\n"),
+            ],
+        )?;
+
+        let manifest = LocalMetadataReader.read_metadata(&path)?;
+        assert_eq!(manifest.name, "Root");
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_all_entries() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("multi.zip");
+        write_zip(
+            &path,
+            &[(
+                "everest.yaml",
+                "- Name: First\n  Version: 1.0.0\n- Name: Second\n  Version: 1.0.0\n",
+            )],
+        )?;
+
+        let names: Vec<String> = LocalMetadataReader
+            .read_all_metadata(&path)?
+            .into_iter()
+            .map(|manifest| manifest.name)
+            .collect();
+        assert_eq!(names, ["First", "Second"]);
+
+        let manifest = LocalMetadataReader.read_metadata(&path)?;
+        assert_eq!(manifest.name, "First");
+        let others: Vec<&str> = manifest.others.iter().map(DeclaredMod::name).collect();
+        assert_eq!(others, ["Second"]);
+
+        // HTML error page served by a mirror
+        std::fs::write(&path, "<html><body>Not Found</body></html>")?;
+        assert!(LocalMetadataReader.read_all_metadata(&path).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_malformed() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("malformed.zip");
+        write_zip(
+            &path,
+            &[(
+                "everest.yaml",
+                "- Name: Malformed\n\tVersion: 0.1.0\n\tVersion: 1.0.0\n",
+            )],
+        )?;
+        let manifest = LocalMetadataReader.read_metadata(&path)?;
+        assert_eq!(manifest.name, "Malformed");
+        assert_eq!(manifest.version, "1.0.0");
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_unpacked() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let unpacked = dir.path().join("Unpacked");
+        std::fs::create_dir_all(unpacked.join("Nested"))?;
+        std::fs::write(
+            unpacked.join("Nested/everest.yaml"),
+            "- Name: Nested\n  Version: 1.0.0\n",
+        )?;
+        let e = LocalMetadataReader.read_metadata(&unpacked).unwrap_err();
+        assert!(e.is_missing_manifest());
+
+        std::fs::write(
+            unpacked.join("everest.yml"),
+            "\u{feff}- Name: Unpacked\n  Version: 1.0.0\n",
+        )?;
+        assert_eq!(
+            LocalMetadataReader.read_metadata(&unpacked)?.name,
+            "Unpacked"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_manifest() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("both.zip");
+        write_zip(&path, &[("Old/everest.yaml", ""), ("everest.yml", "")])?;
+        let contents = ModContents::open(&path)?;
+        assert_eq!(find_manifest(&contents), Some(b"everest.yml".as_slice()));
+
+        write_zip(&path, &[("SomeFolder\\everest.yml", "")])?;
+        let contents = ModContents::open(&path)?;
+        assert_eq!(
+            find_manifest(&contents),
+            Some(b"SomeFolder\\everest.yml".as_slice())
+        );
+
+        let unpacked = dir.path().join("Unpacked");
+        std::fs::create_dir_all(unpacked.join("Nested"))?;
+        std::fs::write(unpacked.join("Nested/everest.yaml"), "")?;
+        assert_eq!(find_manifest(&ModContents::open(&unpacked)?), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_missing_manifest() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("maps.zip");
+        write_zip(&path, &[("Maps/map.bin", "")])?;
+        let e = LocalMetadataReader.read_metadata(&path).unwrap_err();
+        assert!(e.is_missing_manifest());
+
+        write_zip(&path, &[("everest.yaml", "- Name: [broken\n")])?;
+        let e = LocalMetadataReader.read_metadata(&path).unwrap_err();
+        assert!(!e.is_missing_manifest());
+
+        std::fs::write(&path, "<html><body>Not Found</body></html>")?;
+        let e = LocalMetadataReader.read_metadata(&path).unwrap_err();
+        assert!(!e.is_missing_manifest());
+        Ok(())
+    }
+}
@@ -0,0 +1,227 @@
+//! Repair of the malformed `everest.yaml` found in the mods on GameBanana.
+//!
+//! The YAML parser rejects the whole file for a single mistake, so the mod would be skipped.
+//! Only the mistakes which have one obvious meaning are fixed, line by line.
+use std::{collections::HashMap, fmt};
+
+const BOM: char = '\u{feff}';
+
+/// Mistake fixed in the manifest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Repair {
+    /// BOM in the middle of the file, e.g. left by concatenating two manifests.
+    StrayBom,
+    /// Tabs in the indentation, which YAML forbids.
+    Tabs,
+    /// Keys repeated in the same mapping, the last one wins.
+    DuplicateKeys,
+    /// Plain values starting with `@` or `` ` ``, which YAML reserves.
+    ReservedIndicators,
+}
+
+impl fmt::Display for Repair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            Self::StrayBom => "a BOM in the middle",
+            Self::Tabs => "tabs in the indentation",
+            Self::DuplicateKeys => "duplicate keys",
+            Self::ReservedIndicators => "values starting with `@` or `` ` ``",
+        };
+        f.write_str(description)
+    }
+}
+
+/// Fixes the mistakes in the manifest, returns `None` if none is found.
+pub fn repair(text: &str) -> Option<(String, Vec<Repair>)> {
+    let mut repairs = Vec::new();
+    let mut text = text.trim_start_matches(BOM).to_string();
+    if text.contains(BOM) {
+        text = text.replace(BOM, "");
+        repairs.push(Repair::StrayBom);
+    }
+
+    let mut lines: Vec<String> = text.lines().map(String::from).collect();
+    if lines
+        .iter()
+        .any(|line| line.trim_start_matches(' ').starts_with('\t'))
+    {
+        lines.iter_mut().for_each(expand_tabs);
+        repairs.push(Repair::Tabs);
+    }
+    let quoted = lines
+        .iter_mut()
+        .map(quote_reserved)
+        .filter(|&quoted| quoted);
+    if quoted.count() > 0 {
+        repairs.push(Repair::ReservedIndicators);
+    }
+    if let Some(deduped) = drop_duplicate_keys(&lines) {
+        lines = deduped;
+        repairs.push(Repair::DuplicateKeys);
+    }
+
+    (!repairs.is_empty()).then(|| (lines.join("\n") + "\n", repairs))
+}
+
+/// Replaces the tabs in the indentation with two spaces each, the usual width in manifests.
+fn expand_tabs(line: &mut String) {
+    let indent_len = line.len() - line.trim_start_matches([' ', '\t']).len();
+    let indent = line[..indent_len].replace('\t', "  ");
+    line.replace_range(..indent_len, &indent);
+}
+
+/// Quotes the value of the key if it starts with a reserved indicator.
+fn quote_reserved(line: &mut String) -> bool {
+    let Some(key_line) = KeyLine::parse(line) else {
+        return false;
+    };
+    if !key_line.value.starts_with(['@', '`']) {
+        return false;
+    }
+    let quoted = format!(
+        "\"{}\"",
+        key_line.value.replace('\\', "\\\\").replace('"', "\\\"")
+    );
+    let start = line.len() - line.trim_start().len();
+    let value_start = line[start..]
+        .find(key_line.value)
+        .map_or(line.len(), |i| start + i);
+    line.replace_range(value_start.., &quoted);
+    true
+}
+
+/// Drops the keys repeated later in the same mapping with their nested lines.
+fn drop_duplicate_keys(lines: &[String]) -> Option<Vec<String>> {
+    // (column of the keys, key to the line), the innermost mapping last
+    let mut scopes: Vec<(usize, HashMap<&str, usize>)> = Vec::new();
+    let mut dropped = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        let Some(key_line) = KeyLine::parse(line) else {
+            continue;
+        };
+        let column = key_line.column;
+        // NOTE a sequence item starts another mapping at the same column
+        while scopes
+            .last()
+            .is_some_and(|(c, _)| *c > column || (key_line.item && *c == column))
+        {
+            scopes.pop();
+        }
+        if scopes.last().is_none_or(|(c, _)| *c != column) {
+            scopes.push((column, HashMap::new()));
+        }
+        if let Some((_, keys)) = scopes.last_mut()
+            && let Some(previous) = keys.insert(key_line.key, i)
+        {
+            dropped.push(previous);
+        }
+    }
+    if dropped.is_empty() {
+        return None;
+    }
+
+    let mut kept: Vec<Option<String>> = lines.iter().cloned().map(Some).collect();
+    for i in dropped {
+        let Some(key_line) = KeyLine::parse(&lines[i]) else {
+            continue;
+        };
+        let column = key_line.column;
+        // NOTE keep the dash of the item, the following keys become the mapping of the item
+        kept[i] = key_line
+            .item
+            .then(|| lines[i][..column].trim_end().to_string());
+        for (line, kept) in lines.iter().zip(kept.iter_mut()).skip(i + 1) {
+            let content = line.trim_start();
+            let indent = line.len() - content.len();
+            let nested = indent > column
+                || (indent == column && (content == "-" || content.starts_with("- ")));
+            if content.is_empty() || content.starts_with('#') {
+                continue;
+            }
+            if !nested {
+                break;
+            }
+            *kept = None;
+        }
+    }
+    Some(kept.into_iter().flatten().collect())
+}
+
+/// Line of a key in a block mapping, e.g. `  - Name: Foo`.
+struct KeyLine<'a> {
+    /// Column of the key.
+    column: usize,
+    /// Whether the key starts a sequence item.
+    item: bool,
+    key: &'a str,
+    value: &'a str,
+}
+
+impl<'a> KeyLine<'a> {
+    fn parse(line: &'a str) -> Option<Self> {
+        let mut rest = line.trim_start_matches(' ');
+        let mut item = false;
+        while let Some(after) = rest.strip_prefix("- ") {
+            rest = after.trim_start_matches(' ');
+            item = true;
+        }
+        let (key, value) = rest.split_once(':')?;
+        let is_key = !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !is_key || !(value.is_empty() || value.starts_with([' ', '\t'])) {
+            return None;
+        }
+        Some(Self {
+            column: line.len() - rest.len(),
+            item,
+            key,
+            value: value.trim(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests_repair {
+    use super::*;
+
+    #[test]
+    fn test_nothing_to_repair() {
+        assert!(repair("\u{feff}- Name: Foo\n  Version: 1.0.0\n").is_none());
+    }
+
+    #[test]
+    fn test_repair_tabs_and_boms() {
+        let (text, repairs) =
+            repair("- Name: Foo\n\tVersion: 1.0.0\n\u{feff}- Name: Bar\n\tVersion:\t1.0.0\n")
+                .expect("should be repaired");
+        assert_eq!(
+            text,
+            "- Name: Foo\n  Version: 1.0.0\n- Name: Bar\n  Version:\t1.0.0\n"
+        );
+        assert_eq!(repairs, [Repair::StrayBom, Repair::Tabs]);
+    }
+
+    #[test]
+    fn test_repair_duplicate_keys() {
+        let (text, repairs) = repair(
+            "- Name: Foo\n  Version: 0.1.0\n  Dependencies:\n  - Name: Old\n  Version: 1.0.0\n  Dependencies:\n    - Name: Helper\n      Version: 1.0.0\n      Version: 1.2.0\n- Name: Bar\n  Version: 1.0.0\n",
+        )
+        .expect("should be repaired");
+        assert_eq!(
+            text,
+            "- Name: Foo\n  Version: 1.0.0\n  Dependencies:\n    - Name: Helper\n      Version: 1.2.0\n- Name: Bar\n  Version: 1.0.0\n"
+        );
+        assert_eq!(repairs, [Repair::DuplicateKeys]);
+
+        // the first key of the item
+        let (text, _) = repair("- Name: Foo\n  Name: Bar\n  Version: 1.0.0\n").unwrap();
+        assert_eq!(text, "-\n  Name: Bar\n  Version: 1.0.0\n");
+    }
+
+    #[test]
+    fn test_repair_reserved_indicators() {
+        let (text, repairs) =
+            repair("- Name: @Foo\n  Version: `1.0\"`\n").expect("should be repaired");
+        assert_eq!(text, "- Name: \"@Foo\"\n  Version: \"`1.0\\\"`\"\n");
+        assert_eq!(repairs, [Repair::ReservedIndicators]);
+    }
+}
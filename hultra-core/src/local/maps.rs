@@ -0,0 +1,141 @@
+//! Maps shipped inside the mods, named by the dialog of the mod.
+//!
+//! A map is `Maps/{SID}.bin`, and its name is the value of the dialog key of the SID, see
+//! [`dialog::sid_key`].
+use std::path::Path;
+
+use rayon::prelude::*;
+use tracing::warn;
+
+use crate::{
+    LocalMod,
+    local::{
+        contents::ModContents,
+        dialog::{self, Dialog},
+    },
+    log::anonymize,
+};
+
+const MAPS_DIR: &str = "Maps/";
+const MAP_EXTENSION: &str = ".bin";
+
+/// A map found in the archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MapInfo {
+    /// Path of the map without `Maps/` and `.bin`, e.g. `Author/0-Lobbies/Lobby`.
+    pub sid: String,
+    /// Name shown in the chapter select, if the dialog has it.
+    pub name: Option<String>,
+}
+
+/// Maps of the installed mod.
+#[derive(Debug)]
+pub struct ModMaps<'a> {
+    pub installed: &'a LocalMod,
+    pub maps: Vec<MapInfo>,
+}
+
+/// Lists the maps of the installed mods in parallel, skipping the mods without maps.
+pub fn list_installed_maps<'a>(mods: &'a [LocalMod], language: &str) -> Vec<ModMaps<'a>> {
+    mods.par_iter()
+        .filter_map(|installed| {
+            let path = installed.file().path();
+            let maps = list_maps(path, language)
+                .inspect_err(|e| warn!("{}: {}", anonymize(path), e))
+                .ok()?;
+            (!maps.is_empty()).then_some(ModMaps { installed, maps })
+        })
+        .collect()
+}
+
+/// Lists the maps of the mod sorted by SID, with the names in the dialog of the language.
+pub fn list_maps(path: &Path, language: &str) -> Result<Vec<MapInfo>, zip_finder::Error> {
+    let mut contents = ModContents::open(path)?;
+
+    let mut sids: Vec<String> = contents
+        .names()
+        .into_iter()
+        .map(String::from_utf8_lossy)
+        .filter_map(|name| map_sid(&name).map(String::from))
+        .collect();
+    if sids.is_empty() {
+        return Ok(Vec::new());
+    }
+    sids.sort();
+    sids.dedup();
+
+    let dialog = Dialog::read(&mut contents, language)?;
+
+    Ok(sids
+        .into_iter()
+        .map(|sid| MapInfo {
+            // NOTE the following lines of the name are the subtitles, e.g. in collab lobbies
+            name: dialog
+                .get(&dialog::sid_key(&sid))
+                .and_then(|name| name.lines().next())
+                .map(String::from),
+            sid,
+        })
+        .collect())
+}
+
+/// Returns the SID of the entry if it is a map.
+fn map_sid(name: &str) -> Option<&str> {
+    let sid = name.strip_prefix(MAPS_DIR)?.strip_suffix(MAP_EXTENSION)?;
+    (!sid.is_empty() && !sid.ends_with('/')).then_some(sid)
+}
+
+#[cfg(test)]
+mod tests_maps {
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::local::write_zip;
+
+    #[test]
+    fn test_map_sid() {
+        assert_eq!(map_sid("Maps/Author/1-Foo.bin"), Some("Author/1-Foo"));
+        assert_eq!(map_sid("Maps/Author/1-Foo.meta.yaml"), None);
+        assert_eq!(map_sid("Graphics/Maps/Foo.bin"), None);
+        assert_eq!(map_sid("Maps/.bin"), None);
+    }
+
+    #[test]
+    fn test_list_maps() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("Campaign.zip");
+        write_zip(
+            &path,
+            &[
+                ("everest.yaml", "- Name: Campaign\n  Version: 1.0.0\n"),
+                ("Maps/Author/Campaign/2-Bar.bin", ""),
+                ("Maps/Author/Campaign/1-Foo.bin", ""),
+                ("Maps/Author/Campaign/1-Foo.meta.yaml", ""),
+                (
+                    "dialog/english.txt",
+                    "\u{feff}Author_Campaign_1_Foo= Foo Valley\n",
+                ),
+            ],
+        )?;
+        assert_eq!(
+            list_maps(&path, dialog::DEFAULT_LANGUAGE)?,
+            [
+                MapInfo {
+                    sid: "Author/Campaign/1-Foo".into(),
+                    name: Some("Foo Valley".into()),
+                },
+                MapInfo {
+                    sid: "Author/Campaign/2-Bar".into(),
+                    name: None,
+                },
+            ]
+        );
+
+        write_zip(
+            &path,
+            &[("everest.yaml", "- Name: Helper\n  Version: 1.0.0\n")],
+        )?;
+        assert!(list_maps(&path, dialog::DEFAULT_LANGUAGE)?.is_empty());
+        Ok(())
+    }
+}
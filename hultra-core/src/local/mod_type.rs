@@ -0,0 +1,106 @@
+//! Types of the mods, told from the manifest and the entries of the archive.
+use std::{fmt, path::Path};
+
+use rkyv::{Archive, Deserialize, Serialize};
+
+use crate::local::contents::ModContents;
+
+/// Config of SkinModHelper which only skin mods ship, in lowercase.
+const SKIN_CONFIG: &[u8] = b"skinmodhelperconfig.yaml";
+
+/// Sprites of Madeline replaced by the skin mods without SkinModHelper, in lowercase.
+const PLAYER_SPRITES: &[u8] = b"graphics/atlases/gameplay/characters/player/";
+
+/// Type of the mod. A map with its own code is a map, since it is played rather than required.
+#[derive(Archive, Deserialize, Serialize, serde::Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ModType {
+    /// Has `Maps/**/*.bin`.
+    Map,
+    /// Has code, i.e. `DLL` of the manifest or `*.dll` entries, e.g. helpers and tools.
+    Helper,
+    /// Replaces the sprites of Madeline.
+    Skin,
+    /// Anything else, e.g. graphics, audio, or dialog packs.
+    Assets,
+}
+
+impl fmt::Display for ModType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Map => "map",
+            Self::Helper => "helper",
+            Self::Skin => "skin",
+            Self::Assets => "assets",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Classifies the mod by its entries, `has_dll` is whether the manifest declares `DLL`.
+pub fn classify(path: &Path, has_dll: bool) -> Result<ModType, zip_finder::Error> {
+    let contents = ModContents::open(path)?;
+    Ok(classify_entries(contents.names().into_iter(), has_dll))
+}
+
+fn classify_entries<'a>(names: impl Iterator<Item = &'a [u8]>, has_dll: bool) -> ModType {
+    let (mut map, mut code, mut skin) = (false, has_dll, false);
+    for name in names {
+        let lower = name.to_ascii_lowercase();
+        map |= lower.starts_with(b"maps/") && lower.ends_with(b".bin");
+        code |= lower.ends_with(b".dll");
+        skin |= lower == SKIN_CONFIG || lower.starts_with(PLAYER_SPRITES);
+    }
+    match (map, code, skin) {
+        (true, _, _) => ModType::Map,
+        (_, true, _) => ModType::Helper,
+        (_, _, true) => ModType::Skin,
+        _ => ModType::Assets,
+    }
+}
+
+#[cfg(test)]
+mod tests_mod_type {
+    use super::*;
+
+    fn classify_names(names: &[&str], has_dll: bool) -> ModType {
+        classify_entries(names.iter().map(|name| name.as_bytes()), has_dll)
+    }
+
+    #[test]
+    fn test_classify_entries() {
+        assert_eq!(
+            classify_names(&["everest.yaml", "Maps/Me/1-Foo.bin", "Code/Foo.dll"], true),
+            ModType::Map
+        );
+        assert_eq!(classify_names(&["everest.yaml"], true), ModType::Helper);
+        assert_eq!(
+            classify_names(&["everest.yaml", "bin/Helper.DLL"], false),
+            ModType::Helper
+        );
+        assert_eq!(
+            classify_names(
+                &[
+                    "SkinModHelperConfig.yaml",
+                    "Graphics/Atlases/Gameplay/x.png"
+                ],
+                false
+            ),
+            ModType::Skin
+        );
+        assert_eq!(
+            classify_names(
+                &["graphics/atlases/gameplay/characters/player/idle00.png"],
+                false
+            ),
+            ModType::Skin
+        );
+        assert_eq!(
+            classify_names(
+                &["everest.yaml", "Audio/music.bank", "Maps/readme.txt"],
+                false
+            ),
+            ModType::Assets
+        );
+    }
+}
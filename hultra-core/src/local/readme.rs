@@ -0,0 +1,88 @@
+//! README and LICENSE files shipped inside mod archives.
+use std::path::Path;
+
+use zip_finder::ZipArchiveIndex;
+
+/// Stems of the file names treated as notices, compared case-insensitively.
+const NOTICE_STEMS: [&str; 4] = ["readme", "license", "licence", "credits"];
+
+/// Extensions of the file names treated as notices. An empty string means no extension.
+const NOTICE_EXTENSIONS: [&str; 3] = ["", "txt", "md"];
+
+/// A README or LICENSE file found in the archive.
+#[derive(Debug, Clone)]
+pub struct Notice {
+    /// Entry name in the archive.
+    pub name: String,
+    /// Decoded text of the entry.
+    pub text: String,
+}
+
+impl Notice {
+    /// Returns the first `n` lines and the number of remaining lines.
+    pub fn head(&self, n: usize) -> (Vec<&str>, usize) {
+        let mut lines = self.text.lines();
+        let head: Vec<&str> = lines.by_ref().take(n).collect();
+        (head, lines.count())
+    }
+}
+
+/// Checks if the entry at the root of the archive looks like a README or LICENSE.
+fn is_notice(name: &str) -> bool {
+    if name.contains('/') {
+        return false;
+    }
+    let (stem, ext) = name.rsplit_once('.').unwrap_or((name, ""));
+    NOTICE_STEMS.iter().any(|s| stem.eq_ignore_ascii_case(s))
+        && NOTICE_EXTENSIONS
+            .iter()
+            .any(|e| ext.eq_ignore_ascii_case(e))
+}
+
+/// Finds README and LICENSE files at the root of the archive.
+pub fn find_notices(path: &Path) -> Result<Vec<Notice>, zip_finder::Error> {
+    let mut index = ZipArchiveIndex::open(path)?;
+
+    let names: Vec<String> = index
+        .names()
+        .map(String::from_utf8_lossy)
+        .filter(|name| is_notice(name))
+        .map(|name| name.into_owned())
+        .collect();
+
+    let mut notices = Vec::with_capacity(names.len());
+    for name in names {
+        let bytes = index.extract(name.as_bytes())?;
+        // Remove UTF-8 BOM if present
+        let clean_slice = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]).unwrap_or(&bytes);
+        let text = String::from_utf8_lossy(clean_slice).into_owned();
+        notices.push(Notice { name, text });
+    }
+    Ok(notices)
+}
+
+#[cfg(test)]
+mod tests_readme {
+    use super::*;
+
+    #[test]
+    fn test_is_notice() {
+        assert!(is_notice("README.md"));
+        assert!(is_notice("readme.txt"));
+        assert!(is_notice("LICENSE"));
+        assert!(is_notice("Credits.txt"));
+        assert!(!is_notice("Graphics/README.md"));
+        assert!(!is_notice("everest.yaml"));
+        assert!(!is_notice("license.png"));
+    }
+
+    #[test]
+    fn test_head() {
+        let notice = Notice {
+            name: "README.md".into(),
+            text: "a\nb\nc\nd".into(),
+        };
+        assert_eq!(notice.head(2), (vec!["a", "b"], 2));
+        assert_eq!(notice.head(10), (vec!["a", "b", "c", "d"], 0));
+    }
+}
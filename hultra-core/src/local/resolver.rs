@@ -0,0 +1,195 @@
+//! Service for resolving installed mods.
+use std::{collections::HashSet, fs, io, marker::Sync, path::Path, sync::Mutex};
+
+use rayon::prelude::*;
+use tracing::{debug, info, instrument, warn};
+
+use crate::{
+    LocalMod,
+    cache::{self, CachedManifest, FileCacheDb, FileSnapshot},
+    config::ModsConfig,
+    local::{
+        ModFile,
+        manifest::{LocalMetadataReader, Manifest, MetadataReadError, MetadataReader},
+        mod_type, {LocalModFileSource, ModFileSource},
+    },
+    log::anonymize,
+    progress::{self, Task},
+};
+
+/// Scans installed mods.
+///
+/// Manifests of the archives unchanged since the last scan are read from the cache, so the
+/// archives are not opened at all.
+#[instrument(skip_all, fields(mods_dir = %anonymize(&config.mods_dir())))]
+pub fn scan_mods(config: &impl ModsConfig) -> io::Result<Vec<LocalMod>> {
+    let cache_db = cache::load(config);
+    let source = LocalModFileSource::new(config.mods_dir(), config.exclude());
+    let resolver = ModResolver::new(source, CachedMetadataReader::new(&cache_db));
+    let mods = resolver.resolve()?;
+
+    let read = resolver.reader.into_read();
+    let keys: HashSet<String> = mods
+        .iter()
+        .map(|m| cache::cache_key(m.file().path()))
+        .collect();
+    if (!read.is_empty() || cache_db.has_stale_manifests(&keys))
+        && let Err(e) = cache::save_manifests(config, read, &keys)
+    {
+        warn!(?e, "failed to save the manifests to the cache");
+    }
+    Ok(mods)
+}
+
+/// Reads the mod from `everest.yaml` of the archive.
+pub fn read_mod(path: &Path) -> Result<LocalMod, MetadataReadError> {
+    let manifest = LocalMetadataReader.read_metadata(path)?;
+    let file = ModFile::from(path.to_path_buf());
+    Ok(LocalMod::new(file, manifest.name, manifest.version)
+        .with_dependencies(manifest.dependencies)
+        .with_others(manifest.others))
+}
+
+/// Reads the names of all mods declared by `everest.yaml` of the archive.
+pub fn read_mod_names(path: &Path) -> Result<Vec<String>, MetadataReadError> {
+    let manifests = LocalMetadataReader.read_all_metadata(path)?;
+    Ok(manifests
+        .into_iter()
+        .map(|manifest| manifest.name)
+        .collect())
+}
+
+/// Reads the manifests from the cache if the archives are unchanged, or from the archives.
+struct CachedMetadataReader<'a> {
+    cache_db: &'a FileCacheDb,
+    /// Manifests read from the archives, with the key and the snapshot of the files.
+    read: Mutex<Vec<(String, FileSnapshot, CachedManifest)>>,
+}
+
+impl<'a> CachedMetadataReader<'a> {
+    fn new(cache_db: &'a FileCacheDb) -> Self {
+        Self {
+            cache_db,
+            read: Mutex::default(),
+        }
+    }
+
+    fn into_read(self) -> Vec<(String, FileSnapshot, CachedManifest)> {
+        self.read.into_inner().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl MetadataReader for CachedMetadataReader<'_> {
+    fn read_metadata(&self, path: &Path) -> Result<Manifest, MetadataReadError> {
+        let key = cache::cache_key(path);
+        // NOTE the manifest in the unpacked mod is edited without touching the directory
+        let snapshot = fs::metadata(path)
+            .ok()
+            .filter(|meta| meta.is_file())
+            .and_then(|meta| FileSnapshot::new(&meta).ok());
+        if let Some(snapshot) = &snapshot
+            && let Some(cached) = self.cache_db.manifest(&key, snapshot)
+        {
+            let (name, version, dependencies, mod_type, others) = cached.clone().into_parts();
+            return Ok(Manifest {
+                name,
+                version,
+                dependencies,
+                dll: None,
+                mod_type: Some(mod_type),
+                others,
+            });
+        }
+
+        let mut manifest = LocalMetadataReader.read_metadata(path)?;
+        manifest.mod_type = mod_type::classify(path, manifest.dll.is_some())
+            .inspect_err(|e| debug!(?e, "failed to classify {}", anonymize(path)))
+            .ok();
+        if let Some(snapshot) = snapshot
+            && let Some(mod_type) = manifest.mod_type
+        {
+            let cached = CachedManifest::new(
+                manifest.name.clone(),
+                manifest.version.clone(),
+                manifest.dependencies.clone(),
+                mod_type,
+                manifest.others.clone(),
+            );
+            let mut read = self.read.lock().unwrap_or_else(|e| e.into_inner());
+            read.push((key, snapshot, cached));
+        }
+        Ok(manifest)
+    }
+}
+
+/// A service to resolve locally installed mods.
+#[derive(Debug)]
+struct ModResolver<S: ModFileSource, R: MetadataReader> {
+    /// Mods directory scanner.
+    source: S,
+    /// Manifest reader.
+    reader: R,
+}
+
+impl<S: ModFileSource, R: MetadataReader + Sync> ModResolver<S, R> {
+    fn new(source: S, reader: R) -> Self {
+        Self { source, reader }
+    }
+
+    /// Resolves a list of installed mods.
+    ///
+    /// Archives without a manifest are valid, e.g. map-only mods, so only the broken ones are
+    /// warned. Both are counted in the summary.
+    fn resolve(&self) -> io::Result<Vec<LocalMod>> {
+        let files = self.source.fetch_all()?;
+        let total = files.len();
+        let reader = &self.reader;
+        let pb = progress::start(Task::Scanning, total as u64);
+        let results: Vec<_> = files
+            .into_par_iter()
+            .map(|file| {
+                let result = reader.read_metadata(file.path());
+                pb.inc(1);
+                match result {
+                    Ok(manifest) => Ok(LocalMod::new(file, manifest.name, manifest.version)
+                        .with_dependencies(manifest.dependencies)
+                        .with_mod_type(manifest.mod_type)
+                        .with_others(manifest.others)),
+                    Err(e) => Err((file, e)),
+                }
+            })
+            .collect();
+        pb.finish();
+
+        let mut mods = Vec::with_capacity(total);
+        let (mut no_manifest, mut broken) = (0, 0);
+        for result in results {
+            match result {
+                Ok(local_mod) => mods.push(local_mod),
+                Err((file, e)) if e.is_missing_manifest() => {
+                    debug!("{} has no manifest", anonymize(file.path()));
+                    no_manifest += 1;
+                }
+                Err((file, e)) => {
+                    warn!("{}: {}", anonymize(file.path()), e);
+                    broken += 1;
+                }
+            }
+        }
+        if broken > 0 {
+            warn!(
+                "skipped {} of {} archives: {} broken, {} without everest.yaml, run `hultra doctor` for details",
+                broken + no_manifest,
+                total,
+                broken,
+                no_manifest
+            );
+        } else if no_manifest > 0 {
+            info!(
+                "skipped {} of {} archives without everest.yaml",
+                no_manifest, total
+            );
+        }
+        Ok(mods)
+    }
+}
@@ -0,0 +1,77 @@
+//! Disk usage of the Mods directory and the installed mods.
+use std::{fs, io, path::Path};
+
+use crate::LocalMod;
+
+/// Total size of the files under the directory.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DiskUsage {
+    pub bytes: u64,
+    pub files: u64,
+}
+
+/// Sums the sizes of the files under the directory recursively, without following symlinks.
+pub fn dir_usage(dir: &Path) -> io::Result<DiskUsage> {
+    let mut usage = DiskUsage::default();
+    for entry in fs::read_dir(dir)?.flatten() {
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() {
+            let nested = dir_usage(&entry.path())?;
+            usage.bytes += nested.bytes;
+            usage.files += nested.files;
+        } else if file_type.is_file() {
+            usage.bytes += entry.metadata().map(|m| m.len()).unwrap_or_default();
+            usage.files += 1;
+        }
+    }
+    Ok(usage)
+}
+
+/// Returns the size of the mod, the archive read by the scan or the files of the unpacked mod.
+pub fn mod_size(installed: &LocalMod) -> u64 {
+    match installed.file().size() {
+        Some(size) => size,
+        None => dir_usage(installed.file().path())
+            .map(|usage| usage.bytes)
+            .unwrap_or_default(),
+    }
+}
+
+/// Returns the largest mods, the largest first.
+pub fn largest(mods: &[LocalMod], count: usize) -> Vec<(&LocalMod, u64)> {
+    let mut sizes: Vec<(&LocalMod, u64)> = mods.iter().map(|m| (m, mod_size(m))).collect();
+    sizes.sort_by(|(a, a_size), (b, b_size)| {
+        b_size.cmp(a_size).then_with(|| a.name().cmp(b.name()))
+    });
+    sizes.truncate(count);
+    sizes
+}
+
+#[cfg(test)]
+mod tests_usage {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_dir_usage() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        fs::create_dir_all(dir.path().join("Unpacked/Maps"))?;
+        fs::write(dir.path().join("Foo.zip"), [0; 100])?;
+        fs::write(dir.path().join("Unpacked/everest.yaml"), [0; 20])?;
+        fs::write(dir.path().join("Unpacked/Maps/1-Foo.bin"), [0; 3])?;
+
+        let usage = dir_usage(dir.path())?;
+        assert_eq!(
+            usage,
+            DiskUsage {
+                bytes: 123,
+                files: 3
+            }
+        );
+        assert_eq!(dir_usage(&dir.path().join("Unpacked"))?.bytes, 23);
+        Ok(())
+    }
+}
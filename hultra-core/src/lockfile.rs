@@ -0,0 +1,317 @@
+//! `hultra.lock`, the exact set of the installed mods to reproduce it on another machine.
+//!
+//! Each archive is locked by its XXH64, with the URL to download the same file again. The
+//! lockfile is kept in the Mods directory, which is not scanned for anything but mods.
+//!
+//! NOTE unpacked mods are never locked, they cannot be downloaded nor hashed.
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use tempfile::NamedTempFile;
+use tracing::debug;
+
+use crate::{
+    Checksum, LocalMod,
+    cache::{self, FileCacheDb},
+    config::ModsConfig,
+    log::anonymize,
+    registry::ModRegistry,
+};
+
+/// File name of the lockfile in the Mods directory.
+pub const FILE_NAME: &str = "hultra.lock";
+
+/// Version of the format, bumped when an older hultra cannot read it correctly.
+const FORMAT_VERSION: u32 = 1;
+
+const HEADER: &str = "# This file is generated by hultra, do not edit it by hand.\n";
+
+#[derive(Debug, thiserror::Error)]
+pub enum LockfileError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("failed to parse the lockfile")]
+    Parse(#[from] serde_yaml_ng::Error),
+    #[error("the lockfile is of format version {0}, update hultra to read it")]
+    UnsupportedVersion(u32),
+}
+
+/// Returns the path to the lockfile of the installed mods.
+pub fn path(config: &impl ModsConfig) -> PathBuf {
+    config.mods_dir().join(FILE_NAME)
+}
+
+/// The installed archives, sorted by the mod name.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lockfile {
+    version: u32,
+    mods: Vec<LockedMod>,
+}
+
+/// An archive in the Mods directory.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedMod {
+    /// Name of the mod, the first one in `everest.yaml`.
+    pub name: String,
+    pub version: String,
+    /// File name of the archive.
+    pub file: String,
+    /// File size of the archive.
+    pub size: u64,
+    pub xxhash: Checksum,
+    /// Download URL of the same file, `None` if the file is not found in the registry.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+impl Lockfile {
+    /// Locks the archives hashed in `cache_db`, the other ones are skipped.
+    ///
+    /// The URL is looked up in the registry if the archive is the latest one, or taken over
+    /// from the `previous` lockfile if the archive is unchanged.
+    pub fn new(
+        mods: &[LocalMod],
+        cache_db: &FileCacheDb,
+        registry: Option<&ModRegistry>,
+        previous: Option<&Lockfile>,
+    ) -> Self {
+        let mut mods: Vec<LockedMod> = mods
+            .iter()
+            .filter(|m| !m.file().is_dir())
+            .filter_map(|m| {
+                let path = m.file().path();
+                let Some(hash) = cache_db.hash(&cache::cache_key(path)) else {
+                    debug!("{} is not hashed, skipping", anonymize(path));
+                    return None;
+                };
+                let latest = registry
+                    .and_then(|registry| registry.get(m.name()))
+                    .filter(|entry| entry.checksums().is_ok_and(|c| c.contains(&hash)))
+                    .map(|entry| entry.url().to_string());
+                let url = latest.or_else(|| {
+                    previous?
+                        .mods
+                        .iter()
+                        .find(|locked| locked.name == m.name() && locked.xxhash.0 == hash)
+                        .and_then(|locked| locked.url.clone())
+                });
+                Some(LockedMod {
+                    name: m.name().to_string(),
+                    version: m.version().to_string(),
+                    file: file_name(path),
+                    size: m
+                        .file()
+                        .size()
+                        .or_else(|| fs::metadata(path).ok().map(|meta| meta.len()))
+                        .unwrap_or_default(),
+                    xxhash: Checksum(hash),
+                    url,
+                })
+            })
+            .collect();
+        mods.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.file.cmp(&b.file)));
+        Self {
+            version: FORMAT_VERSION,
+            mods,
+        }
+    }
+
+    pub fn mods(&self) -> &[LockedMod] {
+        &self.mods
+    }
+
+    /// Loads the lockfile at the path.
+    pub fn load(path: &Path) -> Result<Self, LockfileError> {
+        let bytes = fs::read(path)?;
+        let lockfile: Self = serde_yaml_ng::from_slice(&bytes)?;
+        if lockfile.version > FORMAT_VERSION {
+            return Err(LockfileError::UnsupportedVersion(lockfile.version));
+        }
+        Ok(lockfile)
+    }
+
+    /// Saves the lockfile via a temporary file, so a broken one is never left.
+    pub fn save(&self, path: &Path) -> Result<(), LockfileError> {
+        let dir = path.parent().unwrap_or(Path::new("."));
+        let mut temp = NamedTempFile::new_in(dir)?;
+        let yaml = serde_yaml_ng::to_string(self)?;
+        io::Write::write_all(temp.as_file_mut(), format!("{}{}", HEADER, yaml).as_bytes())?;
+        temp.as_file().sync_all()?;
+        temp.persist(path).map_err(|e| e.error)?;
+        Ok(())
+    }
+
+    /// Compares the installed mods with the lockfile, by the name and XXH64 of the archives.
+    ///
+    /// A locked mod installed as a directory is taken as it is.
+    pub fn plan<'a>(&'a self, mods: &'a [LocalMod], cache_db: &FileCacheDb) -> SyncPlan<'a> {
+        let mut installed: HashMap<&str, Vec<(&LocalMod, Option<u64>)>> = HashMap::new();
+        for m in mods {
+            let hash = cache_db.hash(&cache::cache_key(m.file().path()));
+            installed.entry(m.name()).or_default().push((m, hash));
+        }
+
+        let mut plan = SyncPlan::default();
+        for locked in &self.mods {
+            let Some(mut candidates) = installed.remove(locked.name.as_str()) else {
+                plan.missing.push(locked);
+                continue;
+            };
+            let found = candidates.iter().position(|(m, hash)| {
+                *hash == Some(locked.xxhash.0) || (hash.is_none() && m.file().is_dir())
+            });
+            match found {
+                Some(i) => {
+                    candidates.remove(i);
+                    plan.unchanged += 1;
+                }
+                None => plan.changed.push((locked, candidates.remove(0).0)),
+            }
+            plan.extraneous
+                .extend(candidates.into_iter().map(|(m, _)| m));
+        }
+        plan.extraneous
+            .extend(installed.into_values().flatten().map(|(m, _)| m));
+        plan.extraneous.sort_by_key(|m| m.file().path());
+        plan
+    }
+}
+
+/// Differences of the installed mods from the lockfile.
+#[derive(Debug, Default)]
+pub struct SyncPlan<'a> {
+    /// Locked mods which are not installed.
+    pub missing: Vec<&'a LockedMod>,
+    /// Locked mods installed as another archive, with the installed one.
+    pub changed: Vec<(&'a LockedMod, &'a LocalMod)>,
+    /// Installed mods which are not locked.
+    pub extraneous: Vec<&'a LocalMod>,
+    /// Number of the locked mods installed as they are.
+    pub unchanged: usize,
+}
+
+impl SyncPlan<'_> {
+    /// Checks if the installed mods are exactly the locked ones.
+    pub fn is_synced(&self) -> bool {
+        self.missing.is_empty() && self.changed.is_empty() && self.extraneous.is_empty()
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests_lockfile {
+    use crate::{ModFile, local::ExcludePatterns};
+
+    use super::*;
+
+    fn archive(dir: &Path, file: &str, name: &str, version: &str, bytes: &[u8]) -> LocalMod {
+        let path = dir.join(file);
+        fs::write(&path, bytes).expect("should be written");
+        LocalMod::new(
+            ModFile::new_unchecked(path),
+            name.to_string(),
+            version.to_string(),
+        )
+    }
+
+    struct TestConfig {
+        mods_dir: PathBuf,
+        cache_db_path: PathBuf,
+        exclude: ExcludePatterns,
+    }
+
+    impl ModsConfig for TestConfig {
+        fn mods_dir(&self) -> PathBuf {
+            self.mods_dir.clone()
+        }
+        fn cache_db_path(&self) -> &Path {
+            &self.cache_db_path
+        }
+        fn exclude(&self) -> &ExcludePatterns {
+            &self.exclude
+        }
+    }
+
+    #[test]
+    fn test_lock_and_plan() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mods_dir = dir.path().join("Mods");
+        fs::create_dir(&mods_dir)?;
+        let config = TestConfig {
+            mods_dir: mods_dir.clone(),
+            cache_db_path: dir.path().join("checksum.cache"),
+            exclude: Default::default(),
+        };
+        let foo = archive(&mods_dir, "Foo.zip", "Foo", "1.0.0", b"foo");
+        let bar = archive(&mods_dir, "Bar.zip", "Bar", "2.0.0", b"bar");
+        let cache_db = cache::sync(&config, false)?;
+
+        let registry: ModRegistry = serde_yaml_ng::from_str(&format!(
+            "Foo:\n  GameBananaId: 1\n  Version: 1.0.0\n  URL: https://gamebanana.com/mmdl/2\n  Size: 3\n  xxHash: [{:016x}]\n",
+            cache_db.hash(&cache::cache_key(foo.file().path())).unwrap()
+        ))?;
+        let lockfile = Lockfile::new(
+            &[foo.clone(), bar.clone()],
+            &cache_db,
+            Some(&registry),
+            None,
+        );
+        let names: Vec<_> = lockfile.mods().iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, ["Bar", "Foo"]);
+        assert_eq!(lockfile.mods()[0].url, None);
+        assert_eq!(
+            lockfile.mods()[1].url.as_deref(),
+            Some("https://gamebanana.com/mmdl/2")
+        );
+
+        let path = path(&config);
+        lockfile.save(&path)?;
+        let loaded = Lockfile::load(&path)?;
+        assert_eq!(loaded, lockfile);
+        // NOTE the URL is kept as long as the archive is unchanged
+        let relocked = Lockfile::new(std::slice::from_ref(&foo), &cache_db, None, Some(&loaded));
+        assert_eq!(relocked.mods()[0].url, lockfile.mods()[1].url);
+
+        assert!(
+            loaded
+                .plan(&[foo.clone(), bar.clone()], &cache_db)
+                .is_synced()
+        );
+
+        fs::remove_file(bar.file().path())?;
+        let foo = archive(&mods_dir, "Foo.zip", "Foo", "1.1.0", b"foo v1.1.0");
+        let baz = archive(&mods_dir, "Baz.zip", "Baz", "1.0.0", b"baz");
+        let cache_db = cache::sync(&config, false)?;
+        let installed = [foo, baz];
+        let plan = loaded.plan(&installed, &cache_db);
+        assert_eq!(plan.missing, [&loaded.mods()[0]]);
+        assert_eq!(plan.changed.len(), 1);
+        assert_eq!(plan.changed[0].1.version(), "1.1.0");
+        assert_eq!(plan.extraneous.len(), 1);
+        assert_eq!(plan.extraneous[0].name(), "Baz");
+        assert_eq!(plan.unchanged, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_unsupported_version() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join(FILE_NAME);
+        fs::write(&path, "version: 2\nmods: []\n")?;
+        assert!(matches!(
+            Lockfile::load(&path),
+            Err(LockfileError::UnsupportedVersion(2))
+        ));
+        Ok(())
+    }
+}
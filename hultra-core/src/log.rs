@@ -0,0 +1,177 @@
+//! Helpers for the logs.
+use std::{
+    borrow::Cow,
+    path::{Component, Path},
+    sync::LazyLock,
+};
+
+/// Swaps user's home directory path with tilde.
+pub fn anonymize(path: &Path) -> String {
+    // 1. trying to detect home dir from env var
+    if let Some(home) = std::env::home_dir()
+        && let Ok(rel) = path.strip_prefix(&home)
+    {
+        return format!("~/{}", rel.display());
+    }
+
+    // 2. trying to guess it from path structure
+    let mut comps = path.components();
+    let root = comps.next();
+    let base = comps.next();
+    let user = comps.next();
+
+    match (root, base, user) {
+        (Some(Component::RootDir), Some(b), Some(_))
+            // NOTE prevent /etc/systemd/system becomes ~/system
+            if b.as_os_str() == "home" || b.as_os_str() == "Users" =>
+        {
+            let rest = comps.as_path();
+            if rest.as_os_str().is_empty() {
+                "~".to_string()
+            } else {
+                format!("~/{}", rest.display())
+            }
+        }
+        // 3. last resort: fallback to original
+        _ => path.to_string_lossy().into_owned(),
+    }
+}
+
+/// Home directory and user name of the current user, looked up once.
+static IDENTITY: LazyLock<Identity> = LazyLock::new(|| Identity {
+    home: std::env::home_dir()
+        .map(|home| home.to_string_lossy().into_owned())
+        .filter(|home| home.len() > 1),
+    user: ["USER", "USERNAME"]
+        .into_iter()
+        .find_map(|key| std::env::var(key).ok()),
+});
+
+struct Identity {
+    home: Option<String>,
+    user: Option<String>,
+}
+
+/// Directories of the users, followed by the user name.
+///
+/// NOTE the doubled backslashes are the Windows paths escaped in the JSON logs
+const USER_DIRS: [&str; 4] = ["/home/", "/Users/", "\\Users\\", "\\\\Users\\\\"];
+
+/// Redacts the home directories and the user name in the text of the logs.
+///
+/// Unlike [`anonymize`], it is applied to whole lines, so the paths and the names in the
+/// messages of other crates, e.g. the errors of the I/O, are redacted too.
+pub fn redact(text: &str) -> Cow<'_, str> {
+    redact_with(text, IDENTITY.home.as_deref(), IDENTITY.user.as_deref())
+}
+
+fn redact_with<'a>(text: &'a str, home: Option<&str>, user: Option<&str>) -> Cow<'a, str> {
+    let mut text = Cow::Borrowed(text);
+    if let Some(home) = home {
+        for home in [home.to_string(), home.replace('\\', "\\\\")] {
+            if text.contains(&home) {
+                text = Cow::Owned(text.replace(&home, "~"));
+            }
+        }
+    }
+    for dir in USER_DIRS {
+        if text.contains(dir) {
+            text = Cow::Owned(redact_user_dirs(&text, dir));
+        }
+    }
+    // NOTE short names like `me` would redact the words of the messages
+    if let Some(user) = user.filter(|user| user.len() >= 3)
+        && text.contains(user)
+    {
+        text = Cow::Owned(redact_word(&text, user, "<user>"));
+    }
+    text
+}
+
+/// Replaces the directories of the users with `~`, including the drive letter on Windows.
+fn redact_user_dirs(text: &str, dir: &str) -> String {
+    let mut redacted = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(dir) {
+        let after = &rest[start + dir.len()..];
+        let len = after
+            .find(|c: char| matches!(c, '/' | '\\' | '"' | '\'' | '`') || c.is_whitespace())
+            .unwrap_or(after.len());
+        let mut prefix = &rest[..start];
+        if dir.starts_with('\\')
+            && let [.., drive, b':'] = prefix.as_bytes()
+            && drive.is_ascii_alphabetic()
+        {
+            prefix = &prefix[..prefix.len() - 2];
+        }
+        // NOTE prevent /etc/home/foo becomes /etc~
+        let is_root = prefix
+            .chars()
+            .next_back()
+            .is_none_or(|c| !(c.is_alphanumeric() || matches!(c, '/' | '\\' | '.' | '-' | '_')));
+        if len == 0 || !is_root {
+            redacted.push_str(&rest[..start]);
+            redacted.push_str(dir);
+            rest = after;
+            continue;
+        }
+        redacted.push_str(prefix);
+        redacted.push('~');
+        rest = &after[len..];
+    }
+    redacted.push_str(rest);
+    redacted
+}
+
+/// Replaces the word, but not the part of another word.
+fn redact_word(text: &str, word: &str, replacement: &str) -> String {
+    let is_word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+    let mut redacted = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(word) {
+        let end = start + word.len();
+        let bounded =
+            !is_word(rest[..start].chars().next_back()) && !is_word(rest[end..].chars().next());
+        redacted.push_str(&rest[..start]);
+        redacted.push_str(if bounded { replacement } else { word });
+        rest = &rest[end..];
+    }
+    redacted.push_str(rest);
+    redacted
+}
+
+#[cfg(test)]
+mod tests_redact {
+    use super::*;
+
+    #[test]
+    fn test_redact() {
+        let home = Some("/home/madeline");
+        let user = Some("madeline");
+        assert_eq!(
+            redact_with(
+                "failed to open /home/madeline/Celeste/Mods/Foo.zip",
+                home,
+                user
+            ),
+            "failed to open ~/Celeste/Mods/Foo.zip"
+        );
+        assert_eq!(
+            redact_with("mounted /Users/theo/Celeste by madeline", home, user),
+            "mounted ~/Celeste by <user>"
+        );
+        assert_eq!(
+            redact_with(r#"{"path":"C:\\Users\\theo\\Celeste"}"#, None, None),
+            r#"{"path":"~\\Celeste"}"#
+        );
+        // NOTE the name in another word is kept
+        assert_eq!(
+            redact_with("madelines /etc/home/theo /home/", home, user),
+            "madelines /etc/home/theo /home/"
+        );
+        assert!(matches!(
+            redact_with("nothing to redact", home, user),
+            Cow::Borrowed(_)
+        ));
+    }
+}
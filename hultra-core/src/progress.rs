@@ -0,0 +1,50 @@
+//! Progress of the long tasks, e.g. hashing hundreds of archives.
+//!
+//! Nothing is drawn unless the frontend sets a reporter.
+use std::sync::OnceLock;
+
+static REPORTER: OnceLock<Box<dyn Reporter>> = OnceLock::new();
+
+/// Long task over the installed mods.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Task {
+    /// Hashing the new or modified archives.
+    Hashing,
+    /// Reading the manifests of the installed mods.
+    Scanning,
+}
+
+/// Progress of a task, updated from multiple threads.
+pub trait Progress: Send + Sync {
+    /// Advances the progress by `delta` items.
+    fn inc(&self, delta: u64);
+
+    /// Called once when the task is done.
+    fn finish(&self);
+}
+
+/// Creates the progress of each task.
+pub trait Reporter: Send + Sync {
+    fn start(&self, task: Task, total: u64) -> Box<dyn Progress>;
+}
+
+/// Sets the reporter of the whole process, returns `false` if it has already been set.
+pub fn set_reporter(reporter: impl Reporter + 'static) -> bool {
+    REPORTER.set(Box::new(reporter)).is_ok()
+}
+
+/// Starts the progress of the task, hidden without a reporter.
+pub(crate) fn start(task: Task, total: u64) -> Box<dyn Progress> {
+    match REPORTER.get() {
+        Some(reporter) => reporter.start(task, total),
+        None => Box::new(Hidden),
+    }
+}
+
+struct Hidden;
+
+impl Progress for Hidden {
+    fn inc(&self, _delta: u64) {}
+
+    fn finish(&self) {}
+}
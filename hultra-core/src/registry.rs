@@ -0,0 +1,316 @@
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
+
+use rkyv::Archive;
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::{
+    Checksum, Checksums, LocalMod, ParseChecksumError,
+    download::{DownloadFile, ParseDownloadFileError},
+    local::ModIdentityService,
+    update::UpdateContext,
+};
+
+/// Mod database parsed from `everest_update.yaml`. The key of main map is the mod name.
+#[derive(Debug, Clone, Deserialize, Archive, rkyv::Serialize, rkyv::Deserialize)]
+#[serde(from = "HashMap<String, Entry>")]
+pub struct ModRegistry {
+    entries: HashMap<String, Entry>,
+    /// GameBanana ID to the names of the mods, since assets share the ID with the map.
+    by_gbid: HashMap<u32, Vec<String>>,
+}
+
+impl From<HashMap<String, Entry>> for ModRegistry {
+    fn from(entries: HashMap<String, Entry>) -> Self {
+        let mut by_gbid: HashMap<u32, Vec<String>> = HashMap::new();
+        for (name, entry) in &entries {
+            by_gbid.entry(entry.id).or_default().push(name.clone());
+        }
+        Self { entries, by_gbid }
+    }
+}
+
+/// Metadata of the mod.
+#[derive(Debug, Clone, Default, Deserialize, Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct Entry {
+    /// Category on GameBanana, e.g. "Mod" or "Tool".
+    #[serde(rename = "GameBananaType", default)]
+    kind: String,
+    /// This is a group ID of the map. It is unique but shared with assets.
+    #[serde(rename = "GameBananaId")]
+    id: u32,
+    /// ID of the file on GameBanana, also used by the mirrors.
+    #[serde(rename = "GameBananaFileId", default)]
+    file_id: u32,
+    /// Version string. This value may not follow any specific versioning scheme. Do not expect it to be SemVer.
+    #[serde(rename = "Version")]
+    version: String,
+    /// Download link for the mod file.
+    #[serde(rename = "URL")]
+    url: String,
+    /// File size of the mod file, a.k.a. `Content-Length`.
+    #[serde(rename = "Size")]
+    file_size: u64,
+    /// XxHash checksums for the file. (e.g. "f437bf0515368130")
+    #[serde(rename = "xxHash")]
+    checksums: Vec<String>,
+    /// UNIX time in seconds when the file was uploaded.
+    #[serde(rename = "LastUpdate", default)]
+    last_update: u64,
+}
+
+impl Entry {
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+    pub fn file_id(&self) -> u32 {
+        self.file_id
+    }
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+    pub fn file_size(&self) -> u64 {
+        self.file_size
+    }
+    /// Parses the checksums to match against the hash of the file.
+    pub fn checksums(&self) -> Result<Checksums, ParseChecksumError> {
+        self.checksums
+            .iter()
+            .map(|s| Checksum::from_str(s))
+            .collect()
+    }
+    pub fn last_update(&self) -> u64 {
+        self.last_update
+    }
+}
+
+impl ModRegistry {
+    /// Looks up the mod by name.
+    pub fn get(&self, name: &str) -> Option<&Entry> {
+        self.entries.get(name)
+    }
+
+    /// Returns the mods uploaded at or after the UNIX time, the newest first.
+    pub fn updated_since(&self, since: u64) -> Vec<(&str, &Entry)> {
+        let mut updated: Vec<(&str, &Entry)> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.last_update >= since)
+            .map(|(name, entry)| (name.as_str(), entry))
+            .collect();
+        updated.sort_by(|(a_name, a), (b_name, b)| {
+            b.last_update
+                .cmp(&a.last_update)
+                .then_with(|| a_name.cmp(b_name))
+        });
+        updated
+    }
+
+    /// Returns names of the mods uploaded under the given GameBanana IDs.
+    pub fn names_by_gbids(&self, ids: &HashSet<u32>) -> HashSet<String> {
+        ids.iter()
+            .filter_map(|id| self.by_gbid.get(id))
+            .flatten()
+            .cloned()
+            .collect()
+    }
+
+    /// Converts Entry to the items for downloads.
+    pub fn download_files(
+        &self,
+        required_names: HashSet<String>,
+        installed_names: HashSet<String>,
+    ) -> Result<Vec<DownloadFile>, ParseDownloadFileError> {
+        let missing_names: HashSet<String> = required_names
+            .into_iter()
+            .filter(|name| !installed_names.contains(name))
+            .collect();
+
+        missing_names
+            .into_iter()
+            .filter_map(|name| {
+                self.get(&name)
+                    .map(|entry| DownloadFile::try_from((name.as_str(), entry)))
+            })
+            .collect()
+    }
+
+    pub fn update_contexts(
+        &self,
+        local_mods: &[LocalMod],
+        service: impl ModIdentityService,
+    ) -> Vec<UpdateContext> {
+        // NOTE the mod installed twice is updated only once
+        let mut seen = HashSet::new();
+        local_mods
+            .iter()
+            .filter(|m| seen.insert(m.name()))
+            .filter_map(|m| {
+                let Some((n, e)) = self.entries.get_key_value(m.name()) else {
+                    debug!("mod not found in registry: {}", m.name());
+                    return None;
+                };
+                let (key, modified) = service
+                    .fetch_id(m.file().path())
+                    .inspect_err(|e| debug!(?e, "failed to fetch metadata for {}", m.name()))
+                    .ok()?;
+                let task = UpdateContext::new(m.version(), key, modified, n.clone(), e).ok()?;
+                Some(task)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests_registry {
+    use std::path::PathBuf;
+
+    use crate::{ModFile, local::MockFileSystemService};
+
+    use super::*;
+
+    const YAML_BYTES: &[u8; 670] = br#"
+puppyposting:
+  GameBananaType: Mod
+  Version: 1.1.0
+  LastUpdate: 1758235322
+  Size: 13937408
+  GameBananaId: 619550
+  GameBananaFileId: 1520739
+  xxHash:
+  - 7f4d96733b93c52c
+  URL: https://gamebanana.com/mmdl/1520739
+BreezeContest:
+  GameBananaType: Mod
+  Version: 1.1.2
+  LastUpdate: 1760568856
+  Size: 234447819
+  GameBananaId: 554453
+  GameBananaFileId: 1539722
+  xxHash:
+  - e4d62f4733631949
+  URL: https://gamebanana.com/mmdl/1539722
+BreezeContestAudio:
+  GameBananaType: Mod
+  Version: 1.0.1
+  LastUpdate: 1731192314
+  Size: 707675460
+  GameBananaId: 554453
+  GameBananaFileId: 1318934
+  xxHash:
+  - de98a344ea44aea4
+  URL: https://gamebanana.com/mmdl/1318934
+"#;
+
+    fn load_registry_from_yaml() -> ModRegistry {
+        serde_yaml_ng::from_slice(YAML_BYTES).expect("YAML format should be parsed")
+    }
+
+    #[test]
+    fn test_updated_since() {
+        let registry = load_registry_from_yaml();
+        let names: Vec<&str> = registry
+            .updated_since(1_758_235_322)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, ["BreezeContest", "puppyposting"]);
+        assert!(registry.updated_since(u64::MAX).is_empty());
+    }
+
+    #[test]
+    fn test_mod_registry_from_slice_and_mods() {
+        let registry = load_registry_from_yaml();
+        let mods = registry.entries;
+        let target = mods.get("puppyposting");
+        assert!(target.is_some_and(|mod_info| {
+            mod_info.id == 619550 && mod_info.url == "https://gamebanana.com/mmdl/1520739"
+        }));
+        let target = target.unwrap();
+        assert_eq!(target.kind(), "Mod");
+        assert_eq!(target.file_id(), 1520739);
+        assert_eq!(target.last_update(), 1758235322);
+    }
+
+    #[test]
+    fn test_get_mod_names_by_id() {
+        let registry = load_registry_from_yaml();
+        let ids: HashSet<u32> = HashSet::from_iter([619550]);
+        let names = registry.names_by_gbids(&ids);
+        assert!(!names.is_empty());
+        assert!(names.contains("puppyposting"))
+    }
+
+    #[test]
+    fn test_get_mod_names_by_id_multiple() {
+        let registry = load_registry_from_yaml();
+        let ids: HashSet<u32> = HashSet::from_iter([554453]);
+        let result = registry.names_by_gbids(&ids);
+        assert!(
+            result.len() == 2
+                && result.contains("BreezeContest")
+                && result.contains("BreezeContestAudio")
+        );
+    }
+
+    #[test]
+    fn test_into_update_context_success() {
+        let registry = load_registry_from_yaml();
+        let file = ModFile::new_unchecked(PathBuf::from("puppyposting.zip"));
+        let local_mods = vec![LocalMod::new(file, "puppyposting".into(), "1.1.0".into())];
+
+        let mock_service = MockFileSystemService { should_fail: false };
+        let results = registry.update_contexts(&local_mods, mock_service);
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].cache_key(), "puppyposting.zip");
+    }
+
+    #[test]
+    fn test_update_contexts_installed_twice() {
+        let registry = load_registry_from_yaml();
+        let local_mods = ["puppyposting.zip", "puppyposting-old.zip"].map(|path| {
+            let file = ModFile::new_unchecked(PathBuf::from(path));
+            LocalMod::new(file, "puppyposting".into(), "1.1.0".into())
+        });
+
+        let mock_service = MockFileSystemService { should_fail: false };
+        let results = registry.update_contexts(&local_mods, mock_service);
+
+        assert_eq!(results.len(), 1);
+        assert!(registry.get("puppyposting").is_some());
+    }
+
+    #[test]
+    fn test_into_update_context_failed_for_metadata() {
+        let registry = load_registry_from_yaml();
+        let file = ModFile::new_unchecked(PathBuf::from("puppyposting.zip"));
+        let local_mods = vec![LocalMod::new(file, "puppyposting".into(), "1.1.0".into())];
+
+        let mock_service = MockFileSystemService { should_fail: true };
+        let results = registry.update_contexts(&local_mods, mock_service);
+
+        assert_eq!(results.len(), 0);
+    }
+
+    #[test]
+    fn test_into_update_context_failed_with_no_match() {
+        let registry = load_registry_from_yaml();
+        let file = ModFile::new_unchecked(PathBuf::from("SpeedrunTool.zip"));
+        let local_mods = vec![LocalMod::new(file, "SpeedrunTool".into(), "3.2.1".into())];
+
+        let mock_service = MockFileSystemService { should_fail: false };
+        let results = registry.update_contexts(&local_mods, mock_service);
+
+        assert_eq!(results.len(), 0);
+    }
+}
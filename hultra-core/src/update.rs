@@ -0,0 +1,251 @@
+use std::{fmt::Display, str::FromStr};
+
+use serde::Serialize;
+use tracing::{debug, instrument};
+
+use crate::{
+    Checksums, ParseChecksumError,
+    cache::FileCacheDb,
+    download::{DownloadFile, ParseDownloadFileError},
+    registry::Entry,
+    version::ModVersion,
+};
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum ParseUpdateStrategyError {
+    #[error("unknown strategy '{0}', expected one of: hash, version, both")]
+    Unknown(String),
+}
+
+/// How to tell if an update is available.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UpdateStrategy {
+    /// The checksum of the installed file is not the one of the latest file.
+    #[default]
+    Hash,
+    /// The latest file is newer by the version, or by the upload time if versions cannot be compared.
+    ///
+    /// Locally repacked mods are not flagged forever, unlike `Hash`.
+    Version,
+    /// Both of the above.
+    Both,
+}
+
+impl FromStr for UpdateStrategy {
+    type Err = ParseUpdateStrategyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "hash" => Ok(Self::Hash),
+            "version" => Ok(Self::Version),
+            "both" => Ok(Self::Both),
+            _ => Err(ParseUpdateStrategyError::Unknown(s.to_string())),
+        }
+    }
+}
+
+/// Identifies required updates by the strategy.
+#[instrument(level = "debug", skip_all, fields(mods = contexts.len(), ?strategy))]
+pub fn scan_updates<'a>(
+    cache_db: &FileCacheDb,
+    contexts: &'a [UpdateContext],
+    strategy: UpdateStrategy,
+) -> Result<UpdateReport<'a>, ParseDownloadFileError> {
+    let mut updates = Vec::new();
+    let mut download_files = Vec::new();
+
+    for ctx in contexts {
+        let is_valid = cache_db.is_cache_valid(&ctx.cache_key, &ctx.checksums);
+        let is_newer = ctx.is_newer();
+
+        debug!(
+            mod=ctx.name,
+            cache_valid=is_valid,
+            newer=is_newer,
+            current_version=ctx.current_version,
+            available_version=ctx.available_version
+        );
+
+        let has_update = match strategy {
+            UpdateStrategy::Hash => !is_valid,
+            UpdateStrategy::Version => is_newer,
+            UpdateStrategy::Both => !is_valid && is_newer,
+        };
+        if has_update {
+            let update_info =
+                UpdateInfo::new(&ctx.name, &ctx.current_version, &ctx.available_version);
+            let download_task = DownloadFile::try_from(ctx)?;
+
+            updates.push(update_info);
+            download_files.push(download_task);
+        }
+    }
+    Ok(UpdateReport {
+        download_files,
+        updates,
+    })
+}
+
+#[derive(Debug)]
+pub struct UpdateContext {
+    current_version: String,
+    available_version: String,
+    /// Key of the installed file in the checksum cache.
+    cache_key: String,
+    /// Modification time of the installed file in UNIX time.
+    modified: i64,
+    /// Upload time of the latest file in UNIX time.
+    last_update: u64,
+    name: String,
+    url: String,
+    size: u64,
+    checksums: Checksums,
+}
+
+impl UpdateContext {
+    pub fn new(
+        current_version: &str,
+        cache_key: String,
+        modified: i64,
+        name: String,
+        entry: &Entry,
+    ) -> Result<Self, ParseChecksumError> {
+        let checksums = entry.checksums()?;
+
+        Ok(Self {
+            current_version: current_version.to_string(),
+            available_version: entry.version().to_string(),
+            cache_key,
+            modified,
+            last_update: entry.last_update(),
+            name,
+            url: entry.url().to_string(),
+            size: entry.file_size(),
+            checksums,
+        })
+    }
+    /// Checks if the latest file is newer than the installed one.
+    ///
+    /// Versions are compared first. If either cannot be parsed, the upload time of the latest file
+    /// is compared against the modification time of the installed one instead.
+    pub fn is_newer(&self) -> bool {
+        match (
+            self.current_version.parse::<ModVersion>(),
+            self.available_version.parse::<ModVersion>(),
+        ) {
+            (Ok(current), Ok(available)) => available > current,
+            _ => i64::try_from(self.last_update).is_ok_and(|uploaded| uploaded > self.modified),
+        }
+    }
+    pub fn cache_key(&self) -> &str {
+        &self.cache_key
+    }
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+    pub fn available_version(&self) -> &str {
+        &self.available_version
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+    pub fn checksums(&self) -> &Checksums {
+        &self.checksums
+    }
+}
+
+/// Result of scanning mods for update.
+#[derive(Debug)]
+pub struct UpdateReport<'a> {
+    /// Files to download.
+    pub download_files: Vec<DownloadFile>,
+    /// A list of mod information to display.
+    pub updates: Vec<UpdateInfo<'a>>,
+}
+
+/// Update information to display.
+#[derive(Debug, Serialize)]
+pub struct UpdateInfo<'a> {
+    name: &'a str,
+    current_version: &'a str,
+    available_version: &'a str,
+}
+
+impl<'a> UpdateInfo<'a> {
+    fn new(name: &'a str, current_version: &'a str, available_version: &'a str) -> Self {
+        Self {
+            name,
+            current_version,
+            available_version,
+        }
+    }
+    pub fn name(&self) -> &str {
+        self.name
+    }
+    pub fn current_version(&self) -> &str {
+        self.current_version
+    }
+}
+
+impl<'a> Display for UpdateInfo<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "* {}: {} -> {}",
+            self.name, self.current_version, self.available_version
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests_update {
+    use super::*;
+
+    fn context(current_version: &str, modified: i64, entry: &str) -> UpdateContext {
+        let entry: Entry = serde_yaml_ng::from_str(entry).unwrap();
+        UpdateContext::new(
+            current_version,
+            "Foo.zip".into(),
+            modified,
+            "Foo".into(),
+            &entry,
+        )
+        .unwrap()
+    }
+
+    const ENTRY: &str = r#"
+GameBananaType: Mod
+Version: 1.2.0
+LastUpdate: 1700000000
+Size: 1024
+GameBananaId: 1
+GameBananaFileId: 2
+xxHash: [f437bf0515368130]
+URL: https://gamebanana.com/mmdl/2
+"#;
+
+    #[test]
+    fn test_parse_strategy() {
+        assert_eq!("hash".parse(), Ok(UpdateStrategy::Hash));
+        assert_eq!("Version".parse(), Ok(UpdateStrategy::Version));
+        assert_eq!("both".parse(), Ok(UpdateStrategy::Both));
+        assert!("mtime".parse::<UpdateStrategy>().is_err());
+    }
+
+    #[test]
+    fn test_is_newer_by_version() {
+        assert!(context("1.1.9", i64::MAX, ENTRY).is_newer());
+        // repacked locally, the checksum differs but the version is the same
+        assert!(!context("1.2.0", 0, ENTRY).is_newer());
+        assert!(!context("1.3.0", 0, ENTRY).is_newer());
+    }
+
+    #[test]
+    fn test_is_newer_by_last_update() {
+        assert!(context("nightly", 1_600_000_000, ENTRY).is_newer());
+        assert!(!context("nightly", 1_800_000_000, ENTRY).is_newer());
+    }
+}
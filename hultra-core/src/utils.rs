@@ -0,0 +1,191 @@
+use std::num::ParseIntError;
+
+#[derive(Debug, thiserror::Error)]
+#[error("input string should contain only ASCII characters")]
+pub struct NonAsciiError;
+
+/// Sanitizes a mod name as file stem for Unix file systems.
+///
+/// # Rules
+/// - Trims leading/trailing whitespace.
+/// - Removes control characters.
+/// - Replaces characters not in the whitelist `[A-Za-z0-9 -_'()]` with `_`.
+/// - Truncates the result to 255 bytes.
+///
+/// # Panics
+/// All characters in given string must be ASCII, otherwise it will panic.
+///
+/// # Notes
+/// Mod database only allows ASCII characters for the mod name. So the name should always valid UTF-8 and ASCII.
+pub fn sanitize_stem(input: &str) -> Result<String, NonAsciiError> {
+    let trimmed = input.trim();
+
+    if !trimmed.is_ascii() {
+        return Err(NonAsciiError);
+    }
+
+    let sanitized_bytes = trimmed
+        .bytes()
+        .filter(|c| !c.is_ascii_control())
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || is_allowed_byte(c) {
+                c
+            } else {
+                b'_'
+            }
+        })
+        .take(u8::MAX as usize)
+        .collect();
+
+    // NOTE This is safe because `input` is always valid UFT-8 and ASCII
+    Ok(unsafe { String::from_utf8_unchecked(sanitized_bytes) })
+}
+
+/// Checks if a byte is allowed in the filename stem.
+#[inline(always)]
+fn is_allowed_byte(b: u8) -> bool {
+    matches!(
+        b,
+        b'A'..=b'Z' |            // Uppercase
+        b'a'..=b'z' |            // Lowercase
+        b'0'..=b'9' |            // Digits
+        b' ' | b'-' | b'_' |     // Separators
+        b'\'' | b'(' | b')' |    // Special allowed chars
+        b'+' | b','              // Special allowed chars 2 (common in mods name)
+    )
+}
+
+#[cfg(test)]
+mod test_sanitize_name {
+    use super::*;
+
+    #[test]
+    fn test_no_change() {
+        let input = "valid-filename_123(final)";
+        let result = sanitize_stem(input).expect("should be sanitized");
+        assert_eq!(result, "valid-filename_123(final)");
+    }
+
+    #[test]
+    fn test_replace_invalid_chars() {
+        let input = "file!?.txt";
+        let result = sanitize_stem(input).expect("should be sanitized");
+        assert_eq!(result, "file___txt");
+    }
+
+    #[test]
+    fn test_remove_control_chars() {
+        // Control chars should be removed, not replaced
+        let input = "file\0name\n";
+        let result = sanitize_stem(input).expect("should be sanitized");
+        assert_eq!(result, "filename");
+    }
+
+    #[test]
+    fn test_mixed_whitelist() {
+        // Ensure added whitelist chars ' and () are respected
+        let input = "  Spooooky's Asset Pack (WIP)  ";
+        let result = sanitize_stem(input).expect("should be sanitized");
+        assert_eq!(result, "Spooooky's Asset Pack (WIP)");
+    }
+
+    #[test]
+    fn test_panic_on_non_ascii() {
+        let result = sanitize_stem("Error_日本語");
+        assert!(result.is_err_and(|err| matches!(err, NonAsciiError)))
+    }
+}
+
+/// Matches text against a glob pattern.
+///
+/// Supports `*` (any sequence of characters, including empty) and `?` (any single character).
+/// Matching is case-sensitive like shells on Linux.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let (p, t): (Vec<char>, Vec<char>) = (pattern.chars().collect(), text.chars().collect());
+    let (mut pi, mut ti) = (0, 0);
+    // Position of the last `*` in the pattern and the text position it matched
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while ti < t.len() {
+        match p.get(pi) {
+            Some('*') => {
+                backtrack = Some((pi, ti));
+                pi += 1;
+            }
+            Some(&c) if c == '?' || c == t[ti] => {
+                pi += 1;
+                ti += 1;
+            }
+            _ => match backtrack {
+                // let the last `*` consume one more character
+                Some((star, matched)) => {
+                    pi = star + 1;
+                    ti = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests_glob_match {
+    use super::glob_match;
+
+    #[test]
+    fn test_literal() {
+        assert!(glob_match("SpeedrunTool.zip", "SpeedrunTool.zip"));
+        assert!(!glob_match("SpeedrunTool.zip", "speedruntool.zip"));
+    }
+
+    #[test]
+    fn test_wildcards() {
+        assert!(glob_match("*.zip.old", "GravityHelper.zip.old"));
+        assert!(!glob_match("*.zip.old", "GravityHelper.zip"));
+        assert!(glob_match("SpringCollab*.zip", "SpringCollab2020.zip"));
+        assert!(glob_match("SpringCollab*.zip", "SpringCollab.zip"));
+        assert!(glob_match("Mod?.zip", "Mod1.zip"));
+        assert!(!glob_match("Mod?.zip", "Mod12.zip"));
+        assert!(glob_match("*backup*", "old-backup-2024.zip"));
+        assert!(glob_match("*", ""));
+    }
+}
+
+pub fn from_str_digest(input: &str) -> Result<u64, ParseIntError> {
+    let clean_input = input.trim().strip_prefix("0x").unwrap_or(input.trim());
+    u64::from_str_radix(clean_input, 16)
+}
+
+#[cfg(test)]
+mod tests_from_str_digest {
+    use super::from_str_digest;
+
+    use anyhow::{Context, Result};
+
+    #[test]
+    fn parses_with_0x() -> Result<()> {
+        assert_eq!(
+            from_str_digest("0x7f4d96733b93c52c").context("should be converted")?,
+            9173153437688513836
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parses_without_0x_and_spaces() -> Result<()> {
+        assert_eq!(
+            from_str_digest(" 7f4d96733b93c52c ").context("should be converted")?,
+            9173153437688513836
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn returns_error_on_invalid() {
+        let result = from_str_digest("not-hex");
+        assert!(result.is_err());
+    }
+}
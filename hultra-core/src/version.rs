@@ -0,0 +1,112 @@
+//! Lenient SemVer of mods, compared the same way as Everest does for dependencies.
+//!
+//! Everest parses `Major.Minor.Patch` ignoring any pre-release or build suffix,
+//! and missing components are treated as zero, e.g. `1.2` equals to `1.2.0`.
+use std::str::FromStr;
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+#[error("invalid version '{0}'")]
+pub struct ParseModVersionError(String);
+
+/// Version of the mod.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ModVersion {
+    major: u32,
+    minor: u32,
+    patch: u32,
+}
+
+impl FromStr for ModVersion {
+    type Err = ParseModVersionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseModVersionError(s.to_string());
+
+        let trimmed = s.trim();
+        let trimmed = trimmed.strip_prefix(['v', 'V']).unwrap_or(trimmed);
+        // NOTE pre-release and build metadata are ignored like Everest does
+        let core = trimmed.split(['-', '+', ' ']).next().unwrap_or_default();
+
+        let mut parts = core.split('.');
+        let mut next = |required: bool| match parts.next() {
+            Some(part) => part.parse::<u32>().map_err(|_| invalid()),
+            None if required => Err(invalid()),
+            None => Ok(0),
+        };
+        // NOTE 4th component (revision) of .NET versions is accepted but ignored
+        Ok(Self {
+            major: next(true)?,
+            minor: next(false)?,
+            patch: next(false)?,
+        })
+    }
+}
+
+impl ModVersion {
+    /// Returns the build number if this is a version of Everest, e.g. `1.4465.0` is build 4465.
+    pub fn everest_build(&self) -> Option<u32> {
+        (self.major == 1).then_some(self.minor)
+    }
+}
+
+/// Checks if the installed version is older than the required one.
+///
+/// Returns `false` if either of them cannot be parsed, since it cannot be told.
+pub fn is_older(installed: &str, required: &str) -> bool {
+    match (
+        installed.parse::<ModVersion>(),
+        required.parse::<ModVersion>(),
+    ) {
+        (Ok(installed), Ok(required)) => installed < required,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests_version {
+    use super::*;
+
+    fn v(major: u32, minor: u32, patch: u32) -> ModVersion {
+        ModVersion {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    #[test]
+    fn test_parse_lenient() {
+        for (input, expected) in [
+            ("1.2.3", v(1, 2, 3)),
+            ("1.2", v(1, 2, 0)),
+            ("1", v(1, 0, 0)),
+            ("v1.2.3", v(1, 2, 3)),
+            ("1.2.3-beta.1", v(1, 2, 3)),
+            ("1.2.3+build5", v(1, 2, 3)),
+            ("1.2.3.4", v(1, 2, 3)),
+            (" 1.6.10 ", v(1, 6, 10)),
+        ] {
+            assert_eq!(input.parse(), Ok(expected), "{}", input);
+        }
+        for input in ["", "abc", "1.x", "1..2"] {
+            assert!(input.parse::<ModVersion>().is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_everest_build() {
+        assert_eq!(v(1, 4465, 0).everest_build(), Some(4465));
+        assert_eq!(v(2, 0, 0).everest_build(), None);
+    }
+
+    #[test]
+    fn test_is_older() {
+        assert!(is_older("1.6.9", "1.6.10"));
+        assert!(is_older("1.2", "1.2.1"));
+        assert!(!is_older("1.6.10", "1.6.10"));
+        assert!(!is_older("2.0.0", "1.9.9"));
+        assert!(!is_older("1.2.0-beta", "1.2"));
+        assert!(!is_older("unknown", "1.0.0"));
+        assert!(!is_older("1.0.0", ""));
+    }
+}
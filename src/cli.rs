@@ -1,72 +1,395 @@
 //! Command list and global options.
 use std::path::PathBuf;
 
+use anyhow::bail;
 use clap::{CommandFactory, Parser, Subcommand};
-use clap_complete::{Shell, generate};
+use clap_complete::Shell;
 
 use crate::{
     commands::{
-        self, DownloadOption,
+        self,
+        blacklist::BlacklistCommand,
+        browse::BrowseArgs,
+        bugreport::BugreportArgs,
+        cache::CacheCommand,
+        config::ConfigCommand,
+        du::DuArgs,
         everest::{EverestSubCommand, network::NetworkCommand},
+        help::HelpArgs,
+        history::HistoryArgs,
         install::InstallArgs,
+        list::ListArgs,
+        maps::MapsArgs,
+        migrate::MigrateArgs,
+        mirrors::MirrorsCommand,
+        offload::{OffloadArgs, RestoreArgs},
+        rdeps::RdepsArgs,
+        search::SearchArgs,
+        serve::ServeArgs,
+        show::ShowArgs,
+        sync::SyncArgs,
+        top::TopArgs,
+        update::UpdateArgs,
+        whatsnew::WhatsnewArgs,
     },
-    config::{AppConfig, CARGO_PKG_NAME},
+    config::AppConfig,
+    core::network::{ProxyUrl, TlsBackend},
     everest::{self, EverestHttpClient},
+    log::LogFormat,
+    ui::{OutputFormat, style::ColorChoice},
 };
 
 /// Command line interface.
 #[derive(Debug, Clone, Parser)]
-#[command(version, about = "A simple cli tool to update/install mods for Celeste.", long_about = None)]
+#[command(
+    version,
+    about = "A simple cli tool to update/install mods for Celeste.",
+    long_about = None,
+    disable_help_subcommand = true,
+    after_help = "See `hultra help topics` for mirrors, blacklists, and profiles.
+
+Exit status:
+  0  Success
+  1  Any other error
+  2  Invalid arguments
+  3  All of the mods failed to download
+  4  Some of the mods failed to download, the others were installed
+  5  Network error, e.g. the database could not be fetched
+  6  Permission denied, e.g. the Mods directory is read-only
+  7  Broken file or response, e.g. the config file"
+)]
 pub struct Cli {
     #[command(subcommand)]
     pub commands: Command,
 
     /// Directory where the Celeste is installed.
-    #[arg(short = 'd', long = "directory", value_name = "DIR", global = true)]
+    #[arg(
+        short = 'd',
+        long = "directory",
+        value_name = "DIR",
+        global = true,
+        value_parser = existing_dir
+    )]
     pub directory: Option<PathBuf>,
 
+    /// Uses the profile of the config file instead of the one matches hostname.
+    #[arg(long, visible_alias = "profile", value_name = "NAME", global = true)]
+    pub config_profile: Option<String>,
+
     /// Writes logs to the specified file.
     #[arg(long, value_name = "PATH", global = true)]
     pub log_file: Option<PathBuf>,
+
+    /// Format of the log file: `text`, or `json` for a JSON object on each line.
+    #[arg(long, value_name = "FORMAT", global = true, default_value = "text")]
+    pub log_format: LogFormat,
+
+    /// Uses only the cached databases and installed mods, without network access.
+    #[arg(long, global = true)]
+    pub offline: bool,
+
+    /// Sends all of the requests through the proxy, e.g. 'socks5h://127.0.0.1:1080'.
+    #[arg(long, value_name = "URL", global = true)]
+    pub proxy: Option<ProxyUrl>,
+
+    /// TLS backend of the connections: `rustls`, or `native` if built with the `native-tls` feature.
+    #[arg(long, value_name = "BACKEND", global = true)]
+    pub tls: Option<TlsBackend>,
+
+    /// Prints only the results, warnings, and errors, without progress bars.
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
+    /// Answers yes to every prompt, e.g. the confirmation of `install`.
+    #[arg(short, long, visible_alias = "non-interactive", global = true)]
+    pub yes: bool,
+
+    /// When to color the output: `auto`, `always`, or `never`. `NO_COLOR` is respected by `auto`.
+    #[arg(long, value_name = "WHEN", global = true, default_value = "auto")]
+    pub color: ColorChoice,
+
+    /// Format of the results of `list`, `show`, `maps`, `du`, `search`, `browse`, `top`, `whatsnew`, `update`, `sync`, `history`, `everest status`, and `doctor`.
+    #[arg(long, value_name = "FORMAT", global = true, default_value = "text")]
+    pub output: OutputFormat,
 }
 
 /// Subcommands of the CLI.
 #[derive(Debug, Clone, Subcommand)]
 pub enum Command {
-    /// Generate shell completion.
-    GenerateCompletion {
+    /// Print the script which enables shell completions, including installed mod names.
+    #[command(
+        alias = "generate-completion",
+        after_help = "Examples:
+  source <(hultra completions bash)
+  hultra completions fish > ~/.config/fish/completions/hultra.fish
+
+Mod names are completed from the checksum cache of the default profile, i.e. the last scan."
+    )]
+    Completions {
         #[arg(value_enum)]
         shell: Shell,
     },
 
     /// List installed mods.
-    List,
+    #[command(after_help = "Examples:
+  hultra list
+  hultra list --sort mtime --filter collab
+  hultra list --type maps
+  hultra list --size
+  hultra list --resolve
+
+Mods installed in multiple archives are reported, `--resolve` keeps the newest one.")]
+    List(ListArgs),
+
+    /// Show details of installed mods.
+    #[command(after_help = "Examples:
+  hultra show SpeedrunTool CollabUtils2
+  hultra show --remote SpeedrunTool
+  hultra list | cut -d ' ' -f 1 | hultra --output json show --stdin")]
+    Show(ShowArgs),
+
+    /// List installed mods which require the mod, directly or through other mods.
+    #[command(after_help = "Examples:
+  hultra rdeps GravityHelper")]
+    Rdeps(RdepsArgs),
+
+    /// Summarize the disk usage of the Mods directory and the largest mods.
+    #[command(after_help = "Examples:
+  hultra du
+  hultra du -n 20
+  hultra du --output json
+
+Other files are anything but the installed mods, e.g. partial downloads and backups.")]
+    Du(DuArgs),
+
+    /// List maps in the installed mods, named by their dialog.
+    #[command(after_help = "Examples:
+  hultra maps
+  hultra maps StrawberryJam2021 --output json
+  hultra maps --language Japanese")]
+    Maps(MapsArgs),
+
+    /// Search mods on GameBanana.
+    #[command(after_help = "Examples:
+  hultra search speedrun tool
+  hultra --output json search collab --page 2")]
+    Search(SearchArgs),
+
+    /// Browse mods on GameBanana by category, and install the selected ones.
+    #[command(after_help = "Examples:
+  hultra browse --category maps --sort newest
+  hultra browse --category helpers --sort downloads --page 2
+  hultra --output json browse --sort likes
+
+On a terminal, mods of the page are installed by their numbers with the missing dependencies.")]
+    Browse(BrowseArgs),
+
+    /// List mods featured on GameBanana, marking the installed ones.
+    #[command(after_help = "Examples:
+  hultra top
+  hultra top --period all")]
+    Top(TopArgs),
+
+    /// List mods recently updated in the mod database, marking the installed ones.
+    #[command(after_help = "Examples:
+  hultra whatsnew
+  hultra whatsnew --days 30 --installed")]
+    Whatsnew(WhatsnewArgs),
 
     /// Install mods from the GameBanana URLs.
+    #[command(after_help = "Examples:
+  hultra install https://gamebanana.com/mods/123456
+  hultra install -p jade,gb https://gamebanana.com/mods/123456 https://gamebanana.com/mods/456789
+  hultra install --file ~/Downloads/SomeMap.zip")]
     Install(InstallArgs),
 
     /// Update mods.
-    Update(DownloadOption),
+    #[command(after_help = "Examples:
+  hultra update
+  hultra update -p auto --mirror-index -j 6
+  hultra update --strategy version
+
+Mods listed in updaterblacklist.txt are skipped, see `hultra help topics blacklists`.")]
+    Update(UpdateArgs),
+
+    /// Make the installed mods match the lockfile, downloading the missing ones.
+    #[command(after_help = "Examples:
+  hultra sync --dry-run
+  hultra sync --lockfile ~/Downloads/hultra.lock
+
+Mods/hultra.lock is written after install, update, offload, and restore, with the name, version,
+xxHash, and download URL of each archive. Mods which are not locked are only reported.")]
+    Sync(SyncArgs),
 
     /// Manage Everest.
     #[command(subcommand)]
     Everest(EverestSubCommand),
+
+    /// Check mirrors.
+    #[command(
+        subcommand,
+        after_help = "Examples:
+  hultra mirrors test
+  hultra mirrors urls 1298450
+
+See `hultra help topics mirrors` for the mirror priority."
+    )]
+    Mirrors(MirrorsCommand),
+
+    /// Copy or move mods to another Celeste installation.
+    #[command(after_help = "Examples:
+  hultra migrate --to /run/media/deck/Celeste
+  hultra migrate --to ~/Games/Celeste --move")]
+    Migrate(MigrateArgs),
+
+    /// Show the recorded changes to the installed mods, the newest first.
+    #[command(after_help = "Examples:
+  hultra history
+  hultra history SpeedrunTool
+  hultra history -n 100 --output json
+
+Installs, updates, offloads, and restores are recorded with the versions and the hash of the archive.")]
+    History(HistoryArgs),
+
+    /// Move mods which have been disabled for a while to the archive directory.
+    #[command(after_help = "Examples:
+  hultra offload --dry-run
+  hultra offload --unused-for 12w --archive /mnt/hdd/CelesteArchive
+
+Only mods listed in blacklist.txt are offloaded, and never the ones required by enabled mods.
+The time a mod was disabled is recorded on each run, the first run estimates it from blacklist.txt.
+Their entries are removed from blacklist.txt, so restored mods are enabled.")]
+    Offload(OffloadArgs),
+
+    /// Bring offloaded mods back to the Mods directory.
+    #[command(after_help = "Examples:
+  hultra restore
+  hultra restore SpeedrunTool CollabUtils2.zip")]
+    Restore(RestoreArgs),
+
+    /// Manage the updater's blacklist, i.e. mods which are never updated.
+    #[command(
+        subcommand,
+        after_help = "Examples:
+  hultra blacklist add SpeedrunTool
+  hultra blacklist remove \"Another Farewell Map CC-Side.zip\"
+  hultra blacklist list
+
+Mod names are resolved to the file names of the installed archives, since updaterblacklist.txt
+only matches file names. Comments and the other lines of the file are kept as they are."
+    )]
+    Blacklist(BlacklistCommand),
+
+    /// Manage the caches of the checksums and the database.
+    #[command(
+        subcommand,
+        after_help = "Examples:
+  hultra cache status
+  hultra cache rebuild
+
+The cache of an older version, or one that cannot be read, is rebuilt automatically."
+    )]
+    Cache(CacheCommand),
+
+    /// Change the settings of the config file.
+    #[command(
+        subcommand,
+        after_help = "Examples:
+  hultra config set celeste-dir ~/Games/Celeste
+
+Without `--directory` or `directory` of the profile, Celeste is searched in the Steam libraries,
+including the Flatpak one and their Proton prefixes, the games installed by itch, Epic, and Heroic,
+and the default locations of them.
+`hultra doctor` shows which installation is used."
+    )]
+    Config(ConfigCommand),
+
+    /// Find mods which failed to load from the latest Everest log.
+    Postmortem,
+
+    /// Check installed mods for broken archives and missing DLLs.
+    Doctor,
+
+    /// Print the versions, settings, connectivity, and recent logs to attach to an issue.
+    #[command(after_help = "Examples:
+  hultra bugreport > bugreport.txt
+  hultra --log-file hultra.log update; hultra bugreport --log hultra.log > bugreport.txt
+
+The home directory, the user name, host names, and credentials are redacted.")]
+    Bugreport(BugreportArgs),
+
+    /// Serve a local HTTP API to install mods from a browser extension.
+    #[command(after_help = "Examples:
+  hultra serve
+  HULTRA_SERVE_TOKEN=secret hultra serve --listen 127.0.0.1:8080")]
+    Serve(ServeArgs),
+
+    /// Print help of the command, or guides with `help topics`.
+    Help(HelpArgs),
+}
+
+/// Accepts only an existing directory.
+fn existing_dir(value: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(value);
+    if path.is_dir() {
+        Ok(path)
+    } else {
+        Err(format!("'{}' is not a directory", value))
+    }
 }
 
 pub async fn dispatch(cmd: Command, config: AppConfig) -> anyhow::Result<()> {
     match cmd {
-        Command::GenerateCompletion { shell } => {
-            let mut cmd = Cli::command();
-            generate(shell, &mut cmd, CARGO_PKG_NAME, &mut std::io::stdout());
-        }
-        Command::List => commands::list::run(&config)?,
+        Command::Completions { shell } => commands::completions::run(shell)?,
+        Command::List(args) => commands::list::run(args, &config).await?,
+        Command::Show(args) => commands::show::run(args, &config).await?,
+        Command::Rdeps(args) => commands::rdeps::run(args, &config)?,
+        Command::Maps(args) => commands::maps::run(args, &config)?,
+        Command::Du(args) => commands::du::run(args, &config)?,
+        Command::Search(args) => commands::search::run(args, &config).await?,
+        Command::Browse(args) => commands::browse::run(args, &config).await?,
+        Command::Top(args) => commands::top::run(args, &config).await?,
+        Command::Whatsnew(args) => commands::whatsnew::run(args, &config).await?,
         Command::Install(args) => commands::install::run(args, &config).await?,
         Command::Update(args) => commands::update::run(args, &config).await?,
+        Command::Sync(args) => commands::sync::run(args, &config).await?,
+        Command::Mirrors(subcommand) => match subcommand {
+            MirrorsCommand::Test(args) => commands::mirrors::test(args, &config).await?,
+            MirrorsCommand::Urls(args) => commands::mirrors::urls(args, &config)?,
+        },
+        Command::Migrate(args) => commands::migrate::run(args, &config)?,
+        Command::History(args) => commands::history::run(args, &config)?,
+        Command::Offload(args) => commands::offload::offload(args, &config)?,
+        Command::Restore(args) => commands::offload::restore(args, &config)?,
+        Command::Blacklist(subcommand) => match subcommand {
+            BlacklistCommand::Add { mods } => commands::blacklist::add(&mods, &config)?,
+            BlacklistCommand::Remove { mods } => commands::blacklist::remove(&mods, &config)?,
+            BlacklistCommand::List => commands::blacklist::list(&config)?,
+        },
+        Command::Cache(subcommand) => match subcommand {
+            CacheCommand::Status => commands::cache::status(&config)?,
+            CacheCommand::Clear => commands::cache::clear(&config)?,
+            CacheCommand::Rebuild => commands::cache::rebuild(&config)?,
+        },
+        Command::Config(cmd) => match cmd {
+            ConfigCommand::Set { key, value } => commands::config::set(key, &value, &config)?,
+        },
+        Command::Postmortem => commands::postmortem::run(&config)?,
+        Command::Doctor => commands::doctor::run(&config)?,
+        Command::Bugreport(args) => commands::bugreport::run(args, &config).await?,
+        Command::Serve(args) => commands::serve::run(args, &config).await?,
+        Command::Help(args) => commands::help::run(args, Cli::command(), &config)?,
         Command::Everest(subcommand) => match subcommand {
             EverestSubCommand::Version => commands::everest::version::run(&config)?,
+            EverestSubCommand::Status(args) => {
+                commands::everest::status::run(&args, &config).await?
+            }
+            EverestSubCommand::NetworkRequired(_) if config.offline() => {
+                bail!("This command requires network access, run without `--offline`")
+            }
             EverestSubCommand::NetworkRequired(action) => {
                 let option = action.network_option();
-                let shared_client = EverestHttpClient::new()?;
+                let shared_client = EverestHttpClient::new(config.client())?;
                 let builds = everest::fetch(shared_client.inner().clone(), option).await?;
 
                 match action {
@@ -92,3 +415,14 @@ pub async fn dispatch(cmd: Command, config: AppConfig) -> anyhow::Result<()> {
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests_cli {
+    use super::*;
+
+    #[test]
+    fn test_verify_cli() {
+        // NOTE catches the global options which collide with the ones of the subcommands
+        Cli::command().debug_assert();
+    }
+}
@@ -2,147 +2,236 @@
 //!
 //! All of the command arguments are defined in this module.
 //! Each modules have `run(args: Args)` function for CLI output.
-//! Actual business logic like `install`, or `update` are defined in the upper modules (src/lib.rs, or core/network/download.rs).
-use std::collections::HashSet;
+//! Actual business logic like `install`, or `update` are defined in the upper modules (src/core.rs, or core/network/downloader.rs).
+use std::{path::PathBuf, time::Duration};
 
-use clap::{Args, ValueEnum};
+use clap::Args;
+use reqwest::Client;
 
-use crate::core::network::downloader::DownloadUrl;
+use crate::{
+    config::AppConfig,
+    core::{
+        mirrorlist::{Mirror, MirrorListError, MirrorPreference, MirrorTable, Mirrors},
+        network::{
+            availability,
+            downloader::{ModDownloader, StallPolicy},
+            probe,
+            ratelimit::Rate,
+            retry::RetryPolicy,
+        },
+        trash::Trash,
+    },
+};
 
+pub mod blacklist;
+pub mod browse;
+pub mod bugreport;
+pub mod cache;
+pub mod completions;
+pub mod config;
+pub mod doctor;
+pub mod du;
 pub mod everest;
+pub mod help;
+pub mod history;
 pub mod install;
 pub mod list;
+pub mod maps;
+pub mod migrate;
+pub mod mirrors;
+pub mod offload;
+pub mod postmortem;
+pub mod rdeps;
+pub mod search;
+pub mod serve;
+pub mod show;
+pub mod sync;
+pub mod top;
 pub mod update;
+pub mod whatsnew;
 
 /// Options specific to downloading.
-#[derive(Debug, Clone, Args)]
+#[derive(Debug, Clone, Default, Args)]
 pub struct DownloadOption {
     /// Comma-separated list of mirror priorities.
     #[arg(
-        value_enum,
         short = 'p',
         long = "mirror-priority",
         value_name = "MIRROR",
-        value_delimiter = ',',
         long_help = "Comma-separated list of mirror priorities.
         This option allows you to specify the order in which mirrors should be tried when downloading mods.
-        You can specify up to 4 mirrors, but providing fewer will restrict download attempts to only those mirrors.",
-        default_value = "otobot,gb,jade,wegfan"
+        Built-in mirrors are gb, jade, wegfan, and otobot. Custom mirrors defined in the config file can be used by their names.
+        Providing fewer mirrors will restrict download attempts to only those mirrors.
+        Use 'auto' to order all mirrors by measured latency.
+        [default: otobot,gb,jade,wegfan]"
     )]
-    pub mirror_priority: Vec<Mirror>,
+    pub mirror_priority: Option<MirrorPreference>,
 
     /// Enables GitHub mirror for database retrieval.
     #[arg(short = 'm', long)]
     pub use_api_mirror: bool,
 
-    /// Maximum number of concurrent downloads [range: 1-6]
-    #[arg(short, long, default_value_t = 4, value_parser = clap::value_parser!(u8).range(1..=6))]
-    pub jobs: u8,
-}
+    /// Skips mirrors which do not host the file according to their indices.
+    #[arg(long)]
+    pub mirror_index: bool,
+
+    /// Re-downloads the databases instead of revalidating the cached ones.
+    #[arg(long)]
+    pub refresh: bool,
+
+    /// Maximum number of concurrent downloads [range: 1-6] [default: 4]
+    #[arg(short, long, value_parser = clap::value_parser!(u8).range(1..=6))]
+    pub jobs: Option<u8>,
+
+    /// Retries of transient failures on each mirror before trying the next one [default: 2]
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u8).range(0..=10))]
+    pub retries: Option<u8>,
+
+    /// Seconds to wait before the first retry, doubled on every retry [default: 1]
+    #[arg(long, value_name = "SECS")]
+    pub retry_delay: Option<u64>,
+
+    /// Seconds after which no more retries are started on the mirror [default: 120]
+    #[arg(long, value_name = "SECS")]
+    pub retry_timeout: Option<u64>,
+
+    /// Limits the total bandwidth of the downloads, e.g. '2MiB' or '500K' per second.
+    #[arg(long, value_name = "RATE")]
+    pub limit_rate: Option<Rate>,
+
+    /// Seconds without receiving data after which the mirror is given up [default: 30]
+    #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..))]
+    pub stall_timeout: Option<u64>,
+
+    /// Gives up the mirror if the average speed over `--stall-timeout` is below this, e.g. '10K'.
+    #[arg(long, value_name = "RATE")]
+    pub min_speed: Option<Rate>,
+
+    /// Downloads only the changed files of the updated archives, reusing the rest of the old ones.
+    #[arg(long)]
+    pub delta: bool,
 
-/// Supported mirrors.
-#[derive(Debug, Clone, PartialEq, Eq, ValueEnum, Hash)]
-#[value(rename_all = "lower")]
-pub enum Mirror {
-    /// Default GameBanana Server (United States).
-    Gb,
-    /// Germany.
-    Jade,
-    /// China.
-    Wegfan,
-    /// North America.
-    Otobot,
+    /// Skips the dependency graph, reading the dependencies from the downloaded mods instead.
+    #[arg(long)]
+    pub lazy_deps: bool,
+
+    /// Custom mirrors defined in the config file.
+    #[arg(skip)]
+    pub custom_mirrors: MirrorTable,
+
+    /// Path to the cached latency of the mirrors, used for `auto`.
+    #[arg(skip)]
+    pub latency_cache_path: PathBuf,
+
+    /// Directory of the cached databases.
+    #[arg(skip)]
+    pub api_cache_dir: PathBuf,
+
+    /// Uses only the cached databases.
+    #[arg(skip)]
+    pub offline: bool,
+
+    /// Where the replaced archives are moved, if configured.
+    #[arg(skip)]
+    pub trash: Option<Trash>,
 }
 
-impl Mirror {
-    /// Generates the full mirror URL for a given GameBanana ID.
-    fn url_for_id(&self, gbid: u32) -> String {
-        match *self {
-            Mirror::Gb => {
-                format!("https://gamebanana.com/mmdl/{}", gbid)
-            }
-            Mirror::Jade => {
-                format!(
-                    "https://celestemodupdater.0x0a.de/banana-mirror/{}.zip",
-                    gbid
-                )
-            }
-            Mirror::Wegfan => {
-                format!(
-                    "https://celeste.weg.fan/api/v2/download/gamebanana-files/{}",
-                    gbid
-                )
-            }
-            Mirror::Otobot => {
-                format!("https://banana-mirror-mods.celestemods.com/{}.zip", gbid)
-            }
+impl DownloadOption {
+    const DEFAULT_MIRROR_PRIORITY: [Mirror; 4] =
+        [Mirror::Otobot, Mirror::Gb, Mirror::Jade, Mirror::Wegfan];
+    const DEFAULT_JOBS: u8 = 4;
+
+    /// Fills the options which are not given in the command line with the values of the config.
+    ///
+    /// Returns an error if the mirror priority contains unknown mirrors.
+    pub fn with_config(mut self, config: &AppConfig) -> Result<Self, MirrorListError> {
+        let profile = config.profile();
+        if self.mirror_priority.is_none() {
+            self.mirror_priority = profile.mirror_priority.clone();
+        }
+        if self.jobs.is_none() {
+            self.jobs = profile.jobs;
+        }
+        self.retries = self.retries.or(profile.retries);
+        self.retry_delay = self.retry_delay.or(profile.retry_delay);
+        self.retry_timeout = self.retry_timeout.or(profile.retry_timeout);
+        self.limit_rate = self.limit_rate.or(profile.limit_rate);
+        self.stall_timeout = self.stall_timeout.or(profile.stall_timeout);
+        self.min_speed = self.min_speed.or(profile.min_speed);
+        self.use_api_mirror |= profile.use_api_mirror.unwrap_or(false);
+        self.mirror_index |= profile.mirror_index.unwrap_or(false);
+        self.delta |= profile.delta.unwrap_or(false);
+        self.lazy_deps |= profile.lazy_deps.unwrap_or(false);
+        self.custom_mirrors = config.mirrors().clone();
+        self.latency_cache_path = config.latency_cache_path().to_path_buf();
+        self.api_cache_dir = config.api_cache_dir().to_path_buf();
+        self.offline = config.offline();
+        self.trash = config.trash();
+
+        // NOTE validate here to fail before fetching the database
+        if let Some(MirrorPreference::List(list)) = &self.mirror_priority {
+            Mirrors::new(list, &self.custom_mirrors)?;
         }
+        Ok(self)
     }
-}
 
-/// Represents mirror priority.
-#[derive(Debug, Clone)]
-pub struct Mirrors(Vec<Mirror>);
+    /// Resolves the mirror preference into the mirror priority.
+    ///
+    /// For `auto`, measures latency of the mirrors unless the cached result is fresh.
+    /// With `--mirror-index`, fetches the indices of the mirrors.
+    pub async fn mirrors(&self, client: &Client) -> Result<Mirrors, MirrorListError> {
+        let priority = match &self.mirror_priority {
+            Some(MirrorPreference::Auto) => {
+                probe::auto_order(client, &self.custom_mirrors, &self.latency_cache_path).await
+            }
+            Some(MirrorPreference::List(list)) => list.clone(),
+            None => Self::DEFAULT_MIRROR_PRIORITY.to_vec(),
+        };
+        let mirrors = Mirrors::new(&priority, &self.custom_mirrors)?;
+        if !self.mirror_index {
+            return Ok(mirrors);
+        }
+        let availability =
+            availability::fetch(client, &priority, &self.api_cache_dir, self.refresh).await;
+        Ok(mirrors.with_availability(availability))
+    }
 
-impl From<Vec<Mirror>> for Mirrors {
-    /// Converts to this type from user input.
-    fn from(value: Vec<Mirror>) -> Self {
-        Self(value)
+    pub fn jobs(&self) -> u8 {
+        self.jobs.unwrap_or(Self::DEFAULT_JOBS)
     }
-}
 
-impl Mirrors {
-    /// Resolves Mirrors into actual list of mirror URLs.
-    ///
-    /// ### Example
-    ///
-    /// ```
-    /// let mirrors = vec![Mirror::Gb, Mirror::Jade, Mirror::Wegfan];
-    /// let urls = mirrors.resolve("https://gamebanan.com/mmdl/123456");
-    /// for url in urls {
-    ///     println!("URL: {}", url)
-    /// }
-    /// ```
-    pub fn resolve(&self, url: &DownloadUrl) -> Vec<String> {
-        // NOTE retains order while removing duplicates
-        let mut seen = HashSet::new();
-        self.0
-            .iter()
-            .filter(|x| seen.insert(*x))
-            .map(|mirror| mirror.url_for_id(url.gbid()))
-            .collect()
+    /// Builds the downloader with the concurrency, retries, timeouts, and bandwidth limit of the options.
+    pub fn downloader(&self, client: Client, mirrors: Mirrors) -> ModDownloader {
+        let mut downloader = ModDownloader::new(client, mirrors, self.jobs())
+            .with_retry(self.retry_policy())
+            .with_stall(self.stall_policy());
+        if let Some(rate) = self.limit_rate {
+            downloader = downloader.with_limit_rate(rate);
+        }
+        if self.delta {
+            downloader = downloader.with_delta();
+        }
+        match &self.trash {
+            Some(trash) => downloader.with_trash(trash.clone()),
+            None => downloader,
+        }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use std::str::FromStr;
-
-    use super::*;
-
-    #[test]
-    fn test_resolve() {
-        let url = DownloadUrl::from_str("https://gamebanana.com/mmdl/1298450")
-            .expect("should be parsed as this type");
-        let mirrors: Mirrors = Mirrors(vec![Mirror::Otobot, Mirror::Gb, Mirror::Jade]);
-        let result = mirrors.resolve(&url);
-        assert_eq!(result.len(), 3, "should return three URLs");
-        assert_eq!(
-            result.first().unwrap(),
-            &"https://banana-mirror-mods.celestemods.com/1298450.zip".to_string()
+    pub fn retry_policy(&self) -> RetryPolicy {
+        let default = RetryPolicy::default();
+        RetryPolicy::new(
+            self.retries.unwrap_or(default.retries()),
+            self.retry_delay
+                .map_or(default.delay(), Duration::from_secs),
+            self.retry_timeout
+                .map_or(default.timeout(), Duration::from_secs),
         )
     }
 
-    #[test]
-    fn test_resolve_duplicate_entries() {
-        let url = DownloadUrl::from_str("https://gamebanana.com/mmdl/1298450")
-            .expect("should be parsed as this type");
-        let mirrors: Mirrors = Mirrors(vec![Mirror::Otobot, Mirror::Otobot, Mirror::Jade]);
-        let result = mirrors.resolve(&url);
-        assert_eq!(result.len(), 2, "should return only two URLs");
-        assert_eq!(
-            result.first().unwrap(),
-            &"https://banana-mirror-mods.celestemods.com/1298450.zip".to_string()
-        )
+    fn stall_policy(&self) -> StallPolicy {
+        let timeout = self
+            .stall_timeout
+            .map_or(StallPolicy::default().timeout(), Duration::from_secs);
+        StallPolicy::new(timeout, self.min_speed)
     }
 }
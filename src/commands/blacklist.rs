@@ -0,0 +1,168 @@
+//! Handle blacklist command.
+use std::collections::{BTreeSet, HashSet};
+
+use anyhow::{Context, bail};
+use clap::Subcommand;
+use clap_complete::ArgValueCandidates;
+use tracing::warn;
+
+use crate::{
+    commands::completions::installed_mod_names,
+    config::AppConfig,
+    core::{
+        LocalMod,
+        blacklist::{self, LocalUpdaterBlacklistSource, UPDATER_BLACKLIST_FILE, UpdaterBlacklist},
+        local,
+    },
+    ui::style,
+};
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum BlacklistCommand {
+    /// Add mods to the updater's blacklist, so they are never updated.
+    Add {
+        /// File names or mod names of the installed mods.
+        #[arg(
+            required = true,
+            value_name = "FILE_OR_MOD",
+            add = ArgValueCandidates::new(installed_mod_names)
+        )]
+        mods: Vec<String>,
+    },
+
+    /// Remove mods from the updater's blacklist.
+    Remove {
+        /// File names or mod names of the installed mods.
+        #[arg(
+            required = true,
+            value_name = "FILE_OR_MOD",
+            add = ArgValueCandidates::new(installed_mod_names)
+        )]
+        mods: Vec<String>,
+    },
+
+    /// List the mods in the updater's blacklist.
+    List,
+}
+
+/// Adds the mods to `updaterblacklist.txt`.
+pub fn add(targets: &[String], config: &AppConfig) -> anyhow::Result<()> {
+    let mods_dir = config.mods_dir();
+    let mods = local::scan_mods(config)?;
+    let listed = read_listed(config)?;
+    let names = resolve(targets, &mods, &listed)?;
+
+    let added = blacklist::append_to_updater_blacklist(&mods_dir, &names)
+        .with_context(|| format!("Failed to write {}", UPDATER_BLACKLIST_FILE))?;
+    for name in &names {
+        if !mods.iter().any(|m| file_name(m) == Some(name.as_str())) {
+            warn!("{} is not installed", name);
+        }
+        if added.contains(name) {
+            println!("{} {}", style::success("Added"), name);
+        } else {
+            println!("{} is already in {}", name, UPDATER_BLACKLIST_FILE);
+        }
+    }
+    Ok(())
+}
+
+/// Removes the mods from `updaterblacklist.txt`.
+pub fn remove(targets: &[String], config: &AppConfig) -> anyhow::Result<()> {
+    let mods_dir = config.mods_dir();
+    let mods = local::scan_mods(config)?;
+    let listed = read_listed(config)?;
+    let names: HashSet<String> = resolve(targets, &mods, &listed)?.into_iter().collect();
+
+    blacklist::remove_from_updater_blacklist(&mods_dir, &names)
+        .with_context(|| format!("Failed to write {}", UPDATER_BLACKLIST_FILE))?;
+    for name in names.iter().collect::<BTreeSet<_>>() {
+        if listed.contains(name) {
+            println!("{} {}", style::success("Removed"), name);
+        } else {
+            warn!("{} is not in {}", name, UPDATER_BLACKLIST_FILE);
+        }
+    }
+    Ok(())
+}
+
+/// Prints the file names in `updaterblacklist.txt`, with the names of the installed mods.
+pub fn list(config: &AppConfig) -> anyhow::Result<()> {
+    let mods = local::scan_mods(config)?;
+    let ublist = read_blacklist(config)?;
+    if ublist.is_empty() {
+        println!("No mods in {}", UPDATER_BLACKLIST_FILE);
+        return Ok(());
+    }
+    for name in ublist.filenames().iter().collect::<BTreeSet<_>>() {
+        match mods.iter().find(|m| file_name(m) == Some(name.as_str())) {
+            Some(installed) => println!("{} ({})", name, installed.name()),
+            None => println!("{} {}", name, style::warning("(not installed)")),
+        }
+    }
+    for pattern in ublist.patterns() {
+        let matched: Vec<&str> = mods
+            .iter()
+            .filter(|m| pattern.matches(file_name(m).unwrap_or_default(), m.name()))
+            .filter_map(file_name)
+            .collect();
+        if matched.is_empty() {
+            println!("{} {}", pattern, style::warning("(matches nothing)"));
+        } else {
+            println!("{} ({})", pattern, matched.join(", "));
+        }
+    }
+    Ok(())
+}
+
+fn read_blacklist(config: &AppConfig) -> anyhow::Result<UpdaterBlacklist> {
+    let source = LocalUpdaterBlacklistSource::new(&config.mods_dir());
+    blacklist::fetch(&source).with_context(|| format!("Failed to read {}", UPDATER_BLACKLIST_FILE))
+}
+
+/// Returns the lines of the blacklist, i.e. the file names and the patterns as written.
+fn read_listed(config: &AppConfig) -> anyhow::Result<HashSet<String>> {
+    let ublist = read_blacklist(config)?;
+    let patterns = ublist.patterns().iter().map(|p| p.text.clone());
+    Ok(ublist.filenames().iter().cloned().chain(patterns).collect())
+}
+
+/// Resolves the arguments to the file names, since the blacklist only matches them.
+///
+/// File names of the installed mods and the lines of the blacklist, including the patterns, are
+/// taken as is, then the names declared in `everest.yaml` are looked up case-insensitively.
+/// A mod may be installed in several archives.
+fn resolve(
+    targets: &[String],
+    mods: &[LocalMod],
+    listed: &HashSet<String>,
+) -> anyhow::Result<Vec<String>> {
+    let mut names = Vec::new();
+    for target in targets {
+        let target = target.trim();
+        if listed.contains(target) || mods.iter().any(|m| file_name(m) == Some(target)) {
+            names.push(target.to_string());
+            continue;
+        }
+        let files: Vec<&str> = mods
+            .iter()
+            .filter(|m| m.name().eq_ignore_ascii_case(target))
+            .filter_map(file_name)
+            .collect();
+        if !files.is_empty() {
+            names.extend(files.into_iter().map(String::from));
+        } else if target.to_ascii_lowercase().ends_with(".zip") {
+            names.push(target.to_string());
+        } else {
+            bail!(
+                "'{}' is neither a file name nor a name of the installed mods, run `hultra list` to see them",
+                target
+            );
+        }
+    }
+    Ok(names)
+}
+
+fn file_name(installed: &LocalMod) -> Option<&str> {
+    installed.file().path().file_name()?.to_str()
+}
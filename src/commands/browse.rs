@@ -0,0 +1,169 @@
+//! Handle browse command.
+use std::{
+    collections::{BTreeSet, HashSet},
+    io::{self, IsTerminal},
+};
+
+use anyhow::bail;
+use clap::Args;
+use tracing::info;
+
+use crate::{
+    commands::DownloadOption,
+    config::AppConfig,
+    core::network::{
+        SharedHttpClient,
+        gamebanana::{self, ModInfo, Sort},
+    },
+    ui::{self, style},
+};
+
+use super::install;
+
+#[derive(Debug, Clone, Args)]
+pub struct BrowseArgs {
+    /// Category of GameBanana, e.g. `maps` or `helpers`. Lists all mods if omitted.
+    #[arg(long, value_name = "NAME")]
+    pub category: Option<String>,
+
+    /// Order of the mods: `newest`, `likes`, `views`, or `downloads`.
+    #[arg(long, value_name = "SORT", default_value = "newest")]
+    pub sort: Sort,
+
+    /// Page of the list to show.
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+    pub page: u16,
+
+    #[command(flatten)]
+    pub option: DownloadOption,
+}
+
+/// Lists the mods of the category on GameBanana, and installs the ones selected by number.
+pub async fn run(args: BrowseArgs, config: &AppConfig) -> anyhow::Result<()> {
+    if config.offline() {
+        bail!("Browsing mods requires network access, run without `--offline`");
+    }
+    let option = args.option.with_config(config)?;
+
+    let output = config.output();
+    let client = SharedHttpClient::new(config.client())?;
+    let categories = match &args.category {
+        Some(_) => gamebanana::categories(client.inner()).await?,
+        None => Vec::new(),
+    };
+    let category = match &args.category {
+        Some(name) => match gamebanana::find_category(&categories, name) {
+            Some(category) => Some(category),
+            None => {
+                let names: Vec<_> = categories.iter().map(|c| c.name.as_str()).collect();
+                bail!(
+                    "Unknown category '{}', choose one of: {}",
+                    name,
+                    names.join(", ")
+                );
+            }
+        },
+        None => None,
+    };
+
+    info!("fetching the list of mods");
+    let mods = gamebanana::list(client.inner(), category, args.sort, args.page).await?;
+    if output.is_json() {
+        ui::print_json(&mods)?;
+        return Ok(());
+    }
+    if mods.is_empty() {
+        println!("No mods on page {}", args.page);
+        return Ok(());
+    }
+
+    let width = mods.len().to_string().len();
+    for (i, found) in mods.iter().enumerate() {
+        print_mod(i + 1, width, found);
+    }
+    println!(
+        "Page {}, use `--page {}` for more",
+        args.page,
+        args.page + 1
+    );
+
+    // NOTE nobody can answer without a terminal, e.g. in scripts
+    if !io::stdin().is_terminal() {
+        return Ok(());
+    }
+    let answer = tokio::task::spawn_blocking(|| {
+        ui::ask("Install mods by number, e.g. `1 3 5-7` (empty to skip):")
+    })
+    .await??;
+    let selected = parse_selection(&answer, mods.len()).map_err(anyhow::Error::msg)?;
+    if selected.is_empty() {
+        return Ok(());
+    }
+    let ids: HashSet<u32> = selected.iter().map(|&i| mods[i - 1].id).collect();
+    let downloaded = install::install_mods(client.inner().clone(), &ids, option, config).await?;
+    if downloaded.is_empty() {
+        println!("You have already installed the selected mods and their dependencies");
+    }
+    for path in &downloaded {
+        println!(
+            "Installed {}",
+            path.file_name().unwrap_or_default().to_string_lossy()
+        );
+    }
+    Ok(())
+}
+
+fn print_mod(number: usize, width: usize, found: &ModInfo) {
+    println!(
+        "{:>width$}. {} ({}) by {} {}",
+        number,
+        style::name(&found.name),
+        found.kind,
+        found.author,
+        style::path(format!("[{}]", found.id)),
+    );
+    let indent = " ".repeat(width + 2);
+    println!(
+        "{}Category: {}, Downloads: {}, Likes: {}",
+        indent, found.category, found.downloads, found.likes
+    );
+    if !found.description.is_empty() {
+        println!("{}{}", indent, found.description);
+    }
+}
+
+/// Parses the numbers like `1 3, 5-7`, numbered from 1 up to `len`.
+fn parse_selection(answer: &str, len: usize) -> Result<BTreeSet<usize>, String> {
+    let mut selected = BTreeSet::new();
+    for token in answer.split([' ', ',']).filter(|token| !token.is_empty()) {
+        let parse = |s: &str| match s.trim().parse::<usize>() {
+            Ok(n) if (1..=len).contains(&n) => Ok(n),
+            _ => Err(format!("'{}' is not a number from 1 to {}", s, len)),
+        };
+        match token.split_once('-') {
+            Some((start, end)) => selected.extend(parse(start)?..=parse(end)?),
+            None => {
+                selected.insert(parse(token)?);
+            }
+        }
+    }
+    Ok(selected)
+}
+
+#[cfg(test)]
+mod tests_browse {
+    use super::*;
+
+    #[test]
+    fn test_parse_selection() {
+        assert_eq!(
+            parse_selection("1 3, 5-7", 10),
+            Ok(BTreeSet::from([1, 3, 5, 6, 7]))
+        );
+        assert_eq!(parse_selection("", 10), Ok(BTreeSet::new()));
+        assert_eq!(parse_selection("2 2", 10), Ok(BTreeSet::from([2])));
+        assert!(parse_selection("0", 10).is_err());
+        assert!(parse_selection("11", 10).is_err());
+        assert!(parse_selection("maps", 10).is_err());
+    }
+}
@@ -0,0 +1,118 @@
+//! Handle cache command.
+use std::{
+    fs, io,
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+use clap::Subcommand;
+use indicatif::{BinaryBytes, HumanDuration};
+use tracing::info;
+
+use crate::{
+    config::AppConfig,
+    core::cache::{self, CacheError},
+    log::anonymize,
+};
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum CacheCommand {
+    /// Show the location, size, and staleness of the checksum cache and the database cache.
+    Status,
+
+    /// Remove the checksum cache and the database cache.
+    Clear,
+
+    /// Discard the checksum cache and hash all of the installed mods again.
+    Rebuild,
+}
+
+/// Prints the state of the caches.
+pub fn status(config: &AppConfig) -> anyhow::Result<()> {
+    let path = config.cache_db_path();
+    match fs::metadata(path) {
+        Ok(meta) => println!(
+            "Checksum cache: {} ({})",
+            anonymize(path),
+            BinaryBytes(meta.len())
+        ),
+        Err(_) => println!("Checksum cache: {}", anonymize(path)),
+    }
+    match cache::status(config) {
+        Ok(status) => println!(
+            "  {} archives, {} to be hashed, {} stale",
+            status.entries, status.pending, status.stale
+        ),
+        Err(CacheError::Io(e)) if e.kind() == io::ErrorKind::NotFound => {
+            println!("  not created yet, it is created by `hultra update`")
+        }
+        Err(e) => println!("  cannot be read ({}), it is rebuilt on the next run", e),
+    }
+
+    let dir = config.api_cache_dir();
+    println!("Database cache: {}", anonymize(dir));
+    let files = match cached_files(dir) {
+        Ok(files) => files,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(e.into()),
+    };
+    if files.is_empty() {
+        println!("  not created yet, it is created on the first download of the database");
+    }
+    for (name, meta) in files {
+        let age = meta
+            .modified()
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .unwrap_or(Duration::ZERO);
+        println!(
+            "  {}: {}, updated {} ago",
+            name,
+            BinaryBytes(meta.len()),
+            HumanDuration(age)
+        );
+    }
+    Ok(())
+}
+
+/// Lists the names and the metadata of the files in the directory, sorted by name.
+fn cached_files(dir: &Path) -> io::Result<Vec<(String, fs::Metadata)>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)?.flatten() {
+        let meta = entry.metadata()?;
+        if meta.is_file() {
+            files.push((entry.file_name().to_string_lossy().into_owned(), meta));
+        }
+    }
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(files)
+}
+
+/// Removes the caches, they are created again on the next run.
+pub fn clear(config: &AppConfig) -> anyhow::Result<()> {
+    let mut removed = false;
+    if cache::clear(config)? {
+        println!("Removed the checksum cache");
+        removed = true;
+    }
+    match fs::remove_dir_all(config.api_cache_dir()) {
+        Ok(()) => {
+            println!("Removed the database cache");
+            removed = true;
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+    if !removed {
+        println!("No cache to remove");
+    }
+    Ok(())
+}
+
+/// Rebuilds the checksum cache from scratch.
+pub fn rebuild(config: &AppConfig) -> anyhow::Result<()> {
+    info!("hashing installed mods");
+    let cache_db = cache::rebuild(config)?;
+    println!("Hashed {} archives", cache_db.len());
+    Ok(())
+}
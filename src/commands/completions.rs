@@ -0,0 +1,58 @@
+//! Handle completions command.
+use std::io;
+
+use anyhow::bail;
+use clap::CommandFactory;
+use clap_complete::{
+    Shell,
+    engine::CompletionCandidate,
+    env::{CompleteEnv, Shells},
+};
+
+use crate::{
+    cli::Cli,
+    config::{AppConfig, CARGO_PKG_NAME},
+    core::cache,
+};
+
+/// Environment variable which makes the binary print the completions instead of running.
+const COMPLETE_VAR: &str = "COMPLETE";
+
+/// Prints the completions and exits, if the shell is asking for them.
+pub fn complete_if_requested() {
+    CompleteEnv::with_factory(Cli::command)
+        .var(COMPLETE_VAR)
+        .complete();
+}
+
+/// Prints the script which registers the completions to the shell.
+///
+/// The script calls `hultra` on each completion, so the installed mod names are always fresh.
+pub fn run(shell: Shell) -> anyhow::Result<()> {
+    let shells = Shells::builtins();
+    let Some(completer) = shells.completer(&shell.to_string()) else {
+        bail!("Completions for {} are not supported", shell);
+    };
+    completer.write_registration(
+        COMPLETE_VAR,
+        CARGO_PKG_NAME,
+        CARGO_PKG_NAME,
+        CARGO_PKG_NAME,
+        &mut io::stdout(),
+    )?;
+    Ok(())
+}
+
+/// Returns the names of the installed mods from the checksum cache, without opening archives.
+///
+/// NOTE `--directory` and `--profile` of the command line being completed are not known here
+pub fn installed_mod_names() -> Vec<CompletionCandidate> {
+    let Ok(config) = AppConfig::new(None, None) else {
+        return Vec::new();
+    };
+    let cache_db = cache::load(&config);
+    let mut names: Vec<&str> = cache_db.mod_names().collect();
+    names.sort_unstable_by_key(|name| name.to_ascii_lowercase());
+    names.dedup();
+    names.into_iter().map(CompletionCandidate::new).collect()
+}
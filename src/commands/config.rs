@@ -0,0 +1,53 @@
+//! Handle config command.
+use std::{fs, path::PathBuf};
+
+use anyhow::{Context, bail};
+use clap::{Subcommand, ValueEnum};
+
+use crate::{
+    config::{self, AppConfig},
+    log::anonymize,
+};
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum ConfigCommand {
+    /// Set a value of the current profile in the config file.
+    Set {
+        /// Name of the setting.
+        key: ConfigKey,
+
+        /// New value of the setting.
+        value: String,
+    },
+}
+
+/// Settings which can be changed from the command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ConfigKey {
+    /// Directory where the Celeste is installed, i.e. `directory` of the profile.
+    CelesteDir,
+}
+
+/// Updates the config file.
+pub fn set(key: ConfigKey, value: &str, config: &AppConfig) -> anyhow::Result<()> {
+    match key {
+        ConfigKey::CelesteDir => {
+            let dir = fs::canonicalize(PathBuf::from(value))
+                .with_context(|| format!("'{}' is not a directory", value))?;
+            if !config::is_install(&dir) {
+                bail!(
+                    "'{}' does not contain Celeste.exe and Mods/, is Everest installed?",
+                    anonymize(&dir)
+                );
+            }
+            let profile = config.set_directory(&dir)?;
+            println!(
+                "Set directory of profile '{}' to {} in {}",
+                profile,
+                anonymize(&dir),
+                anonymize(config.config_path())
+            );
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,73 @@
+//! Handle doctor command.
+use std::path::Path;
+
+use anyhow::bail;
+use serde::Serialize;
+use tracing::info;
+
+use crate::{
+    config::AppConfig,
+    core::local::doctor::{self, Diagnosis},
+    log::anonymize,
+    ui::{self, style},
+};
+
+/// Archive with problems in the JSON output.
+#[derive(Debug, Serialize)]
+struct DiagnosisDetails<'a> {
+    file: &'a Path,
+    issues: Vec<IssueDetails>,
+}
+
+#[derive(Debug, Serialize)]
+struct IssueDetails {
+    kind: &'static str,
+    message: String,
+}
+
+impl<'a> From<&'a Diagnosis> for DiagnosisDetails<'a> {
+    fn from(value: &'a Diagnosis) -> Self {
+        Self {
+            file: &value.path,
+            issues: value
+                .issues
+                .iter()
+                .map(|issue| IssueDetails {
+                    kind: issue.kind(),
+                    message: issue.to_string(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Checks the installed archives for problems which Everest does not report clearly.
+pub fn run(config: &AppConfig) -> anyhow::Result<()> {
+    info!("checking installed mods");
+    let diagnoses = doctor::diagnose_mods(&config.mods_dir(), config.exclude())?;
+    let output = config.output();
+    output.print_text(&format!(
+        "Celeste in {} ({})",
+        anonymize(config.root_dir()),
+        config.install_kind()
+    ));
+
+    if output.is_json() {
+        let details: Vec<DiagnosisDetails> = diagnoses.iter().map(DiagnosisDetails::from).collect();
+        ui::print_json(&details)?;
+    }
+    if diagnoses.is_empty() {
+        output.print_text("No problems found");
+        return Ok(());
+    }
+
+    if !output.is_json() {
+        for diagnosis in &diagnoses {
+            println!("{}", style::path(anonymize(&diagnosis.path)));
+            for issue in &diagnosis.issues {
+                println!("  {} {}", style::error("*"), issue);
+            }
+        }
+    }
+    bail!("Found problems in {} archives", diagnoses.len())
+}
@@ -0,0 +1,105 @@
+//! Handle du command.
+use std::path::Path;
+
+use clap::Args;
+use indicatif::BinaryBytes;
+use serde::Serialize;
+use tracing::info;
+
+use crate::{
+    config::AppConfig,
+    core::local::{self, usage},
+    ui::{self, style},
+};
+
+#[derive(Debug, Clone, Args)]
+pub struct DuArgs {
+    /// Number of the largest mods to show.
+    #[arg(short = 'n', long, value_name = "N", default_value_t = 10)]
+    pub top: usize,
+}
+
+/// Disk usage in the JSON output, in bytes.
+#[derive(Debug, Serialize)]
+struct UsageDetails<'a> {
+    /// All files under the Mods directory.
+    total: u64,
+    files: u64,
+    /// The installed mods, i.e. the archives and folders with everest.yaml.
+    mods: u64,
+    mod_count: usize,
+    /// Anything else, e.g. partial downloads, backups, and archives without everest.yaml.
+    other: u64,
+    largest: Vec<LargestMod<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct LargestMod<'a> {
+    name: &'a str,
+    file: &'a Path,
+    size: u64,
+}
+
+/// Summarizes the disk usage of the Mods directory and the largest mods.
+pub fn run(args: DuArgs, config: &AppConfig) -> anyhow::Result<()> {
+    info!("scanning installed mods");
+    let mods = local::scan_mods(config)?;
+    let total = usage::dir_usage(&config.mods_dir())?;
+    let mods_size: u64 = mods.iter().map(usage::mod_size).sum();
+    let largest = usage::largest(&mods, args.top);
+
+    let details = UsageDetails {
+        total: total.bytes,
+        files: total.files,
+        mods: mods_size,
+        mod_count: mods.len(),
+        other: total.bytes.saturating_sub(mods_size),
+        largest: largest
+            .iter()
+            .map(|(m, size)| LargestMod {
+                name: m.name(),
+                file: m.file().path(),
+                size: *size,
+            })
+            .collect(),
+    };
+    if config.output().is_json() {
+        ui::print_json(&details)?;
+        return Ok(());
+    }
+
+    println!(
+        "Mods directory: {} in {} files",
+        BinaryBytes(details.total),
+        details.files
+    );
+    println!(
+        "  Installed mods: {} in {} mods",
+        BinaryBytes(details.mods),
+        details.mod_count
+    );
+    println!("  Other files: {}", BinaryBytes(details.other));
+    if details.largest.is_empty() {
+        return Ok(());
+    }
+
+    println!("\nLargest mods:");
+    let width = details
+        .largest
+        .iter()
+        .map(|m| BinaryBytes(m.size).to_string().len())
+        .max()
+        .unwrap_or_default();
+    for m in &details.largest {
+        let size = BinaryBytes(m.size).to_string();
+        let file_name = m.file.file_name().unwrap_or_default().to_string_lossy();
+        println!(
+            "  {:>width$}  {} {}",
+            size,
+            style::name(m.name),
+            style::path(format!("({})", file_name)),
+            width = width
+        );
+    }
+    Ok(())
+}
@@ -1,9 +1,10 @@
 //! Everest commands and the sub commands.
 use clap::Subcommand;
 
-use crate::commands::everest::network::NetworkCommand;
+use crate::commands::everest::{network::NetworkCommand, status::StatusArgs};
 
 pub mod network;
+pub mod status;
 pub mod version;
 
 #[derive(Debug, Clone, Subcommand)]
@@ -11,6 +12,12 @@ pub enum EverestSubCommand {
     /// Print the current installed version
     Version,
 
+    /// Show whether Everest is installed, and whether a newer build is available
+    #[command(after_help = "Examples:
+  hultra everest status
+  hultra --offline everest status")]
+    Status(StatusArgs),
+
     #[command(flatten)]
     NetworkRequired(NetworkCommand),
 }
@@ -0,0 +1,127 @@
+//! Everest status command handler.
+use clap::Args;
+use serde::Serialize;
+use tracing::warn;
+
+use super::network::NetworkOption;
+use crate::{
+    config::{self, AppConfig},
+    everest::{
+        self, EverestHttpClient,
+        build::EverestBuild,
+        status::{BuildStatus, Installation},
+        version::FileVersionRepository,
+    },
+    ui::{self, style},
+    utils,
+};
+
+#[derive(Debug, Clone, Args)]
+pub struct StatusArgs {
+    #[command(flatten)]
+    pub option: NetworkOption,
+}
+
+/// Status of Everest in the JSON output.
+#[derive(Debug, Serialize)]
+struct StatusOutput<'a> {
+    directory: String,
+    installed: bool,
+    build: Option<u32>,
+    branch: Option<&'static str>,
+    /// Latest build on the branch, `null` with `--offline` or if the database cannot be fetched.
+    latest_build: Option<u32>,
+    update_available: bool,
+    #[serde(skip)]
+    latest: Option<&'a EverestBuild>,
+}
+
+/// Prints whether Everest is installed, and whether a newer build is available.
+pub async fn run(args: &StatusArgs, config: &AppConfig) -> anyhow::Result<()> {
+    let installation = Installation::inspect(&FileVersionRepository::new(config));
+    let installed = installation.build();
+
+    let builds = if config.offline() {
+        None
+    } else {
+        let client = EverestHttpClient::new(config.client())?;
+        match everest::fetch(client.inner().clone(), &args.option).await {
+            Ok(builds) => Some(builds),
+            Err(e) => {
+                warn!(?e, "failed to check the latest build");
+                None
+            }
+        }
+    };
+    let status = builds
+        .as_deref()
+        .map(|builds| BuildStatus::new(installed, builds));
+    let newer = status
+        .as_ref()
+        .zip(installed)
+        .and_then(|(status, installed)| status.newer_than(installed));
+
+    let output = StatusOutput {
+        directory: config.root_dir().display().to_string(),
+        installed: matches!(installation, Installation::Installed(_)),
+        build: installed,
+        branch: status
+            .as_ref()
+            .and_then(|status| status.branch)
+            .map(|branch| branch.as_str()),
+        latest_build: status
+            .as_ref()
+            .and_then(|status| status.latest)
+            .map(|build| build.version),
+        update_available: newer.is_some(),
+        latest: status.as_ref().and_then(|status| status.latest),
+    };
+    if config.output().is_json() {
+        ui::print_json(&output)?;
+        return Ok(());
+    }
+    print_status(&output, &installation, config);
+    Ok(())
+}
+
+fn print_status(output: &StatusOutput, installation: &Installation, config: &AppConfig) {
+    let game = if config::is_install(config.root_dir()) {
+        String::new()
+    } else {
+        format!(" {}", style::warning("(Celeste is not found)"))
+    };
+    println!("Celeste:  {}{}", style::path(&output.directory), game);
+
+    match installation {
+        Installation::NotInstalled => println!("Everest:  {}", style::error("not installed")),
+        Installation::Installed(number) => match output.branch {
+            Some(branch) => println!("Everest:  build {} ({})", number.value(), branch),
+            None => println!("Everest:  build {}", number.value()),
+        },
+        Installation::Unknown(e) => println!("Everest:  {} ({})", style::warning("unknown"), e),
+    }
+
+    let Some(latest) = output.latest else {
+        if config.offline() {
+            println!("Run without `--offline` to check the latest build");
+        }
+        return;
+    };
+    println!(
+        "Latest:   build {} ({}, released {})",
+        latest.version,
+        latest.branch,
+        utils::format_date(latest.date())
+    );
+    match installation {
+        Installation::Installed(_) if output.update_available => println!(
+            "{}, run `hultra everest update` to update Everest",
+            style::version("A newer build is available")
+        ),
+        Installation::Installed(_) => println!("{}", style::success("Everest is up-to-date")),
+        _ => println!(
+            "Run `hultra everest install {}` to install Everest",
+            latest.version
+        ),
+    }
+}
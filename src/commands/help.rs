@@ -0,0 +1,199 @@
+//! Handle help command.
+//!
+//! Topics are rendered from the definitions in code, so they stay accurate as they change.
+use std::fmt::Write;
+
+use anyhow::bail;
+use clap::Args;
+
+use crate::{
+    config::{AppConfig, CONFIG_EXAMPLE, ENV_VARS},
+    core::{
+        blacklist::{EVEREST_BLACKLIST_FILE, UPDATER_BLACKLIST_FILE},
+        mirrorlist::Mirror,
+        network::probe::CACHE_TTL,
+    },
+    log::anonymize,
+};
+
+use super::DownloadOption;
+
+#[derive(Debug, Clone, Args)]
+pub struct HelpArgs {
+    /// Command to show help for, or `topics [TOPIC]` for guides.
+    #[arg(value_name = "COMMAND")]
+    pub command: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Topic {
+    Mirrors,
+    Blacklists,
+    Profiles,
+}
+
+impl Topic {
+    const ALL: [Topic; 3] = [Self::Mirrors, Self::Blacklists, Self::Profiles];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Mirrors => "mirrors",
+            Self::Blacklists => "blacklists",
+            Self::Profiles => "profiles",
+        }
+    }
+
+    fn summary(self) -> &'static str {
+        match self {
+            Self::Mirrors => "Where mods are downloaded from",
+            Self::Blacklists => "Excluding mods from updates and scans",
+            Self::Profiles => "The config file and machine specific settings",
+        }
+    }
+
+    fn render(self, config: &AppConfig) -> Result<String, std::fmt::Error> {
+        let mut out = String::new();
+        match self {
+            Self::Mirrors => {
+                writeln!(
+                    out,
+                    "Mods are downloaded from the first mirror of the priority, and the next one is tried on failure.\n"
+                )?;
+                writeln!(out, "Built-in mirrors:")?;
+                for mirror in Mirror::BUILTIN {
+                    let template = mirror.builtin_template().unwrap_or_default();
+                    writeln!(out, "  {:<8} {}", mirror, template)?;
+                }
+                let default = DownloadOption::DEFAULT_MIRROR_PRIORITY
+                    .iter()
+                    .map(Mirror::to_string)
+                    .collect::<Vec<_>>()
+                    .join(",");
+                writeln!(out, "\nDefault priority: {}", default)?;
+                writeln!(
+                    out,
+                    "Use `--mirror-priority auto` to order all mirrors by latency, measured by `hultra mirrors test` and reused for {} hours.",
+                    CACHE_TTL.as_secs() / 60 / 60
+                )?;
+                writeln!(
+                    out,
+                    "Use `--mirror-index` to skip mirrors which do not host the file yet."
+                )?;
+                writeln!(
+                    out,
+                    "Custom mirrors are defined in the `mirrors` table of the config file, see `hultra help topics profiles`."
+                )?;
+            }
+            Self::Blacklists => {
+                writeln!(out, "Files in the Mods directory:")?;
+                writeln!(
+                    out,
+                    "  {:<22} Mods listed by file name are never updated. Lines starting with '#' are ignored.",
+                    UPDATER_BLACKLIST_FILE
+                )?;
+                writeln!(
+                    out,
+                    "{:<24} Glob patterns like `SpringCollab*.zip` and `name: SpeedrunTool` also work here,",
+                    ""
+                )?;
+                writeln!(
+                    out,
+                    "{:<24} but not in Everest's own updater. Patterns matching nothing are warned.",
+                    ""
+                )?;
+                writeln!(
+                    out,
+                    "{:<24} Edit it with `hultra blacklist add/remove/list`, which accept mod names too.",
+                    ""
+                )?;
+                writeln!(
+                    out,
+                    "  {:<22} Maintained by Everest, mods listed there are not loaded by the game.",
+                    EVEREST_BLACKLIST_FILE
+                )?;
+                writeln!(
+                    out,
+                    "{:<24} They are still updated unless `update --skip-disabled`.",
+                    ""
+                )?;
+                writeln!(
+                    out,
+                    "\nThe `exclude` list of the config file has glob patterns of the entries in the Mods directory,"
+                )?;
+                writeln!(out, "which are ignored by every command.")?;
+            }
+            Self::Profiles => {
+                writeln!(out, "Config file: {}\n", anonymize(config.config_path()))?;
+                writeln!(
+                    out,
+                    "A profile is selected by `--profile NAME`, or by the hostname of the machine."
+                )?;
+                writeln!(
+                    out,
+                    "The checksum cache and the offload records are kept per profile, so each profile can have its own Mods directory."
+                )?;
+                writeln!(
+                    out,
+                    "Command line options always take precedence over the profile.\n"
+                )?;
+                writeln!(out, "Example:\n")?;
+                for line in CONFIG_EXAMPLE.lines() {
+                    writeln!(out, "  {}", line)?;
+                }
+                writeln!(
+                    out,
+                    "\nEnvironment variables override the profile, and are overridden by the options:"
+                )?;
+                for (name, key) in ENV_VARS {
+                    writeln!(out, "  {:<22} {}", name, key)?;
+                }
+                writeln!(
+                    out,
+                    "`HULTRA_PROFILE` selects the profile like `--profile` if it is not given."
+                )?;
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// Prints help of the command, or the topic.
+pub fn run(args: HelpArgs, mut root: clap::Command, config: &AppConfig) -> anyhow::Result<()> {
+    if let Some((first, rest)) = args.command.split_first()
+        && first == "topics"
+    {
+        return print_topic(rest.first().map(String::as_str), config);
+    }
+
+    root.build();
+    let mut cmd = &mut root;
+    for name in &args.command {
+        match cmd.find_subcommand_mut(name) {
+            Some(sub) => cmd = sub,
+            None => bail!(
+                "Unknown command '{}', see `hultra help` for commands",
+                args.command.join(" ")
+            ),
+        }
+    }
+    cmd.print_long_help()?;
+    Ok(())
+}
+
+fn print_topic(name: Option<&str>, config: &AppConfig) -> anyhow::Result<()> {
+    let Some(name) = name else {
+        println!("Help topics:");
+        for topic in Topic::ALL {
+            println!("  {:<12} {}", topic.name(), topic.summary());
+        }
+        println!("\nRun `hultra help topics <TOPIC>` for details.");
+        return Ok(());
+    };
+
+    let Some(topic) = Topic::ALL.into_iter().find(|topic| topic.name() == name) else {
+        let names = Topic::ALL.map(Topic::name).join(", ");
+        bail!("Unknown topic '{}', available topics are {}", name, names);
+    };
+    print!("{}", topic.render(config)?);
+    Ok(())
+}
@@ -0,0 +1,70 @@
+//! Handle history command.
+use clap::Args;
+
+use crate::{
+    config::AppConfig,
+    core::history::{self, Action},
+    ui::{self, style},
+    utils::format_unix_time,
+};
+
+#[derive(Debug, Clone, Args)]
+pub struct HistoryArgs {
+    /// Shows only the changes to the mod, by mod name or file name.
+    #[arg(value_name = "MOD")]
+    pub name: Option<String>,
+
+    /// Number of the latest changes to show.
+    #[arg(short = 'n', long, value_name = "N", default_value_t = 20)]
+    pub last: usize,
+}
+
+/// Prints the latest changes to the installed mods, the newest first.
+pub fn run(args: HistoryArgs, config: &AppConfig) -> anyhow::Result<()> {
+    let mut records = history::load(config.history_path())?;
+    if let Some(name) = &args.name {
+        records.retain(|record| record.matches(name));
+    }
+    let skip = records.len().saturating_sub(args.last);
+    let records: Vec<_> = records.into_iter().skip(skip).rev().collect();
+
+    let output = config.output();
+    if output.is_json() {
+        ui::print_json(&records)?;
+        return Ok(());
+    }
+    if records.is_empty() {
+        match &args.name {
+            Some(name) => println!("No changes to {} have been recorded", name),
+            None => println!("No changes have been recorded"),
+        }
+        return Ok(());
+    }
+
+    let width = records
+        .iter()
+        .map(|r| r.name.len())
+        .max()
+        .unwrap_or_default();
+    for record in &records {
+        let change = match (&record.old_version, &record.new_version) {
+            (Some(old), Some(new)) => format!("v{} -> v{}", old, new),
+            (Some(version), None) | (None, Some(version)) => format!("v{}", version),
+            (None, None) => String::new(),
+        };
+        let action = match record.action {
+            Action::Install | Action::Restore => style::success(format!("{:<7}", record.action)),
+            Action::Update => style::version(format!("{:<7}", record.action)),
+            Action::Offload => style::warning(format!("{:<7}", record.action)),
+        };
+        println!(
+            "{}  {}  {}  {} {}",
+            format_unix_time(record.time),
+            action,
+            style::name(format!("{:<width$}", record.name)),
+            change,
+            style::path(format!("({})", record.file)),
+        );
+    }
+    Ok(())
+}
@@ -1,15 +1,41 @@
 //! Handle install command.
-use std::{collections::HashSet, ops::Deref, str::FromStr};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File},
+    io::{self, IsTerminal},
+    ops::Deref,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
+use anyhow::{Context, bail};
 use clap::Args;
-use tracing::info;
+use indicatif::BinaryBytes;
+use reqwest::Client;
+use tempfile::NamedTempFile;
+use tracing::{debug, info, instrument, warn};
 
 use crate::{
     config::AppConfig,
     core::{
-        local,
-        network::{SharedHttpClient, api, downloader},
+        LocalMod,
+        dependency::{DependencyGraph, EverestRequirement, InstallGroup},
+        download::DownloadFile,
+        gamebanana::{self, GamebananaIdError},
+        history::{self, Action},
+        installed,
+        known_deps::{self, KnownDependencies},
+        local::{self, readme},
+        network::{
+            SharedHttpClient, api,
+            downloader::{self, DownloadReport, ModDownloader},
+        },
+        registry::ModRegistry,
+        version::is_older,
     },
+    everest::version::{FileVersionRepository, fetch_installed_version},
+    log::anonymize,
+    ui, utils,
 };
 
 use super::DownloadOption;
@@ -17,35 +43,45 @@ use super::DownloadOption;
 #[derive(Debug, Args, Clone)]
 pub struct InstallArgs {
     /// URL(s) of mod page on GameBanana.
-    #[arg(required = true, num_args = 1..20)]
+    #[arg(required_unless_present = "files", num_args = 1..20)]
     pub urls: Vec<GamebananaUrl>,
 
+    /// Installs the downloaded archive and its missing dependencies.
+    #[arg(long = "file", value_name = "ZIP", value_parser = existing_file)]
+    pub files: Vec<PathBuf>,
+
+    /// Prints README and LICENSE files of the installed mods.
+    #[arg(long)]
+    pub show_readme: bool,
+
+    /// Maximum number of lines to print for each README.
+    #[arg(long, value_name = "N", default_value_t = 20, requires = "show_readme")]
+    pub readme_lines: usize,
+
+    /// Asks for confirmation even if stdin is not a terminal.
+    #[arg(short, long, conflicts_with = "yes")]
+    pub interactive: bool,
+
     #[command(flatten)]
     pub option: DownloadOption,
 }
 
-#[derive(thiserror::Error, Debug)]
-pub enum ArgumentError {
-    #[error(
-        "last path segment of URL must be a positive integer up to {}",
-        u32::MAX
-    )]
-    ParseLastSegAsInt(#[from] std::num::ParseIntError),
-    #[error("it must be starts with 'https://gamebanana.com/mods/'")]
-    InvalidUrl,
-}
-
+/// URL of the mod page on GameBanana, validated when the arguments are parsed.
 #[derive(Debug, Clone)]
-pub struct GamebananaUrl(String);
+pub struct GamebananaUrl {
+    raw: String,
+    id: u32,
+}
 
 impl FromStr for GamebananaUrl {
-    type Err = ArgumentError;
+    type Err = GamebananaIdError;
 
     fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
-        s.strip_prefix("https://gamebanana.com/mods/")
-            .ok_or(ArgumentError::InvalidUrl)?
-            .parse::<u32>()?;
-        Ok(GamebananaUrl(s.to_string()))
+        let id = gamebanana::extract_mod_id(s)?;
+        Ok(GamebananaUrl {
+            raw: s.to_string(),
+            id,
+        })
     }
 }
 
@@ -53,63 +89,455 @@ impl Deref for GamebananaUrl {
     type Target = String;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.raw
     }
 }
 
 impl GamebananaUrl {
-    pub fn extract_id(&self) -> Result<u32, ArgumentError> {
-        let id_part = self
-            .0
-            .strip_prefix("https://gamebanana.com/mods/")
-            .ok_or(ArgumentError::InvalidUrl)?;
-        let id = id_part.parse()?;
-        Ok(id)
+    /// Returns the mod ID.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+}
+
+fn existing_file(value: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(value);
+    if path.is_file() {
+        Ok(path)
+    } else {
+        Err(format!("'{}' is not a file", value))
     }
 }
 
 pub async fn run(args: InstallArgs, config: &AppConfig) -> anyhow::Result<()> {
+    let option = args.option.with_config(config)?;
+
     // Initialize client
-    let shared_client = SharedHttpClient::new();
+    let shared_client = SharedHttpClient::new(config.client())?;
+    let client = shared_client.inner().clone();
 
     // Parse mod page URLs to get mod IDs
-    let ids: HashSet<u32> = args
-        .urls
-        .iter()
-        .filter_map(|url| url.extract_id().ok())
-        .collect();
+    let ids: HashSet<u32> = args.urls.iter().map(GamebananaUrl::id).collect();
+
+    // Read the downloaded archives
+    let mut archives = Vec::with_capacity(args.files.len());
+    for path in &args.files {
+        let m = local::read_mod(path)
+            .with_context(|| format!("failed to read everest.yaml of '{}'", path.display()))?;
+        archives.push(m);
+    }
+
+    let plan = plan_install(client.clone(), &ids, &archives, &option, config).await?;
+    let mods_dir = config.mods_dir();
+    if plan.targets.is_empty() {
+        let copied = copy_archives(&plan.archives, &mods_dir, config)?;
+        if copied.is_empty() {
+            println!("You have already installed the mod and its dependencies");
+        } else {
+            super::sync::write_lockfile(config);
+        }
+        if args.show_readme {
+            for path in &copied {
+                print_notices(path, args.readme_lines);
+            }
+        }
+        return Ok(());
+    }
+    if option.offline {
+        print_summary(&plan);
+        bail!("Installing mods requires network access, run without `--offline`");
+    }
+
+    let mirrors = option.mirrors(&client).await?;
+
+    print_summary(&plan);
+    if !config.assume_yes() {
+        // NOTE nobody can answer without a terminal, e.g. in scripts
+        if !args.interactive && !io::stdin().is_terminal() {
+            bail!(
+                "Installing mods requires confirmation, pass `--yes` to install non-interactively"
+            );
+        }
+        // NOTE prefetch while waiting for the answer to shave off the start time
+        let prefetch = downloader::prefetch(&client, &mirrors, &plan.targets);
+        let confirmed =
+            tokio::task::spawn_blocking(|| ui::confirm("Proceed with installation?")).await??;
+        if !confirmed {
+            prefetch.abort();
+            println!("Installation canceled");
+            return Ok(());
+        }
+    }
+
+    let copied = copy_archives(&plan.archives, &mods_dir, config)?;
+
+    info!("downloading mods");
+    let downloader = option.downloader(client, mirrors);
+    let report = download_planned(&downloader, plan, &mods_dir).await?;
+    ui::print_download_report(&report, config.output());
+    record_downloads(&report.succeeded, config);
+    if !copied.is_empty() || !report.succeeded.is_empty() {
+        super::sync::write_lockfile(config);
+    }
+
+    if args.show_readme {
+        for path in copied.iter().chain(&report.succeeded) {
+            print_notices(path, args.readme_lines);
+        }
+    }
+    report.check()?;
+
+    info!("installation completed");
+    Ok(())
+}
+
+/// Installs the mods of given IDs and their missing dependencies.
+///
+/// Returns paths to the downloaded files, empty if all of them are already installed.
+pub(super) async fn install_mods(
+    client: Client,
+    ids: &HashSet<u32>,
+    option: DownloadOption,
+    config: &AppConfig,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let plan = plan_install(client.clone(), ids, &[], &option, config).await?;
+    if plan.targets.is_empty() {
+        return Ok(Vec::new());
+    }
+    if option.offline {
+        bail!("Installing mods requires network access, run without `--offline`");
+    }
+
+    let mirrors = option.mirrors(&client).await?;
+    info!("downloading mods");
+    let downloader = option.downloader(client, mirrors);
+    let report = download_planned(&downloader, plan, &config.mods_dir()).await?;
+    record_downloads(&report.succeeded, config);
+    if !report.succeeded.is_empty() {
+        super::sync::write_lockfile(config);
+    }
+    for (name, e) in &report.failed {
+        warn!(name, reasons = ?e.reasons(), "failed to download");
+    }
+    report.check()?;
+    Ok(report.succeeded)
+}
 
-    info!("fetching databases");
-    let (registry, graph) = api::fetch(shared_client.inner().clone(), &args.option).await?;
+/// Files to download, with the databases to resolve the dependencies found after downloading.
+struct InstallPlan {
+    /// Empty if all of the mods are already installed.
+    targets: Vec<DownloadFile>,
+    /// Names of the mods to download, including the ones not in the registry.
+    requested: HashSet<String>,
+    /// Mods to download which are not in the dependency graph yet.
+    unresolved: HashSet<String>,
+    /// Mods to download grouped by the target.
+    groups: Vec<InstallGroup>,
+    registry: ModRegistry,
+    graph: DependencyGraph,
+    /// Mod name to installed version.
+    installed: HashMap<String, String>,
+    /// Downloaded archives given by the user, with the paths to copy them to.
+    archives: Vec<(LocalMod, PathBuf)>,
+    /// Where the dependencies read from the downloaded mods are recorded.
+    dependencies_path: PathBuf,
+}
+
+/// Resolves the mods of given IDs, the downloaded archives, and their missing dependencies
+/// into the files to download.
+#[instrument(level = "debug", skip_all, fields(ids = ids.len(), archives = archives.len(), lazy_deps = option.lazy_deps))]
+async fn plan_install(
+    client: Client,
+    ids: &HashSet<u32>,
+    archives: &[LocalMod],
+    option: &DownloadOption,
+    config: &AppConfig,
+) -> anyhow::Result<InstallPlan> {
+    let dependencies_path = known_deps::path(&option.api_cache_dir);
+    let (registry, mut graph) = if option.lazy_deps {
+        info!("fetching database");
+        let registry = api::fetch_registry(client, option).await?;
+        let graph = KnownDependencies::load(&dependencies_path).graph(&registry);
+        (registry, graph)
+    } else {
+        info!("fetching databases");
+        api::fetch(client, option).await?
+    };
 
     info!("scanning installed mods");
-    let installed_names: HashSet<String> = local::scan_mods(&config.mods_dir())?
-        .iter()
-        .map(|m| m.name().to_string())
-        .collect();
+    let mods_dir = config.mods_dir();
+    let mut installed: HashMap<String, String> = HashMap::new();
+    let mut installed_paths: HashMap<String, PathBuf> = HashMap::new();
+    for m in local::scan_mods(config)? {
+        // NOTE the manifests tell the dependencies of the installed versions without the graph
+        if option.lazy_deps {
+            graph.insert(&m);
+        }
+        for (name, declared_version) in m.declared() {
+            let version = installed.entry(name.to_string()).or_default();
+            // NOTE keep the newest one if the mod is installed twice
+            if version.is_empty() || is_older(version, declared_version) {
+                *version = declared_version.to_string();
+                installed_paths.insert(name.to_string(), m.file().path().to_path_buf());
+            }
+        }
+    }
+
+    // NOTE the archives are installed as they are, so their manifests take precedence
+    let mut target_names = registry.names_by_gbids(ids);
+    let mut planned_archives = Vec::with_capacity(archives.len());
+    for m in archives {
+        graph.insert(m);
+        for (name, version) in m.declared() {
+            installed.insert(name.to_string(), version.to_string());
+            target_names.insert(name.to_string());
+        }
+        // NOTE replace the installed archive of the mod, so it is not loaded twice
+        let dest = match installed_paths.get(m.name()) {
+            Some(path) => path.clone(),
+            None => mods_dir.join(archive_file_name(m)),
+        };
+        planned_archives.push((m.clone(), dest));
+    }
 
     // Resolve missing deps
     info!("resolving missing dependencies");
-    let targets = graph.resolve_missing_mods(&ids, &registry, &installed_names);
+    let resolution = graph.check_names(target_names, &installed);
 
-    if targets.is_empty() {
-        println!("You have already installed the mod and its dependencies");
-        return Ok(());
+    if !resolution.outdated.is_empty() {
+        println!("Outdated dependencies, run `hultra update` to update them:");
+        for dep in &resolution.outdated {
+            println!(
+                "  {} v{} (requires v{})",
+                dep.name, dep.installed, dep.required
+            );
+        }
+    }
+
+    if let Some(required) = &resolution.everest {
+        check_everest(required, config);
     }
 
     // Convert targets into tasks
-    let tasks = registry.into_download_files(targets, installed_names)?;
+    let installed_names = installed.keys().cloned().collect();
+    let targets = registry.download_files(resolution.missing.clone(), installed_names)?;
+    Ok(InstallPlan {
+        targets,
+        requested: resolution.missing,
+        unresolved: resolution.unresolved,
+        groups: resolution.groups,
+        registry,
+        graph,
+        installed,
+        archives: planned_archives,
+        dependencies_path,
+    })
+}
 
-    // Download all mods
-    info!("downloading mods");
-    downloader::download_all(
-        shared_client.inner().clone(),
-        args.option,
-        tasks,
-        &config.mods_dir(),
-    )
-    .await?;
+/// Returns the file name of the archive in the mods directory.
+///
+/// The name of the mod is preferred like the downloaded ones, falling back to the given name.
+fn archive_file_name(m: &LocalMod) -> PathBuf {
+    match utils::sanitize_stem(m.name()) {
+        Ok(stem) if !stem.is_empty() => PathBuf::from(format!("{}.zip", stem)),
+        _ => PathBuf::from(m.file().path().file_name().unwrap_or_default()),
+    }
+}
 
-    info!("installation completed");
+/// Copies the downloaded archives into the mods directory, returns the paths to the copies.
+///
+/// The copies are recorded in the history, as updates if they replaced an installed archive.
+fn copy_archives(
+    archives: &[(LocalMod, PathBuf)],
+    mods_dir: &Path,
+    config: &AppConfig,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let mut copied = Vec::with_capacity(archives.len());
+    let mut replaced = HashMap::new();
+    for (m, dest) in archives {
+        let src = m.file().path();
+        // NOTE the archive may have been downloaded into the mods directory
+        if dest.canonicalize().ok() == Some(src.canonicalize()?) {
+            debug!("{} is already in the mods directory", anonymize(src));
+            continue;
+        }
+        if let Ok(old) = local::read_mod(dest) {
+            replaced.insert(old.name().to_string(), old.version().to_string());
+            if let Some(trash) = config.trash()
+                && let Err(e) = trash.keep(dest)
+            {
+                warn!(?e, "failed to keep {} in the trash", anonymize(dest));
+            }
+        }
+        copy_archive(src, dest, mods_dir)
+            .with_context(|| format!("failed to copy '{}'", src.display()))?;
+        println!(
+            "Installed {} v{} from {}",
+            m.name(),
+            m.version(),
+            src.display()
+        );
+        copied.push(dest.clone());
+    }
+    let records = history::read_records(Action::Install, &copied, &replaced);
+    history::append_or_warn(config.history_path(), &records);
+    installed::record_or_warn(config.installed_hashes_path(), &records);
+    Ok(copied)
+}
+
+/// Records the downloaded mods in the history, none of them were installed before.
+fn record_downloads(paths: &[PathBuf], config: &AppConfig) {
+    let records = history::read_records(Action::Install, paths, &HashMap::new());
+    history::append_or_warn(config.history_path(), &records);
+    installed::record_or_warn(config.installed_hashes_path(), &records);
+}
+
+/// Copies the archive via a temporary file, so the old archive stays valid until it is replaced.
+fn copy_archive(src: &Path, dest: &Path, mods_dir: &Path) -> io::Result<()> {
+    let mut temp = NamedTempFile::new_in(mods_dir)?;
+    io::copy(&mut File::open(src)?, temp.as_file_mut())?;
+    temp.as_file().sync_all()?;
+    // NOTE temporary files are only readable by the owner
+    fs::set_permissions(temp.path(), fs::metadata(src)?.permissions())?;
+    temp.persist(dest).map_err(|e| e.error)?;
     Ok(())
 }
+
+/// Downloads the planned files, then the dependencies of the ones which were not in the graph.
+///
+/// NOTE brand-new uploads are missing from the dependency graph until it is regenerated,
+/// so their dependencies are read from `everest.yaml` of the downloaded archives instead.
+async fn download_planned(
+    downloader: &ModDownloader,
+    plan: InstallPlan,
+    mods_dir: &Path,
+) -> anyhow::Result<DownloadReport> {
+    let InstallPlan {
+        mut targets,
+        mut requested,
+        mut unresolved,
+        registry,
+        graph,
+        mut installed,
+        dependencies_path,
+        ..
+    } = plan;
+
+    let mut report = DownloadReport::default();
+    let mut downloaded = Vec::new();
+    while !targets.is_empty() {
+        let round = downloader.download_many(targets, mods_dir).await;
+        let paths = &round.succeeded;
+
+        let mut found = HashSet::new();
+        for path in paths {
+            let m = match local::read_mod(path) {
+                Ok(m) => m,
+                Err(e) => {
+                    debug!(?e, "failed to read manifest of {}", anonymize(path));
+                    continue;
+                }
+            };
+            for (name, deps) in m.declared_dependencies() {
+                if unresolved.contains(name) {
+                    let deps = deps.iter().filter(|dep| !dep.is_core());
+                    found.extend(deps.map(|dep| dep.name().to_string()));
+                }
+            }
+            installed.extend(m.declared().map(|(n, v)| (n.to_string(), v.to_string())));
+            downloaded.push(m);
+        }
+        report.merge(round);
+
+        let mut resolution = graph.check_names(found, &installed);
+        // NOTE never download the same mod twice, even if its manifest has another name
+        resolution
+            .missing
+            .retain(|name| requested.insert(name.clone()));
+        if !resolution.missing.is_empty() {
+            info!("downloading dependencies read from the downloaded mods");
+        }
+        unresolved = resolution.unresolved;
+        let installed_names = installed.keys().cloned().collect();
+        targets = registry.download_files(resolution.missing, installed_names)?;
+    }
+    // NOTE recorded without `--lazy-deps` too, so it knows the mods installed before switching
+    known_deps::record_or_warn(&dependencies_path, &downloaded);
+    Ok(report)
+}
+
+/// Prints the mods to be downloaded grouped by the target, and the total download size.
+fn print_summary(plan: &InstallPlan) {
+    let size = |name: &str| plan.registry.get(name).map(|entry| entry.file_size());
+    let line = |name: &str| match size(name) {
+        Some(size) => format!("{} ({})", name, BinaryBytes(size)),
+        None => name.to_string(),
+    };
+
+    println!("Mods to be installed:");
+    for group in &plan.groups {
+        let is_missing = plan.requested.contains(&group.target);
+        if !is_missing && group.dependencies.is_empty() {
+            continue;
+        }
+        match (group.is_collab, is_missing) {
+            (true, true) => println!("  {} [collab]", line(&group.target)),
+            (false, true) => println!("  {}", line(&group.target)),
+            (_, false) if plan.archives.iter().any(|(m, _)| m.name() == group.target) => {
+                println!("  {} (from file)", group.target)
+            }
+            (_, false) => println!("  {} (installed)", group.target),
+        }
+        for dep in &group.dependencies {
+            println!("    {}", line(dep));
+        }
+    }
+    let total: u64 = plan.targets.iter().map(DownloadFile::size).sum();
+    println!(
+        "{} mods, {} in total",
+        plan.targets.len(),
+        BinaryBytes(total)
+    );
+}
+
+/// Warns if the installed Everest is older than the build required by the mods.
+fn check_everest(required: &EverestRequirement, config: &AppConfig) {
+    let repo = FileVersionRepository::new(config);
+    let installed = match fetch_installed_version(&repo) {
+        Ok(number) => number.value(),
+        Err(e) => {
+            debug!(?e, "skipping Everest version check");
+            return;
+        }
+    };
+    if installed < required.build {
+        println!(
+            "{} requires Everest build {} or later, but {} is installed. Run `hultra everest update` to update it",
+            required.required_by, required.build, installed
+        );
+    }
+}
+
+/// Prints the first lines of README and LICENSE files in the archive.
+fn print_notices(path: &Path, lines: usize) {
+    let notices = match readme::find_notices(path) {
+        Ok(notices) => notices,
+        Err(e) => {
+            warn!(?e, "failed to read notices from {}", anonymize(path));
+            return;
+        }
+    };
+
+    let archive = path.file_name().unwrap_or_default().to_string_lossy();
+    for notice in notices {
+        println!("==> {}: {}", archive, notice.name);
+        let (head, rest) = notice.head(lines);
+        for line in head {
+            println!("{}", line);
+        }
+        if rest > 0 {
+            println!("... ({} more lines)", rest);
+        }
+        println!();
+    }
+}
@@ -1,16 +1,375 @@
-use tracing::info;
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    fs,
+    io::{self, IsTerminal},
+    path::Path,
+    time::UNIX_EPOCH,
+};
 
-use crate::{config::AppConfig, core::local};
+use anyhow::Context;
+use clap::{Args, ValueEnum};
+use indicatif::BinaryBytes;
+use serde::Serialize;
+use tracing::{debug, info, warn};
+
+use crate::{
+    commands::DownloadOption,
+    config::AppConfig,
+    core::{
+        LocalMod, blacklist,
+        local::{
+            self, LocalFileSystemService, ModType,
+            duplicate::{self, Duplicate},
+        },
+        network::{SharedHttpClient, api},
+        trash,
+    },
+    log::anonymize,
+    ui::{self, style},
+    utils::format_unix_date,
+};
+
+use super::show::ModDetails;
+
+#[derive(Debug, Clone, Args)]
+pub struct ListArgs {
+    /// Keeps the newest archive of the mods installed twice, and disables the others.
+    #[arg(long)]
+    pub resolve: bool,
+
+    /// Deletes the other archives instead of disabling them.
+    #[arg(long, requires = "resolve")]
+    pub delete: bool,
+
+    /// Order of the mods: `name`, `size` (largest first), `mtime` (newest first), or `updated`.
+    #[arg(long, value_name = "KEY", default_value = "name")]
+    pub sort: SortKey,
+
+    /// Lists only the mods whose name or file name contains the text, ignoring case.
+    #[arg(long, value_name = "TEXT")]
+    pub filter: Option<String>,
+
+    /// Lists only the mods of the type: `maps`, `helpers`, `skins`, or `assets`.
+    #[arg(long = "type", value_name = "TYPE")]
+    pub mod_type: Option<TypeFilter>,
+
+    /// Prints the size of each mod also when piped, and the total size of the listed mods.
+    #[arg(long)]
+    pub size: bool,
+}
+
+/// Types of the mods to list, see [`ModType`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TypeFilter {
+    /// Mods with maps, including the ones with their own code.
+    #[value(alias = "map")]
+    Maps,
+    /// Mods with code and no maps, e.g. helpers and tools.
+    #[value(alias = "helper")]
+    Helpers,
+    /// Mods replacing the sprites of Madeline.
+    #[value(alias = "skin")]
+    Skins,
+    /// Anything else, e.g. graphics, audio, or dialog packs.
+    #[value(alias = "asset")]
+    Assets,
+}
+
+impl From<TypeFilter> for ModType {
+    fn from(value: TypeFilter) -> Self {
+        match value {
+            TypeFilter::Maps => Self::Map,
+            TypeFilter::Helpers => Self::Helper,
+            TypeFilter::Skins => Self::Skin,
+            TypeFilter::Assets => Self::Assets,
+        }
+    }
+}
+
+/// Order of the listed mods.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum SortKey {
+    /// Mod name, ignoring case.
+    #[default]
+    Name,
+    /// Size of the archive, the largest first.
+    Size,
+    /// Modification time of the archive, the newest first.
+    Mtime,
+    /// Mods with an update available first, then by name.
+    Updated,
+}
+
+/// Installed mod in the JSON output.
+#[derive(Debug, Serialize)]
+struct ListedMod<'a> {
+    #[serde(flatten)]
+    details: ModDetails<'a>,
+    /// Listed in Everest's blacklist.
+    disabled: bool,
+}
+
+/// Row of the table.
+struct Row<'a> {
+    installed: &'a LocalMod,
+    disabled: bool,
+    size: u64,
+    /// UNIX time in seconds.
+    modified: u64,
+    /// Newer version in the cached database.
+    update: Option<&'a str>,
+}
+
+impl<'a> Row<'a> {
+    fn new(installed: &'a LocalMod, disabled: bool, updates: &'a HashMap<String, String>) -> Self {
+        let meta = fs::metadata(installed.file().path()).ok();
+        let modified = meta
+            .as_ref()
+            .and_then(|m| m.modified().ok())
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        Self {
+            installed,
+            disabled,
+            // NOTE the unpacked mods have no size, see `hultra du` for their files
+            size: installed.file().size().unwrap_or_default(),
+            modified,
+            update: updates.get(installed.name()).map(String::as_str),
+        }
+    }
+
+    fn matches(&self, filter: &str) -> bool {
+        let filter = filter.to_lowercase();
+        self.installed.name().to_lowercase().contains(&filter)
+            || file_name(self.installed.file().path())
+                .to_lowercase()
+                .contains(&filter)
+    }
+}
 
 /// Lists currently installed mods.
-pub fn run(config: &AppConfig) -> anyhow::Result<()> {
+pub async fn run(args: ListArgs, config: &AppConfig) -> anyhow::Result<()> {
     info!("scanning installed mods");
-    let mods = local::scan_mods(&config.mods_dir())?;
+    let mods_dir = config.mods_dir();
+    let mods = local::scan_mods(config)?;
+    let disabled = blacklist::read_everest_blacklist(&mods_dir)?;
+    let updates = cached_updates(&mods, config).await;
+
+    let mut rows: Vec<Row> = mods
+        .iter()
+        .map(|m| Row::new(m, m.file().is_disabled(&disabled), &updates))
+        .filter(|row| args.filter.as_deref().is_none_or(|f| row.matches(f)))
+        .filter(|row| {
+            args.mod_type
+                .is_none_or(|t| row.installed.mod_type() == Some(t.into()))
+        })
+        .collect();
+    sort_rows(&mut rows, args.sort);
 
-    for installed in &mods {
-        println!("{}", installed)
+    if config.output().is_json() {
+        let listed: Vec<ListedMod> = rows
+            .iter()
+            .map(|row| ListedMod {
+                details: ModDetails::from(row.installed),
+                disabled: row.disabled,
+            })
+            .collect();
+        ui::print_json(&listed)?;
+    } else if io::stdout().is_terminal() {
+        print_table(&rows);
+    } else {
+        // NOTE one mod per line, so it can be piped to `cut` or `show --stdin`
+        for row in &rows {
+            let mut line = row.installed.to_string();
+            if args.size && !row.installed.file().is_dir() {
+                line.push_str(&format!(" {}", BinaryBytes(row.size)));
+            }
+            if row.disabled {
+                line.push_str(" (disabled)");
+            }
+            println!("{}", line);
+        }
+    }
+    if args.size && !config.output().is_json() {
+        let total: u64 = rows.iter().map(|row| row.size).sum();
+        println!("\n{} in {} mods", BinaryBytes(total), rows.len());
+    }
+
+    let disabled_count = rows.iter().filter(|row| row.disabled).count();
+    if disabled_count > 0 {
+        info!("found {} mods, {} disabled", rows.len(), disabled_count);
+    } else {
+        info!("found {} mods", rows.len());
     }
 
-    info!("found {} mods", mods.len());
+    let duplicates = warn_duplicates(&mods, &mods_dir)?;
+    if args.resolve && !duplicates.is_empty() {
+        resolve_duplicates(&duplicates, &mods_dir, args.delete, config)?;
+    }
     Ok(())
 }
+
+/// Returns the newer versions of the installed mods in the cached database, without network.
+async fn cached_updates(mods: &[LocalMod], config: &AppConfig) -> HashMap<String, String> {
+    let registry = async {
+        let mut option = DownloadOption::default().with_config(config)?;
+        option.offline = true;
+        let client = SharedHttpClient::new(config.client())?;
+        api::fetch_registry(client.inner().clone(), &option).await
+    };
+    match registry.await {
+        Ok(registry) => registry
+            .update_contexts(mods, LocalFileSystemService)
+            .into_iter()
+            .filter(|ctx| ctx.is_newer())
+            .map(|ctx| (ctx.name().to_string(), ctx.available_version().to_string()))
+            .collect(),
+        Err(e) => {
+            debug!(?e, "no cached database to check updates");
+            HashMap::new()
+        }
+    }
+}
+
+fn sort_rows(rows: &mut [Row], key: SortKey) {
+    rows.sort_by_cached_key(|row| row.installed.name().to_lowercase());
+    match key {
+        SortKey::Name => {}
+        SortKey::Size => rows.sort_by_key(|row| Reverse(row.size)),
+        SortKey::Mtime => rows.sort_by_key(|row| Reverse(row.modified)),
+        SortKey::Updated => rows.sort_by_key(|row| row.update.is_none()),
+    }
+}
+
+/// Prints the mods as a table aligned by the widest cell of each column.
+fn print_table(rows: &[Row]) {
+    let header = [
+        "NAME", "VERSION", "TYPE", "FILE", "SIZE", "MODIFIED", "UPDATE",
+    ];
+    let cells: Vec<[String; 7]> = rows
+        .iter()
+        .map(|row| {
+            let name = if row.disabled {
+                format!("{} (disabled)", row.installed.name())
+            } else {
+                row.installed.name().to_string()
+            };
+            [
+                name,
+                row.installed.version().to_string(),
+                row.installed
+                    .mod_type()
+                    .map(|t| t.to_string())
+                    .unwrap_or_default(),
+                if row.installed.file().is_dir() {
+                    format!("{}/", file_name(row.installed.file().path()))
+                } else {
+                    file_name(row.installed.file().path())
+                },
+                if row.installed.file().is_dir() {
+                    String::new()
+                } else {
+                    BinaryBytes(row.size).to_string()
+                },
+                format_unix_date(row.modified),
+                row.update.unwrap_or_default().to_string(),
+            ]
+        })
+        .collect();
+    let mut widths = header.map(str::len);
+    for row in &cells {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let header: Vec<String> = header
+        .iter()
+        .zip(widths)
+        .map(|(cell, width)| format!("{:<width$}", cell))
+        .collect();
+    println!("{}", style::name(header.join("  ").trim_end()));
+    for (row, cells) in rows.iter().zip(&cells) {
+        let line: Vec<String> = cells
+            .iter()
+            .zip(widths)
+            .enumerate()
+            .map(|(column, (cell, width))| {
+                // NOTE pads outside of the escape codes, which have no width
+                let padding = " ".repeat(width - cell.chars().count());
+                let cell = match column {
+                    _ if cell.is_empty() => String::new(),
+                    0 if row.disabled => style::warning(cell).to_string(),
+                    1 => style::version(cell).to_string(),
+                    3 => style::path(cell).to_string(),
+                    6 => style::success(cell).to_string(),
+                    _ => cell.to_string(),
+                };
+                cell + &padding
+            })
+            .collect();
+        println!("{}", line.join("  ").trim_end());
+    }
+}
+
+/// Warns about the mod names declared in multiple archives, which break Everest at runtime.
+pub(super) fn warn_duplicates(
+    mods: &[LocalMod],
+    mods_dir: &Path,
+) -> anyhow::Result<Vec<Duplicate>> {
+    let disabled = blacklist::read_everest_blacklist(mods_dir)?;
+    let duplicates = duplicate::find_duplicates(mods, &disabled);
+    for dup in &duplicates {
+        let others: Vec<_> = dup.others.iter().map(|path| file_name(path)).collect();
+        warn!(
+            "{} is installed in multiple archives: {} (newest), {}",
+            dup.name,
+            file_name(&dup.keep),
+            others.join(", ")
+        );
+    }
+    if !duplicates.is_empty() {
+        warn!("run `hultra list --resolve` to disable the older archives");
+    }
+    Ok(duplicates)
+}
+
+fn resolve_duplicates(
+    duplicates: &[Duplicate],
+    mods_dir: &Path,
+    delete: bool,
+    config: &AppConfig,
+) -> anyhow::Result<()> {
+    let output = config.output();
+    let others = duplicates.iter().flat_map(|dup| &dup.others);
+    if delete {
+        let trash = config.trash();
+        for path in others {
+            trash::remove(trash.as_ref(), path)
+                .with_context(|| format!("Failed to delete '{}'", anonymize(path)))?;
+            let action = if trash.is_some() {
+                "Trashed"
+            } else {
+                "Deleted"
+            };
+            output.print_text(&format!("{} {}", action, file_name(path)));
+        }
+    } else {
+        let names: Vec<String> = others.map(|path| file_name(path)).collect();
+        blacklist::append_to_everest_blacklist(mods_dir, &names)
+            .context("Failed to update the blacklist")?;
+        for name in &names {
+            output.print_text(&format!("Disabled {}", name));
+        }
+    }
+    Ok(())
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned()
+}
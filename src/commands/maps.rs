@@ -0,0 +1,112 @@
+//! Handle maps command.
+use std::path::Path;
+
+use clap::Args;
+use clap_complete::ArgValueCandidates;
+use serde::Serialize;
+use tracing::info;
+
+use crate::{
+    commands::completions::installed_mod_names,
+    config::AppConfig,
+    core::local::{
+        self, dialog,
+        maps::{self, MapInfo, ModMaps},
+    },
+    ui::{self, style},
+};
+
+#[derive(Debug, Clone, Args)]
+pub struct MapsArgs {
+    /// Lists only the maps of these mods, by the names in everest.yaml.
+    #[arg(value_name = "MOD_NAME", add = ArgValueCandidates::new(installed_mod_names))]
+    pub names: Vec<String>,
+
+    /// Language of the dialog for the names, e.g. `Japanese` for `Dialog/Japanese.txt` [default: English]
+    #[arg(long, value_name = "LANGUAGE")]
+    pub language: Option<String>,
+}
+
+/// Mod with its maps in the JSON output.
+#[derive(Debug, Serialize)]
+struct MapsDetails<'a> {
+    name: &'a str,
+    version: &'a str,
+    file: &'a Path,
+    maps: Vec<MapDetails<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct MapDetails<'a> {
+    sid: &'a str,
+    name: Option<&'a str>,
+}
+
+impl<'a> From<&'a ModMaps<'a>> for MapsDetails<'a> {
+    fn from(value: &'a ModMaps<'a>) -> Self {
+        Self {
+            name: value.installed.name(),
+            version: value.installed.version(),
+            file: value.installed.file().path(),
+            maps: value
+                .maps
+                .iter()
+                .map(|map| MapDetails {
+                    sid: &map.sid,
+                    name: map.name.as_deref(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Lists the maps in the installed archives, grouped by mod.
+pub fn run(args: MapsArgs, config: &AppConfig) -> anyhow::Result<()> {
+    info!("scanning installed mods");
+    let mut mods = local::scan_mods(config)?;
+    if !args.names.is_empty() {
+        mods.retain(|m| {
+            m.declared()
+                .any(|(name, _)| args.names.iter().any(|n| n.eq_ignore_ascii_case(name)))
+        });
+    }
+    mods.sort_by_key(|m| m.name().to_lowercase());
+
+    let language = args
+        .language
+        .or_else(|| config.profile().language.clone())
+        .unwrap_or_else(|| dialog::DEFAULT_LANGUAGE.to_string());
+    let mod_maps = maps::list_installed_maps(&mods, &language);
+    let output = config.output();
+    if output.is_json() {
+        let details: Vec<MapsDetails> = mod_maps.iter().map(MapsDetails::from).collect();
+        ui::print_json(&details)?;
+    }
+    if mod_maps.is_empty() {
+        output.print_text("No maps found");
+        return Ok(());
+    }
+    if output.is_json() {
+        return Ok(());
+    }
+
+    for (i, ModMaps { installed, maps }) in mod_maps.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!(
+            "{} {}",
+            style::name(installed.name()),
+            style::version(format!("v{}", installed.version()))
+        );
+        for MapInfo { sid, name } in maps {
+            match name {
+                Some(name) => println!("  {} {}", name, style::path(format!("({})", sid))),
+                None => println!("  {}", sid),
+            }
+        }
+    }
+    let count: usize = mod_maps.iter().map(|m| m.maps.len()).sum();
+    println!("\n{} maps in {} mods", count, mod_maps.len());
+    Ok(())
+}
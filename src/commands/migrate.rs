@@ -0,0 +1,97 @@
+//! Handle migrate command.
+use std::{fs, path::PathBuf};
+
+use anyhow::{Context, bail};
+use clap::Args;
+use tracing::{debug, info, warn};
+
+use crate::{
+    config::AppConfig,
+    core::{
+        cache,
+        migrate::{self, LIST_FILES, TransferMode},
+    },
+    log::anonymize,
+};
+
+#[derive(Debug, Clone, Args)]
+pub struct MigrateArgs {
+    /// Directory where the Celeste is installed on the new location.
+    #[arg(long, value_name = "DIR")]
+    pub to: PathBuf,
+
+    /// Removes the original files after they are verified.
+    #[arg(long = "move")]
+    pub move_files: bool,
+}
+
+/// Transfers the mod archives and the list files kept by Everest into the new Mods directory.
+pub fn run(args: MigrateArgs, config: &AppConfig) -> anyhow::Result<()> {
+    let src_dir = config.mods_dir();
+    let dest_dir = args.to.join("Mods");
+    fs::create_dir_all(&dest_dir)
+        .with_context(|| format!("Failed to create '{}'", anonymize(&dest_dir)))?;
+
+    if fs::canonicalize(&src_dir)? == fs::canonicalize(&dest_dir)? {
+        bail!("The destination is the current Mods directory");
+    }
+
+    let mode = if args.move_files {
+        TransferMode::Move
+    } else {
+        TransferMode::Copy
+    };
+
+    let archives = migrate::collect_archives(&src_dir, config.exclude())?;
+    info!(
+        "migrating {} archives to {}",
+        archives.len(),
+        anonymize(&dest_dir)
+    );
+
+    let mut failures = Vec::new();
+    let mut cache_entries = Vec::with_capacity(archives.len());
+    for path in &archives {
+        match migrate::transfer_archive(path, &dest_dir, mode) {
+            Ok(transferred) => {
+                debug!(path = %anonymize(transferred.path()), "transferred");
+                cache_entries.push(transferred.into_cache_entry());
+            }
+            Err(e) => failures.push((path.clone(), e)),
+        }
+    }
+    let transferred = cache_entries.len();
+
+    for name in LIST_FILES {
+        match migrate::transfer_list_file(name, &src_dir, &dest_dir, mode) {
+            Ok(true) => debug!(name, "transferred"),
+            Ok(false) => {}
+            Err(e) => failures.push((src_dir.join(name), e)),
+        }
+    }
+
+    // NOTE record the copies to avoid rehashing all of them at the new location
+    if let Err(e) = cache::replace(config.cache_db_path(), cache_entries) {
+        warn!(?e, "failed to update the checksum cache");
+    }
+
+    match config.update_directory(&args.to) {
+        Ok(true) => info!("updated the directory of the config profile"),
+        Ok(false) => println!(
+            "Pass `--directory {}` or set `directory` of the config profile to use the new location",
+            args.to.display()
+        ),
+        Err(e) => warn!(?e, "failed to update the config file"),
+    }
+
+    println!("Migrated {} of {} archives", transferred, archives.len());
+    if failures.is_empty() {
+        return Ok(());
+    }
+
+    println!("Failed to transfer:");
+    for (path, e) in &failures {
+        println!("  {}: {}", anonymize(path), e);
+    }
+    bail!("{} files failed to transfer", failures.len())
+}
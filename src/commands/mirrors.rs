@@ -0,0 +1,96 @@
+//! Handle mirrors command.
+use anyhow::bail;
+use clap::{Args, Subcommand};
+use tracing::{info, warn};
+
+use crate::{
+    config::AppConfig,
+    core::network::{
+        SharedHttpClient,
+        probe::{self, CACHE_TTL, DEFAULT_PROBE_GBID, LatencyCache},
+    },
+    log::anonymize,
+};
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum MirrorsCommand {
+    /// Measure latency of all mirrors, the result is used for `--mirror-priority auto`.
+    Test(TestArgs),
+
+    /// Print URLs of a GameBanana file and its screenshots on all mirrors.
+    Urls(UrlsArgs),
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct TestArgs {
+    /// GameBanana file ID to request.
+    #[arg(long, value_name = "ID", default_value_t = DEFAULT_PROBE_GBID)]
+    pub gbid: u32,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct UrlsArgs {
+    /// GameBanana file ID.
+    #[arg(value_name = "ID")]
+    pub gbid: u32,
+
+    /// URL of the screenshot on GameBanana, can be given multiple times.
+    #[arg(long = "screenshot", value_name = "URL")]
+    pub screenshots: Vec<String>,
+}
+
+/// Prints URLs of the file and the screenshots on each mirror.
+pub fn urls(args: UrlsArgs, config: &AppConfig) -> anyhow::Result<()> {
+    for mirror in config.mirrors().all_mirrors() {
+        let assets = mirror.asset_urls(args.gbid, &args.screenshots, config.mirrors())?;
+        println!("{}", mirror);
+        println!("  File: {}", assets.file);
+        if let Some(icon) = assets.icon() {
+            println!("  Icon: {}", icon);
+        }
+        for screenshot in assets.screenshots.iter().skip(1) {
+            println!("  Screenshot: {}", screenshot);
+        }
+    }
+    Ok(())
+}
+
+/// Measures latency of the mirrors and caches the result.
+pub async fn test(args: TestArgs, config: &AppConfig) -> anyhow::Result<()> {
+    if config.offline() {
+        bail!("Testing mirrors requires network access, run without `--offline`");
+    }
+    let client = SharedHttpClient::new(config.client())?;
+    let mirrors = config.mirrors().all_mirrors();
+
+    info!("testing {} mirrors", mirrors.len());
+    let mut results = probe::probe(client.inner(), &mirrors, config.mirrors(), args.gbid).await;
+    results.sort_by_key(|result| (!result.is_ok(), result.latency));
+
+    for result in &results {
+        let outcome = match &result.outcome {
+            Ok(status) => status.to_string(),
+            Err(e) => e.clone(),
+        };
+        let latency = if result.is_ok() {
+            format!("{} ms", result.latency.as_millis())
+        } else {
+            "-".to_string()
+        };
+        println!("{:<10} {:>8}  {}", result.mirror, latency, outcome);
+    }
+
+    let cache = LatencyCache::from_results(&results);
+    match cache.save(config.latency_cache_path()) {
+        Ok(_) => info!(
+            "saved the result for {} hours",
+            CACHE_TTL.as_secs() / 60 / 60
+        ),
+        Err(e) => warn!(
+            ?e,
+            "failed to save the result to {}",
+            anonymize(config.latency_cache_path())
+        ),
+    }
+    Ok(())
+}
@@ -0,0 +1,195 @@
+//! Handle offload and restore commands.
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, bail};
+use clap::Args;
+use tracing::{debug, info, warn};
+
+use crate::{
+    config::AppConfig,
+    core::{
+        blacklist::{self, EVEREST_BLACKLIST_FILE},
+        history::{self, Action},
+        local,
+        migrate::{self, TransferMode},
+        offload::{self, DisabledSince, parse_age},
+    },
+    log::anonymize,
+};
+
+#[derive(Debug, Clone, Args)]
+pub struct OffloadArgs {
+    /// Offloads mods which have been disabled for this long, like `90d` or `12w`.
+    #[arg(long, value_name = "AGE", default_value = "90d", value_parser = parse_age)]
+    pub unused_for: Duration,
+
+    /// Shows the mods to be offloaded without moving them.
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Directory to move the mods into. Overrides `archive` of the config profile.
+    #[arg(long, value_name = "DIR")]
+    pub archive: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct RestoreArgs {
+    /// File names of the mods to bring back, with or without `.zip`. Lists offloaded mods if omitted.
+    #[arg(value_name = "NAME")]
+    pub names: Vec<String>,
+
+    /// Directory the mods were offloaded to. Overrides `archive` of the config profile.
+    #[arg(long, value_name = "DIR")]
+    pub archive: Option<PathBuf>,
+}
+
+fn archive_dir(arg: Option<PathBuf>, config: &AppConfig) -> anyhow::Result<PathBuf> {
+    match arg.or_else(|| config.archive_dir().map(Path::to_path_buf)) {
+        Some(dir) => Ok(dir),
+        None => bail!(
+            "No archive directory, set `archive` of the config profile or pass `--archive DIR`"
+        ),
+    }
+}
+
+/// Moves the mods which have been disabled for a while into the archive directory.
+pub fn offload(args: OffloadArgs, config: &AppConfig) -> anyhow::Result<()> {
+    let archive_dir = archive_dir(args.archive, config)?;
+    let mods_dir = config.mods_dir();
+
+    info!("scanning installed mods");
+    let mods = local::scan_mods(config)?;
+    let now = unix_time(SystemTime::now());
+
+    // NOTE the blacklist was last written after the mods listed there were disabled,
+    // so its mtime is a safe estimate for the ones seen for the first time
+    let seen_at = fs::metadata(mods_dir.join(EVEREST_BLACKLIST_FILE))
+        .and_then(|meta| meta.modified())
+        .map(unix_time)
+        .unwrap_or(now)
+        .min(now);
+    let mut disabled = DisabledSince::load(config.disabled_since_path());
+    disabled.sync(&blacklist::read_everest_blacklist(&mods_dir)?, seen_at);
+    if let Err(e) = disabled.save(config.disabled_since_path()) {
+        warn!(?e, "failed to save when the mods were disabled");
+    }
+
+    let cutoff = now.saturating_sub(args.unused_for.as_secs());
+    let unused = offload::find_unused(&mods, &disabled, cutoff);
+
+    if unused.is_empty() {
+        println!("No unused mods to offload");
+        return Ok(());
+    }
+    if args.dry_run {
+        println!("Mods to be offloaded to {}:", anonymize(&archive_dir));
+        for path in &unused {
+            println!("  {}", file_name(path));
+        }
+        return Ok(());
+    }
+
+    fs::create_dir_all(&archive_dir)
+        .with_context(|| format!("Failed to create '{}'", anonymize(&archive_dir)))?;
+
+    let mut offloaded = HashSet::new();
+    let mut archived = Vec::new();
+    let mut failures = Vec::new();
+    for path in &unused {
+        match migrate::transfer_archive(path, &archive_dir, TransferMode::Move) {
+            Ok(transferred) => {
+                debug!(path = %anonymize(transferred.path()), "offloaded");
+                println!("Offloaded {}", file_name(path));
+                offloaded.insert(file_name(path));
+                archived.push(transferred.path().to_path_buf());
+            }
+            Err(e) => failures.push((path, e)),
+        }
+    }
+    let records = history::read_records(Action::Offload, &archived, &HashMap::new());
+    history::append_or_warn(config.history_path(), &records);
+    if !archived.is_empty() {
+        super::sync::write_lockfile(config);
+    }
+
+    // NOTE the archives are gone, so their entries in the blacklist are no longer needed
+    blacklist::remove_from_everest_blacklist(&mods_dir, &offloaded)
+        .context("Failed to update the blacklist")?;
+
+    println!(
+        "Offloaded {} of {} mods to {}",
+        offloaded.len(),
+        unused.len(),
+        anonymize(&archive_dir)
+    );
+    if failures.is_empty() {
+        return Ok(());
+    }
+
+    println!("Failed to offload:");
+    for (path, e) in &failures {
+        println!("  {}: {}", file_name(path), e);
+    }
+    bail!("{} mods failed to offload", failures.len())
+}
+
+/// Moves the offloaded mods back into the Mods directory.
+pub fn restore(args: RestoreArgs, config: &AppConfig) -> anyhow::Result<()> {
+    let archive_dir = archive_dir(args.archive, config)?;
+    let archives = offload::list_archived(&archive_dir)
+        .with_context(|| format!("Failed to read '{}'", anonymize(&archive_dir)))?;
+
+    if args.names.is_empty() {
+        println!("Offloaded mods in {}:", anonymize(&archive_dir));
+        for path in &archives {
+            println!("  {}", file_name(path));
+        }
+        return Ok(());
+    }
+
+    let mut targets = Vec::with_capacity(args.names.len());
+    for name in &args.names {
+        match offload::find_archived(&archives, name) {
+            Some(path) => targets.push(path),
+            None => bail!(
+                "'{}' is not in the archive directory, run `hultra restore` to list offloaded mods",
+                name
+            ),
+        }
+    }
+
+    let mods_dir = config.mods_dir();
+    let mut restored = Vec::with_capacity(targets.len());
+    let result = targets.iter().try_for_each(|path| {
+        let transferred = migrate::transfer_archive(path, &mods_dir, TransferMode::Move)
+            .with_context(|| format!("Failed to restore {}", file_name(path)))?;
+        println!("Restored {}", file_name(path));
+        restored.push(transferred.path().to_path_buf());
+        anyhow::Ok(())
+    });
+    // NOTE the mods restored before a failure are recorded too
+    let records = history::read_records(Action::Restore, &restored, &HashMap::new());
+    history::append_or_warn(config.history_path(), &records);
+    if !restored.is_empty() {
+        super::sync::write_lockfile(config);
+    }
+    result
+}
+
+fn unix_time(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned()
+}
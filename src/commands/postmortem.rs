@@ -0,0 +1,32 @@
+//! Handle postmortem command.
+use anyhow::Context;
+use tracing::info;
+
+use crate::{config::AppConfig, core::local, everest::postmortem};
+
+/// Parses the latest Everest log and suggests actions for the mods failed to load.
+pub fn run(config: &AppConfig) -> anyhow::Result<()> {
+    info!("reading Everest log");
+    let content = postmortem::read_latest_log(config.root_dir())
+        .context("Failed to read `log.txt`. Launch the game with Everest at least once")?;
+
+    let failures = postmortem::parse_failures(&content);
+    if failures.is_empty() {
+        println!("No mods failed to load");
+        return Ok(());
+    }
+
+    println!("Mods failed to load:");
+    for failure in &failures {
+        println!("  {}", failure);
+    }
+
+    info!("scanning installed mods");
+    let installed = local::scan_mods(config)?;
+
+    println!("Suggested actions:");
+    for suggestion in postmortem::diagnose(&failures, &installed) {
+        println!("  * {}", suggestion);
+    }
+    Ok(())
+}
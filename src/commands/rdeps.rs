@@ -0,0 +1,46 @@
+//! Handle rdeps command.
+use clap::Args;
+use clap_complete::ArgValueCandidates;
+use tracing::info;
+
+use crate::{
+    commands::completions::installed_mod_names,
+    config::AppConfig,
+    core::{dependency::ReverseDependencies, local},
+};
+
+#[derive(Debug, Clone, Args)]
+pub struct RdepsArgs {
+    /// Name of the mod in its everest.yaml, e.g. `GravityHelper`.
+    #[arg(value_name = "MOD_NAME", add = ArgValueCandidates::new(installed_mod_names))]
+    pub name: String,
+}
+
+/// Lists the installed mods which require the given mod.
+pub fn run(args: RdepsArgs, config: &AppConfig) -> anyhow::Result<()> {
+    info!("scanning installed mods");
+    let mods = local::scan_mods(config)?;
+    if !mods
+        .iter()
+        .flat_map(|m| m.declared())
+        .any(|(name, _)| name.eq_ignore_ascii_case(&args.name))
+    {
+        println!("{} is not installed", args.name);
+    }
+
+    let dependents = ReverseDependencies::new(&mods).dependents_of(&args.name);
+    if dependents.is_empty() {
+        println!("No installed mod requires {}", args.name);
+        return Ok(());
+    }
+
+    println!("Installed mods which require {}:", args.name);
+    for dependent in &dependents {
+        match (&dependent.via, dependent.version.as_str()) {
+            (Some(via), _) => println!("  {} (via {})", dependent.name, via),
+            (None, "") => println!("  {}", dependent.name),
+            (None, version) => println!("  {} (requires v{})", dependent.name, version),
+        }
+    }
+    Ok(())
+}
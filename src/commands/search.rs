@@ -0,0 +1,97 @@
+//! Handle search command.
+use anyhow::bail;
+use clap::Args;
+use tracing::info;
+
+use crate::{
+    config::AppConfig,
+    core::network::{
+        SharedHttpClient,
+        gamebanana::{self, ModInfo},
+    },
+    ui::{self, OutputFormat},
+};
+
+#[derive(Debug, Clone, Args)]
+pub struct SearchArgs {
+    /// Words to search for in the name, author, and description.
+    #[arg(required = true, value_name = "QUERY")]
+    pub query: Vec<String>,
+
+    /// Page of the results to show.
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+    pub page: u16,
+
+    /// Number of results in each page.
+    #[arg(long, value_name = "N", default_value_t = 10, value_parser = clap::value_parser!(u8).range(1..=50))]
+    pub per_page: u8,
+
+    /// Deprecated, same as `--output json`.
+    #[arg(long, hide = true)]
+    pub json: bool,
+}
+
+/// Searches the mods on GameBanana.
+pub async fn run(args: SearchArgs, config: &AppConfig) -> anyhow::Result<()> {
+    if config.offline() {
+        bail!("Searching mods requires network access, run without `--offline`");
+    }
+
+    let output = if args.json {
+        OutputFormat::Json
+    } else {
+        config.output()
+    };
+    let query = args.query.join(" ");
+    info!("searching mods");
+    let client = SharedHttpClient::new(config.client())?;
+    let results = gamebanana::search(client.inner(), &query).await?;
+
+    let Some(page) = gamebanana::paginate(&results, args.page.into(), args.per_page.into()) else {
+        if output.is_json() {
+            ui::print_json(&Vec::<ModInfo>::new())?;
+        }
+        if results.is_empty() {
+            output.print_text(&format!("No mods found for '{}'", query));
+        } else {
+            output.print_text(&format!(
+                "No page {}, there are {} results",
+                args.page,
+                results.len()
+            ));
+        }
+        return Ok(());
+    };
+
+    if output.is_json() {
+        ui::print_json(&page.items)?;
+        return Ok(());
+    }
+
+    for found in page.items {
+        print_mod(found);
+    }
+    if page.number < page.total {
+        println!(
+            "Page {} of {}, use `--page {}` for more",
+            page.number,
+            page.total,
+            page.number + 1
+        );
+    }
+    Ok(())
+}
+
+fn print_mod(found: &ModInfo) {
+    println!("{} ({}) by {}", found.name, found.kind, found.author);
+    println!(
+        "  Category: {}, Downloads: {}, Likes: {}",
+        found.category, found.downloads, found.likes
+    );
+    if !found.description.is_empty() {
+        println!("  {}", found.description);
+    }
+    if !found.page_url.is_empty() {
+        println!("  {}", found.page_url);
+    }
+}
@@ -0,0 +1,233 @@
+//! Handle serve command.
+//!
+//! Exposes a tiny HTTP API on the local machine so a browser extension can send GameBanana pages
+//! to this tool directly. Every request must have `Authorization: Bearer <TOKEN>` header.
+//!
+//! * `GET /mods`: lists installed mods
+//! * `GET /updates`: lists available updates
+//! * `POST /install`: installs a mod, the body is `{"url": "https://gamebanana.com/mods/123456"}` or `{"id": 123456}`
+use std::{collections::HashSet, env, net::SocketAddr, sync::Arc, time::Duration};
+
+use anyhow::Context;
+use clap::Args;
+use serde::Deserialize;
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::Mutex,
+    time::timeout,
+};
+use tracing::{debug, info, warn};
+
+use crate::{
+    commands::{DownloadOption, install, install::GamebananaUrl, show::ModDetails, update},
+    config::AppConfig,
+    core::{
+        local,
+        network::{
+            SharedHttpClient,
+            http::{self, Request, Response},
+        },
+        update::{UpdateStrategy, scan_updates},
+    },
+};
+
+/// Environment variable to specify the token instead of generating random one.
+const TOKEN_ENV: &str = "HULTRA_SERVE_TOKEN";
+
+/// Time limit for receiving a request.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Args, Clone)]
+pub struct ServeArgs {
+    /// Address to listen on.
+    #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:7777")]
+    pub listen: SocketAddr,
+
+    #[command(flatten)]
+    pub option: DownloadOption,
+}
+
+/// Body of `POST /install`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct InstallRequest {
+    url: Option<String>,
+    id: Option<u32>,
+}
+
+impl InstallRequest {
+    fn mod_id(&self) -> Result<u32, String> {
+        match (&self.url, self.id) {
+            (Some(url), None) => url
+                .parse::<GamebananaUrl>()
+                .map(|url| url.id())
+                .map_err(|e| e.to_string()),
+            (None, Some(id)) => Ok(id),
+            _ => Err("either 'url' or 'id' is required".to_string()),
+        }
+    }
+}
+
+struct Server {
+    config: AppConfig,
+    option: DownloadOption,
+    token: String,
+    client: SharedHttpClient,
+    /// Serializes the tasks which touch the Mods directory.
+    lock: Mutex<()>,
+}
+
+/// Starts the local HTTP API server.
+pub async fn run(args: ServeArgs, config: &AppConfig) -> anyhow::Result<()> {
+    let option = args.option.with_config(config)?;
+
+    if !args.listen.ip().is_loopback() {
+        warn!(
+            "listening on non-loopback address {}, the API is reachable from other machines",
+            args.listen
+        );
+    }
+
+    let token = match env::var(TOKEN_ENV) {
+        Ok(token) if !token.is_empty() => token,
+        _ => {
+            let token = generate_token().context("failed to generate token")?;
+            println!("Token: {}", token);
+            token
+        }
+    };
+
+    let listener = TcpListener::bind(args.listen)
+        .await
+        .with_context(|| format!("failed to listen on {}", args.listen))?;
+    info!("listening on http://{}", args.listen);
+
+    let server = Arc::new(Server {
+        config: config.clone(),
+        option,
+        token,
+        client: SharedHttpClient::new(config.client())?,
+        lock: Mutex::new(()),
+    });
+
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let server = server.clone();
+        tokio::spawn(async move {
+            if let Err(e) = server.serve_connection(stream).await {
+                debug!(?e, %peer, "connection closed with error");
+            }
+        });
+    }
+}
+
+impl Server {
+    async fn serve_connection(&self, mut stream: TcpStream) -> anyhow::Result<()> {
+        let response = match timeout(READ_TIMEOUT, http::read_request(&mut stream)).await {
+            Ok(Ok(request)) => {
+                let response = self.handle(&request).await;
+                info!(
+                    "{} {} -> {}",
+                    request.method(),
+                    request.path(),
+                    response.status()
+                );
+                response
+            }
+            Ok(Err(e)) => Response::error(e.status(), &e.to_string()),
+            Err(_) => return Ok(()),
+        };
+        response.write_to(&mut stream).await?;
+        Ok(())
+    }
+
+    async fn handle(&self, request: &Request) -> Response {
+        // NOTE preflight requests of browsers never contain the token
+        if request.method() == "OPTIONS" {
+            return Response::no_content();
+        }
+
+        if !self.is_authorized(request) {
+            return Response::error(401, "invalid or missing token");
+        }
+
+        let result = match (request.method(), request.path()) {
+            ("GET", "/mods") => self.list(),
+            ("GET", "/updates") => self.check_updates().await,
+            ("POST", "/install") => match serde_json::from_slice::<InstallRequest>(request.body())
+                .map_err(|e| e.to_string())
+                .and_then(|body| body.mod_id())
+            {
+                Ok(id) => self.install(id).await,
+                Err(e) => return Response::error(400, &e),
+            },
+            (_, "/mods" | "/updates" | "/install") => {
+                return Response::error(405, "method not allowed");
+            }
+            _ => return Response::error(404, "not found"),
+        };
+
+        result.unwrap_or_else(|e| {
+            warn!("request failed: {:#}", e);
+            Response::error(500, &format!("{:#}", e))
+        })
+    }
+
+    fn is_authorized(&self, request: &Request) -> bool {
+        request
+            .header("authorization")
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|token| constant_time_eq(token.as_bytes(), self.token.as_bytes()))
+    }
+
+    fn list(&self) -> anyhow::Result<Response> {
+        let mods = local::scan_mods(&self.config)?;
+        let details: Vec<ModDetails> = mods.iter().map(ModDetails::from).collect();
+        Ok(Response::json(200, &details))
+    }
+
+    async fn check_updates(&self) -> anyhow::Result<Response> {
+        let _guard = self.lock.lock().await;
+        let (cache_db, contexts) = update::collect_update_contexts(
+            self.client.inner().clone(),
+            &self.option,
+            &self.config,
+            update::ScanOptions::new(false, false, &self.config),
+        )
+        .await?;
+        let report = scan_updates(&cache_db, &contexts, UpdateStrategy::default())?;
+        Ok(Response::json(200, &report.updates))
+    }
+
+    async fn install(&self, id: u32) -> anyhow::Result<Response> {
+        let _guard = self.lock.lock().await;
+        let downloaded = install::install_mods(
+            self.client.inner().clone(),
+            &HashSet::from([id]),
+            self.option.clone(),
+            &self.config,
+        )
+        .await?;
+        let installed: Vec<_> = downloaded
+            .iter()
+            .filter_map(|path| path.file_name())
+            .map(|name| name.to_string_lossy())
+            .collect();
+        Ok(Response::json(
+            200,
+            &serde_json::json!({ "installed": installed }),
+        ))
+    }
+}
+
+/// Generates a random token from the random source of the OS, e.g. `getrandom(2)` on Linux.
+fn generate_token() -> Result<String, getrandom::Error> {
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes)?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Compares without early return to avoid leaking the token by timing.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
@@ -0,0 +1,266 @@
+//! Handle show command.
+use std::{
+    collections::HashMap,
+    io::{self, BufRead},
+    path::PathBuf,
+};
+
+use clap::Args;
+use clap_complete::ArgValueCandidates;
+use indicatif::BinaryBytes;
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::{
+    commands::{DownloadOption, completions::installed_mod_names},
+    config::AppConfig,
+    core::{
+        LocalMod,
+        dependency::Dependency,
+        local::{self, ModType},
+        network::{SharedHttpClient, api},
+        registry::{Entry, ModRegistry},
+    },
+    log::anonymize,
+    ui::{self, style},
+    utils::format_unix_date,
+};
+
+#[derive(Debug, Args, Clone)]
+pub struct ShowArgs {
+    /// Name(s) of the installed mod.
+    #[arg(required_unless_present = "stdin", add = ArgValueCandidates::new(installed_mod_names))]
+    pub names: Vec<String>,
+
+    /// Reads additional mod names from stdin, one name per line.
+    #[arg(long)]
+    pub stdin: bool,
+
+    /// Deprecated, same as `--output json`.
+    #[arg(long, hide = true)]
+    pub json: bool,
+
+    /// Adds the type, the latest version, and the last update date from the mod database.
+    #[arg(long)]
+    pub remote: bool,
+}
+
+/// Details of the installed mod.
+#[derive(Debug, Serialize)]
+pub(super) struct ModDetails<'a> {
+    name: &'a str,
+    version: &'a str,
+    file: PathBuf,
+    size: Option<u64>,
+    /// Whether the mod is unpacked, which is not updated automatically.
+    directory: bool,
+    #[serde(rename = "type")]
+    mod_type: Option<ModType>,
+    dependencies: Vec<DependencyDetails<'a>>,
+    /// The other mods declared by the same manifest.
+    also_declares: Vec<DeclaredDetails<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    remote: Option<RemoteDetails<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct DependencyDetails<'a> {
+    name: &'a str,
+    version: &'a str,
+}
+
+impl<'a> DependencyDetails<'a> {
+    fn list(dependencies: &'a [Dependency]) -> Vec<Self> {
+        dependencies
+            .iter()
+            .map(|d| Self {
+                name: d.name(),
+                version: d.version(),
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct DeclaredDetails<'a> {
+    name: &'a str,
+    version: &'a str,
+    dependencies: Vec<DependencyDetails<'a>>,
+}
+
+/// Details of the mod in the mod database.
+#[derive(Debug, Serialize)]
+struct RemoteDetails<'a> {
+    kind: &'a str,
+    gamebanana_id: u32,
+    gamebanana_file_id: u32,
+    latest_version: &'a str,
+    /// UNIX time in seconds.
+    last_update: u64,
+}
+
+impl<'a> From<&'a Entry> for RemoteDetails<'a> {
+    fn from(value: &'a Entry) -> Self {
+        Self {
+            kind: value.kind(),
+            gamebanana_id: value.id(),
+            gamebanana_file_id: value.file_id(),
+            latest_version: value.version(),
+            last_update: value.last_update(),
+        }
+    }
+}
+
+impl<'a> From<&'a LocalMod> for ModDetails<'a> {
+    fn from(value: &'a LocalMod) -> Self {
+        Self {
+            name: value.name(),
+            version: value.version(),
+            file: value.file().path().to_path_buf(),
+            size: value.file().size(),
+            directory: value.file().is_dir(),
+            mod_type: value.mod_type(),
+            dependencies: DependencyDetails::list(value.dependencies()),
+            also_declares: value
+                .others()
+                .iter()
+                .map(|m| DeclaredDetails {
+                    name: m.name(),
+                    version: m.version(),
+                    dependencies: DependencyDetails::list(m.dependencies()),
+                })
+                .collect(),
+            remote: None,
+        }
+    }
+}
+
+impl<'a> ModDetails<'a> {
+    fn with_registry(mut self, registry: Option<&'a ModRegistry>) -> Self {
+        self.remote = registry
+            .and_then(|registry| registry.get(self.name))
+            .map(RemoteDetails::from);
+        self
+    }
+}
+
+impl ModDetails<'_> {
+    fn print(&self) {
+        println!("{}", style::name(self.name));
+        println!("  Version: {}", style::version(self.version));
+        if self.directory {
+            println!(
+                "  File: {} (directory, not updated automatically)",
+                style::path(anonymize(&self.file))
+            );
+        } else {
+            println!("  File: {}", style::path(anonymize(&self.file)));
+        }
+        if let Some(size) = self.size {
+            println!("  Size: {}", BinaryBytes(size));
+        }
+        if let Some(mod_type) = self.mod_type {
+            println!("  Type: {}", mod_type);
+        }
+        if !self.dependencies.is_empty() {
+            println!(
+                "  Dependencies: {}",
+                format_dependencies(&self.dependencies)
+            );
+        }
+        for declared in &self.also_declares {
+            println!(
+                "  Also Declares: {} {}",
+                declared.name,
+                style::version(declared.version)
+            );
+            if !declared.dependencies.is_empty() {
+                println!(
+                    "    Dependencies: {}",
+                    format_dependencies(&declared.dependencies)
+                );
+            }
+        }
+        if let Some(remote) = &self.remote {
+            println!("  Type: {}", remote.kind);
+            println!(
+                "  GameBanana: {} (file {})",
+                remote.gamebanana_id, remote.gamebanana_file_id
+            );
+            println!(
+                "  Latest Version: {}",
+                style::version(remote.latest_version)
+            );
+            if remote.last_update > 0 {
+                println!("  Last Update: {}", format_unix_date(remote.last_update));
+            }
+        }
+    }
+}
+
+fn format_dependencies(dependencies: &[DependencyDetails]) -> String {
+    dependencies
+        .iter()
+        .map(|d| format!("{} ({})", d.name, d.version))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Shows details of the installed mods.
+pub async fn run(args: ShowArgs, config: &AppConfig) -> anyhow::Result<()> {
+    let mut names = args.names;
+    if args.stdin {
+        for line in io::stdin().lock().lines() {
+            let line = line?;
+            let name = line.trim();
+            if !name.is_empty() {
+                names.push(name.to_string());
+            }
+        }
+    }
+
+    info!("scanning installed mods");
+    let mods = local::scan_mods(config)?;
+    // NOTE the other mods declared by the manifest are shown with the first one
+    let others = mods.iter().flat_map(|m| {
+        m.others()
+            .iter()
+            .map(move |o| (o.name().to_ascii_lowercase(), m))
+    });
+    let by_name: HashMap<String, &LocalMod> = others
+        .chain(mods.iter().map(|m| (m.name().to_ascii_lowercase(), m)))
+        .collect();
+
+    let registry = if args.remote {
+        info!("fetching database");
+        let option = DownloadOption::default().with_config(config)?;
+        Some(
+            api::fetch_registry(
+                SharedHttpClient::new(config.client())?.inner().clone(),
+                &option,
+            )
+            .await?,
+        )
+    } else {
+        None
+    };
+
+    let mut details = Vec::with_capacity(names.len());
+    for name in &names {
+        match by_name.get(&name.to_ascii_lowercase()) {
+            Some(found) => {
+                details.push(ModDetails::from(*found).with_registry(registry.as_ref()));
+            }
+            None => warn!("mod not installed: {}", name),
+        }
+    }
+
+    if args.json || config.output().is_json() {
+        ui::print_json(&details)?;
+    } else {
+        for detail in &details {
+            detail.print();
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,240 @@
+//! Handle sync command, and write the lockfile after the other commands change the mods.
+use std::{
+    collections::HashMap,
+    io::{self, IsTerminal},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, bail};
+use clap::Args;
+use serde::Serialize;
+use tracing::{debug, info, warn};
+
+use crate::{
+    commands::DownloadOption,
+    config::AppConfig,
+    core::{
+        cache,
+        download::DownloadFile,
+        history::{self, Action},
+        installed, local,
+        lockfile::{self, Lockfile, SyncPlan},
+        network::{SharedHttpClient, api},
+        trash,
+    },
+    log::anonymize,
+    ui::{self, style},
+};
+
+#[derive(Debug, Clone, Args)]
+pub struct SyncArgs {
+    /// Lockfile to sync with, e.g. copied from another machine [default: Mods/hultra.lock]
+    #[arg(long, value_name = "PATH")]
+    pub lockfile: Option<PathBuf>,
+
+    /// Shows the differences from the lockfile without downloading.
+    #[arg(long)]
+    pub dry_run: bool,
+
+    #[command(flatten)]
+    pub option: DownloadOption,
+}
+
+/// Differences from the lockfile in the JSON output.
+#[derive(Debug, Default, Serialize)]
+struct SyncOutput<'a> {
+    missing: Vec<&'a str>,
+    /// Locked mods installed as another archive.
+    changed: Vec<&'a str>,
+    /// File names of the archives which are not locked.
+    extraneous: Vec<String>,
+    /// Locked mods without the download URL.
+    unavailable: Vec<&'a str>,
+    /// File names of the downloaded archives.
+    downloaded: Vec<String>,
+}
+
+/// Downloads the locked mods which are missing or changed, and flags the ones not locked.
+pub async fn run(args: SyncArgs, config: &AppConfig) -> anyhow::Result<()> {
+    let option = args.option.with_config(config)?;
+    let path = args.lockfile.unwrap_or_else(|| lockfile::path(config));
+    let lock = Lockfile::load(&path).with_context(|| {
+        format!(
+            "Failed to read the lockfile '{}', it is written by install, update, offload, and restore",
+            path.display()
+        )
+    })?;
+
+    info!("scanning installed mods");
+    let mods = local::scan_mods(config)?;
+    let cache_db = cache::sync(config, false)?;
+    let plan = lock.plan(&mods, &cache_db);
+
+    let mut targets = Vec::new();
+    let mut unavailable = Vec::new();
+    for locked in plan
+        .missing
+        .iter()
+        .chain(plan.changed.iter().map(|(l, _)| l))
+    {
+        match DownloadFile::try_from(*locked) {
+            Ok(file) => targets.push(file),
+            Err(e) => {
+                debug!(?e, "cannot download {}", locked.name);
+                unavailable.push(locked.name.as_str());
+            }
+        }
+    }
+
+    let output = config.output();
+    let mut result = SyncOutput {
+        missing: plan.missing.iter().map(|l| l.name.as_str()).collect(),
+        changed: plan.changed.iter().map(|(l, _)| l.name.as_str()).collect(),
+        extraneous: plan
+            .extraneous
+            .iter()
+            .map(|m| file_name(m.file().path()))
+            .collect(),
+        unavailable,
+        downloaded: Vec::new(),
+    };
+    if !output.is_json() {
+        print_plan(&plan);
+    }
+    if targets.is_empty() || args.dry_run {
+        if output.is_json() {
+            ui::print_json(&result)?;
+        }
+        return check_unavailable(&result.unavailable);
+    }
+    if option.offline {
+        bail!("Downloading mods requires network access, run without `--offline`");
+    }
+    // NOTE nobody can answer without a terminal, e.g. in scripts
+    if !config.assume_yes()
+        && io::stdin().is_terminal()
+        && !tokio::task::spawn_blocking(|| ui::confirm("Proceed with syncing?")).await??
+    {
+        println!("Sync canceled");
+        return Ok(());
+    }
+
+    let shared_client = SharedHttpClient::new(config.client())?;
+    let client = shared_client.inner().clone();
+    let mirrors = option.mirrors(&client).await?;
+    info!("downloading mods");
+    let report = option
+        .downloader(client, mirrors)
+        .download_many(targets, &config.mods_dir())
+        .await;
+    ui::print_download_report(&report, output);
+
+    // NOTE the replaced archive stays if the download is named differently
+    let mut replaced = HashMap::new();
+    for path in &report.succeeded {
+        let Ok(m) = local::read_mod(path) else {
+            continue;
+        };
+        let Some((_, old)) = plan.changed.iter().find(|(l, _)| l.name == m.name()) else {
+            continue;
+        };
+        replaced.insert(old.name().to_string(), old.version().to_string());
+        let old_path = old.file().path();
+        if old_path != path
+            && let Err(e) = trash::remove(option.trash.as_ref(), old_path)
+        {
+            warn!(?e, "failed to remove {}", anonymize(old_path));
+        }
+    }
+    let records = history::read_records(Action::Install, &report.succeeded, &replaced);
+    history::append_or_warn(config.history_path(), &records);
+    installed::record_or_warn(config.installed_hashes_path(), &records);
+
+    if output.is_json() {
+        result.downloaded = report
+            .succeeded
+            .iter()
+            .map(|path| file_name(path))
+            .collect();
+        ui::print_json(&result)?;
+    }
+    report.check()?;
+    check_unavailable(&result.unavailable)
+}
+
+/// Prints the differences of the installed mods from the lockfile.
+fn print_plan(plan: &SyncPlan) {
+    if plan.is_synced() {
+        println!(
+            "All {} locked mods are installed as they are",
+            plan.unchanged
+        );
+        return;
+    }
+    for locked in &plan.missing {
+        println!(
+            "{}  {} v{}",
+            style::error("Missing   "),
+            style::name(&locked.name),
+            locked.version
+        );
+    }
+    for (locked, installed) in &plan.changed {
+        println!(
+            "{}  {} v{} -> v{}",
+            style::version("Changed   "),
+            style::name(&locked.name),
+            installed.version(),
+            locked.version
+        );
+    }
+    for m in &plan.extraneous {
+        println!(
+            "{}  {} {}",
+            style::warning("Extraneous"),
+            style::name(m.name()),
+            style::path(format!("({})", file_name(m.file().path()))),
+        );
+    }
+    if !plan.extraneous.is_empty() {
+        println!("Extraneous mods are not locked, remove or offload them to match the lockfile");
+    }
+}
+
+fn check_unavailable(names: &[&str]) -> anyhow::Result<()> {
+    if names.is_empty() {
+        return Ok(());
+    }
+    bail!(
+        "No download URL is locked for {}, install them by hand",
+        names.join(", ")
+    )
+}
+
+/// Writes the lockfile of the installed mods, only warns on failure since the change itself
+/// has been made.
+pub(super) fn write_lockfile(config: &AppConfig) {
+    let path = lockfile::path(config);
+    if let Err(e) = lock_installed(&path, config) {
+        warn!(?e, "failed to write the lockfile to {}", anonymize(&path));
+    }
+}
+
+fn lock_installed(path: &Path, config: &AppConfig) -> anyhow::Result<()> {
+    let previous = Lockfile::load(path)
+        .inspect_err(|e| debug!(?e, "no previous lockfile"))
+        .ok();
+    // NOTE the commands changing the mods have just fetched it
+    let registry = api::load_cached_registry(config.api_cache_dir());
+    let mods = local::scan_mods(config)?;
+    let cache_db = cache::sync(config, false)?;
+    Lockfile::new(&mods, &cache_db, registry.as_ref(), previous.as_ref()).save(path)?;
+    Ok(())
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned()
+}
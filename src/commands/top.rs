@@ -0,0 +1,131 @@
+//! Handle top command.
+use std::collections::{HashMap, HashSet};
+
+use anyhow::bail;
+use clap::Args;
+use serde::Serialize;
+use tracing::info;
+
+use crate::{
+    commands::DownloadOption,
+    config::AppConfig,
+    core::{
+        local,
+        network::{
+            SharedHttpClient, api,
+            gamebanana::{self, ModInfo, Period},
+        },
+    },
+    ui::{self, style},
+};
+
+#[derive(Debug, Clone, Args)]
+pub struct TopArgs {
+    /// Period of the ranking: `today`, `week`, `month`, `year`, or `all`.
+    #[arg(long, value_name = "PERIOD", default_value = "week")]
+    pub period: Period,
+}
+
+/// Featured mod in the JSON output.
+#[derive(Debug, Serialize)]
+struct TopMod<'a> {
+    /// Rank in the period, from 1.
+    rank: u32,
+    name: &'a str,
+    kind: &'a str,
+    author: &'a str,
+    gamebanana_id: u32,
+    downloads: u64,
+    likes: u64,
+    page_url: &'a str,
+    /// Installed version, `null` if not installed.
+    installed: Option<&'a str>,
+}
+
+/// Lists the featured mods of the period on GameBanana, marking the installed ones.
+pub async fn run(args: TopArgs, config: &AppConfig) -> anyhow::Result<()> {
+    if config.offline() {
+        bail!("Listing featured mods requires network access, run without `--offline`");
+    }
+    let option = DownloadOption::default().with_config(config)?;
+    let client = SharedHttpClient::new(config.client())?;
+
+    info!("fetching featured mods");
+    let mut featured: Vec<ModInfo> = gamebanana::featured(client.inner())
+        .await?
+        .into_iter()
+        .filter(|m| m.featured.as_ref().is_some_and(|f| args.period.contains(f)))
+        .collect();
+    featured.sort_by_key(|m| m.featured.as_ref().map(|f| f.position));
+
+    // NOTE the featured mods only have GameBanana IDs, the names are looked up in the registry
+    info!("fetching database");
+    let registry = api::fetch_registry(client.inner().clone(), &option).await?;
+    info!("scanning installed mods");
+    let mods = local::scan_mods(config)?;
+    let installed: HashMap<&str, &str> = mods.iter().flat_map(|m| m.declared()).collect();
+
+    let top: Vec<TopMod> = featured
+        .iter()
+        .enumerate()
+        .map(|(i, m)| {
+            let names = registry.names_by_gbids(&HashSet::from([m.id]));
+            let version = names
+                .iter()
+                .find_map(|name| installed.get(name.as_str()).copied());
+            TopMod {
+                rank: i as u32 + 1,
+                name: &m.name,
+                kind: &m.kind,
+                author: &m.author,
+                gamebanana_id: m.id,
+                downloads: m.downloads,
+                likes: m.likes,
+                page_url: &m.page_url,
+                installed: version,
+            }
+        })
+        .collect();
+
+    let output = config.output();
+    if output.is_json() {
+        ui::print_json(&top)?;
+        return Ok(());
+    }
+    if top.is_empty() {
+        println!("No featured mods in the period");
+        return Ok(());
+    }
+
+    let width = top.len().to_string().len();
+    for m in &top {
+        let name = match m.installed {
+            Some(version) => format!(
+                "{} {}",
+                style::success(m.name),
+                style::success(format!("(installed v{})", version))
+            ),
+            None => m.name.to_string(),
+        };
+        println!(
+            "{:>width$}. {} ({}) by {} {}",
+            m.rank,
+            name,
+            m.kind,
+            m.author,
+            style::path(format!("[{}]", m.gamebanana_id)),
+        );
+        let indent = " ".repeat(width + 2);
+        println!("{}Downloads: {}, Likes: {}", indent, m.downloads, m.likes);
+        if !m.page_url.is_empty() {
+            println!("{}{}", indent, m.page_url);
+        }
+    }
+    let installed_count = top.iter().filter(|m| m.installed.is_some()).count();
+    println!(
+        "\n{} featured mods, {} installed",
+        top.len(),
+        installed_count
+    );
+    Ok(())
+}
@@ -1,70 +1,301 @@
 //! Handle update command.
-use tracing::info;
+use std::{
+    collections::HashSet,
+    io::{self, IsTerminal},
+};
+
+use clap::Args;
+use reqwest::Client;
+use serde::Serialize;
+use tracing::{info, warn};
 
 use crate::{
     commands::DownloadOption,
     config::AppConfig,
     core::{
         blacklist::{self, LocalUpdaterBlacklistSource},
-        cache,
+        cache::{self, FileCacheDb},
+        history::{self, Action},
+        installed::{self, InstalledHashes},
         local::{self, LocalFileSystemService, LocalModExt},
-        network::{SharedHttpClient, api, downloader},
-        update,
+        network::{SharedHttpClient, api, downloader::DownloadReport},
+        update::{self, UpdateContext, UpdateInfo, UpdateStrategy},
     },
+    ui,
 };
 
-/// Checks update for the mods and download the latest one if available.
-pub async fn run(args: DownloadOption, config: &AppConfig) -> anyhow::Result<()> {
-    let mods_dir = config.mods_dir();
+#[derive(Debug, Clone, Args)]
+pub struct UpdateArgs {
+    #[command(flatten)]
+    pub option: DownloadOption,
 
-    info!("scanning installed mods");
-    let mut local_mods = local::scan_mods(&mods_dir)?;
-    info!("found {} mods", local_mods.len());
+    /// How to tell if an update is available: `hash`, `version`, or `both`.
+    ///
+    /// `hash` flags any installed file whose checksum differs from the latest one, including
+    /// locally repacked mods. `version` compares versions, falling back to the upload time.
+    #[arg(long, value_name = "STRATEGY", default_value = "hash")]
+    pub strategy: UpdateStrategy,
 
-    info!("checking updater's blacklist");
-    let source = LocalUpdaterBlacklistSource::new(&mods_dir);
-    let ublist = blacklist::fetch(&source)?;
+    /// Also compare sampled ranges of the archives whose size and timestamps are unchanged.
+    ///
+    /// Catches archives rewritten in place by tools that restore the modification time.
+    #[arg(long)]
+    pub paranoid: bool,
 
-    local_mods.apply_blacklist(&ublist)?;
+    /// Skips the mods disabled in Everest's blacklist.txt.
+    #[arg(long)]
+    pub skip_disabled: bool,
+}
 
-    if local_mods.is_empty() {
-        println!("All mods are blacklisted")
+/// How to collect the installed mods to be checked.
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct ScanOptions {
+    /// See [`cache::sync`].
+    pub paranoid: bool,
+    /// Skips the mods disabled in Everest's blacklist.
+    pub skip_disabled: bool,
+}
+
+impl ScanOptions {
+    pub fn new(paranoid: bool, skip_disabled: bool, config: &AppConfig) -> Self {
+        Self {
+            paranoid,
+            skip_disabled: skip_disabled || config.profile().skip_disabled.unwrap_or(false),
+        }
     }
+}
 
-    info!("syncing file cache");
-    let cache_db = cache::sync(config)?;
+/// Result of the update in the JSON output.
+#[derive(Debug, Default, Serialize)]
+struct UpdateOutput<'a> {
+    /// Available updates, including the ones not downloaded with `--offline`.
+    updates: &'a [UpdateInfo<'a>],
+    /// Names of the mods in `updates` whose archives were edited since they were installed.
+    locally_modified: Vec<&'a str>,
+    /// Locally modified mods which were not overwritten.
+    kept: Vec<&'a str>,
+    /// File names of the downloaded archives.
+    downloaded: Vec<String>,
+    skipped: Vec<SkippedDownload<'a>>,
+    failed: Vec<FailedDownload<'a>>,
+    /// Names of the mods canceled by Ctrl-C.
+    canceled: Vec<&'a str>,
+}
+
+#[derive(Debug, Serialize)]
+struct SkippedDownload<'a> {
+    name: &'a str,
+    /// Name of the mod whose download is the same archive.
+    same_as: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+struct FailedDownload<'a> {
+    name: &'a str,
+    reasons: Vec<String>,
+}
+
+impl<'a> UpdateOutput<'a> {
+    fn with_report(mut self, report: &'a DownloadReport) -> Self {
+        self.downloaded = report
+            .succeeded
+            .iter()
+            .filter_map(|path| path.file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+            .collect();
+        self.skipped = report
+            .skipped
+            .iter()
+            .map(|(name, same_as)| SkippedDownload { name, same_as })
+            .collect();
+        self.failed = report
+            .failed
+            .iter()
+            .map(|(name, e)| FailedDownload {
+                name,
+                reasons: e.reasons(),
+            })
+            .collect();
+        self.canceled = report.canceled.iter().map(String::as_str).collect();
+        self
+    }
+}
+
+/// Checks update for the mods and download the latest one if available.
+pub async fn run(args: UpdateArgs, config: &AppConfig) -> anyhow::Result<()> {
+    let strategy = args.strategy;
+    let scan = ScanOptions::new(args.paranoid, args.skip_disabled, config);
+    let args = args.option.with_config(config)?;
+    let mods_dir = config.mods_dir();
 
     // Initialize shared client
-    let shared_client = SharedHttpClient::new();
+    let shared_client = SharedHttpClient::new(config.client())?;
 
-    info!("fetching database");
-    let registry = api::fetch_registry(shared_client.inner().clone(), &args).await?;
+    let (cache_db, contexts) =
+        collect_update_contexts(shared_client.inner().clone(), &args, config, scan).await?;
 
     info!("checking updates");
-    let contexts = registry.into_update_context(&local_mods, LocalFileSystemService);
-    let report = update::scan_updates(&cache_db, &contexts)?;
+    let mut report = update::scan_updates(&cache_db, &contexts, strategy)?;
 
+    // NOTE a mod edited by hand differs from the hash recorded when it was installed
+    let hashes = InstalledHashes::load(config.installed_hashes_path());
+    let modified: HashSet<&str> = contexts
+        .iter()
+        .filter(|ctx| hashes.is_modified(ctx.cache_key(), cache_db.hash(ctx.cache_key())))
+        .map(|ctx| ctx.name())
+        .collect();
+
+    let output = config.output();
+    let mut result = UpdateOutput {
+        updates: &report.updates,
+        locally_modified: report
+            .updates
+            .iter()
+            .filter_map(|info| modified.get(info.name()).copied())
+            .collect(),
+        ..Default::default()
+    };
     if report.updates.is_empty() {
         info!("all mods are up-to-date");
+        if output.is_json() {
+            ui::print_json(&result)?;
+        }
         return Ok(());
     } else {
         // send update info to stdout
         info!("available updates:");
-        for update_info in report.updates {
-            info!("{}", update_info);
+        for update_info in &report.updates {
+            if modified.contains(update_info.name()) {
+                info!("{} (locally modified)", update_info);
+            } else {
+                info!("{}", update_info);
+            }
+        }
+    }
+    if args.offline {
+        if output.is_json() {
+            ui::print_json(&result)?;
         }
+        output.print_text("Run without `--offline` to download the updates");
+        return Ok(());
     }
 
+    result.kept = keep_modified(&result.locally_modified, config).await?;
+    report
+        .download_files
+        .retain(|file| !result.kept.contains(&file.name()));
+
     // Download updates
+    let client = shared_client.inner().clone();
+    let mirrors = args.mirrors(&client).await?;
     info!("downloading mods");
-    downloader::download_all(
-        shared_client.inner().clone(),
-        args,
-        report.download_files,
-        &mods_dir,
-    )
-    .await?;
+    let downloaded = args
+        .downloader(client, mirrors)
+        .download_many(report.download_files, &mods_dir)
+        .await;
+    ui::print_download_report(&downloaded, output);
+    let installed = report
+        .updates
+        .iter()
+        .map(|info| (info.name().to_string(), info.current_version().to_string()))
+        .collect();
+    let records = history::read_records(Action::Update, &downloaded.succeeded, &installed);
+    history::append_or_warn(config.history_path(), &records);
+    installed::record_or_warn(config.installed_hashes_path(), &records);
+    if !downloaded.succeeded.is_empty() {
+        super::sync::write_lockfile(config);
+    }
+    if output.is_json() {
+        ui::print_json(&result.with_report(&downloaded))?;
+    }
+    downloaded.check()?;
 
     info!("updating completed");
     Ok(())
 }
+
+/// Asks whether to overwrite each locally modified mod, returns the ones to keep.
+///
+/// Without a terminal, they are kept unless `--yes` is given.
+async fn keep_modified<'a>(
+    modified: &[&'a str],
+    config: &AppConfig,
+) -> anyhow::Result<Vec<&'a str>> {
+    let mut kept = Vec::new();
+    for &name in modified {
+        let overwrite = if config.assume_yes() {
+            true
+        } else if io::stdin().is_terminal() {
+            let prompt = format!("{} is locally modified, overwrite it?", name);
+            tokio::task::spawn_blocking(move || ui::confirm_no(&prompt)).await??
+        } else {
+            warn!(
+                "{} is locally modified and not updated, run with `--yes` to overwrite it",
+                name
+            );
+            false
+        };
+        if !overwrite {
+            kept.push(name);
+        }
+    }
+    Ok(kept)
+}
+
+/// Collects the update contexts of the installed mods which are not blacklisted.
+pub(super) async fn collect_update_contexts(
+    client: Client,
+    args: &DownloadOption,
+    config: &AppConfig,
+    scan: ScanOptions,
+) -> anyhow::Result<(FileCacheDb, Vec<UpdateContext>)> {
+    let mods_dir = config.mods_dir();
+
+    info!("scanning installed mods");
+    let mut local_mods = local::scan_mods(config)?;
+    info!("found {} mods", local_mods.len());
+    super::list::warn_duplicates(&local_mods, &mods_dir)?;
+
+    info!("checking updater's blacklist");
+    let source = LocalUpdaterBlacklistSource::new(&mods_dir);
+    let ublist = blacklist::fetch(&source)?;
+
+    local_mods.apply_blacklist(&ublist)?;
+
+    if scan.skip_disabled {
+        let disabled = blacklist::read_everest_blacklist(&mods_dir)?;
+        let skipped = local_mods.skip_disabled(&disabled);
+        if skipped > 0 {
+            info!("{} disabled mods were skipped", skipped);
+        }
+    }
+
+    if local_mods.is_empty() {
+        config.output().print_text("All mods are blacklisted")
+    }
+
+    // NOTE hash the installed mods while fetching the database, neither waits for the other
+    info!("syncing file cache");
+    let sync_config = config.clone();
+    let syncing = tokio::task::spawn_blocking(move || cache::sync(&sync_config, scan.paranoid));
+
+    info!("fetching database");
+    let registry = api::fetch_registry(client, args).await;
+    let cache_db = syncing.await??;
+    let registry = registry?;
+
+    // NOTE the unpacked mods may be edited by hand, so they are only told to be outdated
+    let (unpacked, local_mods): (Vec<_>, Vec<_>) =
+        local_mods.into_iter().partition(|m| m.file().is_dir());
+    for ctx in registry.update_contexts(&unpacked, LocalFileSystemService) {
+        if ctx.is_newer() {
+            warn!(
+                "{} is installed as a directory and not updated automatically, {} is available",
+                ctx.name(),
+                ctx.available_version()
+            );
+        }
+    }
+    let contexts = registry.update_contexts(&local_mods, LocalFileSystemService);
+    Ok((cache_db, contexts))
+}
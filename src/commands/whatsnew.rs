@@ -0,0 +1,115 @@
+//! Handle whatsnew command.
+use std::{
+    collections::HashMap,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use clap::Args;
+use serde::Serialize;
+use tracing::info;
+
+use crate::{
+    commands::DownloadOption,
+    config::AppConfig,
+    core::{
+        local,
+        network::{SharedHttpClient, api},
+        version::is_older,
+    },
+    ui::{self, style},
+    utils::format_unix_date,
+};
+
+#[derive(Debug, Clone, Args)]
+pub struct WhatsnewArgs {
+    /// Shows the mods updated in this many days.
+    #[arg(long, value_name = "N", default_value_t = 7, value_parser = clap::value_parser!(u64).range(1..=365))]
+    pub days: u64,
+
+    /// Shows only the installed mods.
+    #[arg(long)]
+    pub installed: bool,
+}
+
+/// Recently updated mod in the JSON output.
+#[derive(Debug, Serialize)]
+struct UpdatedMod<'a> {
+    name: &'a str,
+    version: &'a str,
+    gamebanana_id: u32,
+    /// UNIX time in seconds.
+    last_update: u64,
+    /// Installed version, `null` if not installed.
+    installed: Option<&'a str>,
+}
+
+/// Lists the mods recently updated in the mod database, marking the installed ones.
+pub async fn run(args: WhatsnewArgs, config: &AppConfig) -> anyhow::Result<()> {
+    info!("fetching database");
+    let option = DownloadOption::default().with_config(config)?;
+    let client = SharedHttpClient::new(config.client())?;
+    let registry = api::fetch_registry(client.inner().clone(), &option).await?;
+
+    info!("scanning installed mods");
+    let mods = local::scan_mods(config)?;
+    let installed: HashMap<&str, &str> = mods.iter().flat_map(|m| m.declared()).collect();
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let since = now.saturating_sub(args.days * 86_400);
+    let updated: Vec<UpdatedMod> = registry
+        .updated_since(since)
+        .into_iter()
+        .map(|(name, entry)| UpdatedMod {
+            name,
+            version: entry.version(),
+            gamebanana_id: entry.id(),
+            last_update: entry.last_update(),
+            installed: installed.get(name).copied(),
+        })
+        .filter(|m| !args.installed || m.installed.is_some())
+        .collect();
+
+    let output = config.output();
+    if output.is_json() {
+        ui::print_json(&updated)?;
+    }
+    if updated.is_empty() {
+        output.print_text(&format!("No mods updated in the last {} days", args.days));
+        return Ok(());
+    }
+    if output.is_json() {
+        return Ok(());
+    }
+
+    for m in &updated {
+        let date = format_unix_date(m.last_update);
+        let version = style::version(format!("v{}", m.version));
+        match m.installed {
+            Some(current) if is_older(current, m.version) => println!(
+                "{}  {} {} {}",
+                date,
+                style::success(m.name),
+                version,
+                style::success(format!("(installed v{}, run `hultra update`)", current))
+            ),
+            Some(_) => println!(
+                "{}  {} {} (installed)",
+                date,
+                style::success(m.name),
+                version
+            ),
+            None => println!("{}  {} {}", date, m.name, version),
+        }
+    }
+    let installed_count = updated.iter().filter(|m| m.installed.is_some()).count();
+    println!(
+        "\n{} mods updated in the last {} days, {} installed",
+        updated.len(),
+        args.days,
+        installed_count
+    );
+    Ok(())
+}
@@ -1,21 +1,62 @@
 use std::{
     env,
     fmt::Display,
+    fs,
     path::{Path, PathBuf},
 };
 
-use tracing::warn;
+use tracing::{debug, info, warn};
 
-use crate::log::anonymize;
+use hultra_core::config::ModsConfig;
+
+use crate::{
+    config::file::ConfigFile,
+    core::{
+        local::ExcludePatterns,
+        mirrorlist::MirrorTable,
+        network::{ClientOptions, ProxyUrl, TlsBackend},
+        trash::Trash,
+    },
+    log::anonymize,
+    platform,
+    ui::OutputFormat,
+};
+
+mod discover;
+mod env_vars;
+mod file;
+
+pub use discover::{InstallKind, is_install};
+pub use env_vars::VARS as ENV_VARS;
+pub use file::{EXAMPLE as CONFIG_EXAMPLE, Profile};
 
 pub const CARGO_PKG_NAME: &str = env!("CARGO_PKG_NAME");
 pub const CARGO_PKG_VERSION: &str = env!("CARGO_PKG_VERSION");
-const STEAM_GAME_DIRECTORY: &str = ".local/share/Steam/steamapps/common/Celeste/";
 
 #[derive(thiserror::Error, Debug)]
 pub enum AppConfigError {
     #[error("failed to determine user home directory from environment variable")]
     DetermineHomeDirectory,
+    #[error("failed to load config file '{path}'")]
+    ConfigFile {
+        path: String,
+        #[source]
+        source: file::ConfigFileError,
+    },
+    #[error("profile '{0}' is not defined in the config file")]
+    UnknownProfile(String),
+    #[error("failed to update config file '{path}'")]
+    UpdateConfigFile {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("no profile to update, select one with `--config-profile`")]
+    NoProfile,
+    #[error(transparent)]
+    Env(#[from] env_vars::EnvError),
+    #[error("TLS backend 'native' is not built in, reinstall hultra with `--features native-tls`")]
+    NativeTlsUnavailable,
 }
 
 /// Application configuration.
@@ -24,8 +65,59 @@ pub struct AppConfig {
     /// Directory where `Celeste.exe` is installed originally.
     root_dir: PathBuf,
 
+    /// Mods directory of the profile, if it is not the one in the root directory.
+    mods_dir: Option<PathBuf>,
+
     /// Path to the file hash cache.
     cache_db_path: PathBuf,
+
+    /// Path to the cached latency of the mirrors.
+    latency_cache_path: PathBuf,
+
+    /// Directory of the cached API responses.
+    api_cache_dir: PathBuf,
+
+    /// Path to the record of when the mods were disabled.
+    disabled_since_path: PathBuf,
+
+    /// Path to the log of the changes to the installed mods.
+    history_path: PathBuf,
+
+    /// Path to the hashes of the archives as installed.
+    installed_hashes_path: PathBuf,
+
+    /// Path to the config file, which may not exist.
+    config_path: PathBuf,
+
+    /// Directory where unused mods are offloaded, if configured.
+    archive_dir: Option<PathBuf>,
+
+    /// Directory where replaced and deleted archives are moved, if configured.
+    trash_dir: Option<PathBuf>,
+
+    /// Patterns of the entries in the Mods directory to be ignored.
+    exclude: ExcludePatterns,
+
+    /// Custom mirrors defined in the config file.
+    mirrors: MirrorTable,
+
+    /// Machine specific settings selected from the config file.
+    profile: Profile,
+
+    /// Name of the selected profile, or the hostname to name a new one.
+    profile_name: Option<String>,
+
+    /// Works only with the cached databases, without network access.
+    offline: bool,
+
+    /// Proxy and TLS backend of the clients, from the command line or the profile.
+    client: ClientOptions,
+
+    /// Format of the results printed to stdout.
+    output: OutputFormat,
+
+    /// Answers yes to every prompt.
+    assume_yes: bool,
 }
 
 impl Display for AppConfig {
@@ -41,37 +133,150 @@ impl Display for AppConfig {
 }
 
 impl AppConfig {
-    pub fn new(directory: Option<&Path>) -> Result<Self, AppConfigError> {
+    pub fn new(
+        directory: Option<&Path>,
+        profile_name: Option<&str>,
+    ) -> Result<Self, AppConfigError> {
         // Determine user home directory
         let Some(home) = env::home_dir() else {
             return Err(AppConfigError::DetermineHomeDirectory);
         };
 
-        let cache_db_path = env::var("XDG_STATE_HOME")
-            .map(|value| value.into())
-            .unwrap_or_else(|_| home.join(".local").join("state"))
+        let state_dir = platform::state_dir(&home).join(CARGO_PKG_NAME);
+        let latency_cache_path = state_dir.join("mirrors").with_extension("cache");
+        let api_cache_dir = state_dir.join("api");
+
+        let config_path = platform::config_dir(&home)
             .join(CARGO_PKG_NAME)
-            .join("checksum")
-            .with_extension("cache");
+            .join("config.yaml");
+        debug!(path = %anonymize(&config_path), "loading config file");
+        let file = ConfigFile::load(&config_path).map_err(|source| AppConfigError::ConfigFile {
+            path: anonymize(&config_path),
+            source,
+        })?;
+
+        let hostname = platform::hostname();
+        let profile_name = profile_name.map(String::from).or_else(|| {
+            env::var(env_vars::PROFILE_VAR)
+                .ok()
+                .filter(|name| !name.is_empty())
+        });
+        let profile_name = profile_name.as_deref();
+        // NOTE the state of the installed mods is kept per profile, since their Mods directories
+        // may differ, e.g. one for casual play and another for TAS
+        let (profile_name, profile, mods_state_dir) =
+            match file.select_profile(profile_name, hostname.as_deref()) {
+                Some((name, profile)) => {
+                    info!("using config profile '{}'", name);
+                    let dir = state_dir.join("profiles").join(name);
+                    (Some(name.to_string()), profile.clone(), dir)
+                }
+                None => match profile_name {
+                    Some(name) => return Err(AppConfigError::UnknownProfile(name.to_string())),
+                    None => (hostname, Profile::default(), state_dir),
+                },
+            };
+        // NOTE the environment variables override the profile, the command line overrides both
+        let profile = env_vars::load_profile(env::vars())?.or(profile);
+        let cache_db_path = mods_state_dir.join("checksum").with_extension("cache");
+        let disabled_since_path = mods_state_dir.join("disabled").with_extension("cache");
+        let history_path = mods_state_dir.join("history").with_extension("jsonl");
+        let installed_hashes_path = mods_state_dir.join("installed").with_extension("cache");
 
         let root_dir = directory
             .map(|dir| dir.into())
-            .unwrap_or_else(|| home.join(STEAM_GAME_DIRECTORY));
+            .or_else(|| {
+                profile
+                    .directory
+                    .as_deref()
+                    .map(|dir| expand_home(dir, &home))
+            })
+            .unwrap_or_else(|| discover_root_dir(&home));
 
         let root_dir = resolve_root_dir(&root_dir);
+        debug!(dir = %anonymize(root_dir), kind = %InstallKind::of(root_dir), "Celeste installation");
+        let mods_dir = profile
+            .mods_dir
+            .as_deref()
+            .map(|dir| expand_home(dir, &home));
+        let archive_dir = profile
+            .archive
+            .as_deref()
+            .map(|dir| expand_home(dir, &home));
+        let trash_dir = profile.trash.as_deref().map(|dir| expand_home(dir, &home));
 
         Ok(Self {
             root_dir: root_dir.to_path_buf(),
+            mods_dir,
             cache_db_path,
+            latency_cache_path,
+            api_cache_dir,
+            disabled_since_path,
+            history_path,
+            installed_hashes_path,
+            config_path,
+            archive_dir,
+            trash_dir,
+            exclude: file.exclude,
+            mirrors: file.mirrors,
+            client: ClientOptions {
+                proxy: profile.proxy.clone(),
+                tls: profile.tls.unwrap_or_default(),
+            },
+            profile,
+            profile_name,
+            offline: false,
+            output: OutputFormat::default(),
+            assume_yes: false,
         })
     }
 
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    pub fn with_output(mut self, output: OutputFormat) -> Self {
+        self.output = output;
+        self
+    }
+
+    pub fn with_assume_yes(mut self, assume_yes: bool) -> Self {
+        self.assume_yes = assume_yes;
+        self
+    }
+
+    /// Overrides the proxy of the profile, if given.
+    pub fn with_proxy(mut self, proxy: Option<ProxyUrl>) -> Self {
+        if proxy.is_some() {
+            self.client.proxy = proxy;
+        }
+        self
+    }
+
+    /// Overrides the TLS backend of the profile if given, and checks if it is built in.
+    pub fn with_tls(mut self, tls: Option<TlsBackend>) -> Result<Self, AppConfigError> {
+        if let Some(tls) = tls {
+            self.client.tls = tls;
+        }
+        if !self.client.tls.is_available() {
+            return Err(AppConfigError::NativeTlsUnavailable);
+        }
+        Ok(self)
+    }
+
     pub fn root_dir(&self) -> &Path {
         &self.root_dir
     }
 
     pub fn mods_dir(&self) -> PathBuf {
-        self.root_dir.join("Mods")
+        self.mods_dir
+            .clone()
+            .unwrap_or_else(|| discover::mods_dir(&self.root_dir))
+    }
+
+    pub fn install_kind(&self) -> InstallKind {
+        InstallKind::of(&self.root_dir)
     }
 
     /// Returns path to `update-build.txt` which is used for caching Everest version.
@@ -82,13 +287,184 @@ impl AppConfig {
     pub fn cache_db_path(&self) -> &Path {
         &self.cache_db_path
     }
+
+    pub fn config_path(&self) -> &Path {
+        &self.config_path
+    }
+
+    pub fn latency_cache_path(&self) -> &Path {
+        &self.latency_cache_path
+    }
+
+    pub fn api_cache_dir(&self) -> &Path {
+        &self.api_cache_dir
+    }
+
+    pub fn disabled_since_path(&self) -> &Path {
+        &self.disabled_since_path
+    }
+
+    pub fn history_path(&self) -> &Path {
+        &self.history_path
+    }
+
+    pub fn installed_hashes_path(&self) -> &Path {
+        &self.installed_hashes_path
+    }
+
+    pub fn archive_dir(&self) -> Option<&Path> {
+        self.archive_dir.as_deref()
+    }
+
+    /// Returns the trash of the profile, a relative directory is in the Mods directory.
+    pub fn trash(&self) -> Option<Trash> {
+        let dir = self.trash_dir.as_deref()?;
+        Some(Trash::new(
+            self.mods_dir().join(dir),
+            self.profile.trash_limit,
+        ))
+    }
+
+    pub fn exclude(&self) -> &ExcludePatterns {
+        &self.exclude
+    }
+
+    pub fn mirrors(&self) -> &MirrorTable {
+        &self.mirrors
+    }
+
+    pub fn profile(&self) -> &Profile {
+        &self.profile
+    }
+
+    pub fn profile_name(&self) -> Option<&str> {
+        self.profile_name.as_deref()
+    }
+
+    pub fn offline(&self) -> bool {
+        self.offline
+    }
+
+    pub fn client(&self) -> &ClientOptions {
+        &self.client
+    }
+
+    pub fn output(&self) -> OutputFormat {
+        self.output
+    }
+
+    pub fn assume_yes(&self) -> bool {
+        self.assume_yes
+    }
+
+    /// Sets `directory` of the selected profile in the config file, creating them if needed.
+    ///
+    /// Without a selected profile, a profile named after the hostname is added, so it is
+    /// selected automatically on this machine. Returns the name of the profile.
+    pub fn set_directory(&self, dir: &Path) -> Result<&str, AppConfigError> {
+        let profile = self
+            .profile_name
+            .as_deref()
+            .ok_or(AppConfigError::NoProfile)?;
+        let to_error = |source| AppConfigError::UpdateConfigFile {
+            path: anonymize(&self.config_path),
+            source,
+        };
+
+        let content = match fs::read_to_string(&self.config_path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(to_error(e)),
+        };
+        let content = file::set_directory(&content, profile, dir);
+        // NOTE never leave a file which cannot be loaded on the next run
+        serde_yaml_ng::from_str::<ConfigFile>(&content).map_err(|e| {
+            AppConfigError::ConfigFile {
+                path: anonymize(&self.config_path),
+                source: e.into(),
+            }
+        })?;
+        if let Some(parent) = self.config_path.parent() {
+            fs::create_dir_all(parent).map_err(to_error)?;
+        }
+        fs::write(&self.config_path, content).map_err(to_error)?;
+        Ok(profile)
+    }
+
+    /// Points `directory` of the config file to the new location, if the profile has it.
+    ///
+    /// Returns `false` if the directory is not given by the config file.
+    pub fn update_directory(&self, new: &Path) -> Result<bool, AppConfigError> {
+        let Some(old) = self.profile.directory.as_deref() else {
+            return Ok(false);
+        };
+        let to_error = |source| AppConfigError::UpdateConfigFile {
+            path: anonymize(&self.config_path),
+            source,
+        };
+
+        let content = fs::read_to_string(&self.config_path).map_err(to_error)?;
+        match file::rewrite_directory(&content, old, new) {
+            Some(content) => {
+                fs::write(&self.config_path, content).map_err(to_error)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+impl ModsConfig for AppConfig {
+    fn mods_dir(&self) -> PathBuf {
+        AppConfig::mods_dir(self)
+    }
+
+    fn cache_db_path(&self) -> &Path {
+        AppConfig::cache_db_path(self)
+    }
+
+    fn exclude(&self) -> &ExcludePatterns {
+        AppConfig::exclude(self)
+    }
+}
+
+/// Returns the installation found under home directory, the first one if there are many.
+fn discover_root_dir(home: &Path) -> PathBuf {
+    let mut installs = discover::find_installs(home);
+    if installs.len() > 1 {
+        let found: Vec<String> = installs.iter().map(|dir| anonymize(dir)).collect();
+        warn!(
+            "found {} Celeste installations: {}. Using the first one, choose with `{} config set celeste-dir <DIR>`",
+            installs.len(),
+            found.join(", "),
+            CARGO_PKG_NAME
+        );
+    }
+    if installs.is_empty() {
+        // NOTE the primary Steam library, which is reported as missing later
+        let steam = platform::steam_roots(home).swap_remove(0);
+        platform::steam_game_dir(&steam)
+    } else {
+        installs.swap_remove(0)
+    }
+}
+
+/// Expands a leading `~/` of the path to home directory.
+fn expand_home(path: &Path, home: &Path) -> PathBuf {
+    match path.strip_prefix("~") {
+        Ok(rest) => home.join(rest),
+        Err(_) => path.to_path_buf(),
+    }
 }
 
 /// Resolves installation path by searching Celeste executables.
 fn resolve_root_dir(dir: &Path) -> &Path {
     let is_root = dir.join("Celeste.exe").exists() || dir.join("Celeste.dll").exists();
 
-    let is_mods_dir = dir.ends_with("Mods") || dir.join("blacklist.txt").exists();
+    let is_mods_dir = dir
+        .file_name()
+        .is_some_and(|name| name.eq_ignore_ascii_case("Mods"))
+        || dir.join("blacklist.txt").exists();
 
     if is_mods_dir
         && !is_root
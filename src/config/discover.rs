@@ -0,0 +1,476 @@
+//! Discovery of the Celeste installations.
+//!
+//! Looks into the Steam libraries listed by `libraryfolders.vdf` of the native and Flatpak Steam,
+//! the Proton prefixes in them, the games recorded by the itch app and the Epic Games Launcher
+//! (or legendary of Heroic), then the default locations of the other launchers. See [`platform`]
+//! for the locations.
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::Display,
+    fs::{self, File},
+    io::BufReader,
+    path::{Component, Path, PathBuf},
+};
+
+use flate2::read::GzDecoder;
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::{log::anonymize, platform};
+
+/// Returns the installations of Celeste found on the machine, without duplicates.
+pub fn find_installs(home: &Path) -> Vec<PathBuf> {
+    let libraries: Vec<PathBuf> = platform::steam_roots(home)
+        .into_iter()
+        .flat_map(|root| steam_libraries(&root))
+        .collect();
+    let steam = libraries
+        .iter()
+        .map(|library| platform::steam_game_dir(library));
+    // NOTE the Windows versions added to Steam as non-Steam games run in their own prefixes
+    let prefixes: Vec<PathBuf> = libraries
+        .iter()
+        .flat_map(|library| proton_prefixes(library))
+        .collect();
+    let proton = prefixes.iter().flat_map(|drive_c| {
+        let manifests = drive_c.join("ProgramData").join(platform::EPIC_MANIFESTS);
+        platform::prefix_installs(drive_c)
+            .into_iter()
+            .chain(epic_installs(&manifests, Some(drive_c)))
+    });
+    let itch = platform::itch_apps_dirs(home)
+        .into_iter()
+        .flat_map(|dir| itch_installs(&dir));
+    let epic = platform::epic_manifest_dirs(home)
+        .into_iter()
+        .flat_map(|dir| epic_installs(&dir, None))
+        .chain(
+            platform::legendary_installed_files(home)
+                .into_iter()
+                .flat_map(|file| legendary_installs(&file)),
+        );
+    let others = platform::other_installs(home);
+
+    let mut seen = HashSet::new();
+    steam
+        .chain(proton)
+        .chain(itch)
+        .chain(epic)
+        .chain(others)
+        .filter(|dir| is_install(dir))
+        // NOTE `~/.steam/steam` is usually a symlink to `~/.local/share/Steam`
+        .filter(|dir| seen.insert(fs::canonicalize(dir).unwrap_or_else(|_| dir.clone())))
+        .inspect(|dir| debug!(dir = %anonymize(dir), "found Celeste"))
+        .collect()
+}
+
+/// Checks if the directory has `Celeste.exe` and `Mods/`, i.e. Everest is installed.
+///
+/// NOTE `Celeste.dll` is also accepted like the `--directory`, for the .NET Core Everest
+pub fn is_install(dir: &Path) -> bool {
+    let has_game = ["Celeste.exe", "Celeste.dll"]
+        .iter()
+        .any(|name| dir.join(name).is_file());
+    has_game && mods_dir(dir).is_dir()
+}
+
+/// Returns `Mods/` of the installation, or another casing of it like `mods/`.
+///
+/// NOTE Wine ignores the casing, so Everest in a Proton prefix uses whichever exists
+pub fn mods_dir(root_dir: &Path) -> PathBuf {
+    let exact = root_dir.join("Mods");
+    if exact.is_dir() {
+        return exact;
+    }
+    fs::read_dir(root_dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .find(|entry| {
+            entry.file_name().eq_ignore_ascii_case("Mods")
+                && entry.file_type().is_ok_and(|t| t.is_dir())
+        })
+        .map_or(exact, |entry| entry.path())
+}
+
+/// How Celeste is installed, told from the path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstallKind {
+    Steam,
+    FlatpakSteam,
+    SnapSteam,
+    /// Windows version in a Wine prefix of Proton, e.g. added to Steam as a non-Steam game.
+    Proton,
+    Itch,
+    /// Epic Games Launcher, or Heroic on Linux.
+    Epic,
+    Other,
+}
+
+impl Display for InstallKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self {
+            Self::Steam => "Steam",
+            Self::FlatpakSteam => "Flatpak Steam",
+            Self::SnapSteam => "Snap Steam",
+            Self::Proton => "Proton prefix",
+            Self::Itch => "itch",
+            Self::Epic => "Epic Games",
+            Self::Other => "other launcher or manual install",
+        };
+        write!(f, "{}", kind)
+    }
+}
+
+impl InstallKind {
+    pub fn of(root_dir: &Path) -> Self {
+        let names: Vec<&std::ffi::OsStr> = root_dir
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(name) => Some(name),
+                _ => None,
+            })
+            .collect();
+        let has = |pair: [&str; 2]| names.windows(2).any(|window| window == pair);
+        if has(["pfx", "drive_c"]) {
+            Self::Proton
+        } else if names.contains(&"com.valvesoftware.Steam".as_ref()) {
+            Self::FlatpakSteam
+        } else if has(["snap", "steam"]) {
+            Self::SnapSteam
+        } else if has(["steamapps", "common"]) {
+            Self::Steam
+        } else if has(["itch", "apps"]) {
+            Self::Itch
+        } else if names.contains(&"Epic Games".as_ref()) || names.contains(&"Heroic".as_ref()) {
+            Self::Epic
+        } else {
+            Self::Other
+        }
+    }
+}
+
+/// Returns the Steam root itself, and the libraries listed in its `libraryfolders.vdf`.
+fn steam_libraries(root: &Path) -> Vec<PathBuf> {
+    if !root.is_dir() {
+        return Vec::new();
+    }
+    let mut libraries = vec![root.to_path_buf()];
+    if let Ok(content) = fs::read_to_string(root.join("steamapps").join("libraryfolders.vdf")) {
+        libraries.extend(parse_library_paths(&content));
+    }
+    libraries
+}
+
+/// Returns `drive_c` of each Proton prefix in the Steam library.
+fn proton_prefixes(library: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(library.join("steamapps").join("compatdata")) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .map(|entry| entry.path().join("pfx").join("drive_c"))
+        .filter(|drive_c| drive_c.is_dir())
+        .collect()
+}
+
+/// Title of the game in the records of the launchers.
+const TITLE: &str = "Celeste";
+
+/// `.itch/receipt.json.gz` written by butler of the itch app into each installed game.
+#[derive(Deserialize)]
+struct ItchReceipt {
+    game: ItchGame,
+}
+
+#[derive(Deserialize)]
+struct ItchGame {
+    title: String,
+}
+
+/// Returns the games installed by the itch app in the directory, which are Celeste by receipts.
+///
+/// NOTE the archive may have a top directory, so the subdirectories are the candidates too
+fn itch_installs(apps_dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(apps_dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|dir| {
+            let receipt = dir.join(".itch").join("receipt.json.gz");
+            let receipt: Option<ItchReceipt> = File::open(&receipt).ok().and_then(|file| {
+                serde_json::from_reader(GzDecoder::new(BufReader::new(file))).ok()
+            });
+            receipt.is_some_and(|receipt| receipt.game.title.eq_ignore_ascii_case(TITLE))
+        })
+        .flat_map(|dir| {
+            let mut subdirs: Vec<PathBuf> = fs::read_dir(&dir)
+                .into_iter()
+                .flatten()
+                .flatten()
+                .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+                .map(|entry| entry.path())
+                .filter(|path| path.is_dir())
+                .collect();
+            subdirs.sort();
+            subdirs.insert(0, dir);
+            subdirs
+        })
+        .collect()
+}
+
+/// `.item` written by the Epic Games Launcher for each installed game.
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct EpicManifest {
+    display_name: String,
+    install_location: String,
+}
+
+/// Returns the locations of Celeste in the manifests of the Epic Games Launcher.
+///
+/// The locations are Windows paths, translated into `drive_c` if the launcher is in a prefix.
+fn epic_installs(manifests_dir: &Path, drive_c: Option<&Path>) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(manifests_dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "item"))
+        .filter_map(|path| {
+            let manifest: EpicManifest = serde_json::from_slice(&fs::read(path).ok()?).ok()?;
+            if !manifest.display_name.eq_ignore_ascii_case(TITLE) {
+                return None;
+            }
+            match drive_c {
+                Some(drive_c) => in_prefix(drive_c, &manifest.install_location),
+                None => Some(PathBuf::from(manifest.install_location)),
+            }
+        })
+        .collect()
+}
+
+/// Game in `installed.json` of legendary, keyed by the app name.
+#[derive(Deserialize)]
+struct LegendaryGame {
+    title: String,
+    install_path: PathBuf,
+}
+
+/// Returns the locations of Celeste installed by legendary, i.e. Heroic.
+fn legendary_installs(installed: &Path) -> Vec<PathBuf> {
+    let Ok(content) = fs::read(installed) else {
+        return Vec::new();
+    };
+    let games: BTreeMap<String, LegendaryGame> = match serde_json::from_slice(&content) {
+        Ok(games) => games,
+        Err(e) => {
+            debug!(?e, path = %anonymize(installed), "failed to read the games of legendary");
+            return Vec::new();
+        }
+    };
+    games
+        .into_values()
+        .filter(|game| game.title.eq_ignore_ascii_case(TITLE))
+        .map(|game| game.install_path)
+        .collect()
+}
+
+/// Translates the path on `C:` into `drive_c` of the prefix, e.g. `C:\Games\Celeste`.
+fn in_prefix(drive_c: &Path, windows_path: &str) -> Option<PathBuf> {
+    let (drive, rest) = windows_path.split_once(':')?;
+    if !drive.eq_ignore_ascii_case("c") {
+        return None;
+    }
+    Some(
+        rest.split(['\\', '/'])
+            .filter(|name| !name.is_empty())
+            .fold(drive_c.to_path_buf(), |path, name| path.join(name)),
+    )
+}
+
+/// Reads the values of `"path"` in `libraryfolders.vdf`.
+///
+/// The file is a tree of quoted keys and values, e.g. `"path"  "/mnt/games/SteamLibrary"`.
+fn parse_library_paths(content: &str) -> Vec<PathBuf> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut tokens = line.split('"').skip(1).step_by(2);
+            let key = tokens.next()?;
+            let value = tokens.next()?;
+            key.eq_ignore_ascii_case("path")
+                .then(|| PathBuf::from(value.replace("\\\\", "\\")))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests_discover {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    const LIBRARY_FOLDERS: &str = r#""libraryfolders"
+{
+	"0"
+	{
+		"path"		"/home/user/.local/share/Steam"
+		"label"		""
+		"apps"
+		{
+			"504230"		"1234"
+		}
+	}
+	"1"
+	{
+		"path"		"/mnt/games/Steam\\Library"
+	}
+}
+"#;
+
+    #[test]
+    fn test_parse_library_paths() {
+        assert_eq!(
+            parse_library_paths(LIBRARY_FOLDERS),
+            [
+                PathBuf::from("/home/user/.local/share/Steam"),
+                PathBuf::from("/mnt/games/Steam\\Library"),
+            ]
+        );
+    }
+
+    fn install(dir: &Path) -> anyhow::Result<()> {
+        fs::create_dir_all(dir.join("Mods"))?;
+        fs::write(dir.join("Celeste.exe"), b"")?;
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_find_installs() -> anyhow::Result<()> {
+        let home = tempdir()?;
+        let library = home.path().join("SteamLibrary");
+        let steam = home.path().join(".local/share/Steam");
+        fs::create_dir_all(steam.join("steamapps"))?;
+        fs::write(
+            steam.join("steamapps").join("libraryfolders.vdf"),
+            format!("\"1\"\n{{\n\t\"path\"\t\t\"{}\"\n}}\n", library.display()),
+        )?;
+        assert!(find_installs(home.path()).is_empty());
+
+        // a secondary library, and an installation without Everest
+        install(&library.join("steamapps/common/Celeste"))?;
+        let vanilla = home.path().join("Games/Heroic/Celeste");
+        fs::create_dir_all(&vanilla)?;
+        fs::write(vanilla.join("Celeste.exe"), b"")?;
+        assert_eq!(
+            find_installs(home.path()),
+            [library.join("steamapps/common/Celeste")]
+        );
+
+        // the same library is listed by the symlinked root
+        fs::create_dir(home.path().join(".steam"))?;
+        std::os::unix::fs::symlink(&steam, home.path().join(".steam/steam"))?;
+        install(&vanilla)?;
+        assert_eq!(
+            find_installs(home.path()),
+            [library.join("steamapps/common/Celeste"), vanilla.clone()]
+        );
+
+        // the Windows version in a Proton prefix, with `mods/` created by hand
+        let prefixed = library
+            .join("steamapps/compatdata/3141592653/pfx/drive_c/Program Files/Epic Games/Celeste");
+        fs::create_dir_all(prefixed.join("mods"))?;
+        fs::write(prefixed.join("Celeste.exe"), b"")?;
+        assert_eq!(
+            find_installs(home.path()),
+            [
+                library.join("steamapps/common/Celeste"),
+                prefixed.clone(),
+                vanilla
+            ]
+        );
+        assert_eq!(mods_dir(&prefixed), prefixed.join("mods"));
+        assert_eq!(InstallKind::of(&prefixed), InstallKind::Proton);
+        Ok(())
+    }
+
+    #[test]
+    fn test_launcher_installs() -> anyhow::Result<()> {
+        use std::io::Write;
+
+        use flate2::{Compression, write::GzEncoder};
+
+        let home = tempdir()?;
+        let apps = home.path().join("itch/apps");
+        for (dir, title) in [("celeste", "Celeste"), ("other-game", "Another Game")] {
+            fs::create_dir_all(apps.join(dir).join(".itch"))?;
+            let file = File::create(apps.join(dir).join(".itch/receipt.json.gz"))?;
+            let mut encoder = GzEncoder::new(file, Compression::default());
+            write!(
+                encoder,
+                r#"{{"game":{{"id":1,"title":"{}"}},"files":[]}}"#,
+                title
+            )?;
+            encoder.finish()?;
+        }
+        fs::create_dir(apps.join("celeste/Celeste"))?;
+        assert_eq!(
+            itch_installs(&apps),
+            [apps.join("celeste"), apps.join("celeste/Celeste")]
+        );
+
+        let drive_c = home.path().join("pfx/drive_c");
+        let manifests = drive_c.join("ProgramData").join(platform::EPIC_MANIFESTS);
+        fs::create_dir_all(&manifests)?;
+        fs::write(
+            manifests.join("0123.item"),
+            r#"{"DisplayName": "Celeste", "InstallLocation": "C:\\Program Files\\Epic Games\\Celeste", "AppName": "Salt"}"#,
+        )?;
+        fs::write(manifests.join("0123.json"), "not a manifest")?;
+        assert_eq!(
+            epic_installs(&manifests, Some(&drive_c)),
+            [drive_c.join("Program Files/Epic Games/Celeste")]
+        );
+
+        let installed = home.path().join("installed.json");
+        fs::write(
+            &installed,
+            r#"{"Salt": {"app_name": "Salt", "title": "Celeste", "install_path": "/mnt/games/Celeste"}}"#,
+        )?;
+        assert_eq!(
+            legendary_installs(&installed),
+            [PathBuf::from("/mnt/games/Celeste")]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_install_kind() {
+        let kind = |path: &str| InstallKind::of(Path::new(path));
+        assert_eq!(
+            kind(
+                "/home/user/.var/app/com.valvesoftware.Steam/.local/share/Steam/steamapps/common/Celeste"
+            ),
+            InstallKind::FlatpakSteam
+        );
+        assert_eq!(
+            kind("/home/user/snap/steam/common/.local/share/Steam/steamapps/common/Celeste"),
+            InstallKind::SnapSteam
+        );
+        assert_eq!(
+            kind("/mnt/games/SteamLibrary/steamapps/common/Celeste"),
+            InstallKind::Steam
+        );
+        assert_eq!(kind("/home/user/Games/Heroic/Celeste"), InstallKind::Epic);
+        assert_eq!(
+            kind("/home/user/.config/itch/apps/celeste"),
+            InstallKind::Itch
+        );
+        assert_eq!(kind("/opt/Celeste"), InstallKind::Other);
+    }
+}
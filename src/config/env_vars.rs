@@ -0,0 +1,167 @@
+//! Settings from the environment variables, e.g. `HULTRA_CONCURRENCY=2`.
+//!
+//! They override the profile of the config file, and are overridden by the command line options,
+//! so scripts and containers do not need to pass the options on every command.
+use std::path::PathBuf;
+
+use serde::de::DeserializeOwned;
+
+use crate::config::Profile;
+
+/// Prefix of the environment variables.
+const PREFIX: &str = "HULTRA_";
+
+/// Selects the profile like `--profile`.
+pub const PROFILE_VAR: &str = "HULTRA_PROFILE";
+
+/// Environment variables and the keys of the profile they override, shown in the help.
+pub const VARS: [(&str, &str); 21] = [
+    ("HULTRA_DIRECTORY", "directory"),
+    ("HULTRA_MODS_DIR", "mods_dir"),
+    ("HULTRA_MIRRORS", "mirror_priority"),
+    ("HULTRA_USE_API_MIRROR", "use_api_mirror"),
+    ("HULTRA_MIRROR_INDEX", "mirror_index"),
+    ("HULTRA_CONCURRENCY", "jobs"),
+    ("HULTRA_RETRIES", "retries"),
+    ("HULTRA_RETRY_DELAY", "retry_delay"),
+    ("HULTRA_RETRY_TIMEOUT", "retry_timeout"),
+    ("HULTRA_LIMIT_RATE", "limit_rate"),
+    ("HULTRA_STALL_TIMEOUT", "stall_timeout"),
+    ("HULTRA_MIN_SPEED", "min_speed"),
+    ("HULTRA_DELTA", "delta"),
+    ("HULTRA_LAZY_DEPS", "lazy_deps"),
+    ("HULTRA_PROXY", "proxy"),
+    ("HULTRA_TLS", "tls"),
+    ("HULTRA_ARCHIVE", "archive"),
+    ("HULTRA_SKIP_DISABLED", "skip_disabled"),
+    ("HULTRA_TRASH", "trash"),
+    ("HULTRA_TRASH_LIMIT", "trash_limit"),
+    ("HULTRA_LANGUAGE", "language"),
+];
+
+#[derive(thiserror::Error, Debug)]
+pub enum EnvError {
+    #[error("invalid value of {name}: {source}")]
+    Parse {
+        name: String,
+        #[source]
+        source: serde_yaml_ng::Error,
+    },
+    #[error("{name} must be in range 1-6, but got {value}")]
+    OutOfRange { name: String, value: u8 },
+}
+
+/// Reads the settings from the variables, returns a profile of the given ones.
+///
+/// Empty values are ignored like unset ones. Unknown variables are ignored too, since the other
+/// variables like `HULTRA_SERVE_TOKEN` share the prefix.
+pub fn load_profile(vars: impl IntoIterator<Item = (String, String)>) -> Result<Profile, EnvError> {
+    let mut profile = Profile::default();
+    for (name, value) in vars {
+        let Some(key) = name.strip_prefix(PREFIX) else {
+            continue;
+        };
+        if value.trim().is_empty() {
+            continue;
+        }
+        match key {
+            "DIRECTORY" => profile.directory = Some(PathBuf::from(value)),
+            "MODS_DIR" => profile.mods_dir = Some(PathBuf::from(value)),
+            "ARCHIVE" => profile.archive = Some(PathBuf::from(value)),
+            "TRASH" => profile.trash = Some(PathBuf::from(value)),
+            "LANGUAGE" => profile.language = Some(value),
+            "MIRRORS" => profile.mirror_priority = Some(parse(&name, &value)?),
+            "USE_API_MIRROR" => profile.use_api_mirror = Some(parse(&name, &value)?),
+            "MIRROR_INDEX" => profile.mirror_index = Some(parse(&name, &value)?),
+            "CONCURRENCY" => {
+                let jobs = parse(&name, &value)?;
+                if !(1..=6).contains(&jobs) {
+                    return Err(EnvError::OutOfRange { name, value: jobs });
+                }
+                profile.jobs = Some(jobs);
+            }
+            "RETRIES" => profile.retries = Some(parse(&name, &value)?),
+            "RETRY_DELAY" => profile.retry_delay = Some(parse(&name, &value)?),
+            "RETRY_TIMEOUT" => profile.retry_timeout = Some(parse(&name, &value)?),
+            "LIMIT_RATE" => profile.limit_rate = Some(parse(&name, &value)?),
+            "STALL_TIMEOUT" => profile.stall_timeout = Some(parse(&name, &value)?),
+            "MIN_SPEED" => profile.min_speed = Some(parse(&name, &value)?),
+            "DELTA" => profile.delta = Some(parse(&name, &value)?),
+            "LAZY_DEPS" => profile.lazy_deps = Some(parse(&name, &value)?),
+            "PROXY" => profile.proxy = Some(parse(&name, &value)?),
+            "TLS" => profile.tls = Some(parse(&name, &value)?),
+            "SKIP_DISABLED" => profile.skip_disabled = Some(parse(&name, &value)?),
+            "TRASH_LIMIT" => profile.trash_limit = Some(parse(&name, &value)?),
+            _ => {}
+        }
+    }
+    Ok(profile)
+}
+
+/// Parses the value like the one of the config file, e.g. `true`, `2MiB`, or `jade,gb`.
+fn parse<T: DeserializeOwned>(name: &str, value: &str) -> Result<T, EnvError> {
+    serde_yaml_ng::from_str(value).map_err(|source| EnvError::Parse {
+        name: name.to_string(),
+        source,
+    })
+}
+
+#[cfg(test)]
+mod tests_env_vars {
+    use std::path::Path;
+
+    use super::*;
+    use crate::core::{
+        mirrorlist::{Mirror, MirrorPreference},
+        network::ratelimit::Rate,
+    };
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_load_profile() -> anyhow::Result<()> {
+        let profile = load_profile(vars(&[
+            ("HULTRA_MODS_DIR", "/mnt/tas/Mods"),
+            ("HULTRA_MIRRORS", "jade,gb"),
+            ("HULTRA_CONCURRENCY", "2"),
+            ("HULTRA_MIRROR_INDEX", "true"),
+            ("HULTRA_LIMIT_RATE", "2MiB"),
+            ("HULTRA_RETRIES", ""),
+            ("HULTRA_LANGUAGE", "Japanese"),
+            ("HULTRA_SERVE_TOKEN", "secret"),
+            ("HOME", "/home/user"),
+        ]))?;
+        assert_eq!(
+            profile.mods_dir.as_deref(),
+            Some(Path::new("/mnt/tas/Mods"))
+        );
+        assert_eq!(
+            profile.mirror_priority,
+            Some(MirrorPreference::List(vec![Mirror::Jade, Mirror::Gb]))
+        );
+        assert_eq!(profile.jobs, Some(2));
+        assert_eq!(profile.mirror_index, Some(true));
+        assert_eq!(profile.limit_rate, Some("2MiB".parse::<Rate>()?));
+        assert_eq!(profile.retries, None);
+        assert_eq!(profile.language.as_deref(), Some("Japanese"));
+        assert_eq!(profile.directory, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_reject_invalid_values() {
+        assert!(matches!(
+            load_profile(vars(&[("HULTRA_CONCURRENCY", "8")])),
+            Err(EnvError::OutOfRange { value: 8, .. })
+        ));
+        assert!(matches!(
+            load_profile(vars(&[("HULTRA_USE_API_MIRROR", "maybe")])),
+            Err(EnvError::Parse { name, .. }) if name == "HULTRA_USE_API_MIRROR"
+        ));
+    }
+}
@@ -0,0 +1,478 @@
+//! User configuration file.
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::core::{
+    local::ExcludePatterns,
+    mirrorlist::{MirrorListError, MirrorPreference, MirrorTable},
+    network::{ProxyUrl, TlsBackend, ratelimit::Rate},
+    trash::SizeLimit,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum ConfigFileError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("failed to parse the config file as YAML: {0}")]
+    Parse(#[from] serde_yaml_ng::Error),
+    #[error(transparent)]
+    Mirror(#[from] MirrorListError),
+    #[error("jobs of profile '{profile}' must be in range 1-6, but got {jobs}")]
+    InvalidJobs { profile: String, jobs: u8 },
+}
+
+/// Example of the config file, shown in `hultra help topics profiles`.
+pub const EXAMPLE: &str = r#"exclude:
+  - "*.zip.old"
+  - "Backups/"
+
+mirrors:
+  home: "https://mirror.example.com/celeste/{gbid}.zip"
+
+profiles:
+  deck:
+    hostname: steamdeck
+    directory: ~/.local/share/Steam/steamapps/common/Celeste
+    mirror_priority: [jade, gb]
+    use_api_mirror: true
+    mirror_index: true
+    jobs: 2
+    retries: 4
+    limit_rate: 2MiB
+    archive: /run/media/deck/sdcard/CelesteArchive
+    trash: .hultra-trash
+    trash_limit: 2GiB
+    language: Japanese
+  tas:
+    mods_dir: ~/Games/CelesteTAS/Mods
+    mirror_priority: [gb]
+"#;
+
+/// Contents of `config.yaml`. Every field is optional. See [`EXAMPLE`].
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigFile {
+    /// Glob patterns of the entries in the Mods directory to be ignored.
+    pub exclude: ExcludePatterns,
+
+    /// Custom mirrors, mirror name to URL template with `{gbid}` placeholder.
+    pub mirrors: MirrorTable,
+
+    /// Machine specific settings, keyed by profile name.
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// Settings which override the defaults on a specific machine.
+///
+/// Command line options always take precedence over the profile.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    /// Selects this profile automatically on the machine with this hostname.
+    pub hostname: Option<String>,
+    /// Directory where the Celeste is installed. A leading `~/` is expanded to home directory.
+    pub directory: Option<PathBuf>,
+    /// Mods directory, if it is not `Mods` of `directory`, e.g. a separate set of mods for TAS.
+    pub mods_dir: Option<PathBuf>,
+    /// Comma-separated string, list of mirrors, or `auto`.
+    pub mirror_priority: Option<MirrorPreference>,
+    pub use_api_mirror: Option<bool>,
+    /// Skips mirrors which do not host the file according to their indices.
+    pub mirror_index: Option<bool>,
+    pub jobs: Option<u8>,
+    /// Retries of transient failures on each mirror.
+    pub retries: Option<u8>,
+    /// Seconds to wait before the first retry.
+    pub retry_delay: Option<u64>,
+    /// Seconds after which no more retries are started on the mirror.
+    pub retry_timeout: Option<u64>,
+    /// Total bandwidth of the downloads, e.g. `2MiB` per second.
+    pub limit_rate: Option<Rate>,
+    /// Seconds without receiving data after which the mirror is given up.
+    pub stall_timeout: Option<u64>,
+    /// Average speed below which the mirror is given up, e.g. `10K` per second.
+    pub min_speed: Option<Rate>,
+    /// Downloads only the changed files of the updated archives.
+    pub delta: Option<bool>,
+    /// Skips the dependency graph on `install`, reading the dependencies from the downloaded mods.
+    pub lazy_deps: Option<bool>,
+    /// Proxy for all of the requests, e.g. `http://proxy:8080` or `socks5h://127.0.0.1:1080`.
+    pub proxy: Option<ProxyUrl>,
+    /// TLS backend of the connections, `rustls` or `native`.
+    pub tls: Option<TlsBackend>,
+    /// Directory where `offload` moves unused mods. A leading `~/` is expanded to home directory.
+    pub archive: Option<PathBuf>,
+    /// Skips the mods disabled in Everest's blacklist on `update`.
+    pub skip_disabled: Option<bool>,
+    /// Directory where the replaced and deleted archives are moved instead of being removed.
+    ///
+    /// A relative path is relative to the Mods directory, e.g. `.hultra-trash`.
+    pub trash: Option<PathBuf>,
+    /// Maximum size of the trash, e.g. `2GiB`, the oldest archives are removed first.
+    pub trash_limit: Option<SizeLimit>,
+    /// Language of the dialog for the names of the maps, e.g. `Japanese` for `Dialog/Japanese.txt`.
+    pub language: Option<String>,
+}
+
+impl Profile {
+    /// Returns the profile with the unset fields filled with the ones of `base`.
+    pub fn or(self, base: Profile) -> Profile {
+        Profile {
+            hostname: self.hostname.or(base.hostname),
+            directory: self.directory.or(base.directory),
+            mods_dir: self.mods_dir.or(base.mods_dir),
+            mirror_priority: self.mirror_priority.or(base.mirror_priority),
+            use_api_mirror: self.use_api_mirror.or(base.use_api_mirror),
+            mirror_index: self.mirror_index.or(base.mirror_index),
+            jobs: self.jobs.or(base.jobs),
+            retries: self.retries.or(base.retries),
+            retry_delay: self.retry_delay.or(base.retry_delay),
+            retry_timeout: self.retry_timeout.or(base.retry_timeout),
+            limit_rate: self.limit_rate.or(base.limit_rate),
+            stall_timeout: self.stall_timeout.or(base.stall_timeout),
+            min_speed: self.min_speed.or(base.min_speed),
+            delta: self.delta.or(base.delta),
+            lazy_deps: self.lazy_deps.or(base.lazy_deps),
+            proxy: self.proxy.or(base.proxy),
+            tls: self.tls.or(base.tls),
+            archive: self.archive.or(base.archive),
+            skip_disabled: self.skip_disabled.or(base.skip_disabled),
+            trash: self.trash.or(base.trash),
+            trash_limit: self.trash_limit.or(base.trash_limit),
+            language: self.language.or(base.language),
+        }
+    }
+}
+
+impl ConfigFile {
+    /// Loads the config file, returns default values if it does not exist.
+    pub fn load(path: &Path) -> Result<Self, ConfigFileError> {
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e.into()),
+        };
+
+        // NOTE an empty file is parsed as `null` which is not a map
+        if bytes.iter().all(u8::is_ascii_whitespace) {
+            return Ok(Self::default());
+        }
+
+        let config: Self = serde_yaml_ng::from_slice(&bytes)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), ConfigFileError> {
+        self.mirrors.validate()?;
+
+        for (name, profile) in &self.profiles {
+            if let Some(jobs) = profile.jobs
+                && !(1..=6).contains(&jobs)
+            {
+                return Err(ConfigFileError::InvalidJobs {
+                    profile: name.clone(),
+                    jobs,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the profile of given name, or the one matches given hostname.
+    ///
+    /// A profile matches the hostname if its `hostname` or its name equals to it.
+    pub fn select_profile<'a>(
+        &'a self,
+        name: Option<&str>,
+        hostname: Option<&str>,
+    ) -> Option<(&'a str, &'a Profile)> {
+        if let Some(name) = name {
+            return self
+                .profiles
+                .get_key_value(name)
+                .map(|(k, v)| (k.as_str(), v));
+        }
+
+        let hostname = hostname?;
+        self.profiles
+            .iter()
+            .find(|(_, profile)| profile.hostname.as_deref() == Some(hostname))
+            .or_else(|| self.profiles.get_key_value(hostname))
+            .map(|(k, v)| (k.as_str(), v))
+    }
+}
+
+/// Replaces `directory` of the profiles which equals to `old` with `new`.
+///
+/// Edits lines in place to keep comments and formatting of the file.
+/// Returns `None` if no profile has the directory.
+pub fn rewrite_directory(content: &str, old: &Path, new: &Path) -> Option<String> {
+    let mut replaced = false;
+    let content = content
+        .split_inclusive('\n')
+        .map(|line| {
+            let body = line.trim_start();
+            let value = body
+                .strip_prefix("directory:")
+                .map(|value| value.trim().trim_matches(['"', '\'']));
+            match value {
+                Some(value) if Path::new(value) == old => {
+                    replaced = true;
+                    let indent = &line[..line.len() - body.len()];
+                    let newline = if line.ends_with('\n') { "\n" } else { "" };
+                    format!("{}directory: {:?}{}", indent, new, newline)
+                }
+                _ => line.to_string(),
+            }
+        })
+        .collect();
+    replaced.then_some(content)
+}
+
+/// Sets `directory` of the profile, adding the profile or `profiles` if they do not exist.
+///
+/// Edits lines in place like [`rewrite_directory`], assuming the block style of [`EXAMPLE`].
+pub fn set_directory(content: &str, profile: &str, new: &Path) -> String {
+    let mut lines: Vec<String> = content.lines().map(String::from).collect();
+    let indent_of = |line: &str| line.len() - line.trim_start().len();
+    let is_key = |line: &str| {
+        let body = line.trim_start();
+        !body.is_empty() && !body.starts_with('#')
+    };
+    let entry = |indent: usize| format!("{}directory: {:?}", " ".repeat(indent), new);
+
+    let Some(profiles) = lines.iter().position(|line| line.trim_end() == "profiles:") else {
+        if lines.last().is_some_and(|line| !line.trim().is_empty()) {
+            lines.push(String::new());
+        }
+        lines.push("profiles:".into());
+        lines.push(format!("  {}:", profile));
+        lines.push(entry(4));
+        return lines.join("\n") + "\n";
+    };
+    // NOTE the section ends at the next top level key
+    let end = lines[profiles + 1..]
+        .iter()
+        .position(|line| is_key(line) && indent_of(line) == 0)
+        .map_or(lines.len(), |i| profiles + 1 + i);
+    let header = lines[profiles + 1..end]
+        .iter()
+        .position(|line| line.trim_end().trim_start() == format!("{}:", profile))
+        .map(|i| profiles + 1 + i);
+
+    match header {
+        Some(header) => {
+            let indent = indent_of(&lines[header]);
+            let block_end = lines[header + 1..end]
+                .iter()
+                .position(|line| is_key(line) && indent_of(line) <= indent)
+                .map_or(end, |i| header + 1 + i);
+            let children = header + 1..block_end;
+            let child_indent = lines[children.clone()]
+                .iter()
+                .find(|line| is_key(line))
+                .map_or(indent + 2, |line| indent_of(line));
+            let existing = lines[children]
+                .iter()
+                .position(|line| line.trim_start().starts_with("directory:"));
+            match existing {
+                Some(i) => lines[header + 1 + i] = entry(child_indent),
+                None => lines.insert(header + 1, entry(child_indent)),
+            }
+        }
+        None => {
+            let indent = lines[profiles + 1..end]
+                .iter()
+                .find(|line| is_key(line))
+                .map_or(2, |line| indent_of(line));
+            lines.insert(profiles + 1, entry(indent * 2));
+            lines.insert(profiles + 1, format!("{}{}:", " ".repeat(indent), profile));
+        }
+    }
+    lines.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod tests_config_file {
+    use crate::core::mirrorlist::Mirror;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_exclude() {
+        let config: ConfigFile =
+            serde_yaml_ng::from_str("exclude:\n  - \"*.zip.old\"\n  - Backups/\n")
+                .expect("should be parsed");
+        assert!(config.exclude.is_excluded("Foo.zip.old", false));
+        assert!(config.exclude.is_excluded("Backups", true));
+    }
+
+    const PROFILES: &str = r#"
+profiles:
+  deck:
+    hostname: steamdeck
+    directory: ~/Games/Celeste
+    jobs: 2
+    mirror_priority: [jade, gb]
+  seedbox:
+    use_api_mirror: true
+"#;
+
+    #[test]
+    fn test_select_profile() {
+        let config: ConfigFile = serde_yaml_ng::from_str(PROFILES).expect("should be parsed");
+
+        let (name, profile) = config
+            .select_profile(None, Some("steamdeck"))
+            .expect("should match hostname");
+        assert_eq!(name, "deck");
+        assert_eq!(profile.jobs, Some(2));
+        assert_eq!(
+            profile.mirror_priority,
+            Some(MirrorPreference::List(vec![Mirror::Jade, Mirror::Gb]))
+        );
+
+        // falls back to the profile name
+        let (name, _) = config
+            .select_profile(None, Some("seedbox"))
+            .expect("should match name");
+        assert_eq!(name, "seedbox");
+
+        // explicit name wins over hostname
+        let (name, _) = config
+            .select_profile(Some("seedbox"), Some("steamdeck"))
+            .expect("should be found");
+        assert_eq!(name, "seedbox");
+
+        assert!(config.select_profile(None, Some("desktop")).is_none());
+        assert!(config.select_profile(Some("unknown"), None).is_none());
+    }
+
+    #[test]
+    fn test_profile_or() {
+        let config: ConfigFile = serde_yaml_ng::from_str(PROFILES).expect("should be parsed");
+        let over = Profile {
+            jobs: Some(4),
+            use_api_mirror: Some(false),
+            ..Default::default()
+        };
+        let merged = over.or(config.profiles["deck"].clone());
+        assert_eq!(merged.jobs, Some(4));
+        assert_eq!(merged.use_api_mirror, Some(false));
+        assert_eq!(
+            merged.directory.as_deref(),
+            Some(Path::new("~/Games/Celeste"))
+        );
+    }
+
+    #[test]
+    fn test_reject_invalid_jobs() {
+        let config: ConfigFile =
+            serde_yaml_ng::from_str("profiles:\n  deck:\n    jobs: 8\n").expect("should be parsed");
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigFileError::InvalidJobs { jobs: 8, .. })
+        ));
+    }
+
+    #[test]
+    fn test_reject_invalid_mirror_template() {
+        let config: ConfigFile =
+            serde_yaml_ng::from_str("mirrors:\n  home: https://example.com/latest.zip\n")
+                .expect("should be parsed");
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigFileError::Mirror(MirrorListError::InvalidTemplate(_)))
+        ));
+    }
+
+    #[test]
+    fn test_example_is_valid() {
+        let config: ConfigFile = serde_yaml_ng::from_str(EXAMPLE).expect("should be parsed");
+        assert!(config.validate().is_ok());
+        assert!(config.profiles.contains_key("deck"));
+        assert_eq!(
+            config.profiles["tas"].mods_dir.as_deref(),
+            Some(Path::new("~/Games/CelesteTAS/Mods"))
+        );
+    }
+
+    #[test]
+    fn test_reject_unknown_fields() {
+        let result = serde_yaml_ng::from_str::<ConfigFile>("excludes: []\n");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_rewrite_directory() {
+        let content = "profiles:\n  deck:\n    # SD card\n    directory: ~/Games/Celeste\n  desktop:\n    directory: /mnt/Celeste\n";
+        let rewritten = rewrite_directory(
+            content,
+            Path::new("~/Games/Celeste"),
+            Path::new("/run/media/deck/Celeste"),
+        )
+        .expect("should be rewritten");
+        assert_eq!(
+            rewritten,
+            "profiles:\n  deck:\n    # SD card\n    directory: \"/run/media/deck/Celeste\"\n  desktop:\n    directory: /mnt/Celeste\n"
+        );
+
+        let config: ConfigFile = serde_yaml_ng::from_str(&rewritten).expect("should be parsed");
+        assert_eq!(
+            config.profiles["deck"].directory.as_deref(),
+            Some(Path::new("/run/media/deck/Celeste"))
+        );
+
+        assert!(rewrite_directory(content, Path::new("/other"), Path::new("/new")).is_none());
+    }
+
+    #[test]
+    fn test_set_directory() {
+        let dir = Path::new("/mnt/Celeste");
+        let parse = |content: &str| -> ConfigFile {
+            serde_yaml_ng::from_str(content).expect("should be parsed")
+        };
+
+        // no config file
+        let content = set_directory("", "desktop", dir);
+        assert_eq!(
+            content,
+            "profiles:\n  desktop:\n    directory: \"/mnt/Celeste\"\n"
+        );
+
+        // replaces the existing one
+        let content =
+            "profiles:\n  deck:\n    directory: ~/Games/Celeste\n    jobs: 2\nexclude: []\n";
+        let content = set_directory(content, "deck", dir);
+        assert_eq!(
+            content,
+            "profiles:\n  deck:\n    directory: \"/mnt/Celeste\"\n    jobs: 2\nexclude: []\n"
+        );
+
+        // added to the profile, or as a new profile
+        let content = "exclude: []\nprofiles:\n    deck:\n        jobs: 2\n";
+        let updated = set_directory(content, "deck", dir);
+        assert_eq!(
+            parse(&updated).profiles["deck"].directory.as_deref(),
+            Some(dir)
+        );
+        assert_eq!(parse(&updated).profiles["deck"].jobs, Some(2));
+        let updated = set_directory(content, "desktop", dir);
+        let config = parse(&updated);
+        assert_eq!(config.profiles["desktop"].directory.as_deref(), Some(dir));
+        assert_eq!(config.profiles["deck"].jobs, Some(2));
+
+        // appended after the other settings
+        let updated = set_directory("exclude: []", "desktop", dir);
+        assert_eq!(
+            parse(&updated).profiles["desktop"].directory.as_deref(),
+            Some(dir)
+        );
+    }
+}
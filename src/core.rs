@@ -1,32 +1,42 @@
 //! All about mods. For Everest commands, see src/everest.rs and its submodules.
 //!
-//! --- Raw Data From YAML File ---
-//! * manifest.rs: `everest.yaml`, metadata file in ZIP archive
-//! * registry.rs: `everest_update.yaml`, database for check updates, also used for installing mods
-//! * dependency.rs: `dependency_graph.yaml`, database for resolving mod dependencies
+//! The registry, dependency resolution, local scanning, and download planning live in the
+//! `hultra-core` crate, re-exported here. This module adds the features of the CLI on top.
 //!
-//! --- Core Domain Logic ---
-//! * checksum.rs: xxhash64 of mod file, used for checking updates
-//! * cache.rs: cache the file checksum to avoid re-hash
+//! --- Features ---
+//! * history.rs: log of the installed, updated, offloaded, and restored mods
+//! * installed.rs: hashes of the archives as installed, to tell locally modified ones
+//! * known_deps.rs: dependencies read from the downloaded mods, for `--lazy-deps`
+//! * migrate.rs: move the Mods directory to another installation
+//! * offload.rs: move unused mods to the archive directory and back
+//! * trash.rs: keep the replaced and deleted archives to recover them
 //!
 //! --- Networking ---
+//! * mirrorlist.rs: mirrors of GameBanana files
 //! * network.rs: SharedHttpClient
 //! * network/api.rs: fetch database from API endpoint
+//! * network/availability.rs: indices of the files hosted by mirrors
+//! * network/cancel.rs: cancellation of the downloads by Ctrl-C
+//! * network/delta.rs: delta downloads reusing the unchanged entries of the old archives
 //! * network/downloader.rs: download mods
-//!
-//! --- Local File ---
-//! * local.rs: represents installed mod
-pub mod blacklist;
-pub mod cache;
-pub mod checksum;
-pub mod dependency;
-pub mod local;
-pub mod network;
-pub mod registry;
-pub mod update;
+//! * network/fetch.rs: conditional fetching with the cached copy of responses
+//! * network/filename.rs: file names of the downloaded archives
+//! * network/gamebanana.rs: search mods on GameBanana
+//! * network/http.rs: minimal HTTP server for the `serve` command
+//! * network/partial.rs: partial downloads to resume with `Range` requests
+//! * network/probe.rs: health-check and latency of mirrors
+//! * network/ratelimit.rs: bandwidth limiting shared by the downloads
+//! * network/retry.rs: retrying of transient failures with backoff
+pub use hultra_core::{
+    ChecksumVerificationError, LocalMod, blacklist, cache, dependency, download, gamebanana, local,
+    lockfile, registry, update, version,
+};
 
-pub use checksum::{Checksum, ChecksumVerificationError, Checksums, ParseChecksumError};
-pub use local::LocalMod;
-
-#[cfg(test)]
-pub use local::ModFile;
+pub mod history;
+pub mod installed;
+pub mod known_deps;
+pub mod migrate;
+pub mod mirrorlist;
+pub mod network;
+pub mod offload;
+pub mod trash;
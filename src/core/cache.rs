@@ -1,188 +0,0 @@
-use std::{
-    collections::{BTreeMap, HashSet},
-    fs::{self, File},
-    io::{self, Read, Write},
-    os::unix::fs::{MetadataExt, OpenOptionsExt},
-    path::Path,
-};
-
-use rkyv::{Archive, Deserialize, Serialize, deserialize, rancor};
-use tracing::{debug, instrument};
-use xxhash_rust::xxh64::Xxh64;
-
-use crate::{config::AppConfig, core::Checksums, log::anonymize};
-
-#[derive(thiserror::Error, Debug)]
-pub enum CacheError {
-    #[error(transparent)]
-    Io(#[from] io::Error),
-    #[error(transparent)]
-    Archive(#[from] rancor::Error),
-}
-
-/// Represents database of file cache.
-#[derive(Archive, Deserialize, Serialize, Debug, Default)]
-#[rkyv(compare(PartialEq), derive(Debug))]
-pub struct FileCacheDb {
-    entries: BTreeMap<u64, CacheEntry>,
-}
-
-impl FileCacheDb {
-    /// Checks if the given key is exist and the value contains given value.
-    pub fn is_cache_valid(&self, inode: &u64, checksums: &Checksums) -> bool {
-        self.entries
-            .get(inode)
-            .map(|entry| checksums.contains(entry.hash()))
-            .unwrap_or(false)
-    }
-
-    /// Checks if cache entry exists and is still valid.
-    ///
-    /// ### Returns
-    /// * `true`: It means no cache (new record), or contents are modified.
-    /// * `false`: It means the entry is still valid, no need to rehash them.
-    pub fn should_rehash(&self, inode: &u64, mtime: i64, size: u64) -> bool {
-        self.entries
-            .get(inode)
-            .map(|entry| !entry.is_unchanged(mtime, size))
-            .unwrap_or(true)
-    }
-}
-
-/// Snapshot of the file when it was last hashed.
-#[derive(Archive, Deserialize, Serialize, Debug)]
-#[rkyv(compare(PartialEq), derive(Debug))]
-pub struct CacheEntry {
-    file_name: String, // for DEBUG purpose
-    mtime: i64,
-    size: u64,
-    hash: u64, // XXH64
-}
-
-impl CacheEntry {
-    pub fn new(file_name: &str, mtime: i64, size: u64, hash: u64) -> Self {
-        Self {
-            file_name: file_name.to_string(),
-            mtime,
-            size,
-            hash,
-        }
-    }
-
-    pub fn file_name(&self) -> &str {
-        &self.file_name
-    }
-
-    pub fn hash(&self) -> &u64 {
-        &self.hash
-    }
-}
-
-impl CacheEntry {
-    /// Checks if the metadata is unchanged.
-    pub fn is_unchanged(&self, mtime: i64, size: u64) -> bool {
-        self.mtime == mtime && self.size == size
-    }
-}
-
-/// Gets up-to-date file cache.
-#[instrument(skip(config), fields(path = %anonymize(config.cache_db_path())))]
-pub fn sync(config: &AppConfig) -> Result<FileCacheDb, CacheError> {
-    // Load existing cache database
-    let mut cache = load_cache_db(config.cache_db_path()).unwrap_or_default();
-
-    if update_cache(&mut cache, &config.mods_dir())? {
-        save_cache_db(&cache, config.cache_db_path())?;
-    }
-
-    Ok(cache)
-}
-
-/// Updates cache entries based on current filesystem state.
-fn update_cache(cache: &mut FileCacheDb, mods_dir: &Path) -> io::Result<bool> {
-    let mut current_keys = HashSet::new();
-    let mut updated = false;
-
-    for entry in (mods_dir.read_dir()?).flatten() {
-        // Skip anything that isn't a regular file *or* isn't a `.zip`
-        if !entry.path().is_file()
-            || !entry
-                .path()
-                .extension()
-                .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
-        {
-            continue;
-        }
-
-        // Get file metadata
-        if let Ok(meta) = entry.metadata() {
-            let key = meta.ino();
-            current_keys.insert(key);
-
-            let (path, mtime, size) = (entry.path(), meta.mtime(), meta.size());
-
-            if cache.should_rehash(&key, mtime, size) {
-                let hash = hash_file(&path)?;
-
-                // NOTE Extracting only filename; mods directory is constant
-                let file_name = path
-                    .file_name()
-                    .map(|name| name.to_string_lossy())
-                    .unwrap_or_else(|| path.to_string_lossy());
-
-                // Create new cache entry
-                let cache_entry = CacheEntry::new(&file_name, mtime, size, hash);
-                debug!(?cache_entry, "new entry created");
-                cache.entries.insert(key, cache_entry);
-                updated = true;
-            }
-        }
-    }
-
-    // Remove stale cache entries (files that no longer exist)
-    let stale_count = cache.entries.len();
-    cache.entries.retain(|key, _| current_keys.contains(key));
-    updated |= cache.entries.len() != stale_count;
-
-    Ok(updated)
-}
-
-/// Loads cache database from disk using rkyv.
-fn load_cache_db(cache_path: &Path) -> Result<FileCacheDb, CacheError> {
-    let bytes = fs::read(cache_path)?;
-    let archived = rkyv::access::<ArchivedFileCacheDb, rancor::Error>(&bytes)?;
-    let cache = deserialize::<FileCacheDb, rancor::Error>(archived)?;
-    Ok(cache)
-}
-
-/// Saves cache database to disk using rkyv.
-fn save_cache_db(cache: &FileCacheDb, cache_path: &Path) -> Result<(), CacheError> {
-    let bytes = rkyv::to_bytes::<rancor::Error>(cache)?;
-    let mut file = fs::OpenOptions::new()
-        .create(true)
-        .write(true)
-        .truncate(true)
-        .mode(0o600)
-        .open(cache_path)?;
-    file.write_all(&bytes)?;
-    Ok(())
-}
-
-/// Returns digest of xxhash by calculating given file.
-fn hash_file(file_path: &Path) -> io::Result<u64> {
-    let mut reader = File::open(file_path)?;
-
-    // NOTE Use Box<[T]> to avoid stack overflow
-    let mut buffer = vec![0u8; 64 * 1024].into_boxed_slice();
-    let mut hasher = Xxh64::new(0);
-
-    loop {
-        let bytes_read = reader.read(&mut buffer)?;
-        if bytes_read == 0 {
-            break;
-        }
-        hasher.update(&buffer[..bytes_read]);
-    }
-
-    Ok(hasher.digest())
-}
@@ -0,0 +1,238 @@
+//! Append-only log of the changes to the installed mods, kept in the state directory.
+//!
+//! Each line is a JSON object, so a broken line only loses itself and the file can be read
+//! with `jq` too.
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::{
+    core::{LocalMod, cache, local},
+    log::anonymize,
+};
+
+/// What happened to the mod.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Install,
+    Update,
+    /// Moved to the archive directory.
+    Offload,
+    /// Moved back from the archive directory.
+    Restore,
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let action = match self {
+            Self::Install => "install",
+            Self::Update => "update",
+            Self::Offload => "offload",
+            Self::Restore => "restore",
+        };
+        f.pad(action)
+    }
+}
+
+/// A change to an installed mod.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Record {
+    /// UNIX time in seconds.
+    pub time: u64,
+    pub action: Action,
+    pub name: String,
+    /// Version before the change, `None` if the mod was not installed.
+    pub old_version: Option<String>,
+    /// Version after the change, `None` if the mod is no longer installed.
+    pub new_version: Option<String>,
+    /// File name of the archive.
+    pub file: String,
+    /// XXH64 of the archive like `0x0123456789abcdef`, `None` if it could not be read.
+    pub hash: Option<String>,
+}
+
+impl Record {
+    /// Creates the record of the mod, `old_version` is the installed version before `action`.
+    ///
+    /// The version after the change is the one of `m`, unless the mod was offloaded.
+    /// Installing over an installed version is recorded as an update.
+    pub fn new(action: Action, m: &LocalMod, old_version: Option<&str>) -> Self {
+        let path = m.file().path();
+        let hash = match cache::hash_file(path) {
+            Ok(digests) => Some(format!("0x{:016x}", digests.xxh64())),
+            Err(e) => {
+                debug!(?e, "failed to hash {}", anonymize(path));
+                None
+            }
+        };
+        let (action, old_version, new_version) = match (action, old_version) {
+            (Action::Offload, _) => (action, Some(m.version()), None),
+            (Action::Install, Some(_)) => (Action::Update, old_version, Some(m.version())),
+            _ => (action, old_version, Some(m.version())),
+        };
+        Self {
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            action,
+            name: m.name().to_string(),
+            old_version: old_version.map(String::from),
+            new_version: new_version.map(String::from),
+            file: path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned(),
+            hash,
+        }
+    }
+
+    /// Checks if the record is of the mod, by mod name or file name, ignoring case.
+    pub fn matches(&self, name: &str) -> bool {
+        let stem = self.file.strip_suffix(".zip").unwrap_or(&self.file);
+        [self.name.as_str(), &self.file, stem]
+            .iter()
+            .any(|candidate| candidate.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Reads the manifests of the archives to create the records.
+///
+/// `installed` maps the mod names to the versions installed before `action`. Archives whose
+/// manifest cannot be read are not recorded.
+pub fn read_records(
+    action: Action,
+    paths: &[PathBuf],
+    installed: &HashMap<String, String>,
+) -> Vec<Record> {
+    paths
+        .iter()
+        .filter_map(|path| match local::read_mod(path) {
+            Ok(m) => Some(Record::new(
+                action,
+                &m,
+                installed.get(m.name()).map(String::as_str),
+            )),
+            Err(e) => {
+                warn!(?e, "failed to read {} for the history", anonymize(path));
+                None
+            }
+        })
+        .collect()
+}
+
+/// Appends the records to the log, creating it if needed.
+pub fn append(path: &Path, records: &[Record]) -> io::Result<()> {
+    if records.is_empty() {
+        return Ok(());
+    }
+    let mut lines = Vec::new();
+    for record in records {
+        serde_json::to_writer(&mut lines, record)?;
+        lines.push(b'\n');
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // NOTE written at once, so the lines of concurrent runs are not interleaved
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(&lines)?;
+    file.sync_all()
+}
+
+/// Appends the records, only warns on failure since the change itself has been made.
+pub fn append_or_warn(path: &Path, records: &[Record]) {
+    if let Err(e) = append(path, records) {
+        warn!(?e, "failed to record the history to {}", anonymize(path));
+    }
+}
+
+/// Loads the records in the order they were appended, empty if the log does not exist.
+///
+/// Broken lines are skipped, e.g. the ones edited by hand.
+pub fn load(path: &Path) -> io::Result<Vec<Record>> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let records = text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .filter_map(|(i, line)| match serde_json::from_str(line) {
+            Ok(record) => Some(record),
+            Err(e) => {
+                debug!(?e, line = i + 1, "skipping broken line of the history");
+                None
+            }
+        })
+        .collect();
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests_history {
+    use super::*;
+
+    fn record(action: Action, name: &str, old: Option<&str>, new: Option<&str>) -> Record {
+        Record {
+            time: 1_700_000_000,
+            action,
+            name: name.to_string(),
+            old_version: old.map(String::from),
+            new_version: new.map(String::from),
+            file: format!("{}.zip", name),
+            hash: Some("0x0123456789abcdef".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_append_and_load() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("profiles").join("history.jsonl");
+        assert!(load(&path)?.is_empty());
+
+        let install = record(Action::Install, "Foo", None, Some("1.0.0"));
+        let update = record(Action::Update, "Foo", Some("1.0.0"), Some("1.1.0"));
+        append(&path, std::slice::from_ref(&install))?;
+        append(&path, &[])?;
+        // NOTE a line edited by hand is skipped
+        fs::write(
+            &path,
+            format!("{}{{\"time\":\n", fs::read_to_string(&path)?),
+        )?;
+        append(&path, std::slice::from_ref(&update))?;
+
+        assert_eq!(load(&path)?, [install, update]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialized_action() -> anyhow::Result<()> {
+        let offload = record(Action::Offload, "Foo", Some("1.0.0"), None);
+        let json = serde_json::to_value(&offload)?;
+        assert_eq!(json["action"], "offload");
+        assert_eq!(json["new_version"], serde_json::Value::Null);
+        Ok(())
+    }
+
+    #[test]
+    fn test_matches() {
+        let record = record(Action::Install, "SpeedrunTool", None, Some("3.0.0"));
+        for name in ["SpeedrunTool", "speedruntool", "SpeedrunTool.zip"] {
+            assert!(record.matches(name), "{}", name);
+        }
+        assert!(!record.matches("Speedrun"));
+    }
+}
@@ -0,0 +1,131 @@
+//! Hashes of the archives as hultra installed them, kept in the state directory.
+//!
+//! An archive whose hash matches no file of the registry is either outdated or edited by hand.
+//! Only the latter differs from the hash recorded here, so `update` can ask before overwriting it.
+use std::{collections::BTreeMap, fs, path::Path};
+
+use hultra_core::Checksum;
+use rkyv::{Archive, Deserialize, Serialize, rancor};
+use tracing::warn;
+
+use crate::{
+    core::{
+        cache::{self, CacheError},
+        history::{Action, Record},
+    },
+    log::anonymize,
+};
+
+/// XXH64 of each archive right after it was installed or updated.
+#[derive(Archive, Deserialize, Serialize, Debug, Default)]
+pub struct InstalledHashes {
+    /// File name to XXH64.
+    entries: BTreeMap<String, u64>,
+}
+
+impl InstalledHashes {
+    /// Loads the record, returns an empty one if it does not exist or is broken.
+    pub fn load(path: &Path) -> Self {
+        cache::read_archived(path).unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), CacheError> {
+        let bytes = rkyv::to_bytes::<rancor::Error>(self)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    /// Records the hashes of the installed and updated archives in the history records.
+    pub fn record(&mut self, records: &[Record]) {
+        for record in records {
+            // NOTE offloading and restoring only move the archives, they are not installed again
+            if !matches!(record.action, Action::Install | Action::Update) {
+                continue;
+            }
+            if let Some(Checksum(hash)) = record.hash.as_deref().and_then(|h| h.parse().ok()) {
+                self.entries.insert(record.file.clone(), hash);
+            }
+        }
+    }
+
+    /// Checks if the archive has changed since it was installed.
+    ///
+    /// Archives installed before the hashes were recorded are never told to be modified.
+    pub fn is_modified(&self, file: &str, hash: Option<u64>) -> bool {
+        match (self.entries.get(file), hash) {
+            (Some(installed), Some(current)) => *installed != current,
+            _ => false,
+        }
+    }
+}
+
+/// Records the hashes of the records, only warns on failure since the change itself has been made.
+pub fn record_or_warn(path: &Path, records: &[Record]) {
+    if records.is_empty() {
+        return;
+    }
+    let mut hashes = InstalledHashes::load(path);
+    hashes.record(records);
+    if let Err(e) = hashes.save(path) {
+        warn!(
+            ?e,
+            "failed to record the installed hashes to {}",
+            anonymize(path)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests_installed {
+    use super::*;
+
+    fn record(action: Action, file: &str, hash: Option<&str>) -> Record {
+        Record {
+            time: 1_700_000_000,
+            action,
+            name: file.trim_end_matches(".zip").to_string(),
+            old_version: None,
+            new_version: Some("1.0.0".to_string()),
+            file: file.to_string(),
+            hash: hash.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_is_modified() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("installed.cache");
+        record_or_warn(
+            &path,
+            &[
+                record(Action::Install, "Foo.zip", Some("0x0000000000000001")),
+                record(Action::Update, "Bar.zip", Some("0x0000000000000002")),
+                record(Action::Restore, "Baz.zip", Some("0x0000000000000003")),
+                record(Action::Install, "Qux.zip", None),
+            ],
+        );
+
+        let hashes = InstalledHashes::load(&path);
+        assert!(!hashes.is_modified("Foo.zip", Some(1)));
+        assert!(hashes.is_modified("Bar.zip", Some(1)));
+        // unknown archives and hashes are not told to be modified
+        assert!(!hashes.is_modified("Baz.zip", Some(1)));
+        assert!(!hashes.is_modified("Qux.zip", Some(1)));
+        assert!(!hashes.is_modified("Foo.zip", None));
+
+        // updating again records the new hash
+        record_or_warn(
+            &path,
+            &[record(
+                Action::Update,
+                "Bar.zip",
+                Some("0x0000000000000001"),
+            )],
+        );
+        assert!(!InstalledHashes::load(&path).is_modified("Bar.zip", Some(1)));
+        Ok(())
+    }
+}
@@ -0,0 +1,174 @@
+//! Dependencies read from the manifests of the downloaded mods, kept in the cache directory.
+//!
+//! With `--lazy-deps`, the dependency graph of a few megabytes is not fetched. The dependencies
+//! of the mods downloaded before are known from this record as long as their versions are the
+//! latest, and the others are read from the archives after they are downloaded.
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use rkyv::{Archive, Deserialize, Serialize, rancor};
+use tracing::warn;
+
+use crate::{
+    core::{
+        LocalMod,
+        cache::{self, CacheError},
+        dependency::{Dependency, DependencyGraph},
+        registry::ModRegistry,
+    },
+    log::anonymize,
+};
+
+/// File name in the cache directory of the databases.
+///
+/// NOTE bump the suffix when the fields change, like the cached databases
+const CACHE_NAME: &str = "known_dependencies.v1.cache";
+
+/// Dependencies of each mod at the version they were read.
+#[derive(Archive, Deserialize, Serialize, Debug, Default)]
+pub struct KnownDependencies {
+    entries: BTreeMap<String, Known>,
+}
+
+#[derive(Archive, Deserialize, Serialize, Debug)]
+struct Known {
+    version: String,
+    dependencies: Vec<Dependency>,
+}
+
+/// Returns the path to the record in the cache directory.
+pub fn path(cache_dir: &Path) -> PathBuf {
+    cache_dir.join(CACHE_NAME)
+}
+
+impl KnownDependencies {
+    /// Loads the record, returns an empty one if it does not exist or is broken.
+    pub fn load(path: &Path) -> Self {
+        cache::read_archived(path).unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), CacheError> {
+        let bytes = rkyv::to_bytes::<rancor::Error>(self)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    /// Records the dependencies of the mods declared by the archive.
+    pub fn record(&mut self, m: &LocalMod) {
+        for ((name, version), (_, dependencies)) in m.declared().zip(m.declared_dependencies()) {
+            let known = Known {
+                version: version.to_string(),
+                dependencies: dependencies.to_vec(),
+            };
+            self.entries.insert(name.to_string(), known);
+        }
+    }
+
+    /// Builds the graph of the mods recorded at their latest versions in the registry.
+    ///
+    /// The mods updated since then are left out, so they are read from the archives again.
+    pub fn graph(&self, registry: &ModRegistry) -> DependencyGraph {
+        let mut graph = DependencyGraph::default();
+        for (name, known) in &self.entries {
+            if registry
+                .get(name)
+                .is_none_or(|entry| entry.version() == known.version)
+            {
+                graph.insert_dependencies(name, known.dependencies.clone());
+            }
+        }
+        graph
+    }
+}
+
+/// Records the dependencies of the mods, only warns on failure since it is only a cache.
+pub fn record_or_warn(path: &Path, mods: &[LocalMod]) {
+    if mods.is_empty() {
+        return;
+    }
+    let mut known = KnownDependencies::load(path);
+    for m in mods {
+        known.record(m);
+    }
+    if let Err(e) = known.save(path) {
+        warn!(
+            ?e,
+            "failed to record the dependencies to {}",
+            anonymize(path)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests_known_deps {
+    use std::{collections::HashMap, io::Write};
+
+    use zip::{ZipWriter, write::SimpleFileOptions};
+
+    use super::*;
+    use crate::core::local;
+
+    fn write_mod(path: &Path, manifest: &str) -> anyhow::Result<LocalMod> {
+        let mut writer = ZipWriter::new(fs::File::create(path)?);
+        writer.start_file("everest.yaml", SimpleFileOptions::default())?;
+        writer.write_all(manifest.as_bytes())?;
+        writer.finish()?;
+        Ok(local::read_mod(path)?)
+    }
+
+    #[test]
+    fn test_save_and_load() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = path(dir.path());
+        assert!(KnownDependencies::load(&path).entries.is_empty());
+
+        let foo = write_mod(
+            &dir.path().join("Foo.zip"),
+            "- Name: Foo\n  Version: 1.0.0\n  Dependencies:\n    - Name: Bar\n      Version: 1.0.0\n",
+        )?;
+        let mut known = KnownDependencies::default();
+        known.record(&foo);
+        known.save(&path)?;
+
+        let loaded = KnownDependencies::load(&path);
+        assert_eq!(loaded.entries.len(), 1);
+        assert_eq!(loaded.entries["Foo"].version, "1.0.0");
+        assert_eq!(loaded.entries["Foo"].dependencies[0].name(), "Bar");
+        Ok(())
+    }
+
+    #[test]
+    fn test_graph_of_latest() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let foo = write_mod(
+            &dir.path().join("Foo.zip"),
+            "- Name: Foo\n  Version: 1.0.0\n  Dependencies:\n    - Name: Bar\n      Version: 1.0.0\n",
+        )?;
+        let path = path(dir.path());
+        record_or_warn(&path, &[foo]);
+
+        let registry: ModRegistry = serde_yaml_ng::from_str(
+            "Foo:\n  GameBananaId: 1\n  Version: 1.0.0\n  URL: https://gamebanana.com/mmdl/2\n  Size: 3\n  xxHash: [f437bf0515368130]\n",
+        )?;
+        let graph = KnownDependencies::load(&path).graph(&registry);
+        let resolution = graph.check_names(["Foo".to_string()].into(), &HashMap::new());
+        assert!(resolution.missing.contains("Bar"));
+        assert!(resolution.unresolved.contains("Bar"));
+        assert!(!resolution.unresolved.contains("Foo"));
+
+        // an update may have other dependencies, so it is read again
+        let registry: ModRegistry = serde_yaml_ng::from_str(
+            "Foo:\n  GameBananaId: 1\n  Version: 1.1.0\n  URL: https://gamebanana.com/mmdl/3\n  Size: 3\n  xxHash: [f437bf0515368130]\n",
+        )?;
+        let graph = KnownDependencies::load(&path).graph(&registry);
+        let resolution = graph.check_names(["Foo".to_string()].into(), &HashMap::new());
+        assert!(resolution.unresolved.contains("Foo"));
+        Ok(())
+    }
+}
@@ -0,0 +1,274 @@
+//! Migration of the Mods directory to another Celeste installation.
+//!
+//! Archives are copied through a temporary file and verified by hashing before and after the
+//! transfer, so a broken copy never replaces anything at the destination.
+use std::{
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+};
+
+use tempfile::NamedTempFile;
+
+use crate::core::{
+    blacklist::{EVEREST_BLACKLIST_FILE, UPDATER_BLACKLIST_FILE},
+    cache::{self, CacheEntry, FileDigests, FileSnapshot},
+    local::{ExcludePatterns, is_mod_archive},
+};
+
+/// Text files kept by Everest in the Mods directory.
+pub const LIST_FILES: [&str; 3] = [
+    EVEREST_BLACKLIST_FILE,
+    UPDATER_BLACKLIST_FILE,
+    "modoptionsorder.txt",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferMode {
+    Copy,
+    /// Removes the original after the copy is verified.
+    Move,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum MigrateError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("a different file already exists at the destination")]
+    Conflict,
+    #[error("content of the copy does not match the original")]
+    Mismatch,
+}
+
+/// Archive transferred to the destination.
+#[derive(Debug)]
+pub struct Transferred {
+    path: PathBuf,
+    snapshot: FileSnapshot,
+    digests: FileDigests,
+}
+
+impl Transferred {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Converts into the entry of the checksum cache.
+    pub fn into_cache_entry(self) -> (String, CacheEntry) {
+        let entry = CacheEntry::new(self.snapshot, self.digests);
+        (cache::cache_key(&self.path), entry)
+    }
+}
+
+/// Lists the archives in the Mods directory which are not excluded.
+pub fn collect_archives(mods_dir: &Path, exclude: &ExcludePatterns) -> io::Result<Vec<PathBuf>> {
+    let mut archives: Vec<PathBuf> = fs::read_dir(mods_dir)?
+        .flatten()
+        .filter(|entry| is_mod_archive(entry, exclude))
+        .map(|entry| entry.path())
+        .collect();
+    archives.sort();
+    Ok(archives)
+}
+
+/// Transfers the archive into the directory, verifying the content by hash.
+///
+/// An identical file at the destination is accepted as already transferred.
+pub fn transfer_archive(
+    src: &Path,
+    dest_dir: &Path,
+    mode: TransferMode,
+) -> Result<Transferred, MigrateError> {
+    let file_name = src
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?;
+    let dest = dest_dir.join(file_name);
+    let expected = cache::hash_file(src)?;
+
+    if dest.exists() {
+        if cache::hash_file(&dest)? != expected {
+            return Err(MigrateError::Conflict);
+        }
+    } else {
+        let src_meta = fs::metadata(src)?;
+        let mut temp = NamedTempFile::new_in(dest_dir)?;
+        io::copy(&mut File::open(src)?, temp.as_file_mut())?;
+        temp.as_file().sync_all()?;
+        // NOTE keep the metadata, the game and users may sort mods by date
+        temp.as_file().set_modified(src_meta.modified()?)?;
+        fs::set_permissions(temp.path(), src_meta.permissions())?;
+
+        if cache::hash_file(temp.path())? != expected {
+            return Err(MigrateError::Mismatch);
+        }
+        temp.persist(&dest).map_err(|e| e.error)?;
+    }
+
+    if mode == TransferMode::Move {
+        fs::remove_file(src)?;
+    }
+
+    let meta = fs::metadata(&dest)?;
+    Ok(Transferred {
+        snapshot: FileSnapshot::new(&meta)?,
+        path: dest,
+        digests: expected,
+    })
+}
+
+/// Transfers the list file kept by Everest, rewriting absolute paths to the old directory.
+///
+/// A different file at the destination is kept with `.bak` extension.
+/// Returns `false` if the file does not exist in the source directory.
+pub fn transfer_list_file(
+    name: &str,
+    src_dir: &Path,
+    dest_dir: &Path,
+    mode: TransferMode,
+) -> Result<bool, MigrateError> {
+    let src = src_dir.join(name);
+    let content = match fs::read_to_string(&src) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e.into()),
+    };
+    let content = rewrite_paths(&content, src_dir, dest_dir);
+
+    let dest = dest_dir.join(name);
+    match fs::read_to_string(&dest) {
+        Ok(existing) if existing == content => {}
+        Ok(_) => {
+            fs::rename(&dest, dest.with_extension("txt.bak"))?;
+            fs::write(&dest, &content)?;
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => fs::write(&dest, &content)?,
+        Err(e) => return Err(e.into()),
+    }
+
+    if mode == TransferMode::Move {
+        fs::remove_file(&src)?;
+    }
+    Ok(true)
+}
+
+/// Replaces absolute paths under the old directory with the new one.
+///
+/// Everest writes file names only, but users may write absolute paths by hand.
+fn rewrite_paths(content: &str, old: &Path, new: &Path) -> String {
+    let (Some(old), Some(new)) = (old.to_str(), new.to_str()) else {
+        return content.to_string();
+    };
+    content
+        .split_inclusive('\n')
+        .map(|line| match line.trim_start().strip_prefix(old) {
+            Some(rest) if rest.starts_with('/') => {
+                let indent = &line[..line.len() - line.trim_start().len()];
+                format!("{}{}{}", indent, new, rest)
+            }
+            _ => line.to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests_migrate {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_copy_archive() -> anyhow::Result<()> {
+        let src = tempdir()?;
+        let dest = tempdir()?;
+        let path = src.path().join("Foo.zip");
+        fs::write(&path, b"foo")?;
+
+        let transferred = transfer_archive(&path, dest.path(), TransferMode::Copy)?;
+        assert_eq!(transferred.path(), dest.path().join("Foo.zip"));
+        assert_eq!(fs::read(transferred.path())?, b"foo");
+        assert!(path.exists());
+
+        let (key, entry) = transferred.into_cache_entry();
+        assert_eq!(key, "Foo.zip");
+        let meta = fs::metadata(dest.path().join("Foo.zip"))?;
+        assert!(entry.is_unchanged(&FileSnapshot::new(&meta)?));
+        Ok(())
+    }
+
+    #[test]
+    fn test_move_archive() -> anyhow::Result<()> {
+        let src = tempdir()?;
+        let dest = tempdir()?;
+        let path = src.path().join("Foo.zip");
+        fs::write(&path, b"foo")?;
+
+        transfer_archive(&path, dest.path(), TransferMode::Move)?;
+        assert!(!path.exists());
+        assert_eq!(fs::read(dest.path().join("Foo.zip"))?, b"foo");
+        Ok(())
+    }
+
+    #[test]
+    fn test_existing_archive() -> anyhow::Result<()> {
+        let src = tempdir()?;
+        let dest = tempdir()?;
+        let path = src.path().join("Foo.zip");
+        fs::write(&path, b"foo")?;
+
+        // identical file is accepted
+        fs::write(dest.path().join("Foo.zip"), b"foo")?;
+        assert!(transfer_archive(&path, dest.path(), TransferMode::Copy).is_ok());
+
+        // different file is never overwritten
+        fs::write(dest.path().join("Foo.zip"), b"bar")?;
+        assert!(matches!(
+            transfer_archive(&path, dest.path(), TransferMode::Move),
+            Err(MigrateError::Conflict)
+        ));
+        assert!(path.exists());
+        assert_eq!(fs::read(dest.path().join("Foo.zip"))?, b"bar");
+        Ok(())
+    }
+
+    #[test]
+    fn test_transfer_list_file() -> anyhow::Result<()> {
+        let src = tempdir()?;
+        let dest = tempdir()?;
+        let content = format!("Foo.zip\n{}/Bar.zip\n", src.path().display());
+        fs::write(src.path().join("blacklist.txt"), content)?;
+        fs::write(dest.path().join("blacklist.txt"), "# default\n")?;
+
+        assert!(transfer_list_file(
+            "blacklist.txt",
+            src.path(),
+            dest.path(),
+            TransferMode::Copy
+        )?);
+        assert_eq!(
+            fs::read_to_string(dest.path().join("blacklist.txt"))?,
+            format!("Foo.zip\n{}/Bar.zip\n", dest.path().display())
+        );
+        assert_eq!(
+            fs::read_to_string(dest.path().join("blacklist.txt.bak"))?,
+            "# default\n"
+        );
+
+        assert!(!transfer_list_file(
+            "updaterblacklist.txt",
+            src.path(),
+            dest.path(),
+            TransferMode::Copy
+        )?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rewrite_paths() {
+        let old = Path::new("/old/Mods");
+        let new = Path::new("/new/Mods");
+        assert_eq!(
+            rewrite_paths("Foo.zip\n  /old/Mods/Bar.zip\n/old/ModsX/Baz.zip", old, new),
+            "Foo.zip\n  /new/Mods/Bar.zip\n/old/ModsX/Baz.zip"
+        );
+    }
+}
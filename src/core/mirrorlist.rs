@@ -0,0 +1,639 @@
+//! Mirrors of the GameBanana files.
+//!
+//! Built-in mirrors are hard-coded. Users can add their own mirrors to the config file
+//! as a table of mirror name to URL template which contains `{gbid}` placeholder.
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt,
+    str::FromStr,
+};
+
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::core::download::DownloadUrl;
+
+/// Placeholder of the GameBanana file ID in the URL template.
+const GBID_PLACEHOLDER: &str = "{gbid}";
+
+/// Host of the original screenshots.
+const GAMEBANANA_IMAGES: &str = "https://images.gamebanana.com/";
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum MirrorListError {
+    #[error(
+        "invalid mirror name '{0}': expected one of gb, jade, wegfan, otobot, or a custom mirror name of ASCII letters, digits, '-' and '_'"
+    )]
+    InvalidName(String),
+    #[error("unknown mirror '{name}': available mirrors are {available}")]
+    UnknownMirror { name: String, available: String },
+    #[error("custom mirror '{0}' conflicts with the built-in mirror")]
+    ReservedName(String),
+    #[error("'auto' cannot be combined with other mirrors")]
+    AutoInList,
+    #[error("URL template of mirror '{0}' must start with 'https://' and contain '{{gbid}}'")]
+    InvalidTemplate(String),
+}
+
+/// Supported mirrors.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Mirror {
+    /// Default GameBanana Server (United States).
+    Gb,
+    /// Germany.
+    Jade,
+    /// China.
+    Wegfan,
+    /// North America.
+    Otobot,
+    /// Defined in the config file.
+    Custom(String),
+}
+
+impl FromStr for Mirror {
+    type Err = MirrorListError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mirror = match s {
+            "gb" => Self::Gb,
+            "jade" => Self::Jade,
+            "wegfan" => Self::Wegfan,
+            "otobot" => Self::Otobot,
+            "auto" => return Err(MirrorListError::AutoInList),
+            name if is_valid_name(name) => Self::Custom(name.to_string()),
+            name => return Err(MirrorListError::InvalidName(name.to_string())),
+        };
+        Ok(mirror)
+    }
+}
+
+impl fmt::Display for Mirror {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Gb => "gb",
+            Self::Jade => "jade",
+            Self::Wegfan => "wegfan",
+            Self::Otobot => "otobot",
+            Self::Custom(name) => name,
+        };
+        f.pad(name)
+    }
+}
+
+impl TryFrom<String> for Mirror {
+    type Error = MirrorListError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl Mirror {
+    /// All of the built-in mirrors.
+    pub const BUILTIN: [Mirror; 4] = [Self::Gb, Self::Jade, Self::Wegfan, Self::Otobot];
+
+    /// Generates the full mirror URL for a given GameBanana ID.
+    pub fn url_for_id(&self, gbid: u32, table: &MirrorTable) -> Result<String, MirrorListError> {
+        let template = table.template(self)?;
+        Ok(render(template, gbid))
+    }
+
+    /// Returns URL template of the built-in mirror.
+    pub fn builtin_template(&self) -> Option<&'static str> {
+        let template = match self {
+            Self::Gb => "https://gamebanana.com/mmdl/{gbid}",
+            Self::Jade => "https://celestemodupdater.0x0a.de/banana-mirror/{gbid}.zip",
+            Self::Wegfan => "https://celeste.weg.fan/api/v2/download/gamebanana-files/{gbid}",
+            Self::Otobot => "https://banana-mirror-mods.celestemods.com/{gbid}.zip",
+            Self::Custom(_) => return None,
+        };
+        Some(template)
+    }
+
+    /// Builds URLs of the file and the screenshots on this mirror.
+    ///
+    /// Screenshots are the original GameBanana image URLs, which are kept as is if this mirror
+    /// does not host images.
+    pub fn asset_urls(
+        &self,
+        gbid: u32,
+        screenshots: &[String],
+        table: &MirrorTable,
+    ) -> Result<AssetUrls, MirrorListError> {
+        Ok(AssetUrls {
+            file: self.url_for_id(gbid, table)?,
+            screenshots: screenshots
+                .iter()
+                .map(|original| {
+                    self.screenshot_url(original)
+                        .unwrap_or_else(|| original.clone())
+                })
+                .collect(),
+        })
+    }
+
+    /// Returns URL of the screenshot on this mirror, if the mirror hosts it.
+    ///
+    /// Mirrors convert the images to PNG and flatten the path,
+    /// e.g. `img/ss/mods/5f0b.jpg` is stored as `img_ss_mods_5f0b.png`.
+    fn screenshot_url(&self, original: &str) -> Option<String> {
+        let base = match self {
+            Self::Jade => "https://celestemodupdater.0x0a.de/banana-mirror-images/",
+            Self::Otobot => "https://banana-mirror-images.celestemods.com/",
+            _ => return None,
+        };
+        let path = original.strip_prefix(GAMEBANANA_IMAGES)?;
+        let stem = path.rsplit_once('.').map_or(path, |(stem, _)| stem);
+        Some(format!("{}{}.png", base, stem.replace('/', "_")))
+    }
+
+    /// Returns URL of the list of the hosted files, if the mirror publishes it.
+    ///
+    /// NOTE these mirrors only carry files the mirror bot has synced, so brand-new files are missing.
+    pub fn index_url(&self) -> Option<&'static str> {
+        match self {
+            Self::Jade => Some("https://celestemodupdater.0x0a.de/banana-mirror/list.json"),
+            Self::Otobot => Some("https://banana-mirror-mods.celestemods.com/list.json"),
+            _ => None,
+        }
+    }
+}
+
+/// URLs of the assets of a GameBanana file on a mirror.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssetUrls {
+    /// Archive of the mod.
+    pub file: String,
+    /// Screenshots of the mod, the first one is used as the icon.
+    pub screenshots: Vec<String>,
+}
+
+impl AssetUrls {
+    pub fn icon(&self) -> Option<&str> {
+        self.screenshots.first().map(String::as_str)
+    }
+}
+
+/// Replaces the placeholder in the template with the ID.
+fn render(template: &str, gbid: u32) -> String {
+    template.replace(GBID_PLACEHOLDER, &gbid.to_string())
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+/// Custom mirrors defined by the user, mirror name to URL template.
+///
+/// ### Example
+///
+/// ```yaml
+/// mirrors:
+///   home: "https://mirror.example.com/celeste/{gbid}.zip"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct MirrorTable(BTreeMap<String, String>);
+
+impl MirrorTable {
+    /// Checks names and URL templates of the custom mirrors.
+    pub fn validate(&self) -> Result<(), MirrorListError> {
+        for (name, template) in &self.0 {
+            match name.parse::<Mirror>()? {
+                Mirror::Custom(_) => {}
+                _ => return Err(MirrorListError::ReservedName(name.clone())),
+            }
+            // NOTE the HTTP client only allows HTTPS
+            if !template.starts_with("https://") || !template.contains(GBID_PLACEHOLDER) {
+                return Err(MirrorListError::InvalidTemplate(name.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns URL template of the mirror.
+    fn template<'a>(&'a self, mirror: &Mirror) -> Result<&'a str, MirrorListError> {
+        match mirror {
+            Mirror::Custom(name) => {
+                self.0
+                    .get(name)
+                    .map(String::as_str)
+                    .ok_or_else(|| MirrorListError::UnknownMirror {
+                        name: name.clone(),
+                        available: self.available_names(),
+                    })
+            }
+            builtin => Ok(builtin
+                .builtin_template()
+                .expect("built-in mirror should have template")),
+        }
+    }
+}
+
+impl MirrorTable {
+    /// Returns all of the built-in and custom mirrors.
+    pub fn all_mirrors(&self) -> Vec<Mirror> {
+        Mirror::BUILTIN
+            .into_iter()
+            .chain(self.0.keys().cloned().map(Mirror::Custom))
+            .collect()
+    }
+
+    /// Returns comma-separated names of the built-in and custom mirrors.
+    fn available_names(&self) -> String {
+        Mirror::BUILTIN
+            .iter()
+            .map(Mirror::to_string)
+            .chain(self.0.keys().cloned())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl From<BTreeMap<String, String>> for MirrorTable {
+    fn from(value: BTreeMap<String, String>) -> Self {
+        Self(value)
+    }
+}
+
+/// Mirror preference given by the user.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "RawPreference")]
+pub enum MirrorPreference {
+    /// Orders all mirrors by measured latency.
+    Auto,
+    /// Static priority list.
+    List(Vec<Mirror>),
+}
+
+/// Accepts both `"jade,gb"` and `[jade, gb]` in the config file.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawPreference {
+    Text(String),
+    List(Vec<Mirror>),
+}
+
+impl TryFrom<RawPreference> for MirrorPreference {
+    type Error = MirrorListError;
+
+    fn try_from(value: RawPreference) -> Result<Self, Self::Error> {
+        match value {
+            RawPreference::Text(text) => text.parse(),
+            RawPreference::List(list) => Ok(Self::List(list)),
+        }
+    }
+}
+
+impl FromStr for MirrorPreference {
+    type Err = MirrorListError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim() == "auto" {
+            return Ok(Self::Auto);
+        }
+        let list = s
+            .split(',')
+            .map(|name| name.trim().parse())
+            .collect::<Result<_, _>>()?;
+        Ok(Self::List(list))
+    }
+}
+
+/// Files hosted by the mirrors, read from their indices.
+///
+/// Mirrors without an index are assumed to host every file.
+#[derive(Debug, Clone, Default)]
+pub struct Availability(HashMap<Mirror, HashSet<u32>>);
+
+impl Availability {
+    pub fn insert(&mut self, mirror: Mirror, gbids: HashSet<u32>) {
+        self.0.insert(mirror, gbids);
+    }
+
+    /// Checks if the mirror may host the file.
+    pub fn may_host(&self, mirror: &Mirror, gbid: u32) -> bool {
+        self.0.get(mirror).is_none_or(|gbids| gbids.contains(&gbid))
+    }
+}
+
+/// Parses the index of the mirror, a JSON array of file names like `"1298450.zip"`.
+///
+/// Entries which are not named after the file ID are ignored.
+pub fn parse_index(bytes: &[u8]) -> Result<HashSet<u32>, serde_json::Error> {
+    let entries: Vec<serde_json::Value> = serde_json::from_slice(bytes)?;
+    let gbids = entries
+        .iter()
+        .filter_map(|entry| match entry {
+            serde_json::Value::String(name) => {
+                name.strip_suffix(".zip").unwrap_or(name).parse().ok()
+            }
+            serde_json::Value::Number(id) => id.as_u64().and_then(|id| id.try_into().ok()),
+            _ => None,
+        })
+        .collect();
+    Ok(gbids)
+}
+
+/// Represents mirror priority.
+#[derive(Debug, Clone)]
+pub struct Mirrors {
+    priority: Vec<Mirror>,
+    table: MirrorTable,
+    availability: Availability,
+}
+
+impl Mirrors {
+    /// Validates the mirror priority with the custom mirrors.
+    ///
+    /// Duplicated mirrors are removed while retaining order.
+    pub fn new(priority: &[Mirror], table: &MirrorTable) -> Result<Self, MirrorListError> {
+        let mut seen = HashSet::new();
+        let mut mirrors = Vec::with_capacity(priority.len());
+        for mirror in priority.iter().filter(|mirror| seen.insert(*mirror)) {
+            table.template(mirror)?;
+            mirrors.push(mirror.clone());
+        }
+        Ok(Self {
+            priority: mirrors,
+            table: table.clone(),
+            availability: Availability::default(),
+        })
+    }
+
+    /// Skips the mirrors known not to host the file in `resolve`.
+    pub fn with_availability(mut self, availability: Availability) -> Self {
+        self.availability = availability;
+        self
+    }
+
+    /// Resolves Mirrors into actual list of mirror URLs, paired with the mirror.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// let mirrors = Mirrors::new(&[Mirror::Gb, Mirror::Jade], &MirrorTable::default())?;
+    /// let urls = mirrors.resolve(&"https://gamebanana.com/mmdl/123456".parse()?);
+    /// for (mirror, url) in urls {
+    ///     println!("{}: {}", mirror, url)
+    /// }
+    /// ```
+    ///
+    /// Mirrors known not to host the file are skipped, unless no mirror is left.
+    pub fn resolve(&self, url: &DownloadUrl) -> Vec<(&Mirror, String)> {
+        let gbid = url.gbid();
+        let (available, missing): (Vec<&Mirror>, Vec<&Mirror>) = self
+            .priority
+            .iter()
+            .partition(|mirror| self.availability.may_host(mirror, gbid));
+
+        let mirrors = if available.is_empty() {
+            // NOTE the index may be outdated, trying them is better than nothing
+            missing
+        } else {
+            if !missing.is_empty() {
+                debug!(
+                    gbid,
+                    ?missing,
+                    "skipping mirrors which do not host the file"
+                );
+            }
+            available
+        };
+
+        // NOTE every mirror is validated in `new`, so this never drops one
+        mirrors
+            .into_iter()
+            .filter_map(|mirror| {
+                let url = mirror.url_for_id(gbid, &self.table).ok()?;
+                Some((mirror, url))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests_mirrorlist {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn custom_table() -> MirrorTable {
+        MirrorTable::from(BTreeMap::from([(
+            "home".to_string(),
+            "https://mirror.example.com/{gbid}.zip".to_string(),
+        )]))
+    }
+
+    #[test]
+    fn test_resolve() {
+        let url = DownloadUrl::from_str("https://gamebanana.com/mmdl/1298450")
+            .expect("should be parsed as this type");
+        let mirrors = Mirrors::new(
+            &[Mirror::Otobot, Mirror::Gb, Mirror::Jade],
+            &MirrorTable::default(),
+        )
+        .expect("built-in mirrors should be resolved");
+        let result = mirrors.resolve(&url);
+        assert_eq!(result.len(), 3, "should return three URLs");
+        assert_eq!(
+            result.first().unwrap().1,
+            "https://banana-mirror-mods.celestemods.com/1298450.zip"
+        )
+    }
+
+    #[test]
+    fn test_resolve_duplicate_entries() {
+        let url = DownloadUrl::from_str("https://gamebanana.com/mmdl/1298450")
+            .expect("should be parsed as this type");
+        let mirrors = Mirrors::new(
+            &[Mirror::Otobot, Mirror::Otobot, Mirror::Jade],
+            &MirrorTable::default(),
+        )
+        .expect("built-in mirrors should be resolved");
+        let result = mirrors.resolve(&url);
+        assert_eq!(result.len(), 2, "should return only two URLs");
+        assert_eq!(
+            result.first().unwrap().1,
+            "https://banana-mirror-mods.celestemods.com/1298450.zip"
+        )
+    }
+
+    #[test]
+    fn test_resolve_with_availability() {
+        let url = DownloadUrl::from_str("https://gamebanana.com/mmdl/42")
+            .expect("should be parsed as this type");
+        let mut availability = Availability::default();
+        availability.insert(Mirror::Otobot, HashSet::from([1, 2]));
+        availability.insert(Mirror::Jade, HashSet::from([42]));
+
+        let mirrors = Mirrors::new(
+            &[Mirror::Otobot, Mirror::Jade, Mirror::Gb],
+            &MirrorTable::default(),
+        )
+        .expect("built-in mirrors should be resolved")
+        .with_availability(availability.clone());
+        let result: Vec<&Mirror> = mirrors.resolve(&url).into_iter().map(|(m, _)| m).collect();
+        assert_eq!(result, vec![&Mirror::Jade, &Mirror::Gb]);
+
+        // falls back to all mirrors if none of them hosts the file
+        let mirrors = Mirrors::new(&[Mirror::Otobot], &MirrorTable::default())
+            .expect("built-in mirrors should be resolved")
+            .with_availability(availability);
+        assert_eq!(mirrors.resolve(&url).len(), 1);
+    }
+
+    #[test]
+    fn test_parse_index() {
+        let gbids =
+            parse_index(br#"["1298450.zip", "42.zip", 7, "list.json"]"#).expect("should be parsed");
+        assert_eq!(gbids, HashSet::from([1298450, 42, 7]));
+        assert!(parse_index(b"{}").is_err());
+    }
+
+    #[test]
+    fn test_resolve_custom_mirror() {
+        let priority: Vec<Mirror> = ["home", "gb"]
+            .iter()
+            .map(|s| s.parse().expect("should be parsed"))
+            .collect();
+        let mirrors = Mirrors::new(&priority, &custom_table()).expect("should be resolved");
+        let url = DownloadUrl::from_str("https://gamebanana.com/mmdl/42")
+            .expect("should be parsed as this type");
+        let urls: Vec<String> = mirrors.resolve(&url).into_iter().map(|(_, u)| u).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://mirror.example.com/42.zip".to_string(),
+                "https://gamebanana.com/mmdl/42".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_custom_mirror() {
+        let result = Mirrors::new(&[Mirror::Custom("nope".into())], &custom_table());
+        assert_eq!(
+            result.unwrap_err(),
+            MirrorListError::UnknownMirror {
+                name: "nope".into(),
+                available: "gb, jade, wegfan, otobot, home".into()
+            }
+        );
+    }
+
+    #[test]
+    fn test_validate_table() {
+        assert!(custom_table().validate().is_ok());
+
+        let reserved = MirrorTable::from(BTreeMap::from([(
+            "gb".to_string(),
+            "https://example.com/{gbid}".to_string(),
+        )]));
+        assert_eq!(
+            reserved.validate(),
+            Err(MirrorListError::ReservedName("gb".into()))
+        );
+
+        let no_placeholder = MirrorTable::from(BTreeMap::from([(
+            "home".to_string(),
+            "https://example.com/file.zip".to_string(),
+        )]));
+        assert_eq!(
+            no_placeholder.validate(),
+            Err(MirrorListError::InvalidTemplate("home".into()))
+        );
+    }
+
+    #[test]
+    fn test_url_for_id() {
+        assert_eq!(
+            Mirror::Jade.url_for_id(42, &MirrorTable::default()),
+            Ok("https://celestemodupdater.0x0a.de/banana-mirror/42.zip".to_string())
+        );
+        assert_eq!(
+            Mirror::Custom("home".into()).url_for_id(42, &custom_table()),
+            Ok("https://mirror.example.com/42.zip".to_string())
+        );
+    }
+
+    #[test]
+    fn test_asset_urls() {
+        let screenshots = vec![
+            "https://images.gamebanana.com/img/ss/mods/5f0b1c2d.jpg".to_string(),
+            "https://example.com/other.png".to_string(),
+        ];
+
+        let assets = Mirror::Jade
+            .asset_urls(42, &screenshots, &MirrorTable::default())
+            .expect("built-in mirror should be resolved");
+        assert_eq!(
+            assets.file,
+            "https://celestemodupdater.0x0a.de/banana-mirror/42.zip"
+        );
+        assert_eq!(
+            assets.icon(),
+            Some("https://celestemodupdater.0x0a.de/banana-mirror-images/img_ss_mods_5f0b1c2d.png")
+        );
+        // not hosted on GameBanana, kept as is
+        assert_eq!(assets.screenshots[1], "https://example.com/other.png");
+
+        // the mirror without images links to the originals
+        let assets = Mirror::Custom("home".into())
+            .asset_urls(42, &screenshots, &custom_table())
+            .expect("custom mirror should be resolved");
+        assert_eq!(assets.file, "https://mirror.example.com/42.zip");
+        assert_eq!(assets.screenshots, screenshots);
+    }
+
+    #[test]
+    fn test_display_roundtrip() {
+        for mirror in Mirror::BUILTIN
+            .into_iter()
+            .chain([Mirror::Custom("home".into())])
+        {
+            assert_eq!(mirror.to_string().parse(), Ok(mirror));
+        }
+    }
+
+    #[test]
+    fn test_parse_preference() {
+        assert_eq!(
+            MirrorPreference::from_str("auto"),
+            Ok(MirrorPreference::Auto)
+        );
+        assert_eq!(
+            MirrorPreference::from_str("jade,home"),
+            Ok(MirrorPreference::List(vec![
+                Mirror::Jade,
+                Mirror::Custom("home".into())
+            ]))
+        );
+        assert_eq!(
+            MirrorPreference::from_str("gb,auto"),
+            Err(MirrorListError::AutoInList)
+        );
+
+        let from_yaml: MirrorPreference =
+            serde_yaml_ng::from_str("[jade, gb]").expect("should be parsed");
+        assert_eq!(
+            from_yaml,
+            MirrorPreference::List(vec![Mirror::Jade, Mirror::Gb])
+        );
+    }
+
+    #[test]
+    fn test_parse_mirror_name() {
+        assert_eq!(Mirror::from_str("jade"), Ok(Mirror::Jade));
+        assert_eq!(
+            Mirror::from_str("my-mirror_2"),
+            Ok(Mirror::Custom("my-mirror_2".into()))
+        );
+        assert!(Mirror::from_str("bad mirror").is_err());
+        assert!(Mirror::from_str("").is_err());
+    }
+}
@@ -1,53 +1,101 @@
 //! API Client.
 //!
 //! Fetches mod registry and dependency graph from server.
-use std::time::Duration;
+//! Parsed responses are cached on disk and revalidated with `ETag` and `Last-Modified`,
+//! so an unchanged database costs a single round trip instead of a few megabytes of YAML.
+//! Each resource is retried on its own, so a flaky response of one does not fail the other.
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
 
 use reqwest::Client;
-use tokio::try_join;
-use tracing::instrument;
+use rkyv::{
+    Archive, Deserialize, Serialize,
+    api::high::{HighSerializer, HighValidator},
+    bytecheck::CheckBytes,
+    de::Pool,
+    rancor::{self, Strategy},
+    ser::allocator::ArenaHandle,
+    util::AlignedVec,
+};
+use tokio::join;
+use tracing::{debug, instrument, warn};
 
 use crate::{
     commands::DownloadOption,
-    core::{dependency::DependencyGraph, registry::EverestUpdateYaml},
+    core::{
+        cache::{self, CacheError},
+        dependency::DependencyGraph,
+        network::{
+            fetch::{self, Fetched, Validators},
+            retry::{self, RetryPolicy},
+        },
+        registry::ModRegistry,
+    },
+    log::anonymize,
     ui::create_spinner,
 };
 
 /// Fetches registry and graph at once.
+///
+/// Only the registry is required. The graph is replaced with an empty one if it cannot be
+/// fetched, so the dependencies are read from the downloaded mods instead.
 pub async fn fetch(
     client: Client,
     opt: &DownloadOption,
-) -> anyhow::Result<(EverestUpdateYaml, DependencyGraph)> {
-    let api_client = ApiClient::new(client);
+) -> anyhow::Result<(ModRegistry, DependencyGraph)> {
+    let api_client = ApiClient::new(client, opt);
     let source = ApiSource::from(opt);
 
-    let spinner = create_spinner();
-    let (registry, graph) = try_join!(
+    let (registry, graph) = join!(
         api_client.fetch_everest_update_yaml(source),
         api_client.fetch_graph(source)
-    )?;
-    spinner.finish_and_clear();
-    Ok((registry, graph))
+    );
+    let graph = graph.unwrap_or_else(|e| {
+        warn!(
+            ?e,
+            "dependency graph is unavailable, reading the dependencies from the downloaded mods instead"
+        );
+        DependencyGraph::default()
+    });
+    Ok((registry?, graph))
 }
 
 /// Fetches registry.
-pub async fn fetch_registry(
-    client: Client,
-    opt: &DownloadOption,
-) -> anyhow::Result<EverestUpdateYaml> {
-    let api_client = ApiClient::new(client);
+pub async fn fetch_registry(client: Client, opt: &DownloadOption) -> anyhow::Result<ModRegistry> {
+    let api_client = ApiClient::new(client, opt);
     let source = ApiSource::from(opt);
+    Ok(api_client.fetch_everest_update_yaml(source).await?)
+}
 
-    let spinner = create_spinner();
-    let registry = api_client.fetch_everest_update_yaml(source).await?;
-    spinner.finish_and_clear();
-    Ok(registry)
+/// Loads the registry cached by the last fetch from either source, without network.
+///
+/// Returns `None` if nothing is cached, since the callers can go on without the registry.
+pub fn load_cached_registry(cache_dir: &Path) -> Option<ModRegistry> {
+    let path = cache_dir.join(ApiResource::Registry.cache_name());
+    match CachedResponse::<ModRegistry>::load(&path) {
+        Ok(cached) => Some(cached.value),
+        Err(e) => {
+            debug!(?e, "no usable cache at {}", anonymize(&path));
+            None
+        }
+    }
 }
 
 /// Client for API.
 #[derive(Debug, Clone)]
 pub struct ApiClient {
     client: reqwest::Client,
+    /// Directory of the cached responses.
+    cache_dir: PathBuf,
+    /// Ignores the cached responses if `true`.
+    refresh: bool,
+    /// Uses only the cached responses if `true`, takes precedence over `refresh`.
+    offline: bool,
+    /// Retries of each resource when nothing is cached.
+    retry: RetryPolicy,
 }
 
 /// API sources.
@@ -74,6 +122,26 @@ enum ApiResource {
     DependencyGraph,
 }
 
+impl ApiResource {
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Registry => "mod registry",
+            Self::DependencyGraph => "dependency graph",
+        }
+    }
+
+    /// File name of the cached response.
+    ///
+    /// NOTE bump the suffix when the fields of the parsed types change, archives of the old
+    /// layout must not be read as the new one.
+    fn cache_name(&self) -> &'static str {
+        match self {
+            Self::Registry => "everest_update.v4.cache",
+            Self::DependencyGraph => "mod_dependency_graph.v2.cache",
+        }
+    }
+}
+
 impl ApiSource {
     fn url_for(&self, resource: ApiResource) -> &'static str {
         match (self, resource) {
@@ -99,37 +167,159 @@ pub enum ApiError {
     Network(#[from] reqwest::Error),
     #[error("Failed to parse API response as YAML format")]
     DeserializeYaml(#[from] serde_yaml_ng::Error),
+    #[error("No cached database, run once without `--offline` to download it")]
+    NotCached,
+}
+
+/// Parsed response stored with the validators of the server.
+#[derive(Archive, Deserialize, Serialize, Debug)]
+struct CachedResponse<T> {
+    /// Cache is discarded when the source is switched.
+    url: String,
+    validators: Validators,
+    value: T,
+}
+
+impl<T> CachedResponse<T> {
+    fn load(path: &Path) -> Result<Self, CacheError>
+    where
+        T: Archive,
+        T::Archived: for<'a> CheckBytes<HighValidator<'a, rancor::Error>>
+            + Deserialize<T, Strategy<Pool, rancor::Error>>,
+    {
+        cache::read_archived(path)
+    }
+
+    fn save(&self, path: &Path) -> Result<(), CacheError>
+    where
+        T: for<'a> Serialize<HighSerializer<AlignedVec, ArenaHandle<'a>, rancor::Error>>,
+    {
+        let bytes = rkyv::to_bytes::<rancor::Error>(self)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, bytes)?;
+        Ok(())
+    }
 }
 
 impl ApiClient {
-    pub fn new(client: reqwest::Client) -> Self {
-        Self { client }
+    pub fn new(client: reqwest::Client, opt: &DownloadOption) -> Self {
+        Self {
+            client,
+            cache_dir: opt.api_cache_dir.clone(),
+            refresh: opt.refresh,
+            offline: opt.offline,
+            retry: opt.retry_policy(),
+        }
     }
 
+    /// Fetches the resource, or returns the cached one if the server reports it is not modified.
+    ///
+    /// The cached one is also used when the server cannot be reached.
     #[instrument(skip(self))]
     async fn fetch_yaml<T>(&self, source: ApiSource, resource: ApiResource) -> Result<T, ApiError>
     where
         for<'de> T: serde::Deserialize<'de>,
+        T: Archive + for<'a> Serialize<HighSerializer<AlignedVec, ArenaHandle<'a>, rancor::Error>>,
+        T::Archived: for<'a> CheckBytes<HighValidator<'a, rancor::Error>>
+            + Deserialize<T, Strategy<Pool, rancor::Error>>,
     {
         let url = source.url_for(resource);
+        let cache_path = self.cache_dir.join(resource.cache_name());
 
-        let bytes = self
-            .client
-            .get(url)
-            .timeout(Duration::from_secs(10))
-            .send()
-            .await?
-            .error_for_status()?
-            .bytes()
-            .await?;
-
-        Ok(serde_yaml_ng::from_slice(&bytes)?)
+        let cached = if self.refresh && !self.offline {
+            None
+        } else {
+            match CachedResponse::<T>::load(&cache_path) {
+                Ok(cached) if cached.url == url => Some(cached),
+                Ok(_) => None,
+                Err(e) => {
+                    debug!(?e, "no usable cache at {}", anonymize(&cache_path));
+                    None
+                }
+            }
+        };
+
+        if self.offline {
+            return cached.map(|cached| cached.value).ok_or(ApiError::NotCached);
+        }
+
+        let validators = cached.as_ref().map(|cached| &cached.validators);
+        let result = self
+            .get_with_retry(url, resource, validators, cached.is_none())
+            .await;
+        let (validators, body) = match (result, cached) {
+            (Ok(Fetched::Modified { validators, body }), _) => (validators, body),
+            (Ok(Fetched::NotModified), Some(cached)) => {
+                debug!("database is not modified");
+                return Ok(cached.value);
+            }
+            (Err(e), Some(cached)) => {
+                warn!(?e, "failed to fetch database, using the cached one");
+                return Ok(cached.value);
+            }
+            (Err(e), None) => return Err(e.into()),
+            (Ok(Fetched::NotModified), None) => unreachable!("not modified without validators"),
+        };
+
+        let cached = CachedResponse {
+            url: url.to_string(),
+            validators,
+            value: serde_yaml_ng::from_slice(&body)?,
+        };
+        if let Err(e) = cached.save(&cache_path) {
+            warn!(?e, "failed to save database to {}", anonymize(&cache_path));
+        }
+        Ok(cached.value)
+    }
+
+    /// Sends the conditional request with a spinner of the resource.
+    ///
+    /// Transient failures are retried only without the cached one, which is used at once instead.
+    async fn get_with_retry(
+        &self,
+        url: &str,
+        resource: ApiResource,
+        validators: Option<&Validators>,
+        retry_on_failure: bool,
+    ) -> reqwest::Result<Fetched> {
+        let spinner = create_spinner();
+        spinner.set_message(format!("fetching {}...", resource.label()));
+        let started = Instant::now();
+        let mut retry = 0;
+        let result = loop {
+            let request = self.client.get(url).timeout(Duration::from_secs(10));
+            let e = match fetch::conditional_get(request, validators).await {
+                Err(e) if retry_on_failure && retry::is_retryable(&e) => e,
+                result => break result,
+            };
+            retry += 1;
+            let Some(delay) = self.retry.next_delay(retry, started, None) else {
+                break Err(e);
+            };
+            debug!(
+                ?e,
+                retry,
+                ?delay,
+                "failed to fetch {}, retrying",
+                resource.label()
+            );
+            spinner.set_message(format!(
+                "fetching {}... (retry {})",
+                resource.label(),
+                retry
+            ));
+            tokio::time::sleep(delay).await;
+        };
+        spinner.finish_and_clear();
+        result
     }
 
     pub async fn fetch_everest_update_yaml(
         &self,
         source: ApiSource,
-    ) -> Result<EverestUpdateYaml, ApiError> {
+    ) -> Result<ModRegistry, ApiError> {
         self.fetch_yaml(source, ApiResource::Registry).await
     }
 
@@ -137,3 +327,80 @@ impl ApiClient {
         self.fetch_yaml(source, ApiResource::DependencyGraph).await
     }
 }
+
+#[cfg(test)]
+mod tests_api_cache {
+    use std::collections::HashSet;
+
+    use reqwest::header::{ETAG, HeaderMap, HeaderValue};
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_cached_response_roundtrip() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let path = dir
+            .path()
+            .join("api")
+            .join(ApiResource::Registry.cache_name());
+
+        let yaml = "Foo:\n  GameBananaId: 1\n  Version: 1.0.0\n  URL: https://gamebanana.com/mmdl/2\n  Size: 3\n  xxHash: [f437bf0515368130]\n";
+        let registry: ModRegistry = serde_yaml_ng::from_str(yaml)?;
+        let mut headers = HeaderMap::new();
+        headers.insert(ETAG, HeaderValue::from_static("\"abc\""));
+        let url = ApiSource::Primary.url_for(ApiResource::Registry);
+        CachedResponse {
+            url: url.to_string(),
+            validators: Validators::from_headers(&headers),
+            value: registry,
+        }
+        .save(&path)?;
+
+        let cached = CachedResponse::<ModRegistry>::load(&path)?;
+        assert_eq!(cached.url, url);
+        assert_eq!(cached.validators, Validators::from_headers(&headers));
+        let names = cached.value.names_by_gbids(&[1].into());
+        assert_eq!(names, HashSet::from(["Foo".to_string()]));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_offline() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let api_client = ApiClient {
+            client: reqwest::Client::new(),
+            cache_dir: dir.path().to_path_buf(),
+            refresh: true,
+            offline: true,
+            retry: RetryPolicy::default(),
+        };
+        let result = api_client.fetch_graph(ApiSource::Primary).await;
+        assert!(matches!(result, Err(ApiError::NotCached)));
+
+        let graph: DependencyGraph = serde_yaml_ng::from_str("Foo:\n  Dependencies: []\n")?;
+        CachedResponse {
+            url: ApiSource::Primary
+                .url_for(ApiResource::DependencyGraph)
+                .to_string(),
+            validators: Validators::default(),
+            value: graph,
+        }
+        .save(&dir.path().join(ApiResource::DependencyGraph.cache_name()))?;
+        // NOTE no request is sent, the cached one is returned even with `refresh`
+        let graph = api_client.fetch_graph(ApiSource::Primary).await?;
+        // Foo is found in the cached graph
+        let resolution = graph.check_names(["Foo".into()].into(), &Default::default());
+        assert!(resolution.unresolved.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_corrupted_cache() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join(ApiResource::DependencyGraph.cache_name());
+        fs::write(&path, b"truncated")?;
+        assert!(CachedResponse::<DependencyGraph>::load(&path).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,60 @@
+//! Fetches indices of the mirrors to skip the ones which do not host the file.
+use std::{collections::HashSet, path::Path, time::Duration};
+
+use futures_util::future::join_all;
+use reqwest::Client;
+use tracing::{debug, warn};
+
+use crate::core::{
+    mirrorlist::{self, Availability, Mirror},
+    network::fetch,
+};
+
+/// Time limit of each request, the index is a few hundred kilobytes.
+const INDEX_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Fetches the indices of the given mirrors concurrently.
+///
+/// Mirrors whose index is not available are assumed to host every file.
+/// Indices are revalidated against the copies in the cache directory.
+pub async fn fetch(
+    client: &Client,
+    mirrors: &[Mirror],
+    cache_dir: &Path,
+    refresh: bool,
+) -> Availability {
+    let tasks = mirrors.iter().filter_map(|mirror| {
+        let url = mirror.index_url()?;
+        Some(async move { (mirror, fetch_index(client, url, cache_dir, refresh).await) })
+    });
+
+    let mut availability = Availability::default();
+    for (mirror, result) in join_all(tasks).await {
+        match result {
+            Ok(gbids) => {
+                debug!(%mirror, count = gbids.len(), "fetched mirror index");
+                availability.insert(mirror.clone(), gbids);
+            }
+            Err(e) => warn!(%mirror, "failed to fetch mirror index: {}", e),
+        }
+    }
+    availability
+}
+
+async fn fetch_index(
+    client: &Client,
+    url: &str,
+    cache_dir: &Path,
+    refresh: bool,
+) -> Result<HashSet<u32>, Error> {
+    let bytes = fetch::fetch_remote_data(client, url, INDEX_TIMEOUT, cache_dir, refresh).await?;
+    Ok(mirrorlist::parse_index(&bytes)?)
+}
+
+#[derive(thiserror::Error, Debug)]
+enum Error {
+    #[error(transparent)]
+    Network(#[from] reqwest::Error),
+    #[error("invalid index: {0}")]
+    Parse(#[from] serde_json::Error),
+}
@@ -0,0 +1,61 @@
+//! Cancellation of the downloads by Ctrl-C.
+//!
+//! While mods are being downloaded, the first Ctrl-C cancels them instead of killing the process,
+//! so the parts are flushed to be resumed, and the progress bars and the report are drawn cleanly.
+//! Otherwise, or on the second Ctrl-C, the process exits right away as without the handler.
+use std::sync::{
+    LazyLock,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, warn};
+
+/// Exit status on Ctrl-C, 128 + SIGINT like shells.
+const EXIT_INTERRUPTED: i32 = 130;
+
+/// Canceled on the first Ctrl-C during the downloads, never reset within the process.
+static TOKEN: LazyLock<CancellationToken> = LazyLock::new(CancellationToken::new);
+
+/// Number of the batches of downloads running.
+static RUNNING: AtomicUsize = AtomicUsize::new(0);
+
+/// Starts listening to Ctrl-C in the background, for the rest of the process.
+pub fn listen() {
+    tokio::spawn(async {
+        loop {
+            if let Err(e) = tokio::signal::ctrl_c().await {
+                debug!(?e, "failed to listen to Ctrl-C");
+                return;
+            }
+            if RUNNING.load(Ordering::SeqCst) > 0 && !TOKEN.is_cancelled() {
+                warn!("canceling the downloads, press Ctrl-C again to quit now");
+                TOKEN.cancel();
+            } else {
+                std::process::exit(EXIT_INTERRUPTED);
+            }
+        }
+    });
+}
+
+/// Returns the token canceled by Ctrl-C.
+pub fn token() -> CancellationToken {
+    TOKEN.clone()
+}
+
+/// Marks the downloads running while alive, so Ctrl-C cancels them instead of quitting.
+#[derive(Debug)]
+pub struct Running(());
+
+impl Running {
+    pub fn start() -> Self {
+        RUNNING.fetch_add(1, Ordering::SeqCst);
+        Self(())
+    }
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        RUNNING.fetch_sub(1, Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,339 @@
+//! Delta downloads of the updates, reusing the unchanged entries of the old archive.
+//!
+//! Updates of large mods often change a few files of the archive. The central directory of the
+//! new archive is fetched from its tail with `Range` requests, and each entry whose CRC-32 and
+//! sizes match the entry of the same name in the old archive is copied from the old one, while
+//! the rest is fetched by ranges. The result is verified with the checksum of the registry like
+//! any other download, and downloaded as a whole if it does not match.
+use std::{
+    io::{self, Read, Seek, SeekFrom},
+    ops::Range,
+    path::Path,
+    time::Duration,
+};
+
+use reqwest::{Client, Response, StatusCode, header::RANGE};
+use tracing::debug;
+use zip_finder::{EntryInfo, ZipArchiveIndex};
+
+use crate::core::network::partial;
+
+/// Bytes fetched from the end first, enough for the EOCD with the longest comment.
+const TAIL_SIZE: u64 = 22 + u16::MAX as u64;
+/// Entries smaller than this are fetched instead of reused, since each gap costs a request.
+const MIN_REUSE: u64 = 64 * 1024;
+/// A whole download is cheaper than this many requests.
+const MAX_REQUESTS: usize = 32;
+/// Time limit of the requests for the tail, which is small.
+const TAIL_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(thiserror::Error, Debug)]
+pub enum DeltaError {
+    #[error(transparent)]
+    Network(#[from] reqwest::Error),
+    #[error("failed to read the archive")]
+    Archive(#[from] zip_finder::Error),
+    #[error("server returned another range than requested")]
+    UnexpectedRange,
+}
+
+/// End of the new archive, from the central directory to the last byte.
+#[derive(Debug)]
+pub struct Tail {
+    /// Offset of the first byte in the archive.
+    start: u64,
+    bytes: Vec<u8>,
+}
+
+impl Tail {
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    /// Returns the bytes in the range, which must be within the tail.
+    pub fn slice(&self, range: Range<u64>) -> &[u8] {
+        &self.bytes[(range.start - self.start) as usize..(range.end - self.start) as usize]
+    }
+
+    fn size(&self) -> u64 {
+        self.start + self.bytes.len() as u64
+    }
+
+    fn reader(&self) -> TailReader<'_> {
+        TailReader {
+            tail: self,
+            position: 0,
+        }
+    }
+}
+
+/// Reads the tail as the whole archive, failing outside of the tail.
+struct TailReader<'a> {
+    tail: &'a Tail,
+    position: u64,
+}
+
+impl Read for TailReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position < self.tail.start {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "read before the fetched tail",
+            ));
+        }
+        let offset = ((self.position - self.tail.start) as usize).min(self.tail.bytes.len());
+        let n = (&self.tail.bytes[offset..]).read(buf)?;
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for TailReader<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.tail.size().checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+        self.position = position.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "seek before the archive")
+        })?;
+        Ok(self.position)
+    }
+}
+
+/// Fetches the tail of the new archive, including its central directory.
+///
+/// Returns `None` if the server ignores the range or the archive is not of the expected size.
+pub async fn fetch_tail(client: &Client, url: &str, size: u64) -> Result<Option<Tail>, DeltaError> {
+    let response = client
+        .get(url)
+        .header(RANGE, format!("bytes=-{}", TAIL_SIZE.min(size)))
+        .timeout(TAIL_TIMEOUT)
+        .send()
+        .await?
+        .error_for_status()?;
+    if response.status() != StatusCode::PARTIAL_CONTENT {
+        debug!(status = %response.status(), "server does not support ranges");
+        return Ok(None);
+    }
+    let total = partial::content_range_total(response.headers());
+    if total != Some(size) {
+        debug!(?total, size, "size differs from the registry");
+        return Ok(None);
+    }
+    let start =
+        partial::content_range_start(response.headers()).ok_or(DeltaError::UnexpectedRange)?;
+    let mut tail = Tail {
+        start,
+        bytes: response.bytes().await?.to_vec(),
+    };
+    if tail.size() != size {
+        return Err(DeltaError::UnexpectedRange);
+    }
+
+    let directory = zip_finder::central_directory_range(&mut tail.reader())?;
+    if directory.start < tail.start {
+        let range = directory.start..tail.start;
+        let mut bytes = fetch_range(client, url, range.clone())
+            .await?
+            .bytes()
+            .await?
+            .to_vec();
+        if bytes.len() as u64 != range.end - range.start {
+            return Err(DeltaError::UnexpectedRange);
+        }
+        bytes.append(&mut tail.bytes);
+        tail = Tail {
+            start: range.start,
+            bytes,
+        };
+    }
+    Ok(Some(tail))
+}
+
+/// Requests the range of the archive, the body is checked to start at the range.
+pub async fn fetch_range(
+    client: &Client,
+    url: &str,
+    range: Range<u64>,
+) -> Result<Response, DeltaError> {
+    let response = client
+        .get(url)
+        .header(RANGE, format!("bytes={}-{}", range.start, range.end - 1))
+        .send()
+        .await?
+        .error_for_status()?;
+    if response.status() != StatusCode::PARTIAL_CONTENT
+        || partial::content_range_start(response.headers()) != Some(range.start)
+    {
+        return Err(DeltaError::UnexpectedRange);
+    }
+    Ok(response)
+}
+
+/// Source of a part of the new archive, in the order of its bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    /// Range of the new archive, fetched or taken from the tail.
+    Remote(Range<u64>),
+    /// Range of the old archive, which holds the same bytes.
+    Local(Range<u64>),
+}
+
+impl Segment {
+    fn len(&self) -> u64 {
+        let (Self::Remote(range) | Self::Local(range)) = self;
+        range.end - range.start
+    }
+}
+
+/// Plans how to build the new archive from the old one and the server.
+///
+/// Returns `None` if too little can be reused, or it takes too many requests to skip the rest.
+pub fn plan(tail: &Tail, old: &Path) -> Result<Option<Vec<Segment>>, DeltaError> {
+    let new = ZipArchiveIndex::new(tail.reader())?;
+    let mut old = ZipArchiveIndex::open(old)?;
+
+    let mut entries: Vec<EntryInfo> = new.entries().collect();
+    entries.sort_by_key(|entry| entry.offset());
+
+    let mut segments = Vec::new();
+    let mut position = 0;
+    for (i, entry) in entries.iter().enumerate() {
+        // NOTE the data ends where the next entry starts, unless a data descriptor follows it
+        let end = entries
+            .get(i + 1)
+            .map_or(new.central_directory_offset(), |next| next.offset());
+        let size = entry.compressed_size() as u64;
+        let reusable = !entry.has_data_descriptor()
+            && size >= MIN_REUSE
+            && end
+                .checked_sub(size)
+                .is_some_and(|start| start > entry.offset())
+            && old
+                .info(entry.name())
+                .is_some_and(|old| is_same(&old, entry));
+        if !reusable {
+            continue;
+        }
+        let source = old.data_range(entry.name())?;
+        segments.push(Segment::Remote(position..end - size));
+        segments.push(Segment::Local(source));
+        position = end;
+    }
+    segments.push(Segment::Remote(position..tail.size()));
+
+    let reused: u64 = segments
+        .iter()
+        .filter(|segment| matches!(segment, Segment::Local(_)))
+        .map(Segment::len)
+        .sum();
+    let requests = segments
+        .iter()
+        .filter(|segment| matches!(segment, Segment::Remote(range) if range.start < tail.start))
+        .count();
+    debug!(reused, requests, "planned delta download");
+    if reused == 0 || requests > MAX_REQUESTS {
+        return Ok(None);
+    }
+    Ok(Some(segments))
+}
+
+/// Checks if the entries are likely compressed into the same bytes.
+///
+/// NOTE another compressor may produce other bytes of the same sizes, which is caught by the checksum
+fn is_same(old: &EntryInfo, new: &EntryInfo) -> bool {
+    old.crc32() == new.crc32()
+        && old.compression_method() == new.compression_method()
+        && old.compressed_size() == new.compressed_size()
+        && old.uncompressed_size() == new.uncompressed_size()
+}
+
+#[cfg(test)]
+mod tests_delta {
+    use std::io::{Cursor, Write};
+
+    use zip::{CompressionMethod, ZipWriter, write::SimpleFileOptions};
+
+    use super::*;
+
+    fn archive(files: &[(&str, &[u8])]) -> anyhow::Result<Vec<u8>> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+        for (name, content) in files {
+            writer.start_file(*name, options)?;
+            writer.write_all(content)?;
+        }
+        Ok(writer.finish()?.into_inner())
+    }
+
+    /// Builds the new archive by the plan like the downloader.
+    fn assemble(segments: &[Segment], old: &[u8], new: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for segment in segments {
+            match segment {
+                Segment::Remote(range) => {
+                    bytes.extend_from_slice(&new[range.start as usize..range.end as usize])
+                }
+                Segment::Local(range) => {
+                    bytes.extend_from_slice(&old[range.start as usize..range.end as usize])
+                }
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_plan() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let maps: Vec<u8> = (0..200_000u32).map(|i| (i * 7 % 251) as u8).collect();
+        let old = archive(&[
+            ("everest.yaml", b"- Name: Foo\n  Version: 1.0.0\n"),
+            ("Maps/Foo.bin", &maps),
+        ])?;
+        let new = archive(&[
+            ("everest.yaml", b"- Name: Foo\n  Version: 1.1.0\n"),
+            ("Graphics/Atlases/Gameplay/foo.png", b"new sprite"),
+            ("Maps/Foo.bin", &maps),
+        ])?;
+        let old_path = dir.path().join("Foo.zip");
+        std::fs::write(&old_path, &old)?;
+
+        let tail = Tail {
+            start: new.len() as u64 - 1024,
+            bytes: new[new.len() - 1024..].to_vec(),
+        };
+        let segments = plan(&tail, &old_path)?.unwrap();
+        assert_eq!(assemble(&segments, &old, &new), new);
+        let reused: u64 = segments
+            .iter()
+            .filter(|segment| matches!(segment, Segment::Local(_)))
+            .map(Segment::len)
+            .sum();
+        assert_eq!(reused, maps.len() as u64);
+
+        // nothing is reused from an unrelated archive
+        std::fs::write(&old_path, archive(&[("everest.yaml", b"- Name: Bar\n")])?)?;
+        assert_eq!(plan(&tail, &old_path)?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_tail_reader() -> anyhow::Result<()> {
+        let tail = Tail {
+            start: 10,
+            bytes: b"0123456789".to_vec(),
+        };
+        let mut reader = tail.reader();
+        assert_eq!(reader.seek(SeekFrom::End(-4))?, 16);
+        let mut buf = [0; 4];
+        reader.read_exact(&mut buf)?;
+        assert_eq!(&buf, b"6789");
+        // before the tail is not fetched
+        reader.seek(SeekFrom::Start(5))?;
+        assert!(reader.read(&mut buf).is_err());
+        assert_eq!(tail.slice(12..14), b"23");
+        Ok(())
+    }
+}
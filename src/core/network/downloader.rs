@@ -1,243 +1,158 @@
-use std::{fmt::Display, path::Path, str::FromStr, sync::Arc, time::Duration};
+use std::{
+    collections::HashMap,
+    fs,
+    io::SeekFrom,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use futures_util::StreamExt;
-use indicatif::{MultiProgress, ProgressBar};
-use reqwest::Client;
-use tempfile::{self, Builder, NamedTempFile};
+use futures_util::{StreamExt, stream};
+use indicatif::{BinaryBytes, MultiProgress, ProgressBar};
+use reqwest::{
+    Client, StatusCode,
+    header::{CONTENT_LENGTH, IF_RANGE, RANGE},
+};
 use tokio::{
-    io::AsyncWriteExt,
+    fs::{File, OpenOptions},
+    io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt},
     sync::{AcquireError, Semaphore},
-    task::{JoinError, JoinSet},
+    task::{JoinError, JoinHandle, JoinSet},
 };
-use tracing::instrument;
+use tokio_util::sync::CancellationToken;
+use tracing::{Instrument, debug, instrument, warn};
 use xxhash_rust::xxh64::Xxh64;
 
 use crate::{
-    commands::{DownloadOption, Mirrors},
-    config::CARGO_PKG_NAME,
     core::{
-        Checksum, ChecksumVerificationError, Checksums, ParseChecksumError, registry::Entry,
-        update::UpdateContext,
+        ChecksumVerificationError, LocalMod,
+        download::DownloadFile,
+        local::{self, MetadataReadError},
+        mirrorlist::{Mirror, Mirrors},
+        network::{
+            cancel,
+            delta::{self, DeltaError, Segment},
+            filename::{self, FileNames},
+            partial::{self, PartMeta, PartialFile},
+            ratelimit::{Rate, RateLimiter},
+            retry::{self, RetryPolicy},
+        },
+        trash::Trash,
     },
     log::anonymize,
-    ui::create_download_progress_bar,
-    utils,
+    ui::{self, Throughput, create_download_progress_bar},
 };
 
-/// Downloads multiple files concurrently.
-pub async fn download_all(
-    client: Client,
-    args: DownloadOption,
-    targets: Vec<DownloadFile>,
-    mods_dir: &Path,
-) -> anyhow::Result<()> {
-    let downloader = Arc::new(ModDownloader::new(client, args));
-    let mut set = JoinSet::new();
-    let mp = MultiProgress::new();
-
-    for target in targets {
-        let downloader = downloader.clone();
-        let dest = mods_dir.join(target.name()).with_extension("zip");
-        let pb = mp.add(create_download_progress_bar(target.name(), target.size()));
-
-        set.spawn(async move {
-            downloader
-                .download_with_fallbacks(&target, &dest, &pb)
-                .await
-        });
-    }
-
-    while let Some(result) = set.join_next().await {
-        result??
-    }
-    Ok(())
+/// Time limit for receiving the response headers.
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(120);
+/// Shows the stalled state after this duration without receiving bytes.
+const STALL_NOTICE: Duration = Duration::from_secs(5);
+
+/// When to give up a mirror which accepted the connection but sends too little.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StallPolicy {
+    /// Duration without receiving bytes, and the window of the average speed.
+    timeout: Duration,
+    /// Average speed below which the mirror is given up, like curl's `--speed-limit`.
+    min_speed: Option<Rate>,
 }
 
-/// Metadata of target mod to be downloaded.
-#[derive(Debug, Clone)]
-pub struct DownloadFile {
-    /// Original download URL for the mod.
-    url: DownloadUrl,
-    /// A name of the mod. Used for file name.
-    name: FileStem,
-    /// File size used for the progress bar.
-    size: u64,
-    /// A expected list of XxHash64.
-    checksums: Checksums,
-}
-
-impl DownloadFile {
-    fn url(&self) -> &DownloadUrl {
-        &self.url
-    }
-    fn name(&self) -> &str {
-        &self.name.0
-    }
-    fn size(&self) -> u64 {
-        self.size
-    }
-    fn checksums(&self) -> &Checksums {
-        &self.checksums
-    }
-}
-
-#[derive(Debug, thiserror::Error)]
-pub enum ParseDownloadFileError {
-    #[error(transparent)]
-    Url(#[from] ParseUrlError),
-    #[error(transparent)]
-    Name(#[from] ParseNameError),
-    #[error(transparent)]
-    Checksum(#[from] ParseChecksumError),
-}
-
-impl TryFrom<&UpdateContext> for DownloadFile {
-    type Error = ParseDownloadFileError;
-
-    fn try_from(value: &UpdateContext) -> Result<Self, Self::Error> {
-        let url = DownloadUrl::from_str(value.url())?;
-        let name = FileStem::from_str(value.name())?;
-
-        Ok(Self {
-            url,
-            name,
-            size: value.size(),
-            checksums: value.checksums().to_owned(),
-        })
-    }
-}
-
-impl TryFrom<(String, Entry)> for DownloadFile {
-    type Error = ParseDownloadFileError;
-
-    fn try_from((name, entry): (String, Entry)) -> Result<Self, Self::Error> {
-        let url = DownloadUrl::from_str(entry.url())?;
-        let name = FileStem::from_str(&name)?;
-        let checksums = entry
-            .checksums()
-            .iter()
-            .map(|s| Checksum::from_str(s))
-            .collect::<Result<Checksums, _>>()?;
-
-        Ok(Self {
-            url,
-            name,
-            size: entry.file_size(),
-            checksums,
-        })
+impl Default for StallPolicy {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(30),
+            min_speed: None,
+        }
     }
 }
 
-/// Download URL of the mod. This is the original form used in the GameBanana.
-///
-/// Valid form:
-/// `https://gamebanana.com/mmdl/{ID}`: ID should be parsed as unsigned 32 bit integer.
-#[derive(Debug, Clone)]
-pub(crate) struct DownloadUrl {
-    raw: String,
-    id: u32,
-}
-
-impl DownloadUrl {
-    const PREFIX: &str = "https://gamebanana.com/mmdl/";
-
-    pub fn raw(&self) -> &str {
-        &self.raw
+impl StallPolicy {
+    pub fn new(timeout: Duration, min_speed: Option<Rate>) -> Self {
+        Self { timeout, min_speed }
     }
 
-    pub fn gbid(&self) -> u32 {
-        self.id
+    pub fn timeout(&self) -> Duration {
+        self.timeout
     }
 }
 
-#[derive(Debug, thiserror::Error)]
-pub enum ParseUrlError {
-    #[error(
-        "last path segment of URL must be a positive integer up to {}",
-        u32::MAX
-    )]
-    InvalidId(#[from] std::num::ParseIntError),
-    #[error(
-        "invalid download URL: must start with `https://gamebanana.com/mmdl/` followed only by a numeric ID"
-    )]
-    InvalidUrl,
+/// Average speed over the windows of the stall timeout, to catch the mirrors trickling bytes.
+#[derive(Debug)]
+struct SpeedCheck {
+    min: u64,
+    window: Duration,
+    started: Instant,
+    received: u64,
 }
 
-impl FromStr for DownloadUrl {
-    type Err = ParseUrlError;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let id_str = s
-            .strip_prefix(Self::PREFIX)
-            .ok_or(ParseUrlError::InvalidUrl)?;
-        let id = id_str.parse::<u32>()?;
-
-        Ok(DownloadUrl {
-            raw: s.to_string(),
-            id,
+impl SpeedCheck {
+    fn new(policy: &StallPolicy, now: Instant) -> Option<Self> {
+        Some(Self {
+            min: policy.min_speed?.bytes_per_sec(),
+            window: policy.timeout,
+            started: now,
+            received: 0,
         })
     }
-}
-
-impl Display for DownloadUrl {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.raw())
-    }
-}
-
-#[cfg(test)]
-mod tests_download_url {
-    use super::*;
-
-    #[test]
-    fn test_parse_valid_url() {
-        let input = "https://gamebanana.com/mmdl/12345";
-        let result = DownloadUrl::from_str(input);
-
-        assert!(result.is_ok());
-        let download_url = result.unwrap();
-        assert_eq!(download_url.gbid(), 12345);
-        assert_eq!(download_url.raw(), input);
-    }
-
-    #[test]
-    fn test_parse_invalid_prefix() {
-        let input = "https://google.com/12345";
-        let result = DownloadUrl::from_str(input);
-
-        assert!(matches!(result, Err(ParseUrlError::InvalidUrl)));
-    }
 
-    #[test]
-    fn test_parse_invalid_id() {
-        assert!(matches!(
-            DownloadUrl::from_str("https://gamebanana.com/mmdl/abc"),
-            Err(ParseUrlError::InvalidId(_))
-        ));
-
-        assert!(matches!(
-            DownloadUrl::from_str("https://gamebanana.com/mmdl/4294967296"),
-            Err(ParseUrlError::InvalidId(_))
-        ));
+    /// Records the received bytes, returns the average speed if the window closed below the minimum.
+    fn record(&mut self, bytes: u64, now: Instant) -> Option<u64> {
+        self.received += bytes;
+        let elapsed = now.duration_since(self.started);
+        if elapsed < self.window {
+            return None;
+        }
+        let speed = (self.received as f64 / elapsed.as_secs_f64()) as u64;
+        self.started = now;
+        self.received = 0;
+        (speed < self.min).then_some(speed)
     }
 }
 
-#[derive(Debug, thiserror::Error)]
-pub enum ParseNameError {
-    #[error(transparent)]
-    NonAscii(#[from] utils::NonAsciiError),
-}
-
-#[derive(Debug, Clone)]
-struct FileStem(String);
-
-impl FromStr for FileStem {
-    type Err = ParseNameError;
+/// Time limit of each request in `prefetch`.
+const PREFETCH_TIMEOUT: Duration = Duration::from_secs(10);
+/// Maximum number of concurrent requests in `prefetch`.
+const PREFETCH_CONCURRENCY: usize = 8;
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let clean_s = utils::sanitize_stem(s)?;
-        Ok(Self(clean_s))
-    }
+/// Sends HEAD requests to the first mirror of each file in the background.
+///
+/// Warms up the connections of the shared client while waiting for the user, and checks the
+/// sizes against the registry. Abort the returned handle to cancel.
+pub fn prefetch(client: &Client, mirrors: &Mirrors, targets: &[DownloadFile]) -> JoinHandle<()> {
+    let requests: Vec<(String, String, u64)> = targets
+        .iter()
+        .filter_map(|target| {
+            let (_, url) = mirrors.resolve(target.url()).into_iter().next()?;
+            Some((target.name().to_string(), url, target.size()))
+        })
+        .collect();
+    let client = client.clone();
+
+    tokio::spawn(async move {
+        stream::iter(requests)
+            .for_each_concurrent(PREFETCH_CONCURRENCY, |(name, url, size)| {
+                let client = &client;
+                async move {
+                    match client.head(&url).timeout(PREFETCH_TIMEOUT).send().await {
+                        Ok(response) => {
+                            // NOTE `content_length` is the size of the body, which is empty for HEAD
+                            let length = response
+                                .headers()
+                                .get(CONTENT_LENGTH)
+                                .and_then(|value| value.to_str().ok()?.parse::<u64>().ok());
+                            if length.is_some_and(|length| length != size) {
+                                debug!(name, ?length, size, "size differs from the registry");
+                            } else {
+                                debug!(name, status = %response.status(), "prefetched");
+                            }
+                        }
+                        Err(e) => debug!(name, ?e, "prefetch failed"),
+                    }
+                }
+            })
+            .await
+    })
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -252,6 +167,26 @@ pub enum Error {
     Join(#[from] JoinError),
     #[error("failed to acquire semaphore")]
     SemaphoreClosed(#[from] AcquireError),
+    #[error("canceled by Ctrl-C")]
+    Canceled,
+    #[error("no data received for {0} seconds")]
+    Stalled(u64),
+    #[error("received only {}/s in {} seconds", BinaryBytes(*.speed), .secs)]
+    TooSlow { speed: u64, secs: u64 },
+    #[error("server is busy ({status}), asked to retry after {} seconds", .delay.as_secs())]
+    Busy { status: StatusCode, delay: Duration },
+    #[error("server returned another range than requested")]
+    UnexpectedRange,
+    #[error("failed to download the changes")]
+    Delta(#[from] DeltaError),
+    #[error("downloaded file is not a valid mod archive")]
+    InvalidArchive(#[from] MetadataReadError),
+    #[error("downloaded archive is '{found}' instead of '{expected}'")]
+    UnexpectedMod { expected: String, found: String },
+    #[error("downloaded archive is version {found} instead of {expected}")]
+    UnexpectedVersion { expected: String, found: String },
+    #[error("server sent {found} bytes instead of {expected}")]
+    SizeMismatch { expected: u64, found: u64 },
     #[error("all mirrors failed for '{name}'")]
     AllMirrorsFailed {
         name: String,
@@ -259,46 +194,298 @@ pub enum Error {
     },
 }
 
-/// Context for downloading mods.
-#[derive(Debug)]
+impl Error {
+    /// Describes why the download failed, a line for each mirror.
+    pub fn reasons(&self) -> Vec<String> {
+        match self {
+            Self::AllMirrorsFailed { errors, .. } => errors
+                .iter()
+                .map(|(url, e)| {
+                    let host = url::Url::parse(url)
+                        .ok()
+                        .and_then(|url| url.host_str().map(String::from))
+                        .unwrap_or_else(|| url.clone());
+                    format!("{}: {}", host, chain(e))
+                })
+                .collect(),
+            _ => vec![chain(self)],
+        }
+    }
+
+    /// Checks if the error is transient, which may not happen on retry with the same mirror.
+    ///
+    /// Corrupted files and missing files are permanent, the next mirror should be tried instead.
+    /// So are the slow mirrors, which rarely speed up on retry.
+    fn is_retryable(&self) -> bool {
+        match self {
+            Self::Network(e) => retry::is_retryable(e),
+            Self::Stalled(_) | Self::Busy { .. } | Self::UnexpectedRange => true,
+            _ => false,
+        }
+    }
+
+    /// Returns the delay asked by the server before the retry.
+    fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::Busy { delay, .. } => Some(*delay),
+            _ => None,
+        }
+    }
+}
+
+/// Outcome of the downloads.
+#[derive(Debug, Default)]
+pub struct DownloadReport {
+    /// Paths to the downloaded files.
+    pub succeeded: Vec<PathBuf>,
+    /// Names of the mods failed to download, with the reasons.
+    pub failed: Vec<(String, Error)>,
+    /// Names of the mods not downloaded, with the names of the same archive downloaded instead.
+    pub skipped: Vec<(String, String)>,
+    /// Names of the mods canceled by Ctrl-C, their parts are kept to be resumed.
+    pub canceled: Vec<String>,
+}
+
+/// Some of the mods failed to download, or were canceled.
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum DownloadFailure {
+    #[error("{failed} of {total} mods failed to download")]
+    Failed { failed: usize, total: usize },
+    #[error("downloads were canceled, {canceled} of {total} mods were not downloaded")]
+    Canceled { canceled: usize, total: usize },
+}
+
+impl DownloadFailure {
+    /// Checks if none of the mods were downloaded because of the failures.
+    pub fn is_total(&self) -> bool {
+        matches!(self, Self::Failed { failed, total } if failed == total)
+    }
+}
+
+impl DownloadReport {
+    pub fn merge(&mut self, other: Self) {
+        self.succeeded.extend(other.succeeded);
+        self.failed.extend(other.failed);
+        self.skipped.extend(other.skipped);
+        self.canceled.extend(other.canceled);
+    }
+
+    /// Returns an error if any of the mods failed to download or were canceled.
+    ///
+    /// Cancellation is reported over the failures, since the user stopped the rest of them.
+    pub fn check(&self) -> Result<(), DownloadFailure> {
+        let total = self.failed.len() + self.succeeded.len() + self.canceled.len();
+        if !self.canceled.is_empty() {
+            return Err(DownloadFailure::Canceled {
+                canceled: self.canceled.len(),
+                total,
+            });
+        }
+        if self.failed.is_empty() {
+            return Ok(());
+        }
+        Err(DownloadFailure::Failed {
+            failed: self.failed.len(),
+            total,
+        })
+    }
+}
+
+/// Joins the messages of the error and its sources.
+fn chain(e: &dyn std::error::Error) -> String {
+    let mut message = e.to_string();
+    let mut source = e.source();
+    while let Some(e) = source {
+        message.push_str(": ");
+        message.push_str(&e.to_string());
+        source = e.source();
+    }
+    message
+}
+
+/// Engine of the mod downloads, shared by `install` and `update`.
+///
+/// Clones share the limits of the concurrency and the bandwidth, so they hold across batches.
+#[derive(Debug, Clone)]
 pub struct ModDownloader {
     client: Client,
     semaphore: Arc<Semaphore>,
-    mirror_priority: Mirrors,
+    mirror_priority: Arc<Mirrors>,
+    retry: RetryPolicy,
+    stall: StallPolicy,
+    limiter: Option<Arc<RateLimiter>>,
+    /// Canceled by Ctrl-C, see [`cancel`].
+    cancel: CancellationToken,
+    /// Keeps the archives replaced by the downloads.
+    trash: Option<Arc<Trash>>,
+    /// Reuses the unchanged entries of the old archives, see [`delta`].
+    delta: bool,
+    progress: MultiProgress,
 }
 
 impl ModDownloader {
-    pub fn new(client: Client, args: DownloadOption) -> Self {
+    pub fn new(client: Client, mirror_priority: Mirrors, jobs: u8) -> Self {
         Self {
             client,
-            semaphore: Arc::new(Semaphore::new(args.jobs as usize)),
-            mirror_priority: Mirrors::from(args.mirror_priority),
+            semaphore: Arc::new(Semaphore::new(jobs as usize)),
+            mirror_priority: Arc::new(mirror_priority),
+            retry: RetryPolicy::default(),
+            stall: StallPolicy::default(),
+            limiter: None,
+            cancel: cancel::token(),
+            trash: None,
+            delta: false,
+            progress: MultiProgress::new(),
+        }
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn with_stall(mut self, stall: StallPolicy) -> Self {
+        self.stall = stall;
+        self
+    }
+
+    /// Limits the total bandwidth of the concurrent downloads.
+    pub fn with_limit_rate(mut self, rate: Rate) -> Self {
+        self.limiter = Some(Arc::new(RateLimiter::new(rate)));
+        self
+    }
+
+    /// Keeps a copy of each archive in the trash before it is replaced.
+    pub fn with_trash(mut self, trash: Trash) -> Self {
+        self.trash = Some(Arc::new(trash));
+        self
+    }
+
+    /// Downloads only the changed entries of the archives which exist at the destinations.
+    pub fn with_delta(mut self) -> Self {
+        self.delta = true;
+        self
+    }
+
+    /// Downloads multiple files concurrently.
+    ///
+    /// A failure does not stop the other downloads, every outcome is in the report.
+    /// On Ctrl-C, the running downloads are stopped and the rest are not started.
+    pub async fn download_many(
+        &self,
+        targets: Vec<DownloadFile>,
+        mods_dir: &Path,
+    ) -> DownloadReport {
+        let _running = cancel::Running::start();
+        let mut report = DownloadReport::default();
+        let mut set = JoinSet::new();
+        let mut names = HashMap::new();
+        let mut urls: HashMap<String, String> = HashMap::new();
+        let mut file_names = FileNames::default();
+
+        for target in targets {
+            // NOTE mods in the same archive have the same URL, download it only once
+            if let Some(downloaded_as) = urls.get(target.url().raw()) {
+                report
+                    .skipped
+                    .push((target.name().to_string(), downloaded_as.clone()));
+                continue;
+            }
+            urls.insert(target.url().raw().to_string(), target.name().to_string());
+
+            let downloader = self.clone();
+            let gbid = target.url().gbid();
+            // NOTE named by the response later if the mod name cannot be a file name
+            let stem = match target.file_stem() {
+                Some(stem) => file_names.claim(stem, gbid),
+                None => gbid.to_string(),
+            };
+            let dest = mods_dir.join(format!("{}.zip", stem));
+            let pb = self
+                .progress
+                .add(create_download_progress_bar(target.name(), target.size()));
+
+            let name = target.name().to_string();
+            let handle = set.spawn(
+                async move {
+                    let result = downloader
+                        .download_with_fallbacks(&target, &dest, &pb)
+                        .await;
+                    // NOTE the bars of the canceled ones are cleared, the report tells them instead
+                    if matches!(result, Err(Error::Canceled)) {
+                        pb.finish_and_clear();
+                    }
+                    result
+                }
+                .in_current_span(),
+            );
+            names.insert(handle.id(), name);
         }
+
+        while let Some(result) = set.join_next_with_id().await {
+            match result {
+                Ok((_, Ok(path))) => report.succeeded.push(path),
+                Ok((id, Err(Error::Canceled))) => report.canceled.push(names[&id].clone()),
+                Ok((id, Err(e))) => report.failed.push((names[&id].clone(), e)),
+                Err(e) => report.failed.push((names[&e.id()].clone(), e.into())),
+            }
+        }
+        report
     }
 }
 
 impl ModDownloader {
     /// Retry downloading a file for given mirror urls until success or all mirrors are exhausted.
+    ///
+    /// Transient failures are retried on the same mirror with backoff before failing over.
+    #[instrument(level = "debug", skip_all, fields(name = item.name(), size = item.size()))]
     async fn download_with_fallbacks(
         &self,
         item: &DownloadFile,
         dest: &Path,
         pb: &ProgressBar,
-    ) -> Result<(), Error> {
-        let _permit = self.semaphore.acquire().await?;
+    ) -> Result<PathBuf, Error> {
+        let _permit = tokio::select! {
+            permit = self.semaphore.acquire() => permit?,
+            _ = self.cancel.cancelled() => return Err(Error::Canceled),
+        };
+
+        if self.delta
+            && let Some(path) = self.download_delta(item, dest, pb).await?
+        {
+            return Ok(path);
+        }
 
         let mut errors = Vec::new();
 
-        let urls = &self.mirror_priority.resolve(item.url());
-
-        for url in urls {
-            match self.download(url, item, dest, pb).await {
-                Ok(_) => return Ok(()),
-                Err(e) => {
-                    errors.push((url.clone(), e));
-                    pb.reset();
+        for (mirror, url) in self.mirror_priority.resolve(item.url()) {
+            let started = Instant::now();
+            let mut retry = 0;
+            let e = loop {
+                let e = match self.download(mirror, &url, item, dest, pb).await {
+                    Ok(path) => return Ok(path),
+                    Err(Error::Canceled) => return Err(Error::Canceled),
+                    Err(e) => e,
+                };
+                pb.reset();
+                retry += 1;
+                let delay = e
+                    .is_retryable()
+                    .then(|| self.retry.next_delay(retry, started, e.retry_after()))
+                    .flatten();
+                let Some(delay) = delay else {
+                    break e;
+                };
+                debug!(%mirror, ?e, retry, ?delay, "download failed, retrying");
+                ui::show_retrying(pb, retry, delay);
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => {}
+                    _ = self.cancel.cancelled() => return Err(Error::Canceled),
                 }
-            }
+            };
+            debug!(%mirror, ?e, "download failed, trying next mirror");
+            errors.push((url, e));
+            pb.set_prefix("");
         }
 
         Err(Error::AllMirrorsFailed {
@@ -310,57 +497,493 @@ impl ModDownloader {
     /// Downloads a file while hashing, verifying its integrity before final persistence.
     ///
     /// ### Note
-    /// - Uses `tempfile` (typically in `tmpfs`) to avoid polluting the destination
-    ///   with corrupt/partial data if verification fails.
-    /// - Performs `tokio::fs::copy` instead of `tempfile::persist` because `temp_path` and `dest`
-    ///   often reside on different filesystems (e.g., RAM vs. Disk).
-    #[instrument(skip_all, fields(%url, ?item, path = %anonymize(dest)))]
+    /// - Bytes are written to `{dest}.part` to avoid polluting the destination with corrupt or
+    ///   partial data. The part is synced and renamed over the destination after verification.
+    /// - The part of the failed attempt is kept, and resumed with a `Range` request by the next
+    ///   attempt, even on another mirror. It starts over if the server ignores the range.
+    #[instrument(level = "debug", skip_all, fields(%mirror, %url, path = %anonymize(dest)))]
     async fn download(
         &self,
+        mirror: &Mirror,
         url: &str,
         item: &DownloadFile,
         dest: &Path,
         pb: &ProgressBar,
-    ) -> Result<(), Error> {
-        let response = self
-            .client
-            .get(url)
-            .timeout(Duration::from_secs(120))
-            .send()
-            .await?
-            .error_for_status()?;
-
-        // Use a temp file for "Verify-then-Commit" strategy.
-        let temp_dir = Builder::new()
-            .prefix(&format!("{}-", CARGO_PKG_NAME))
-            .rand_bytes(6)
-            .tempdir()?;
-        let named_temp_file = NamedTempFile::new_in(temp_dir.path())?;
-        let temp_path = named_temp_file.path();
-
-        // Reopen handle to keep `named_temp_file` (and its path) alive for the final copy.
-        let std_file = named_temp_file.reopen()?;
-        let mut writer = tokio::fs::File::from_std(std_file);
+    ) -> Result<PathBuf, Error> {
+        let partial = PartialFile::new(dest);
+        let checksums = item.checksums().sorted();
+        let resumable = partial.resumable(&checksums, item.size());
+
+        let mut request = self.client.get(url);
+        if let Some((offset, validator)) = &resumable {
+            request = request.header(RANGE, format!("bytes={}-", offset));
+            if let Some(validator) = validator {
+                request = request.header(IF_RANGE, validator);
+            }
+        }
+        // NOTE the body is governed by the stall detector below, so large files on slow
+        // connections are not cut off as long as bytes keep arriving.
+        let response = tokio::select! {
+            response = tokio::time::timeout(RESPONSE_TIMEOUT, request.send()) => response,
+            _ = self.cancel.cancelled() => return Err(Error::Canceled),
+        };
+        let response = response.map_err(|_| Error::Stalled(RESPONSE_TIMEOUT.as_secs()))??;
+        if let Some(delay) = retry::retry_after(&response) {
+            return Err(Error::Busy {
+                status: response.status(),
+                delay,
+            });
+        }
+        let response = response.error_for_status()?;
+        // NOTE without the hashes, the size in the registry is all to check the bytes against
+        if item.checksums().is_empty()
+            && response.status() != StatusCode::PARTIAL_CONTENT
+            && let Some(length) = response.content_length()
+            && length != item.size()
+        {
+            return Err(Error::SizeMismatch {
+                expected: item.size(),
+                found: length,
+            });
+        }
 
         let mut hasher = Xxh64::new(0);
+        let mut writer = match resumable {
+            Some((offset, _)) if response.status() == StatusCode::PARTIAL_CONTENT => {
+                if partial::content_range_start(response.headers()) != Some(offset) {
+                    partial.discard()?;
+                    return Err(Error::UnexpectedRange);
+                }
+                debug!(offset, "resuming download");
+                // NOTE the state of the hasher cannot be stored, so the part is hashed again
+                hash_file(&mut hasher, partial.part_path()).await?;
+                pb.set_position(offset);
+                OpenOptions::new()
+                    .append(true)
+                    .open(partial.part_path())
+                    .await?
+            }
+            _ => {
+                partial.save_meta(&PartMeta::new(checksums, item.size(), response.headers()))?;
+                File::create(partial.part_path()).await?
+            }
+        };
+
+        let dest = match item.file_stem() {
+            Some(_) => dest.to_path_buf(),
+            None => {
+                let stem = filename::determine_filename(response.headers(), response.url())
+                    .unwrap_or_else(|| item.url().gbid().to_string());
+                dest.with_file_name(format!("{}.zip", stem))
+            }
+        };
+
         let mut stream = response.bytes_stream();
 
+        let mut throughput = Throughput::new(Instant::now());
+        let mut last_received = Instant::now();
+        let mut speed_check = SpeedCheck::new(&self.stall, last_received);
+
         // Stream download while hashing to minimize RAM usage.
-        while let Some(chunk) = stream.next().await {
-            let chunk = chunk?;
+        loop {
+            let next = tokio::select! {
+                next = tokio::time::timeout(STALL_NOTICE, stream.next()) => next,
+                _ = self.cancel.cancelled() => {
+                    // NOTE the part is kept to be resumed, unless nothing has been received
+                    close_part(writer).await?;
+                    if fs::metadata(partial.part_path()).is_ok_and(|meta| meta.len() == 0) {
+                        partial.discard()?;
+                    }
+                    return Err(Error::Canceled);
+                }
+            };
+            let chunk = match next {
+                Ok(Some(chunk)) => chunk?,
+                Ok(None) => break,
+                Err(_) => {
+                    // No bytes for a while, fail over to the next mirror if it does not recover
+                    let stalled_for = last_received.elapsed();
+                    ui::show_stalled(pb, stalled_for);
+                    if stalled_for >= self.stall.timeout {
+                        close_part(writer).await?;
+                        return Err(Error::Stalled(stalled_for.as_secs()));
+                    }
+                    continue;
+                }
+            };
+            last_received = Instant::now();
+            // NOTE a trickle resets the idle time above, so the average speed is checked too
+            if let Some(speed) = speed_check
+                .as_mut()
+                .and_then(|check| check.record(chunk.len() as u64, last_received))
+            {
+                close_part(writer).await?;
+                return Err(Error::TooSlow {
+                    speed,
+                    secs: self.stall.timeout.as_secs(),
+                });
+            }
             hasher.update(&chunk);
             writer.write_all(&chunk).await?;
             pb.inc(chunk.len() as u64);
+            if let Some(limiter) = &self.limiter {
+                limiter.acquire(chunk.len() as u64).await;
+            }
+            if throughput.record(chunk.len() as u64, last_received) {
+                ui::show_throughput(pb, &throughput);
+            }
         }
-        writer.flush().await?;
+        close_part(writer).await?;
+
+        // Abort if the file is corrupt, the part cannot be resumed either.
+        if item.checksums().is_empty() {
+            let found = fs::metadata(partial.part_path())?.len();
+            if found != item.size() {
+                partial.discard()?;
+                return Err(Error::SizeMismatch {
+                    expected: item.size(),
+                    found,
+                });
+            }
+        } else if let Err(e) = item.checksums().verify(&hasher.digest()) {
+            partial.discard()?;
+            return Err(e.into());
+        }
+        self.complete(item, &partial, &dest, pb).await
+    }
 
-        // Abort if the file is corrupt. NamedTempFile will be auto-deleted.
-        let digest = hasher.digest();
-        item.checksums().verify(&digest)?;
+    /// Checks the mod in the verified part, and replaces the destination with it.
+    async fn complete(
+        &self,
+        item: &DownloadFile,
+        partial: &PartialFile,
+        dest: &Path,
+        pb: &ProgressBar,
+    ) -> Result<PathBuf, Error> {
+        if let Err(e) = verify_archive(item, partial.part_path()).await {
+            partial.discard()?;
+            return Err(e);
+        }
+        if item.checksums().is_empty() {
+            warn!(
+                "{} is unverified, the registry has no hash of it, so only its size and everest.yaml were checked",
+                item.name()
+            );
+        }
 
-        // Finalize the download by copying across filesystem boundaries.
-        tokio::fs::copy(temp_path, dest).await?;
+        if let Some(trash) = &self.trash
+            && dest.is_file()
+        {
+            // NOTE only warns, failing to keep the old archive should not fail the update
+            if let Err(e) = trash.keep(dest) {
+                warn!(?e, "failed to keep {} in the trash", anonymize(dest));
+            }
+        }
+        partial.finish(dest)?;
         pb.finish_with_message(format!("{} 🍓", item.name()));
+        Ok(dest.to_path_buf())
+    }
+
+    /// Builds the new archive from the old one at the destination and the ranges of the first
+    /// mirror, returns `None` to download it as a whole instead.
+    #[instrument(level = "debug", skip_all)]
+    async fn download_delta(
+        &self,
+        item: &DownloadFile,
+        dest: &Path,
+        pb: &ProgressBar,
+    ) -> Result<Option<PathBuf>, Error> {
+        let partial = PartialFile::new(dest);
+        // NOTE resuming the part of the last attempt costs less, and the assembled archive can
+        // only be trusted by its hash
+        if !dest.is_file()
+            || item.checksums().is_empty()
+            || partial
+                .resumable(&item.checksums().sorted(), item.size())
+                .is_some()
+        {
+            return Ok(None);
+        }
+        let Some((mirror, url)) = self.mirror_priority.resolve(item.url()).into_iter().next()
+        else {
+            return Ok(None);
+        };
+        match self.assemble_delta(&url, item, dest, &partial, pb).await {
+            Ok(true) => self.complete(item, &partial, dest, pb).await.map(Some),
+            Ok(false) => Ok(None),
+            Err(Error::Canceled) => {
+                partial.discard()?;
+                Err(Error::Canceled)
+            }
+            Err(e) => {
+                debug!(%mirror, ?e, "delta download failed, downloading the whole archive");
+                partial.discard()?;
+                pb.reset();
+                Ok(None)
+            }
+        }
+    }
+
+    /// Writes the new archive into the part and verifies it, returns `false` if the old archive
+    /// has too little in common to be worth it.
+    async fn assemble_delta(
+        &self,
+        url: &str,
+        item: &DownloadFile,
+        dest: &Path,
+        partial: &PartialFile,
+        pb: &ProgressBar,
+    ) -> Result<bool, Error> {
+        let tail = tokio::select! {
+            tail = delta::fetch_tail(&self.client, url, item.size()) => tail?,
+            _ = self.cancel.cancelled() => return Err(Error::Canceled),
+        };
+        let Some(tail) = tail else {
+            return Ok(false);
+        };
+        let old = dest.to_path_buf();
+        let (tail, segments) = tokio::task::spawn_blocking(move || {
+            let segments = delta::plan(&tail, &old);
+            (tail, segments)
+        })
+        .await?;
+        let Some(segments) = segments? else {
+            return Ok(false);
+        };
+
+        let mut hasher = Xxh64::new(0);
+        let mut writer = File::create(partial.part_path()).await?;
+        let mut old = File::open(dest).await?;
+        let mut buffer = vec![0; 64 * 1024];
+        for segment in segments {
+            if self.cancel.is_cancelled() {
+                return Err(Error::Canceled);
+            }
+            match segment {
+                Segment::Local(range) => {
+                    old.seek(SeekFrom::Start(range.start)).await?;
+                    let mut remaining = range.end - range.start;
+                    while remaining > 0 {
+                        let len = buffer.len().min(remaining as usize);
+                        old.read_exact(&mut buffer[..len]).await?;
+                        hasher.update(&buffer[..len]);
+                        writer.write_all(&buffer[..len]).await?;
+                        pb.inc(len as u64);
+                        remaining -= len as u64;
+                    }
+                }
+                Segment::Remote(range) => {
+                    // NOTE the end of the archive has been fetched with the central directory
+                    let fetched = range.start..range.end.min(tail.start());
+                    if !fetched.is_empty() {
+                        self.fetch_segment(url, fetched, &mut writer, &mut hasher, pb)
+                            .await?;
+                    }
+                    let rest = range.start.max(tail.start())..range.end;
+                    if !rest.is_empty() {
+                        let bytes = tail.slice(rest);
+                        hasher.update(bytes);
+                        writer.write_all(bytes).await?;
+                        pb.inc(bytes.len() as u64);
+                    }
+                }
+            }
+        }
+        writer.flush().await?;
+        writer.sync_all().await?;
+        drop(writer);
+
+        item.checksums().verify(&hasher.digest())?;
+        Ok(true)
+    }
+
+    /// Streams the range of the new archive into the part, with the same stall detection and
+    /// bandwidth limit as the whole downloads.
+    async fn fetch_segment(
+        &self,
+        url: &str,
+        range: Range<u64>,
+        writer: &mut File,
+        hasher: &mut Xxh64,
+        pb: &ProgressBar,
+    ) -> Result<(), Error> {
+        let request = delta::fetch_range(&self.client, url, range.clone());
+        let response = tokio::select! {
+            response = tokio::time::timeout(RESPONSE_TIMEOUT, request) => response,
+            _ = self.cancel.cancelled() => return Err(Error::Canceled),
+        };
+        let response = response.map_err(|_| Error::Stalled(RESPONSE_TIMEOUT.as_secs()))??;
+
+        let mut stream = response.bytes_stream();
+        let mut remaining = range.end - range.start;
+        while remaining > 0 {
+            let next = tokio::select! {
+                next = tokio::time::timeout(self.stall.timeout, stream.next()) => next,
+                _ = self.cancel.cancelled() => return Err(Error::Canceled),
+            };
+            let next = next.map_err(|_| Error::Stalled(self.stall.timeout.as_secs()))?;
+            let Some(chunk) = next else {
+                return Err(Error::UnexpectedRange);
+            };
+            let chunk = chunk?;
+            // NOTE some servers send more than the requested range
+            let chunk = &chunk[..chunk.len().min(remaining as usize)];
+            hasher.update(chunk);
+            writer.write_all(chunk).await?;
+            pb.inc(chunk.len() as u64);
+            remaining -= chunk.len() as u64;
+            if let Some(limiter) = &self.limiter {
+                limiter.acquire(chunk.len() as u64).await;
+            }
+        }
         Ok(())
     }
 }
+
+/// Checks that the archive declares the expected mod in its `everest.yaml`, before it replaces
+/// the old one.
+async fn verify_archive(item: &DownloadFile, path: &Path) -> Result<(), Error> {
+    let path = path.to_path_buf();
+    let m = tokio::task::spawn_blocking(move || local::read_mod(&path)).await??;
+    // NOTE the hash proves the file already, so the version is only checked without it
+    let version = item.checksums().is_empty().then(|| item.version());
+    check_declared(&m, item.name(), version)
+}
+
+/// Checks that the mod is declared, at the version if given.
+fn check_declared(m: &LocalMod, name: &str, version: Option<&str>) -> Result<(), Error> {
+    let Some((_, found)) = m.declared().find(|(declared, _)| *declared == name) else {
+        return Err(Error::UnexpectedMod {
+            expected: name.to_string(),
+            found: m
+                .declared()
+                .map(|(name, _)| name)
+                .collect::<Vec<_>>()
+                .join(", "),
+        });
+    };
+    match version {
+        Some(expected) if expected != found => Err(Error::UnexpectedVersion {
+            expected: expected.to_string(),
+            found: found.to_string(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Flushes the part and closes it, since the writes of `tokio::fs::File` may still be pending in
+/// the background when the part is resumed from its length or verified.
+async fn close_part(mut writer: File) -> std::io::Result<()> {
+    writer.flush().await?;
+    writer.sync_all().await
+}
+
+/// Feeds the content of the file to the hasher.
+async fn hash_file(hasher: &mut Xxh64, path: &Path) -> std::io::Result<()> {
+    let mut file = File::open(path).await?;
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let n = file.read(&mut buffer).await?;
+        if n == 0 {
+            return Ok(());
+        }
+        hasher.update(&buffer[..n]);
+    }
+}
+
+#[cfg(test)]
+mod tests_download_report {
+    use super::*;
+
+    #[test]
+    fn test_speed_check() {
+        let policy = StallPolicy::new(Duration::from_secs(10), "1K".parse().ok());
+        let start = Instant::now();
+        let mut check = SpeedCheck::new(&policy, start).unwrap();
+        assert_eq!(check.record(5000, start + Duration::from_secs(5)), None);
+        // 10000 bytes in 10 seconds is below 1KiB/s
+        assert_eq!(
+            check.record(5000, start + Duration::from_secs(10)),
+            Some(1000)
+        );
+        // a new window starts after the check
+        assert_eq!(check.record(20480, start + Duration::from_secs(20)), None);
+        assert!(SpeedCheck::new(&StallPolicy::default(), start).is_none());
+    }
+
+    #[test]
+    fn test_check() {
+        let mut report = DownloadReport::default();
+        assert_eq!(report.check(), Ok(()));
+
+        report.failed.push(("Foo".into(), Error::Stalled(30)));
+        let failure = report.check().unwrap_err();
+        assert!(failure.is_total());
+
+        report.merge(DownloadReport {
+            succeeded: vec![PathBuf::from("/Mods/Bar.zip")],
+            skipped: vec![("Baz".into(), "Bar".into())],
+            ..Default::default()
+        });
+        let failure = report.check().unwrap_err();
+        assert!(!failure.is_total());
+        assert_eq!(failure.to_string(), "1 of 2 mods failed to download");
+
+        report.canceled.push("Qux".into());
+        let failure = report.check().unwrap_err();
+        assert!(!failure.is_total());
+        assert_eq!(
+            failure.to_string(),
+            "downloads were canceled, 1 of 3 mods were not downloaded"
+        );
+    }
+
+    #[test]
+    fn test_check_declared() -> anyhow::Result<()> {
+        use std::io::Write;
+
+        use zip::{ZipWriter, write::SimpleFileOptions};
+
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("Foo.zip");
+        let mut writer = ZipWriter::new(fs::File::create(&path)?);
+        writer.start_file("everest.yaml", SimpleFileOptions::default())?;
+        writer
+            .write_all(b"- Name: Foo\n  Version: 1.0.0\n- Name: FooHelper\n  Version: 0.1.0\n")?;
+        writer.finish()?;
+        let m = local::read_mod(&path)?;
+
+        assert!(check_declared(&m, "FooHelper", None).is_ok());
+        assert!(check_declared(&m, "Foo", Some("1.0.0")).is_ok());
+        assert!(matches!(
+            check_declared(&m, "Foo", Some("1.1.0")),
+            Err(Error::UnexpectedVersion { .. })
+        ));
+        assert!(matches!(
+            check_declared(&m, "Bar", None),
+            Err(Error::UnexpectedMod { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_reasons() {
+        let e = Error::AllMirrorsFailed {
+            name: "Foo".into(),
+            errors: vec![
+                ("https://gamebanana.com/mmdl/1".into(), Error::Stalled(30)),
+                (
+                    "https://celeste.weg.fan/api/v2/download/mods/1".into(),
+                    Error::UnexpectedRange,
+                ),
+            ],
+        };
+        assert_eq!(
+            e.reasons(),
+            [
+                "gamebanana.com: no data received for 30 seconds",
+                "celeste.weg.fan: server returned another range than requested"
+            ]
+        );
+    }
+}
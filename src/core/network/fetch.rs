@@ -0,0 +1,269 @@
+//! Conditional fetching of remote data.
+//!
+//! Validators of the responses (`ETag` and `Last-Modified`) are stored with the cached copy and
+//! sent back as `If-None-Match` and `If-Modified-Since`, so the server answers
+//! `304 Not Modified` without a body when nothing has changed.
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use reqwest::{
+    Client, RequestBuilder, Response, StatusCode,
+    header::{ETAG, HeaderMap, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+};
+use rkyv::{Archive, Deserialize, Serialize, rancor};
+use tracing::{debug, warn};
+use xxhash_rust::xxh3::xxh3_64;
+
+use crate::{
+    core::{
+        cache::{self, CacheError},
+        network::retry,
+    },
+    log::anonymize,
+};
+
+/// Longest delay asked by `Retry-After` to wait for the databases.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(30);
+
+/// Validators of the response, used to ask the server if the cached copy is still current.
+#[derive(Archive, Deserialize, Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Validators {
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+impl Validators {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(String::from)
+        };
+        Self {
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        }
+    }
+
+    /// Adds the conditional headers to the request.
+    fn apply(&self, mut request: RequestBuilder) -> RequestBuilder {
+        if let Some(etag) = &self.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &self.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+        request
+    }
+}
+
+/// Response of the conditional request.
+#[derive(Debug)]
+pub enum Fetched {
+    /// The cached copy is still current.
+    NotModified,
+    Modified {
+        validators: Validators,
+        body: Vec<u8>,
+    },
+}
+
+/// Sends the request with the validators of the cached copy, if any.
+pub async fn conditional_get(
+    request: RequestBuilder,
+    validators: Option<&Validators>,
+) -> reqwest::Result<Fetched> {
+    let request = match validators {
+        Some(validators) => validators.apply(request),
+        None => request,
+    };
+    let response = send_honoring_retry_after(request)
+        .await?
+        .error_for_status()?;
+    // NOTE 304 is only expected when the validators were sent
+    if response.status() == StatusCode::NOT_MODIFIED && validators.is_some() {
+        return Ok(Fetched::NotModified);
+    }
+    let validators = Validators::from_headers(response.headers());
+    let body = response.bytes().await?.to_vec();
+    Ok(Fetched::Modified { validators, body })
+}
+
+/// Sends the request, and once again after the delay if the server asks with `Retry-After`.
+///
+/// Delays longer than [`MAX_RETRY_AFTER`] are not waited, the failure is returned instead.
+async fn send_honoring_retry_after(request: RequestBuilder) -> reqwest::Result<Response> {
+    let Some(retry) = request.try_clone() else {
+        return request.send().await;
+    };
+    let response = request.send().await?;
+    match retry::retry_after(&response) {
+        Some(delay) if delay <= MAX_RETRY_AFTER => {
+            debug!(status = %response.status(), ?delay, "server is busy, retrying");
+            tokio::time::sleep(delay).await;
+            retry.send().await
+        }
+        _ => Ok(response),
+    }
+}
+
+/// Raw response stored on disk.
+#[derive(Archive, Deserialize, Serialize, Debug)]
+struct StoredResponse {
+    url: String,
+    validators: Validators,
+    body: Vec<u8>,
+}
+
+impl StoredResponse {
+    /// Path to the stored response of the URL.
+    fn path(cache_dir: &Path, url: &str) -> PathBuf {
+        cache_dir.join(format!("{:016x}.cache", xxh3_64(url.as_bytes())))
+    }
+
+    /// Loads the stored response of the URL, `None` if it does not exist or is unusable.
+    fn load(path: &Path, url: &str) -> Option<Self> {
+        match cache::read_archived::<Self>(path) {
+            // NOTE guard against hash collisions
+            Ok(stored) if stored.url == url => Some(stored),
+            Ok(_) => None,
+            Err(CacheError::Io(_)) => None,
+            Err(e) => {
+                debug!(?e, "discarding corrupted response at {}", anonymize(path));
+                None
+            }
+        }
+    }
+
+    fn save(&self, path: &Path) -> Result<(), StoreError> {
+        let bytes = rkyv::to_bytes::<rancor::Error>(self)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, bytes)?;
+        Ok(())
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+enum StoreError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Archive(#[from] rancor::Error),
+}
+
+/// Fetches the body of the URL, revalidating the copy stored in the cache directory.
+///
+/// The stored copy is also used when the server cannot be reached.
+/// With `refresh`, the stored copy is ignored and replaced.
+pub async fn fetch_remote_data(
+    client: &Client,
+    url: &str,
+    timeout: Duration,
+    cache_dir: &Path,
+    refresh: bool,
+) -> reqwest::Result<Vec<u8>> {
+    let path = StoredResponse::path(cache_dir, url);
+    let stored = if refresh {
+        None
+    } else {
+        StoredResponse::load(&path, url)
+    };
+
+    let request = client.get(url).timeout(timeout);
+    match conditional_get(request, stored.as_ref().map(|s| &s.validators)).await {
+        Ok(Fetched::NotModified) => {
+            debug!(url, "not modified");
+            Ok(stored.map(|s| s.body).unwrap_or_default())
+        }
+        Ok(Fetched::Modified { validators, body }) => {
+            let stored = StoredResponse {
+                url: url.to_string(),
+                validators,
+                body,
+            };
+            if let Err(e) = stored.save(&path) {
+                warn!(?e, "failed to save response to {}", anonymize(&path));
+            }
+            Ok(stored.body)
+        }
+        Err(e) => match stored {
+            Some(stored) => {
+                warn!(url, "failed to fetch, using the cached copy: {}", e);
+                Ok(stored.body)
+            }
+            None => Err(e),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests_fetch {
+    use reqwest::header::HeaderValue;
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_validators_from_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(ETAG, HeaderValue::from_static("W/\"abc\""));
+        headers.insert(
+            LAST_MODIFIED,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        let validators = Validators::from_headers(&headers);
+        assert_eq!(validators.etag.as_deref(), Some("W/\"abc\""));
+        assert_eq!(
+            validators.last_modified.as_deref(),
+            Some("Wed, 21 Oct 2015 07:28:00 GMT")
+        );
+
+        let request = validators
+            .apply(Client::new().get("https://example.com"))
+            .build()
+            .unwrap();
+        assert_eq!(request.headers()[IF_NONE_MATCH], "W/\"abc\"");
+        assert_eq!(
+            request.headers()[IF_MODIFIED_SINCE],
+            "Wed, 21 Oct 2015 07:28:00 GMT"
+        );
+        assert!(
+            Validators::default()
+                .apply(Client::new().get("https://example.com"))
+                .build()
+                .unwrap()
+                .headers()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_stored_response_per_url() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let url = "https://example.com/list.json";
+        let path = StoredResponse::path(dir.path(), url);
+        assert_ne!(
+            path,
+            StoredResponse::path(dir.path(), "https://example.org/list.json")
+        );
+
+        StoredResponse {
+            url: url.to_string(),
+            validators: Validators::default(),
+            body: b"[1]".to_vec(),
+        }
+        .save(&path)?;
+        assert_eq!(StoredResponse::load(&path, url).unwrap().body, b"[1]");
+        assert!(StoredResponse::load(&path, "https://example.org/list.json").is_none());
+
+        fs::write(&path, b"truncated")?;
+        assert!(StoredResponse::load(&path, url).is_none());
+        Ok(())
+    }
+}
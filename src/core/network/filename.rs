@@ -0,0 +1,164 @@
+//! File names of the downloaded archives.
+//!
+//! The name of the mod is preferred, since Everest and the users know the mod by it.
+//! If it cannot be a file name, e.g. non-ASCII, the name given by the server is used:
+//! `Content-Disposition` of the response, then the last segment of the URL after redirects.
+use std::collections::HashSet;
+
+use reqwest::header::{CONTENT_DISPOSITION, HeaderMap};
+use url::Url;
+
+use crate::utils;
+
+/// Returns the stem of the file name given by the response, without `.zip`.
+pub fn determine_filename(headers: &HeaderMap, final_url: &Url) -> Option<String> {
+    content_disposition_filename(headers)
+        .or_else(|| final_url.path_segments()?.next_back().map(percent_decode))
+        .and_then(|name| to_stem(&name))
+}
+
+/// Reads the file name of `Content-Disposition`, preferring the encoded `filename*`.
+fn content_disposition_filename(headers: &HeaderMap) -> Option<String> {
+    let value = headers.get(CONTENT_DISPOSITION)?.to_str().ok()?;
+    let mut plain = None;
+    for param in value.split(';').map(str::trim) {
+        let Some((key, value)) = param.split_once('=') else {
+            continue;
+        };
+        match key.trim().to_ascii_lowercase().as_str() {
+            // e.g. `filename*=UTF-8''Foo%20Bar.zip`
+            "filename*" => {
+                let (_, encoded) = value.trim().rsplit_once('\'')?;
+                return Some(percent_decode(encoded));
+            }
+            "filename" => plain = Some(value.trim().trim_matches('"').to_string()),
+            _ => {}
+        }
+    }
+    plain
+}
+
+/// Strips the directories and `.zip` of the file name, and sanitizes it.
+fn to_stem(name: &str) -> Option<String> {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or(name);
+    let stem = match name.len().checked_sub(4) {
+        Some(i) if name.is_char_boundary(i) && name[i..].eq_ignore_ascii_case(".zip") => &name[..i],
+        _ => name,
+    };
+    let stem = utils::sanitize_stem(stem).ok()?;
+    (!stem.is_empty()).then_some(stem)
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// File names claimed in a batch of downloads.
+#[derive(Debug, Default)]
+pub struct FileNames {
+    /// Lowercase, since some filesystems ignore case.
+    used: HashSet<String>,
+}
+
+impl FileNames {
+    /// Returns the stem as is if it is not used yet, or with the file ID appended.
+    ///
+    /// Different mods may be sanitized to the same name, e.g. `Foo:Bar` and `Foo?Bar`.
+    pub fn claim(&mut self, stem: &str, gbid: u32) -> String {
+        if self.used.insert(stem.to_ascii_lowercase()) {
+            return stem.to_string();
+        }
+        let unique = format!("{}-{}", stem, gbid);
+        self.used.insert(unique.to_ascii_lowercase());
+        unique
+    }
+}
+
+#[cfg(test)]
+mod tests_filename {
+    use reqwest::header::HeaderValue;
+
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_content_disposition_is_preferred() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            CONTENT_DISPOSITION,
+            HeaderValue::from_static("attachment; filename=\"Plain Name.zip\""),
+        );
+        let final_url = url("https://files.gamebanana.com/mods/fromurl_1234.zip");
+        assert_eq!(
+            determine_filename(&headers, &final_url).as_deref(),
+            Some("Plain Name")
+        );
+
+        headers.insert(
+            CONTENT_DISPOSITION,
+            HeaderValue::from_static(
+                "attachment; filename=\"fallback.zip\"; filename*=UTF-8''Encoded%20Name.ZIP",
+            ),
+        );
+        assert_eq!(
+            determine_filename(&headers, &final_url).as_deref(),
+            Some("Encoded Name")
+        );
+    }
+
+    #[test]
+    fn test_url_after_redirects() {
+        let headers = HeaderMap::new();
+        assert_eq!(
+            determine_filename(
+                &headers,
+                &url("https://files.gamebanana.com/mods/my%20mod_1234.zip")
+            )
+            .as_deref(),
+            Some("my mod_1234")
+        );
+        // nothing usable
+        assert_eq!(
+            determine_filename(&headers, &url("https://example.com/")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_to_stem() {
+        assert_eq!(to_stem("../../etc/Foo.zip").as_deref(), Some("Foo"));
+        assert_eq!(to_stem("C:\\Mods\\Foo.Bar.zip").as_deref(), Some("Foo_Bar"));
+        assert_eq!(to_stem(".zip"), None);
+        assert_eq!(to_stem("ミラー.zip"), None);
+    }
+
+    #[test]
+    fn test_claim() {
+        let mut names = FileNames::default();
+        assert_eq!(names.claim("Foo_Bar", 1), "Foo_Bar");
+        assert_eq!(names.claim("foo_bar", 2), "foo_bar-2");
+        assert_eq!(names.claim("Other", 3), "Other");
+    }
+}
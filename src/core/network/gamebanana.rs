@@ -0,0 +1,347 @@
+//! Search and browsing of the mods on GameBanana through the API by maddie480.
+//!
+//! Results have richer information than the registry, like the author and download counts.
+//! The search API returns the best matches at once, so they are paginated locally. The list API
+//! is paginated by the server instead.
+use std::{str::FromStr, time::Duration};
+
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+const SEARCH_URL: &str = "https://maddie480.ovh/celeste/gamebanana-search";
+const LIST_URL: &str = "https://maddie480.ovh/celeste/gamebanana-list";
+const CATEGORIES_URL: &str = "https://maddie480.ovh/celeste/gamebanana-categories";
+const FEATURED_URL: &str = "https://maddie480.ovh/celeste/gamebanana-featured";
+
+/// Time limit of the requests.
+const SEARCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(thiserror::Error, Debug)]
+pub enum SearchError {
+    #[error("Failed to fetch mods from GameBanana")]
+    Network(#[from] reqwest::Error),
+    #[error("Failed to parse search results")]
+    Parse(#[from] serde_json::Error),
+    #[error("Failed to parse the list of mods")]
+    ParseYaml(#[from] serde_yaml_ng::Error),
+}
+
+/// Mod found on GameBanana.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ModInfo {
+    /// Category on GameBanana, e.g. "Mod" or "Tool".
+    #[serde(rename = "GameBananaType", default)]
+    pub kind: String,
+    #[serde(rename = "GameBananaId", default)]
+    pub id: u32,
+    #[serde(rename = "Name", default)]
+    pub name: String,
+    #[serde(rename = "Author", default)]
+    pub author: String,
+    /// Short description, the full text is on the page.
+    #[serde(rename = "Description", default)]
+    pub description: String,
+    #[serde(rename = "CategoryName", default)]
+    pub category: String,
+    #[serde(rename = "Downloads", default)]
+    pub downloads: u64,
+    #[serde(rename = "Likes", default)]
+    pub likes: u64,
+    #[serde(rename = "PageURL", default)]
+    pub page_url: String,
+    /// Only in the featured mods.
+    #[serde(rename = "Featured", default, skip_serializing_if = "Option::is_none")]
+    pub featured: Option<Featured>,
+}
+
+/// Rank of the featured mod on GameBanana.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Featured {
+    /// Period of the ranking, e.g. "week" or "alltime".
+    #[serde(rename = "Category")]
+    pub period: String,
+    /// Rank in the period, from 0.
+    #[serde(rename = "Position", default)]
+    pub position: u32,
+}
+
+/// Searches the mods, the best matches first.
+pub async fn search(client: &Client, query: &str) -> Result<Vec<ModInfo>, SearchError> {
+    let url = Url::parse_with_params(
+        SEARCH_URL,
+        [("q", query), ("full", "true"), ("format", "json")],
+    )
+    .expect("search URL should be valid");
+    let bytes = fetch(client, url).await?;
+    Ok(serde_json::from_slice(&bytes)?)
+}
+
+/// Fetches the featured mods of all periods, like the front page of GameBanana.
+pub async fn featured(client: &Client) -> Result<Vec<ModInfo>, SearchError> {
+    let url = Url::parse(FEATURED_URL).expect("featured URL should be valid");
+    let bytes = fetch(client, url).await?;
+    Ok(serde_yaml_ng::from_slice(&bytes)?)
+}
+
+/// Period of the featured mods.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Period {
+    Today,
+    #[default]
+    Week,
+    Month,
+    Year,
+    All,
+}
+
+impl Period {
+    /// Checks if the mod is featured in the period, the category of the featured API.
+    pub fn contains(self, featured: &Featured) -> bool {
+        let category = match self {
+            Self::Today => "today",
+            Self::Week => "week",
+            Self::Month => "month",
+            Self::Year => "year",
+            Self::All => "alltime",
+        };
+        featured.period == category
+    }
+}
+
+impl FromStr for Period {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "today" => Ok(Self::Today),
+            "week" => Ok(Self::Week),
+            "month" => Ok(Self::Month),
+            "year" => Ok(Self::Year),
+            "all" | "alltime" => Ok(Self::All),
+            _ => Err(format!(
+                "unknown period '{}', expected today, week, month, year, or all",
+                s
+            )),
+        }
+    }
+}
+
+/// Order of the mods in the list.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Sort {
+    /// Latest uploads first.
+    #[default]
+    Newest,
+    Likes,
+    Views,
+    Downloads,
+}
+
+impl Sort {
+    /// Value of the `sort` parameter of the list API.
+    fn param(self) -> &'static str {
+        match self {
+            Self::Newest => "latest",
+            Self::Likes => "likes",
+            Self::Views => "views",
+            Self::Downloads => "downloads",
+        }
+    }
+}
+
+impl FromStr for Sort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "newest" | "latest" => Ok(Self::Newest),
+            "likes" => Ok(Self::Likes),
+            "views" => Ok(Self::Views),
+            "downloads" => Ok(Self::Downloads),
+            _ => Err(format!(
+                "unknown sort '{}', expected newest, likes, views, or downloads",
+                s
+            )),
+        }
+    }
+}
+
+/// Category of the mods on GameBanana.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Category {
+    /// Type of the items, e.g. "Mod" or "Tool".
+    #[serde(rename = "itemtype")]
+    pub kind: String,
+    /// `None` for the types without categories, which are listed as a whole.
+    #[serde(rename = "categoryid", default)]
+    pub id: Option<u32>,
+    /// Name of the category, e.g. "Maps" or "Helpers".
+    #[serde(rename = "formatted")]
+    pub name: String,
+    /// Number of the mods in the category.
+    #[serde(default)]
+    pub count: u64,
+}
+
+/// Finds the category by the name, or by the item type, ignoring case.
+pub fn find_category<'a>(categories: &'a [Category], name: &str) -> Option<&'a Category> {
+    categories
+        .iter()
+        .find(|category| category.name.eq_ignore_ascii_case(name))
+        .or_else(|| {
+            categories
+                .iter()
+                .find(|category| category.id.is_none() && category.kind.eq_ignore_ascii_case(name))
+        })
+}
+
+/// Fetches the categories of the mods.
+pub async fn categories(client: &Client) -> Result<Vec<Category>, SearchError> {
+    let url = Url::parse_with_params(CATEGORIES_URL, [("version", "3")])
+        .expect("categories URL should be valid");
+    let bytes = fetch(client, url).await?;
+    Ok(serde_yaml_ng::from_slice(&bytes)?)
+}
+
+/// Fetches the page of the mods in the category, or of all mods, numbered from 1.
+pub async fn list(
+    client: &Client,
+    category: Option<&Category>,
+    sort: Sort,
+    page: u16,
+) -> Result<Vec<ModInfo>, SearchError> {
+    let page = page.to_string();
+    let mut params = vec![("sort", sort.param()), ("page", &page), ("full", "true")];
+    let id;
+    if let Some(category) = category {
+        params.push(("type", &category.kind));
+        if let Some(category_id) = category.id {
+            id = category_id.to_string();
+            params.push(("category", &id));
+        }
+    }
+    let url = Url::parse_with_params(LIST_URL, params).expect("list URL should be valid");
+    let bytes = fetch(client, url).await?;
+    // NOTE the list is in YAML, which also accepts JSON
+    Ok(serde_yaml_ng::from_slice(&bytes)?)
+}
+
+async fn fetch(client: &Client, url: Url) -> reqwest::Result<Vec<u8>> {
+    let bytes = client
+        .get(url)
+        .timeout(SEARCH_TIMEOUT)
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    Ok(bytes.to_vec())
+}
+
+/// Page of the results, numbered from 1.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Page<'a, T> {
+    pub items: &'a [T],
+    pub number: usize,
+    pub total: usize,
+}
+
+/// Slices the page of the items, `None` if the page is out of range.
+pub fn paginate<T>(items: &[T], number: usize, per_page: usize) -> Option<Page<'_, T>> {
+    let per_page = per_page.max(1);
+    let start = number.checked_sub(1)?.checked_mul(per_page)?;
+    if start >= items.len() {
+        return None;
+    }
+    let end = (start + per_page).min(items.len());
+    Some(Page {
+        items: &items[start..end],
+        number,
+        total: items.len().div_ceil(per_page),
+    })
+}
+
+#[cfg(test)]
+mod tests_gamebanana_search {
+    use super::*;
+
+    #[test]
+    fn test_parse_results() -> anyhow::Result<()> {
+        let json = r#"[
+            {
+                "GameBananaType": "Tool",
+                "GameBananaId": 6597,
+                "Name": "Speedrun Tool",
+                "Author": "DemoJameson",
+                "Description": "Save states and more",
+                "CategoryName": "Other/Misc",
+                "Downloads": 123456,
+                "Likes": 42,
+                "Views": 999999,
+                "PageURL": "https://gamebanana.com/tools/6597",
+                "Files": []
+            },
+            { "GameBananaType": "Mod", "GameBananaId": 1, "Name": "Sparse" }
+        ]"#;
+        let results: Vec<ModInfo> = serde_json::from_str(json)?;
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].kind, "Tool");
+        assert_eq!(results[0].author, "DemoJameson");
+        assert_eq!(results[0].downloads, 123456);
+        // missing fields do not fail the whole search
+        assert_eq!(results[1].name, "Sparse");
+        assert!(results[1].page_url.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_paginate() {
+        let items: Vec<u32> = (1..=25).collect();
+        let page = paginate(&items, 1, 10).unwrap();
+        assert_eq!((page.items, page.total), (&items[0..10], 3));
+        let page = paginate(&items, 3, 10).unwrap();
+        assert_eq!(page.items, &items[20..25]);
+        assert!(paginate(&items, 4, 10).is_none());
+        assert!(paginate(&items, 0, 10).is_none());
+        assert!(paginate::<u32>(&[], 1, 10).is_none());
+    }
+
+    #[test]
+    fn test_find_category() -> anyhow::Result<()> {
+        let yaml = "- itemtype: Mod\n  categoryid: 6800\n  formatted: Maps\n  count: 2100\n- itemtype: Mod\n  categoryid: 4632\n  formatted: Helpers\n  count: 300\n- itemtype: Tool\n  formatted: Tools\n  count: 40\n";
+        let categories: Vec<Category> = serde_yaml_ng::from_str(yaml)?;
+        assert_eq!(
+            find_category(&categories, "maps").and_then(|c| c.id),
+            Some(6800)
+        );
+        // NOTE the types without categories are also found by the type
+        assert_eq!(
+            find_category(&categories, "tool").map(|c| c.name.as_str()),
+            Some("Tools")
+        );
+        assert!(find_category(&categories, "mod").is_none());
+        assert_eq!("Downloads".parse(), Ok(Sort::Downloads));
+        assert!("popular".parse::<Sort>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_featured_period() -> anyhow::Result<()> {
+        let yaml = "- GameBananaId: 1\n  Name: Weekly\n  Featured:\n    Category: week\n    Position: 0\n- GameBananaId: 2\n  Name: Classic\n  Featured:\n    Category: alltime\n    Position: 3\n";
+        let mods: Vec<ModInfo> = serde_yaml_ng::from_str(yaml)?;
+        let names = |period: Period| -> Vec<&str> {
+            mods.iter()
+                .filter(|m| m.featured.as_ref().is_some_and(|f| period.contains(f)))
+                .map(|m| m.name.as_str())
+                .collect()
+        };
+        assert_eq!(
+            names("week".parse().map_err(anyhow::Error::msg)?),
+            ["Weekly"]
+        );
+        assert_eq!(names(Period::All), ["Classic"]);
+        assert!(names(Period::Month).is_empty());
+        Ok(())
+    }
+}
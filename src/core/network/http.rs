@@ -0,0 +1,244 @@
+//! Minimal HTTP/1.1 server side for the `serve` command.
+//!
+//! Only supports what a browser extension needs: one request per connection,
+//! `Content-Length` bodies, and JSON responses. Anything else is rejected.
+use serde::Serialize;
+use tokio::io::{
+    self, AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt,
+};
+
+/// Maximum size of the request line and headers.
+const MAX_HEAD_SIZE: usize = 8 * 1024;
+/// Maximum size of the request body.
+const MAX_BODY_SIZE: usize = 64 * 1024;
+
+#[derive(thiserror::Error, Debug)]
+pub enum HttpError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("request is too large")]
+    TooLarge,
+    #[error("malformed request")]
+    Malformed,
+}
+
+impl HttpError {
+    /// Status code to respond with.
+    pub fn status(&self) -> u16 {
+        match self {
+            Self::TooLarge => 413,
+            Self::Io(_) | Self::Malformed => 400,
+        }
+    }
+}
+
+/// HTTP request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    method: String,
+    path: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    /// Path without query string.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns value of the header, name is case-insensitive.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+}
+
+/// Reads a request from the stream.
+pub async fn read_request<R: AsyncRead + Unpin>(reader: R) -> Result<Request, HttpError> {
+    let mut reader = io::BufReader::new(reader.take((MAX_HEAD_SIZE + MAX_BODY_SIZE) as u64));
+    let mut head_size = 0;
+
+    let request_line = read_line(&mut reader, &mut head_size).await?;
+    let mut parts = request_line.split(' ');
+    let (Some(method), Some(target), Some(version)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return Err(HttpError::Malformed);
+    };
+    if !version.starts_with("HTTP/1.") {
+        return Err(HttpError::Malformed);
+    }
+    let path = target.split_once('?').map_or(target, |(path, _)| path);
+
+    let mut headers = Vec::new();
+    loop {
+        let line = read_line(&mut reader, &mut head_size).await?;
+        if line.is_empty() {
+            break;
+        }
+        let (name, value) = line.split_once(':').ok_or(HttpError::Malformed)?;
+        headers.push((name.trim().to_string(), value.trim().to_string()));
+    }
+
+    let mut request = Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        headers,
+        body: Vec::new(),
+    };
+
+    if request.header("transfer-encoding").is_some() {
+        // NOTE chunked encoding is not supported
+        return Err(HttpError::Malformed);
+    }
+    let length = match request.header("content-length") {
+        Some(value) => value.parse::<usize>().map_err(|_| HttpError::Malformed)?,
+        None => 0,
+    };
+    if length > MAX_BODY_SIZE {
+        return Err(HttpError::TooLarge);
+    }
+    request.body = vec![0; length];
+    reader.read_exact(&mut request.body).await?;
+
+    Ok(request)
+}
+
+/// Reads a line of the request head, and adds its size to `head_size`.
+async fn read_line<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    head_size: &mut usize,
+) -> Result<String, HttpError> {
+    let mut line = String::new();
+    *head_size += reader.read_line(&mut line).await?;
+    if *head_size > MAX_HEAD_SIZE {
+        return Err(HttpError::TooLarge);
+    }
+    if !line.ends_with('\n') {
+        return Err(HttpError::Malformed);
+    }
+    Ok(line.trim_end().to_string())
+}
+
+/// HTTP response, the body is always JSON.
+#[derive(Debug, Clone)]
+pub struct Response {
+    status: u16,
+    body: Vec<u8>,
+}
+
+impl Response {
+    pub fn json<T: Serialize>(status: u16, value: &T) -> Self {
+        Self {
+            status,
+            body: serde_json::to_vec(value).unwrap_or_default(),
+        }
+    }
+
+    /// Response with `{"error": message}` body.
+    pub fn error(status: u16, message: &str) -> Self {
+        Self::json(status, &serde_json::json!({ "error": message }))
+    }
+
+    pub fn no_content() -> Self {
+        Self {
+            status: 204,
+            body: Vec::new(),
+        }
+    }
+
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    /// Writes the response and closes the connection.
+    pub async fn write_to<W: AsyncWrite + Unpin>(&self, mut writer: W) -> io::Result<()> {
+        // NOTE allow requests from any origin since every endpoint requires the token
+        let head = format!(
+            "HTTP/1.1 {} {}\r\n\
+            Content-Type: application/json\r\n\
+            Content-Length: {}\r\n\
+            Connection: close\r\n\
+            Access-Control-Allow-Origin: *\r\n\
+            Access-Control-Allow-Headers: Authorization, Content-Type\r\n\
+            Access-Control-Allow-Methods: GET, POST, OPTIONS\r\n\r\n",
+            self.status,
+            reason_phrase(self.status),
+            self.body.len()
+        );
+        writer.write_all(head.as_bytes()).await?;
+        writer.write_all(&self.body).await?;
+        writer.shutdown().await
+    }
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        204 => "No Content",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        _ => "Internal Server Error",
+    }
+}
+
+#[cfg(test)]
+mod tests_http {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_request() -> anyhow::Result<()> {
+        let raw = b"POST /install?x=1 HTTP/1.1\r\nHost: localhost\r\nauthorization: Bearer abc\r\nContent-Length: 9\r\n\r\n{\"id\":42}";
+        let request = read_request(&raw[..]).await?;
+        assert_eq!(request.method(), "POST");
+        assert_eq!(request.path(), "/install");
+        assert_eq!(request.header("Authorization"), Some("Bearer abc"));
+        assert_eq!(request.body(), b"{\"id\":42}");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reject_malformed_request() {
+        let result = read_request(&b"GET /\r\n\r\n"[..]).await;
+        assert!(matches!(result, Err(HttpError::Malformed)));
+
+        // connection closed before the end of headers
+        let result = read_request(&b"GET / HTTP/1.1\r\nHost: a"[..]).await;
+        assert!(matches!(result, Err(HttpError::Malformed)));
+    }
+
+    #[tokio::test]
+    async fn test_reject_too_large_body() {
+        let raw = format!(
+            "POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY_SIZE + 1
+        );
+        let result = read_request(raw.as_bytes()).await;
+        assert!(matches!(result, Err(HttpError::TooLarge)));
+    }
+
+    #[tokio::test]
+    async fn test_write_response() -> anyhow::Result<()> {
+        let mut buffer = Vec::new();
+        Response::error(401, "unauthorized")
+            .write_to(&mut buffer)
+            .await?;
+        let text = String::from_utf8(buffer)?;
+        assert!(text.starts_with("HTTP/1.1 401 Unauthorized\r\n"));
+        assert!(text.ends_with("\r\n\r\n{\"error\":\"unauthorized\"}"));
+        Ok(())
+    }
+}
@@ -0,0 +1,209 @@
+//! Partial downloads kept next to the destination, resumed with `Range` requests.
+//!
+//! `{dest}.part` holds the received bytes, and `{dest}.part.meta` records the file they belong to,
+//! so the part of another version of the mod is never resumed.
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use reqwest::header::{CONTENT_RANGE, ETAG, HeaderMap, LAST_MODIFIED};
+use rkyv::{Archive, Deserialize, Serialize, rancor};
+use tracing::debug;
+
+use crate::{core::cache, log::anonymize, platform};
+
+/// Record of the file being downloaded.
+#[derive(Archive, Deserialize, Serialize, Debug, PartialEq, Eq)]
+pub struct PartMeta {
+    /// Expected checksums, sorted.
+    checksums: Vec<u64>,
+    size: u64,
+    /// Strong `ETag` or `Last-Modified` of the response, sent back as `If-Range`.
+    validator: Option<String>,
+}
+
+impl PartMeta {
+    pub fn new(checksums: Vec<u64>, size: u64, headers: &HeaderMap) -> Self {
+        let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+        // NOTE weak ETags cannot be used for `If-Range`
+        let validator = header(ETAG)
+            .filter(|etag| !etag.starts_with("W/"))
+            .or_else(|| header(LAST_MODIFIED))
+            .map(String::from);
+        Self {
+            checksums,
+            size,
+            validator,
+        }
+    }
+}
+
+/// Partial file of the destination.
+#[derive(Debug)]
+pub struct PartialFile {
+    part: PathBuf,
+    meta: PathBuf,
+}
+
+impl PartialFile {
+    pub fn new(dest: &Path) -> Self {
+        let mut part = dest.as_os_str().to_owned();
+        part.push(".part");
+        let mut meta = part.clone();
+        meta.push(".meta");
+        Self {
+            part: part.into(),
+            meta: meta.into(),
+        }
+    }
+
+    pub fn part_path(&self) -> &Path {
+        &self.part
+    }
+
+    /// Returns the offset to resume from and the validator of the part, or `None` if it cannot
+    /// be resumed since it does not exist, belongs to another file, or is already complete.
+    pub fn resumable(&self, checksums: &[u64], size: u64) -> Option<(u64, Option<String>)> {
+        let meta = self.load_meta()?;
+        if meta.checksums != checksums || meta.size != size {
+            debug!(path = %anonymize(&self.part), "part belongs to another file");
+            return None;
+        }
+        let received = fs::metadata(&self.part).ok()?.len();
+        (received > 0 && received < size).then_some((received, meta.validator))
+    }
+
+    fn load_meta(&self) -> Option<PartMeta> {
+        cache::read_archived(&self.meta).ok()
+    }
+
+    pub fn save_meta(&self, meta: &PartMeta) -> io::Result<()> {
+        let bytes = rkyv::to_bytes::<rancor::Error>(meta).map_err(io::Error::other)?;
+        fs::write(&self.meta, bytes)
+    }
+
+    /// Replaces the destination with the completed part atomically.
+    ///
+    /// The part is next to the destination, so the rename never crosses filesystems, and the
+    /// old archive stays valid until it is replaced. Permissions of the old archive are kept.
+    pub fn finish(&self, dest: &Path) -> io::Result<()> {
+        if let Ok(old) = fs::metadata(dest) {
+            fs::set_permissions(&self.part, old.permissions())?;
+        }
+        fs::rename(&self.part, dest)?;
+        // NOTE persists the rename itself, in case of a power loss right after the update
+        if let Some(parent) = dest.parent() {
+            platform::sync_dir(parent)?;
+        }
+        remove_if_exists(&self.meta)
+    }
+
+    /// Removes the part, e.g. when it turned out to be corrupted.
+    pub fn discard(&self) -> io::Result<()> {
+        remove_if_exists(&self.part)?;
+        remove_if_exists(&self.meta)
+    }
+}
+
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Returns the first byte of `Content-Range` in the partial response, e.g. `bytes 100-199/200`.
+pub fn content_range_start(headers: &HeaderMap) -> Option<u64> {
+    let value = headers.get(CONTENT_RANGE)?.to_str().ok()?;
+    let range = value.strip_prefix("bytes ")?;
+    let (start, _) = range.split_once('-')?;
+    start.trim().parse().ok()
+}
+
+/// Returns the size of the whole file in `Content-Range`, `None` if it is unknown (`*`).
+pub fn content_range_total(headers: &HeaderMap) -> Option<u64> {
+    let value = headers.get(CONTENT_RANGE)?.to_str().ok()?;
+    let (_, total) = value.strip_prefix("bytes ")?.rsplit_once('/')?;
+    total.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests_partial {
+    #[cfg(unix)]
+    use std::os::unix::fs::PermissionsExt;
+
+    use reqwest::header::HeaderValue;
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_meta_validator() {
+        let mut headers = HeaderMap::new();
+        headers.insert(ETAG, HeaderValue::from_static("W/\"weak\""));
+        headers.insert(
+            LAST_MODIFIED,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        let meta = PartMeta::new(vec![1], 10, &headers);
+        assert_eq!(
+            meta.validator.as_deref(),
+            Some("Wed, 21 Oct 2015 07:28:00 GMT")
+        );
+
+        headers.insert(ETAG, HeaderValue::from_static("\"strong\""));
+        let meta = PartMeta::new(vec![1], 10, &headers);
+        assert_eq!(meta.validator.as_deref(), Some("\"strong\""));
+    }
+
+    #[test]
+    fn test_resumable() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let dest = dir.path().join("Foo.zip");
+        let partial = PartialFile::new(&dest);
+        assert_eq!(partial.part_path(), dir.path().join("Foo.zip.part"));
+        assert_eq!(partial.resumable(&[1], 10), None);
+
+        fs::write(partial.part_path(), b"12345")?;
+        let mut headers = HeaderMap::new();
+        headers.insert(ETAG, HeaderValue::from_static("\"abc\""));
+        partial.save_meta(&PartMeta::new(vec![1], 10, &headers))?;
+        assert_eq!(
+            partial.resumable(&[1], 10),
+            Some((5, Some("\"abc\"".into())))
+        );
+        // another version of the mod
+        assert_eq!(partial.resumable(&[2], 10), None);
+
+        fs::write(partial.part_path(), b"0123456789")?;
+        partial.finish(&dest)?;
+        assert_eq!(fs::read(&dest)?, b"0123456789");
+
+        // replaces the old archive, keeping its permissions
+        #[cfg(unix)]
+        fs::set_permissions(&dest, fs::Permissions::from_mode(0o640))?;
+        fs::write(partial.part_path(), b"9876543210")?;
+        partial.finish(&dest)?;
+        assert_eq!(fs::read(&dest)?, b"9876543210");
+        #[cfg(unix)]
+        assert_eq!(fs::metadata(&dest)?.permissions().mode() & 0o777, 0o640);
+        assert!(!partial.part_path().exists());
+        assert_eq!(partial.resumable(&[1], 10), None);
+        partial.discard()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_content_range_start() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(content_range_start(&headers), None);
+        headers.insert(CONTENT_RANGE, HeaderValue::from_static("bytes 100-199/200"));
+        assert_eq!(content_range_start(&headers), Some(100));
+        headers.insert(CONTENT_RANGE, HeaderValue::from_static("bytes */200"));
+        assert_eq!(content_range_start(&headers), None);
+        assert_eq!(content_range_total(&headers), Some(200));
+        headers.insert(CONTENT_RANGE, HeaderValue::from_static("bytes 0-99/*"));
+        assert_eq!(content_range_total(&headers), None);
+    }
+}
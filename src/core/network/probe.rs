@@ -0,0 +1,243 @@
+//! Mirror health-check.
+//!
+//! Measures latency of the mirrors with HEAD requests for a known file.
+//! Results are cached for a few hours and used to order mirrors for `--mirror-priority auto`.
+use std::{
+    fs,
+    path::Path,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use futures_util::future::join_all;
+use reqwest::Client;
+use rkyv::{Archive, Deserialize, Serialize, rancor};
+use tracing::{debug, warn};
+
+use crate::{
+    core::{
+        cache::{self, CacheError},
+        mirrorlist::{Mirror, MirrorTable},
+    },
+    log::anonymize,
+};
+
+/// GameBanana file ID used for the health-check, which is hosted on all built-in mirrors.
+pub const DEFAULT_PROBE_GBID: u32 = 1298450;
+
+/// Time limit of each request.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Measured results are reused for this duration.
+pub const CACHE_TTL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Result of the health-check of a mirror.
+#[derive(Debug)]
+pub struct ProbeResult {
+    pub mirror: Mirror,
+    pub latency: Duration,
+    /// HTTP status code, or the error message if the request failed.
+    pub outcome: Result<u16, String>,
+}
+
+impl ProbeResult {
+    pub fn is_ok(&self) -> bool {
+        matches!(self.outcome, Ok(status) if (200..400).contains(&status))
+    }
+}
+
+/// Sends HEAD requests to all mirrors concurrently.
+pub async fn probe(
+    client: &Client,
+    mirrors: &[Mirror],
+    table: &MirrorTable,
+    gbid: u32,
+) -> Vec<ProbeResult> {
+    let tasks = mirrors.iter().map(|mirror| async move {
+        let start = Instant::now();
+        let outcome = match mirror.url_for_id(gbid, table) {
+            Ok(url) => client
+                .head(url)
+                .timeout(PROBE_TIMEOUT)
+                .send()
+                .await
+                .map(|response| response.status().as_u16())
+                .map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        let result = ProbeResult {
+            mirror: mirror.clone(),
+            latency: start.elapsed(),
+            outcome,
+        };
+        debug!(?result, "probed");
+        result
+    });
+    join_all(tasks).await
+}
+
+/// Cached latency of the mirrors.
+#[derive(Archive, Deserialize, Serialize, Debug, Default)]
+pub struct LatencyCache {
+    /// UNIX time in seconds.
+    measured_at: u64,
+    entries: Vec<LatencyEntry>,
+}
+
+#[derive(Archive, Deserialize, Serialize, Debug)]
+struct LatencyEntry {
+    mirror: String,
+    /// `None` if the mirror failed.
+    latency_ms: Option<u64>,
+}
+
+impl LatencyCache {
+    pub fn from_results(results: &[ProbeResult]) -> Self {
+        let entries = results
+            .iter()
+            .map(|result| LatencyEntry {
+                mirror: result.mirror.to_string(),
+                latency_ms: result.is_ok().then_some(result.latency.as_millis() as u64),
+            })
+            .collect();
+        Self {
+            measured_at: unix_now(),
+            entries,
+        }
+    }
+
+    /// Checks if the cache is not expired and covers all given mirrors.
+    fn is_fresh(&self, mirrors: &[Mirror], now: u64) -> bool {
+        now.saturating_sub(self.measured_at) < CACHE_TTL.as_secs()
+            && mirrors
+                .iter()
+                .all(|mirror| self.entries.iter().any(|e| e.mirror == mirror.to_string()))
+    }
+
+    /// Orders the mirrors from fastest to slowest. Failed mirrors are kept at the end as a last resort.
+    fn order(&self, mirrors: &[Mirror]) -> Vec<Mirror> {
+        let mut ordered = mirrors.to_vec();
+        ordered.sort_by_key(|mirror| {
+            self.entries
+                .iter()
+                .find(|e| e.mirror == mirror.to_string())
+                .and_then(|e| e.latency_ms)
+                .unwrap_or(u64::MAX)
+        });
+        ordered
+    }
+
+    pub fn load(path: &Path) -> Result<Self, CacheError> {
+        cache::read_archived(path)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), CacheError> {
+        let bytes = rkyv::to_bytes::<rancor::Error>(self)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, bytes)?;
+        Ok(())
+    }
+}
+
+/// Returns all mirrors ordered by latency, measures them if the cache is stale.
+pub async fn auto_order(client: &Client, table: &MirrorTable, cache_path: &Path) -> Vec<Mirror> {
+    let mirrors = table.all_mirrors();
+
+    if let Ok(cache) = LatencyCache::load(cache_path)
+        && cache.is_fresh(&mirrors, unix_now())
+    {
+        debug!("using cached mirror latency");
+        return cache.order(&mirrors);
+    }
+
+    debug!("measuring mirror latency");
+    let results = probe(client, &mirrors, table, DEFAULT_PROBE_GBID).await;
+    let cache = LatencyCache::from_results(&results);
+    if let Err(e) = cache.save(cache_path) {
+        warn!(
+            ?e,
+            "failed to save mirror latency to {}",
+            anonymize(cache_path)
+        );
+    }
+    cache.order(&mirrors)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests_probe {
+    use super::*;
+
+    fn cache(measured_at: u64) -> LatencyCache {
+        LatencyCache {
+            measured_at,
+            entries: vec![
+                LatencyEntry {
+                    mirror: "gb".into(),
+                    latency_ms: Some(300),
+                },
+                LatencyEntry {
+                    mirror: "jade".into(),
+                    latency_ms: None,
+                },
+                LatencyEntry {
+                    mirror: "otobot".into(),
+                    latency_ms: Some(50),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_order_by_latency() {
+        let mirrors = [Mirror::Gb, Mirror::Jade, Mirror::Otobot, Mirror::Wegfan];
+        assert_eq!(
+            cache(0).order(&mirrors),
+            vec![Mirror::Otobot, Mirror::Gb, Mirror::Jade, Mirror::Wegfan]
+        );
+    }
+
+    #[test]
+    fn test_is_fresh() {
+        let mirrors = [Mirror::Gb, Mirror::Otobot];
+        let now = 1_000_000;
+        assert!(cache(now - 60).is_fresh(&mirrors, now));
+        assert!(!cache(now - CACHE_TTL.as_secs()).is_fresh(&mirrors, now));
+
+        // new mirror is not measured yet
+        assert!(!cache(now).is_fresh(&[Mirror::Wegfan], now));
+    }
+
+    #[test]
+    fn test_save_and_load() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("state").join("mirrors.cache");
+        cache(42).save(&path)?;
+        let loaded = LatencyCache::load(&path)?;
+        assert_eq!(loaded.measured_at, 42);
+        assert_eq!(loaded.entries.len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_reload_orders_like_measured() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("mirrors.cache");
+        let mirrors = [Mirror::Gb, Mirror::Jade, Mirror::Otobot];
+        let now = unix_now();
+        cache(now).save(&path)?;
+
+        // an empty cache would be stale and measure the mirrors again
+        let loaded = LatencyCache::load(&path)?;
+        assert!(loaded.is_fresh(&mirrors, now));
+        assert_eq!(loaded.order(&mirrors), cache(now).order(&mirrors));
+        Ok(())
+    }
+}
@@ -0,0 +1,163 @@
+//! Bandwidth limiting shared by all of the concurrent downloads.
+//!
+//! A token bucket refilled at the given rate, each received chunk takes the tokens of its size.
+//! Chunks larger than the remaining tokens are let through, and the deficit is paid by waiting.
+use std::{
+    str::FromStr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serde::Deserialize;
+
+use crate::utils;
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum ParseRateError {
+    #[error("rate must be a positive size per second like '2MiB' or '500K', but got '{0}'")]
+    Invalid(String),
+}
+
+/// Bytes per second, like `2MiB` or `500K`.
+///
+/// Units are binary like curl's `--limit-rate`, i.e. `K`, `KB`, and `KiB` are all 1024 bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "RawRate")]
+pub struct Rate(u64);
+
+/// Accepts both `"2MiB"` and `2097152` in the config file.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawRate {
+    Bytes(u64),
+    Text(String),
+}
+
+impl TryFrom<RawRate> for Rate {
+    type Error = ParseRateError;
+
+    fn try_from(value: RawRate) -> Result<Self, Self::Error> {
+        match value {
+            RawRate::Bytes(0) => Err(ParseRateError::Invalid(0.to_string())),
+            RawRate::Bytes(bytes) => Ok(Self(bytes)),
+            RawRate::Text(text) => text.parse(),
+        }
+    }
+}
+
+impl FromStr for Rate {
+    type Err = ParseRateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim();
+        let trimmed = trimmed.strip_suffix("/s").unwrap_or(trimmed);
+        utils::parse_size(trimmed)
+            .map(Self)
+            .ok_or_else(|| ParseRateError::Invalid(s.to_string()))
+    }
+}
+
+impl Rate {
+    pub fn bytes_per_sec(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Token bucket shared by the download tasks.
+#[derive(Debug)]
+pub struct RateLimiter {
+    rate: f64,
+    state: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    /// Available bytes, negative if the chunks have been let through in advance.
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(rate: Rate) -> Self {
+        let rate = rate.bytes_per_sec() as f64;
+        Self {
+            rate,
+            // NOTE starts empty so that the concurrent downloads do not burst at once
+            state: Mutex::new(Bucket {
+                tokens: 0.0,
+                refilled_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Takes the tokens of the received bytes, returns how long to wait for them.
+    fn reserve(&self, bytes: u64, now: Instant) -> Duration {
+        let mut bucket = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let elapsed = now.saturating_duration_since(bucket.refilled_at);
+        // NOTE the burst is limited to one second of the rate
+        bucket.tokens = (bucket.tokens + elapsed.as_secs_f64() * self.rate).min(self.rate);
+        bucket.refilled_at = now;
+        bucket.tokens -= bytes as f64;
+        if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.tokens / self.rate)
+        }
+    }
+
+    /// Waits until the received bytes are within the rate.
+    pub async fn acquire(&self, bytes: u64) {
+        let delay = self.reserve(bytes, Instant::now());
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_ratelimit {
+    use super::*;
+
+    #[test]
+    fn test_parse_rate() {
+        for (input, expected) in [
+            ("2MiB", 2 * 1024 * 1024),
+            ("2M", 2 * 1024 * 1024),
+            ("500k", 500 * 1024),
+            ("500 KB/s", 500 * 1024),
+            ("1.5MiB", 1536 * 1024),
+            ("1048576", 1024 * 1024),
+            ("1G", 1024 * 1024 * 1024),
+        ] {
+            assert_eq!(input.parse(), Ok(Rate(expected)), "{}", input);
+        }
+        for input in ["", "0", "0.1", "MiB", "2TiB", "-1M", "1.2.3M"] {
+            assert!(input.parse::<Rate>().is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_deserialize_rate() {
+        let rate: Rate = serde_yaml_ng::from_str("2MiB").expect("should be parsed");
+        assert_eq!(rate, Rate(2 * 1024 * 1024));
+        let rate: Rate = serde_yaml_ng::from_str("1024").expect("should be parsed");
+        assert_eq!(rate, Rate(1024));
+        assert!(serde_yaml_ng::from_str::<Rate>("0").is_err());
+    }
+
+    #[test]
+    fn test_reserve() {
+        let limiter = RateLimiter::new(Rate(1000));
+        let start = limiter.state.lock().unwrap().refilled_at;
+
+        // the bucket starts empty
+        assert_eq!(limiter.reserve(500, start), Duration::from_millis(500));
+        // the deficit is carried over to the other tasks
+        assert_eq!(limiter.reserve(500, start), Duration::from_secs(1));
+
+        // refilled up to one second of the rate, not more
+        let later = start + Duration::from_secs(10);
+        assert_eq!(limiter.reserve(1000, later), Duration::ZERO);
+        assert_eq!(limiter.reserve(100, later), Duration::from_millis(100));
+    }
+}
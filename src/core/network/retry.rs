@@ -0,0 +1,192 @@
+//! Retrying of transient failures on the same mirror, with exponential backoff.
+//!
+//! Flaky connections often recover in a few seconds, so failing over to the next mirror at once
+//! would burn through all of them. Permanent failures like 404 still fail over immediately.
+//! Servers which are busy may tell how long to wait with `Retry-After`, which is honored.
+use std::{
+    collections::hash_map::RandomState,
+    hash::BuildHasher,
+    time::{Duration, Instant},
+};
+
+use reqwest::{
+    Response, StatusCode,
+    header::{HeaderMap, RETRY_AFTER},
+};
+
+/// Upper bound of the delay between attempts.
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// How many times and how long to retry on a mirror.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Number of retries after the first attempt.
+    retries: u8,
+    /// Delay before the first retry, doubled on every retry.
+    delay: Duration,
+    /// No retry is started after this duration since the first attempt.
+    timeout: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: 2,
+            delay: Duration::from_secs(1),
+            timeout: Duration::from_secs(120),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn new(retries: u8, delay: Duration, timeout: Duration) -> Self {
+        Self {
+            retries,
+            delay,
+            timeout,
+        }
+    }
+
+    pub fn retries(&self) -> u8 {
+        self.retries
+    }
+
+    pub fn delay(&self) -> Duration {
+        self.delay
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Returns the delay before the given retry (numbered from 1) without jitter.
+    fn backoff(&self, retry: u8) -> Duration {
+        let factor = 1u32.checked_shl(u32::from(retry.saturating_sub(1)));
+        factor
+            .and_then(|factor| self.delay.checked_mul(factor))
+            .map_or(MAX_DELAY, |delay| delay.min(MAX_DELAY))
+    }
+
+    /// Returns the delay before the next retry, or `None` if the retries are exhausted.
+    ///
+    /// The delay is randomized to the range of its half to full, so the concurrent downloads
+    /// failed at once do not retry at once either. It is extended to the delay asked by the
+    /// server, if any, and the mirror is given up if that exceeds the timeout.
+    pub fn next_delay(
+        &self,
+        retry: u8,
+        started: Instant,
+        retry_after: Option<Duration>,
+    ) -> Option<Duration> {
+        if retry > self.retries {
+            return None;
+        }
+        let backoff = self.backoff(retry);
+        let delay = backoff / 2 + backoff.mul_f64(jitter() / 2.0);
+        let delay = retry_after.map_or(delay, |retry_after| retry_after.max(delay));
+        (started.elapsed() + delay < self.timeout).then_some(delay)
+    }
+}
+
+/// Returns a random number in `[0, 1)`, good enough to spread the retries.
+fn jitter() -> f64 {
+    let random = RandomState::new().hash_one(Instant::now());
+    (random >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Checks if the status may succeed on retry, i.e. rate limiting or server errors.
+pub fn is_retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Returns the delay asked by `Retry-After` of the rate limited or unavailable response.
+pub fn retry_after(response: &Response) -> Option<Duration> {
+    match response.status() {
+        StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE => {
+            parse_retry_after(response.headers())
+        }
+        _ => None,
+    }
+}
+
+/// Parses `Retry-After` in seconds.
+///
+/// NOTE the form of HTTP date is ignored, the registry and the mirrors send seconds
+fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?;
+    value.trim().parse().ok().map(Duration::from_secs)
+}
+
+/// Checks if the error may not happen again, i.e. timeouts, connection failures, interrupted
+/// bodies, or retryable statuses.
+pub fn is_retryable(e: &reqwest::Error) -> bool {
+    match e.status() {
+        Some(status) => is_retryable_status(status),
+        None => e.is_timeout() || e.is_connect() || e.is_request() || e.is_body(),
+    }
+}
+
+#[cfg(test)]
+mod tests_retry {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy::new(10, Duration::from_secs(1), Duration::from_secs(600));
+        let delays: Vec<u64> = (1..=7).map(|n| policy.backoff(n).as_secs()).collect();
+        assert_eq!(delays, [1, 2, 4, 8, 16, 30, 30]);
+        assert_eq!(policy.backoff(u8::MAX), MAX_DELAY);
+    }
+
+    #[test]
+    fn test_next_delay() {
+        let policy = RetryPolicy::new(2, Duration::from_secs(4), Duration::from_secs(60));
+        let started = Instant::now();
+        for retry in 1..=2 {
+            let delay = policy.next_delay(retry, started, None).unwrap();
+            let backoff = policy.backoff(retry);
+            assert!(backoff / 2 <= delay && delay <= backoff, "{:?}", delay);
+        }
+        // retries are exhausted
+        assert_eq!(policy.next_delay(3, started, None), None);
+
+        // the server may ask for a longer delay, but not beyond the timeout
+        let asked = Some(Duration::from_secs(10));
+        assert_eq!(policy.next_delay(1, started, asked), asked);
+        assert_eq!(
+            policy.next_delay(1, started, Some(Duration::from_secs(90))),
+            None
+        );
+
+        // total timeout would be exceeded
+        let policy = RetryPolicy::new(2, Duration::from_secs(4), Duration::from_secs(1));
+        assert_eq!(policy.next_delay(1, started, None), None);
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(parse_retry_after(&headers), None);
+        headers.insert(RETRY_AFTER, "120".parse().unwrap());
+        assert_eq!(parse_retry_after(&headers), Some(Duration::from_secs(120)));
+        headers.insert(
+            RETRY_AFTER,
+            "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap(),
+        );
+        assert_eq!(parse_retry_after(&headers), None);
+    }
+
+    #[test]
+    fn test_is_retryable_status() {
+        for status in [
+            StatusCode::TOO_MANY_REQUESTS,
+            StatusCode::BAD_GATEWAY,
+            StatusCode::SERVICE_UNAVAILABLE,
+        ] {
+            assert!(is_retryable_status(status), "{}", status);
+        }
+        for status in [StatusCode::NOT_FOUND, StatusCode::FORBIDDEN] {
+            assert!(!is_retryable_status(status), "{}", status);
+        }
+    }
+}
@@ -0,0 +1,263 @@
+//! Offloading of unused mods to the archive directory, usually on a bigger and slower disk.
+//!
+//! A mod is unused if it has been disabled in Everest's blacklist for a while,
+//! and no enabled mod depends on it. Nothing is deleted, archives are only moved.
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use rkyv::{Archive, Deserialize, Serialize, rancor};
+
+use crate::core::{
+    LocalMod,
+    cache::{self, CacheError},
+};
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum ParseAgeError {
+    #[error("age must be a number of days or weeks like '90d' or '12w', but got '{0}'")]
+    Invalid(String),
+}
+
+/// Parses the age like `90d`, `12w`, or `90` (days).
+pub fn parse_age(s: &str) -> Result<Duration, ParseAgeError> {
+    let invalid = || ParseAgeError::Invalid(s.to_string());
+    let (number, unit) = match s.strip_suffix('d') {
+        Some(number) => (number, 1),
+        None => match s.strip_suffix('w') {
+            Some(number) => (number, 7),
+            None => (s, 1),
+        },
+    };
+    let days = number.parse::<u64>().map_err(|_| invalid())?;
+    days.checked_mul(unit * SECS_PER_DAY)
+        .map(Duration::from_secs)
+        .ok_or_else(invalid)
+}
+
+/// Times when the mods were first seen disabled, kept in the state directory.
+///
+/// Access time of the archives cannot tell the usage, since every scan of the manifests updates it.
+#[derive(Archive, Deserialize, Serialize, Debug, Default)]
+pub struct DisabledSince {
+    /// File name to UNIX time in seconds.
+    entries: BTreeMap<String, u64>,
+}
+
+impl DisabledSince {
+    /// Loads the record, returns an empty one if it does not exist or is broken.
+    pub fn load(path: &Path) -> Self {
+        cache::read_archived(path).unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), CacheError> {
+        let bytes = rkyv::to_bytes::<rancor::Error>(self)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    /// Syncs with the current blacklist, newly disabled mods are recorded with `seen_at`.
+    pub fn sync(&mut self, disabled: &HashSet<String>, seen_at: u64) {
+        self.entries.retain(|name, _| disabled.contains(name));
+        for name in disabled {
+            self.entries.entry(name.clone()).or_insert(seen_at);
+        }
+    }
+}
+
+/// Finds the archives of the mods disabled before `cutoff`, which no enabled mod depends on.
+pub fn find_unused(mods: &[LocalMod], disabled: &DisabledSince, cutoff: u64) -> Vec<PathBuf> {
+    let file_name = |m: &LocalMod| {
+        m.file()
+            .path()
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    };
+    let (disabled_mods, enabled): (Vec<&LocalMod>, Vec<&LocalMod>) = mods
+        .iter()
+        .partition(|m| disabled.entries.contains_key(&file_name(m)));
+
+    // Collects all dependencies of the enabled mods, including transitive ones
+    let by_name: HashMap<&str, &LocalMod> = mods
+        .iter()
+        .flat_map(|m| m.declared().map(move |(name, _)| (name, m)))
+        .collect();
+    let mut required = HashSet::new();
+    let mut queue: VecDeque<&LocalMod> = enabled.into_iter().collect();
+    while let Some(current) = queue.pop_front() {
+        let deps = current.declared_dependencies().flat_map(|(_, deps)| deps);
+        for dep in deps {
+            if required.insert(dep.name())
+                && let Some(m) = by_name.get(dep.name())
+            {
+                queue.push_back(m);
+            }
+        }
+    }
+
+    let mut unused: Vec<PathBuf> = disabled_mods
+        .into_iter()
+        .filter(|m| !m.declared().any(|(name, _)| required.contains(name)))
+        .filter(|m| disabled.entries[&file_name(m)] < cutoff)
+        .map(|m| m.file().path().to_path_buf())
+        .collect();
+    unused.sort();
+    unused
+}
+
+/// Lists the archives in the archive directory.
+pub fn list_archived(archive_dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut archives: Vec<PathBuf> = fs::read_dir(archive_dir)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+        })
+        .collect();
+    archives.sort();
+    Ok(archives)
+}
+
+/// Finds the archive by file name, with or without `.zip`, ignoring case.
+pub fn find_archived(archives: &[PathBuf], name: &str) -> Option<PathBuf> {
+    archives
+        .iter()
+        .find(|path| {
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            file_name.eq_ignore_ascii_case(name) || stem.eq_ignore_ascii_case(name)
+        })
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests_offload {
+    use crate::core::{dependency::Dependency, local::ModFile};
+
+    use super::*;
+
+    fn local_mod(name: &str, deps: &[&str]) -> LocalMod {
+        let file = ModFile::new_unchecked(PathBuf::from(format!("/Mods/{}.zip", name)));
+        let deps = deps
+            .iter()
+            .map(|dep| serde_yaml_ng::from_str::<Dependency>(&format!("Name: {}", dep)).unwrap())
+            .collect();
+        LocalMod::new(file, name.to_string(), "1.0.0".to_string()).with_dependencies(deps)
+    }
+
+    #[test]
+    fn test_parse_age() {
+        assert_eq!(parse_age("90d"), Ok(Duration::from_secs(90 * SECS_PER_DAY)));
+        assert_eq!(parse_age("2w"), Ok(Duration::from_secs(14 * SECS_PER_DAY)));
+        assert_eq!(parse_age("30"), Ok(Duration::from_secs(30 * SECS_PER_DAY)));
+        for input in ["", "d", "-1d", "3m", "99999999999999999w"] {
+            assert_eq!(
+                parse_age(input),
+                Err(ParseAgeError::Invalid(input.into())),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_find_unused() {
+        let mods = [
+            local_mod("Map", &["Helper"]),
+            local_mod("Helper", &["Library"]),
+            local_mod("Library", &[]),
+            local_mod("OldMap", &["OldHelper"]),
+            local_mod("OldHelper", &[]),
+            local_mod("Recent", &[]),
+        ];
+        let mut disabled = DisabledSince::default();
+        let names =
+            ["Helper", "Library", "OldMap", "OldHelper"].map(|name| format!("{}.zip", name));
+        disabled.sync(&names.into(), 100);
+        let names = ["Helper", "Library", "OldMap", "OldHelper", "Recent"]
+            .map(|name| format!("{}.zip", name));
+        disabled.sync(&names.into(), 200);
+        assert_eq!(disabled.entries["OldMap.zip"], 100);
+        assert_eq!(disabled.entries["Recent.zip"], 200);
+
+        // Helper and Library are required by the enabled Map, even though disabled
+        assert_eq!(
+            find_unused(&mods, &disabled, 150),
+            vec![
+                PathBuf::from("/Mods/OldHelper.zip"),
+                PathBuf::from("/Mods/OldMap.zip")
+            ]
+        );
+
+        // re-enabled mods are forgotten
+        disabled.sync(&HashSet::from(["OldMap.zip".to_string()]), 300);
+        assert_eq!(
+            find_unused(&mods, &disabled, 150),
+            vec![PathBuf::from("/Mods/OldMap.zip")]
+        );
+    }
+
+    #[test]
+    fn test_disabled_since_roundtrip() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("disabled.cache");
+        assert!(DisabledSince::load(&path).entries.is_empty());
+
+        let mut disabled = DisabledSince::default();
+        disabled.sync(&HashSet::from(["Foo.zip".to_string()]), 42);
+        disabled.save(&path)?;
+        assert_eq!(DisabledSince::load(&path).entries["Foo.zip"], 42);
+        Ok(())
+    }
+
+    #[test]
+    fn test_disabled_since_survives_runs() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("disabled.cache");
+        let mods = [local_mod("OldMap", &[]), local_mod("Map", &[])];
+        let old = HashSet::from(["OldMap.zip".to_string()]);
+
+        let mut disabled = DisabledSince::default();
+        disabled.sync(&old, 100);
+        disabled.save(&path)?;
+
+        // the next run keeps the first time, which a lost record would reset to now
+        let mut disabled = DisabledSince::load(&path);
+        disabled.sync(&old, 1000);
+        disabled.save(&path)?;
+        assert_eq!(
+            find_unused(&mods, &DisabledSince::load(&path), 500),
+            vec![PathBuf::from("/Mods/OldMap.zip")]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_archived() {
+        let archives = [
+            PathBuf::from("/archive/SpeedrunTool.zip"),
+            PathBuf::from("/archive/Other.zip"),
+        ];
+        for name in ["SpeedrunTool", "speedruntool.zip"] {
+            assert_eq!(
+                find_archived(&archives, name),
+                Some(archives[0].clone()),
+                "{}",
+                name
+            );
+        }
+        assert_eq!(find_archived(&archives, "Speedrun"), None);
+    }
+}
@@ -0,0 +1,229 @@
+//! Trash directory for the archives replaced or deleted by hultra, so they can be recovered.
+//!
+//! Each archive is prefixed with the time it was trashed, e.g. `1700000000-Foo.zip`, so the same
+//! file name can be trashed many times. The oldest ones are removed when the size exceeds the limit.
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::Deserialize;
+use tracing::{debug, warn};
+
+use crate::{log::anonymize, utils};
+
+/// Default size of the trash.
+const DEFAULT_LIMIT: u64 = 1024 * 1024 * 1024;
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum ParseSizeError {
+    #[error("size must be a positive size like '1GiB' or '500M', but got '{0}'")]
+    Invalid(String),
+}
+
+/// Maximum size of the trash, like `1GiB` or `500M`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "RawSize")]
+pub struct SizeLimit(u64);
+
+/// Accepts both `"1GiB"` and `1073741824` in the config file.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawSize {
+    Bytes(u64),
+    Text(String),
+}
+
+impl TryFrom<RawSize> for SizeLimit {
+    type Error = ParseSizeError;
+
+    fn try_from(value: RawSize) -> Result<Self, Self::Error> {
+        match value {
+            RawSize::Bytes(0) => Err(ParseSizeError::Invalid(0.to_string())),
+            RawSize::Bytes(bytes) => Ok(Self(bytes)),
+            RawSize::Text(text) => text.parse(),
+        }
+    }
+}
+
+impl FromStr for SizeLimit {
+    type Err = ParseSizeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        utils::parse_size(s)
+            .map(Self)
+            .ok_or_else(|| ParseSizeError::Invalid(s.to_string()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trash {
+    dir: PathBuf,
+    limit: u64,
+}
+
+impl Trash {
+    pub fn new(dir: PathBuf, limit: Option<SizeLimit>) -> Self {
+        Self {
+            dir,
+            limit: limit.map_or(DEFAULT_LIMIT, |limit| limit.0),
+        }
+    }
+
+    /// Moves the file into the trash, returns the path in the trash.
+    pub fn put(&self, path: &Path) -> io::Result<PathBuf> {
+        let dest = self.dest(path)?;
+        match fs::rename(path, &dest) {
+            Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+                fs::copy(path, &dest)?;
+                fs::remove_file(path)?;
+            }
+            result => result?,
+        }
+        self.collect_garbage();
+        Ok(dest)
+    }
+
+    /// Keeps a copy of the file in the trash, before it is replaced by rename.
+    ///
+    /// The copy is a hard link if possible, which costs no space until the file is replaced.
+    pub fn keep(&self, path: &Path) -> io::Result<PathBuf> {
+        let dest = self.dest(path)?;
+        if let Err(e) = fs::hard_link(path, &dest) {
+            debug!(?e, "failed to link {}, copying instead", anonymize(path));
+            fs::copy(path, &dest)?;
+        }
+        self.collect_garbage();
+        Ok(dest)
+    }
+
+    fn dest(&self, path: &Path) -> io::Result<PathBuf> {
+        fs::create_dir_all(&self.dir)?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let mut dest = self.dir.join(format!("{}-{}", now, name));
+        // NOTE the same file may be trashed twice in a second, e.g. by two profiles
+        let mut n = 1;
+        while dest.exists() {
+            dest = self.dir.join(format!("{}-{}-{}", now, n, name));
+            n += 1;
+        }
+        Ok(dest)
+    }
+
+    /// Removes the oldest files until the trash fits in the limit, the newest one is always kept.
+    ///
+    /// Only warns on failure, since the files have been trashed anyway.
+    pub fn collect_garbage(&self) {
+        let mut files = match self.files() {
+            Ok(files) => files,
+            Err(e) => {
+                warn!(?e, "failed to list the trash {}", anonymize(&self.dir));
+                return;
+            }
+        };
+        let mut total: u64 = files.iter().map(|(_, size)| size).sum();
+        files.pop();
+        for (path, size) in files {
+            if total <= self.limit {
+                break;
+            }
+            match fs::remove_file(&path) {
+                Ok(()) => {
+                    debug!("removed {} from the trash", anonymize(&path));
+                    total -= size;
+                }
+                Err(e) => warn!(?e, "failed to remove {}", anonymize(&path)),
+            }
+        }
+    }
+
+    /// Lists the files in the trash with their sizes, the oldest first.
+    pub fn files(&self) -> io::Result<Vec<(PathBuf, u64)>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut files = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            let meta = entry.metadata()?;
+            if meta.is_file() {
+                files.push((trashed_at(&entry.path()), entry.path(), meta.len()));
+            }
+        }
+        files.sort();
+        Ok(files
+            .into_iter()
+            .map(|(_, path, size)| (path, size))
+            .collect())
+    }
+}
+
+/// Reads the time from the prefix of the file name, 0 for the files put by hand.
+fn trashed_at(path: &Path) -> u64 {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.split_once('-'))
+        .and_then(|(time, _)| time.parse().ok())
+        .unwrap_or_default()
+}
+
+/// Moves the file into the trash if given, otherwise removes it.
+pub fn remove(trash: Option<&Trash>, path: &Path) -> io::Result<()> {
+    match trash {
+        Some(trash) => trash.put(path).map(|_| ()),
+        None => fs::remove_file(path),
+    }
+}
+
+#[cfg(test)]
+mod tests_trash {
+    use super::*;
+
+    #[test]
+    fn test_put_and_keep() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let trash = Trash::new(dir.path().join(".hultra-trash"), None);
+        let foo = dir.path().join("Foo.zip");
+        fs::write(&foo, "old")?;
+
+        let kept = trash.keep(&foo)?;
+        fs::write(dir.path().join("new.zip"), "new")?;
+        fs::rename(dir.path().join("new.zip"), &foo)?;
+        assert_eq!(fs::read_to_string(&kept)?, "old");
+
+        let put = trash.put(&foo)?;
+        assert!(!foo.exists());
+        assert_eq!(fs::read_to_string(&put)?, "new");
+        assert_ne!(kept, put);
+        assert_eq!(trash.files()?.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_collect_garbage() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let trash = Trash::new(dir.path().to_path_buf(), "10".parse().ok());
+        fs::write(dir.path().join("100-Old.zip"), "123456")?;
+        fs::write(dir.path().join("200-Mid.zip"), "123456")?;
+        fs::write(dir.path().join("300-New.zip"), "123456789012")?;
+
+        trash.collect_garbage();
+        // NOTE the newest one is kept even if it alone exceeds the limit
+        let names: Vec<_> = trash
+            .files()?
+            .into_iter()
+            .map(|(path, _)| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, ["300-New.zip"]);
+        assert!("0".parse::<SizeLimit>().is_err());
+        Ok(())
+    }
+}
@@ -2,6 +2,8 @@ mod api;
 pub mod build;
 mod downloader;
 mod installer;
+pub mod postmortem;
+pub mod status;
 pub mod version;
 
 pub use api::fetch;
@@ -9,14 +11,16 @@ pub use downloader::download;
 pub use installer::install;
 use reqwest::Client;
 
+use crate::core::network::{self, ClientOptions};
+
 #[derive(Debug, Clone)]
 pub struct EverestHttpClient {
     inner: Client,
 }
 
 impl EverestHttpClient {
-    pub fn new() -> reqwest::Result<Self> {
-        let client = Client::builder().https_only(true).gzip(true).build()?;
+    pub fn new(options: &ClientOptions) -> reqwest::Result<Self> {
+        let client = network::client_builder(options)?.build()?;
         Ok(Self { inner: client })
     }
 
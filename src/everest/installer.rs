@@ -1,11 +1,11 @@
 use std::{
-    fs,
     io::{self, BufRead, BufReader},
-    os::unix::fs::PermissionsExt,
     path::{Path, PathBuf},
     process::{Command, Stdio},
 };
 
+use crate::platform;
+
 /// Install Everest by running MiniInstaller.
 pub fn install(root_dir: &Path) -> io::Result<()> {
     let installer = MiniInstaller::new(root_dir);
@@ -21,24 +21,13 @@ struct MiniInstaller {
 impl MiniInstaller {
     fn new(root_dir: &Path) -> Self {
         Self {
-            path: root_dir.join("MiniInstaller-linux"),
+            path: root_dir.join(platform::MINI_INSTALLER),
         }
     }
 
     /// Grants execute permission to the installer.
     fn grant_execute_permission(&self) -> io::Result<()> {
-        let user_exec_bit = 0o100;
-
-        let metadata = fs::metadata(&self.path)?;
-        let mut perms = metadata.permissions();
-        let current_mode = perms.mode();
-
-        if (current_mode & user_exec_bit) != 0 {
-            return Ok(());
-        }
-
-        perms.set_mode(current_mode | user_exec_bit);
-        fs::set_permissions(&self.path, perms)
+        platform::grant_execute_permission(&self.path)
     }
 
     /// Executes the installer.
@@ -0,0 +1,323 @@
+//! Domain model of the Everest log for the `postmortem` command.
+//!
+//! Everest writes `log.txt` to the game root on every launch. Each line looks like this:
+//! `(03/07/2026 19:48:53) [Everest] [Warn] [loader] Failed loading everest.yaml in Mods/Foo.zip: ...`
+use std::{
+    collections::BTreeSet,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use tracing::debug;
+
+use crate::core::LocalMod;
+
+/// A single record of the Everest log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct LogRecord<'a> {
+    level: &'a str,
+    tag: &'a str,
+    message: &'a str,
+}
+
+impl<'a> LogRecord<'a> {
+    /// Parses a line of the log, returns `None` if it is not written by Everest.
+    fn parse(line: &'a str) -> Option<Self> {
+        let (_, rest) = line.split_once("[Everest] ")?;
+        let (level, rest) = strip_brackets(rest)?;
+        let (tag, message) = strip_brackets(rest.trim_start())?;
+        Some(Self {
+            level,
+            tag,
+            message: message.trim(),
+        })
+    }
+}
+
+/// Splits `[value] rest` into `("value", "rest")`.
+fn strip_brackets(s: &str) -> Option<(&str, &str)> {
+    let (value, rest) = s.strip_prefix('[')?.split_once(']')?;
+    Some((value, rest))
+}
+
+/// Reason why Everest failed to load the mod.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoadFailure {
+    /// The mod requires a dependency which is not loaded.
+    MissingDependency {
+        mod_name: String,
+        dependency: String,
+    },
+    /// The mod requires a newer version of the dependency.
+    OutdatedDependency {
+        mod_name: String,
+        dependency: String,
+        required: String,
+    },
+    /// The archive could not be read or its `everest.yaml` is broken.
+    CorruptedArchive { archive: String },
+    /// The mod was found but failed to load for other reasons (e.g. an exception from its DLL).
+    Other { mod_name: String },
+}
+
+impl LoadFailure {
+    /// Classifies a log record as load failure.
+    fn from_record(record: &LogRecord) -> Option<Self> {
+        if !record.tag.eq_ignore_ascii_case("loader") || record.level.eq_ignore_ascii_case("info") {
+            return None;
+        }
+
+        let message = record.message;
+
+        if let Some(rest) = message
+            .strip_prefix("Failed loading everest.yaml in ")
+            .or_else(|| message.strip_prefix("Failed opening "))
+        {
+            let archive = rest.split_once(": ").map_or(rest, |(archive, _)| archive);
+            return Some(Self::CorruptedArchive {
+                archive: archive.trim().to_string(),
+            });
+        }
+
+        if let Some(rest) = message.strip_prefix("Dependency ") {
+            let (dep, rest) = rest.split_once(" of mod ")?;
+            let (dep_name, dep_version) = split_meta(dep);
+            let (mod_name, _) = split_meta(rest);
+            let lower = rest.to_ascii_lowercase();
+
+            return if lower.contains("version") || lower.contains("too old") {
+                Some(Self::OutdatedDependency {
+                    mod_name,
+                    dependency: dep_name,
+                    required: dep_version,
+                })
+            } else if lower.contains("delaying") {
+                // NOTE Everest retries delayed mods after loading others, so this is not a failure yet
+                None
+            } else {
+                Some(Self::MissingDependency {
+                    mod_name,
+                    dependency: dep_name,
+                })
+            };
+        }
+
+        if let Some(rest) = message.strip_prefix("Failed loading mod ") {
+            let (mod_name, _) = split_meta(rest);
+            return Some(Self::Other { mod_name });
+        }
+
+        None
+    }
+}
+
+impl fmt::Display for LoadFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingDependency {
+                mod_name,
+                dependency,
+            } => write!(f, "{}: missing dependency '{}'", mod_name, dependency),
+            Self::OutdatedDependency {
+                mod_name,
+                dependency,
+                required,
+            } => write!(
+                f,
+                "{}: dependency '{}' must be v{} or newer",
+                mod_name, dependency, required
+            ),
+            Self::CorruptedArchive { archive } => write!(f, "{}: could not be read", archive),
+            Self::Other { mod_name } => write!(f, "{}: failed to load", mod_name),
+        }
+    }
+}
+
+/// Splits Everest module metadata `Name 1.2.3 ...` into name and version.
+///
+/// The version is the first whitespace separated token which starts with a digit.
+fn split_meta(s: &str) -> (String, String) {
+    let mut name = Vec::new();
+    let mut version = String::new();
+
+    for token in s.split_whitespace() {
+        if token.starts_with(|c: char| c.is_ascii_digit()) {
+            version = token.trim_end_matches(['!', '.', ',', ':']).to_string();
+            break;
+        }
+        name.push(token);
+    }
+
+    (name.join(" "), version)
+}
+
+/// Collects unique load failures from the content of the log.
+pub fn parse_failures(content: &str) -> Vec<LoadFailure> {
+    let mut failures = Vec::new();
+    for failure in content
+        .lines()
+        .filter_map(LogRecord::parse)
+        .filter_map(|r| LoadFailure::from_record(&r))
+    {
+        if !failures.contains(&failure) {
+            failures.push(failure);
+        }
+    }
+    failures
+}
+
+/// Reads the latest Everest log in the game directory.
+pub fn read_latest_log(root_dir: &Path) -> io::Result<String> {
+    let bytes = fs::read(root_dir.join("log.txt"))?;
+    // NOTE The log may contain non UTF-8 bytes from the exception messages
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Action to fix the load failure.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Suggestion {
+    /// Run `update` for the mod.
+    Update { name: String },
+    /// Install the dependency which is not installed.
+    Install { name: String },
+    /// Remove and download the archive again.
+    Redownload { file: PathBuf },
+    /// Nothing can be done automatically, check the log for details.
+    Inspect { name: String },
+}
+
+impl fmt::Display for Suggestion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Update { name } => write!(f, "update '{}'", name),
+            Self::Install { name } => write!(f, "install missing dependency '{}'", name),
+            Self::Redownload { file } => write!(f, "re-download '{}'", file.display()),
+            Self::Inspect { name } => write!(f, "check the log for errors of '{}'", name),
+        }
+    }
+}
+
+/// Maps the load failures to the installed mods and returns actions to fix them.
+pub fn diagnose(failures: &[LoadFailure], installed: &[LocalMod]) -> BTreeSet<Suggestion> {
+    let find_by_name = |name: &str| installed.iter().find(|m| m.name() == name);
+
+    failures
+        .iter()
+        .map(|failure| match failure {
+            LoadFailure::MissingDependency { dependency, .. } => {
+                if find_by_name(dependency).is_some() {
+                    // NOTE the dependency itself failed to load, so it may be outdated
+                    Suggestion::Update {
+                        name: dependency.clone(),
+                    }
+                } else {
+                    Suggestion::Install {
+                        name: dependency.clone(),
+                    }
+                }
+            }
+            LoadFailure::OutdatedDependency { dependency, .. } => Suggestion::Update {
+                name: dependency.clone(),
+            },
+            LoadFailure::CorruptedArchive { archive } => {
+                let archive_name = Path::new(archive).file_name();
+                let file = installed
+                    .iter()
+                    .map(|m| m.file().path())
+                    .find(|path| path.file_name() == archive_name)
+                    .map(Path::to_path_buf)
+                    .unwrap_or_else(|| PathBuf::from(archive));
+                Suggestion::Redownload { file }
+            }
+            LoadFailure::Other { mod_name } => match find_by_name(mod_name) {
+                Some(m) => Suggestion::Update {
+                    name: m.name().to_string(),
+                },
+                None => Suggestion::Inspect {
+                    name: mod_name.clone(),
+                },
+            },
+        })
+        .inspect(|s| debug!(?s, "suggestion"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests_postmortem {
+    use crate::core::local::ModFile;
+
+    use super::*;
+
+    const LOG: &str = r#"
+(03/07/2026 19:48:53) [Everest] [Info] [core] Booting Everest
+(03/07/2026 19:48:54) [Everest] [Info] [loader] Dependency CollabUtils2 1.6.13 of mod darkmoonruins 1.1.4 not loaded! Delaying.
+(03/07/2026 19:48:54) [Everest] [Warn] [loader] Failed loading everest.yaml in Mods/Broken.zip: YamlException
+(03/07/2026 19:48:55) [Everest] [Warn] [loader] Dependency CollabUtils2 1.6.13 of mod darkmoonruins 1.1.4 not found!
+(03/07/2026 19:48:55) [Everest] [Warn] [loader] Dependency MaxHelpingHand 1.30.0 of mod SomeMap 1.0.0 has a version mismatch!
+(03/07/2026 19:48:55) [Everest] [Warn] [loader] Dependency CollabUtils2 1.6.13 of mod darkmoonruins 1.1.4 not found!
+"#;
+
+    #[test]
+    fn test_parse_record() {
+        let record = LogRecord::parse("(03/07/2026 19:48:53) [Everest] [Warn] [loader] message");
+        assert_eq!(
+            record,
+            Some(LogRecord {
+                level: "Warn",
+                tag: "loader",
+                message: "message"
+            })
+        );
+        assert!(LogRecord::parse("Unhandled exception").is_none());
+    }
+
+    #[test]
+    fn test_parse_failures() {
+        let failures = parse_failures(LOG);
+        assert_eq!(
+            failures,
+            vec![
+                LoadFailure::CorruptedArchive {
+                    archive: "Mods/Broken.zip".into()
+                },
+                LoadFailure::MissingDependency {
+                    mod_name: "darkmoonruins".into(),
+                    dependency: "CollabUtils2".into()
+                },
+                LoadFailure::OutdatedDependency {
+                    mod_name: "SomeMap".into(),
+                    dependency: "MaxHelpingHand".into(),
+                    required: "1.30.0".into()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diagnose() {
+        let installed = vec![
+            LocalMod::new(
+                ModFile::new_unchecked(PathBuf::from("/game/Mods/Broken.zip")),
+                "Broken".into(),
+                "1.0.0".into(),
+            ),
+            LocalMod::new(
+                ModFile::new_unchecked(PathBuf::from("/game/Mods/MaxHelpingHand.zip")),
+                "MaxHelpingHand".into(),
+                "1.29.0".into(),
+            ),
+        ];
+        let suggestions = diagnose(&parse_failures(LOG), &installed);
+
+        assert_eq!(suggestions.len(), 3);
+        assert!(suggestions.contains(&Suggestion::Install {
+            name: "CollabUtils2".into()
+        }));
+        assert!(suggestions.contains(&Suggestion::Update {
+            name: "MaxHelpingHand".into()
+        }));
+        assert!(suggestions.contains(&Suggestion::Redownload {
+            file: PathBuf::from("/game/Mods/Broken.zip")
+        }));
+    }
+}
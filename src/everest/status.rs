@@ -0,0 +1,123 @@
+//! Installation status of Everest for the `everest status` command.
+use std::io;
+
+use crate::everest::{
+    build::{Branch, EverestBuild, EverestBuildExt},
+    version::{
+        InstalledVersionProvider, VersionNumber, VersionParseError, fetch_installed_version,
+    },
+};
+
+/// Everest found in the Celeste directory.
+#[derive(Debug)]
+pub enum Installation {
+    /// `update-build.txt` does not exist.
+    NotInstalled,
+    Installed(VersionNumber),
+    /// `update-build.txt` exists but does not have a valid build number.
+    Unknown(VersionParseError),
+}
+
+impl Installation {
+    pub fn inspect(repo: &impl InstalledVersionProvider) -> Self {
+        match fetch_installed_version(repo) {
+            Ok(number) => Self::Installed(number),
+            Err(VersionParseError::Io(e)) if e.kind() == io::ErrorKind::NotFound => {
+                Self::NotInstalled
+            }
+            Err(e) => Self::Unknown(e),
+        }
+    }
+
+    pub fn build(&self) -> Option<u32> {
+        match self {
+            Self::Installed(number) => Some(number.value()),
+            _ => None,
+        }
+    }
+}
+
+/// The installed build compared with the database.
+#[derive(Debug)]
+pub struct BuildStatus<'a> {
+    /// Branch of the installed build, `None` if it is not in the database.
+    pub branch: Option<&'a Branch>,
+    /// Latest build on the branch, on `stable` if the branch is unknown.
+    pub latest: Option<&'a EverestBuild>,
+}
+
+impl<'a> BuildStatus<'a> {
+    pub fn new(installed: Option<u32>, builds: &'a [EverestBuild]) -> Self {
+        let branch = installed.and_then(|version| builds.get_installed_branch(version));
+        let latest = builds.get_latest_build_for_branch(branch.unwrap_or(&Branch::Stable));
+        Self { branch, latest }
+    }
+
+    /// Returns the latest build if it is newer than the installed one.
+    pub fn newer_than(&self, installed: u32) -> Option<&'a EverestBuild> {
+        self.latest.filter(|build| build.version > installed)
+    }
+}
+
+#[cfg(test)]
+mod tests_status {
+    use super::*;
+
+    struct MockFileRepo(io::Result<&'static str>);
+    impl InstalledVersionProvider for MockFileRepo {
+        fn fetch(&self) -> io::Result<String> {
+            match &self.0 {
+                Ok(content) => Ok(content.to_string()),
+                Err(e) => Err(io::Error::new(e.kind(), e.to_string())),
+            }
+        }
+    }
+
+    fn build(version: u32, branch: Branch) -> EverestBuild {
+        // NOTE the date is private, so the build is not written as a literal
+        let mut build = EverestBuild::default();
+        build.version = version;
+        build.branch = branch;
+        build
+    }
+
+    #[test]
+    fn test_inspect() {
+        let missing = MockFileRepo(Err(io::ErrorKind::NotFound.into()));
+        assert!(matches!(
+            Installation::inspect(&missing),
+            Installation::NotInstalled
+        ));
+        let broken = MockFileRepo(Ok("not a number"));
+        assert!(matches!(
+            Installation::inspect(&broken),
+            Installation::Unknown(_)
+        ));
+        assert_eq!(
+            Installation::inspect(&MockFileRepo(Ok("5821\n"))).build(),
+            Some(5821)
+        );
+    }
+
+    #[test]
+    fn test_build_status() {
+        let builds = [
+            build(5800, Branch::Stable),
+            build(5821, Branch::Stable),
+            build(5830, Branch::Beta),
+            build(5835, Branch::Beta),
+        ];
+
+        let status = BuildStatus::new(Some(5830), &builds);
+        assert_eq!(status.branch, Some(&Branch::Beta));
+        assert_eq!(status.newer_than(5830).map(|b| b.version), Some(5835));
+
+        let status = BuildStatus::new(Some(5821), &builds);
+        assert!(status.newer_than(5821).is_none());
+
+        // NOTE a build missing from the database is compared with the stable one
+        let status = BuildStatus::new(Some(5000), &builds);
+        assert_eq!(status.branch, None);
+        assert_eq!(status.newer_than(5000).map(|b| b.version), Some(5821));
+    }
+}
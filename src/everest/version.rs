@@ -19,6 +19,7 @@ pub enum VersionParseError {
 }
 
 /// Represents version number of Everest.
+#[derive(Debug)]
 pub struct VersionNumber(u32);
 
 impl VersionNumber {
@@ -1,36 +1,79 @@
 use std::{
     fs::File,
-    io,
-    path::{Component, Path},
+    io::{self, IsTerminal},
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
 };
 
+use clap::ValueEnum;
 use tracing_subscriber::{
     EnvFilter, Layer,
-    fmt::{self, format::FmtSpan},
+    fmt::{self, MakeWriter, format::FmtSpan},
     layer::SubscriberExt,
     util::SubscriberInitExt,
 };
 
-pub fn init_logger(log_file: Option<&Path>) -> Result<(), io::Error> {
-    // if the variable `$RUST_LOG` is not set, do not display any logs to the console
-    let env_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+use crate::ui::style;
+
+pub use hultra_core::log::{anonymize, redact};
+
+/// Whether the progress bars are drawn, decided by [`init_logger`].
+static SHOW_PROGRESS: AtomicBool = AtomicBool::new(false);
+
+/// Format of the log file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    /// A JSON object for each line, with the fields of the spans.
+    Json,
+}
+
+/// Initializes the logs, `quiet` hides the info logs and the progress bars.
+///
+/// NOTE `$RUST_LOG` takes precedence over `quiet`
+pub fn init_logger(
+    log_file: Option<&Path>,
+    format: LogFormat,
+    quiet: bool,
+) -> Result<(), io::Error> {
+    let default_level = if quiet { "warn" } else { "info" };
+    let env_filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(default_level));
+
+    // NOTE progress bars only clutter the logs of cron jobs and pipes
+    let show_progress = !quiet
+        && io::stdout().is_terminal()
+        && io::stderr().is_terminal()
+        && EnvFilter::from_default_env()
+            .max_level_hint()
+            .is_some_and(|lvl| lvl < tracing::Level::DEBUG);
+    SHOW_PROGRESS.store(show_progress, Ordering::Relaxed);
 
     let console_layer = fmt::layer()
-        .with_writer(std::io::stderr)
+        .with_writer(Redacting(std::io::stderr))
         .with_target(false)
         .without_time()
+        .with_ansi(style::stderr_colored())
         .with_filter(env_filter);
 
+    // NOTE the closed spans are logged with their busy and idle time, to find the slow phases
     let file_layer = if let Some(p) = log_file {
         let file = File::create(p)?;
-
-        Some(
-            fmt::layer()
-                .with_writer(file)
-                .with_span_events(FmtSpan::NEW | FmtSpan::CLOSE)
-                .with_ansi(false)
-                .with_filter(EnvFilter::new("hultra=debug,info")), // always debug for file, info for deps
-        )
+        let layer = fmt::layer()
+            .with_writer(Redacting(file))
+            .with_span_events(FmtSpan::NEW | FmtSpan::CLOSE)
+            .with_ansi(false);
+        let layer = match format {
+            LogFormat::Text => layer.boxed(),
+            LogFormat::Json => layer
+                .json()
+                .with_current_span(true)
+                .with_span_list(true)
+                .boxed(),
+        };
+        // always debug for file, info for deps
+        Some(layer.with_filter(EnvFilter::new("hultra=debug,info")))
     } else {
         None
     };
@@ -43,42 +86,36 @@ pub fn init_logger(log_file: Option<&Path>) -> Result<(), io::Error> {
     Ok(())
 }
 
-/// Swaps user's home directory path with tilde.
-pub fn anonymize(path: &Path) -> String {
-    // 1. trying to detect home dir from env var
-    if let Some(home) = std::env::home_dir()
-        && let Ok(rel) = path.strip_prefix(&home)
-    {
-        return format!("~/{}", rel.display());
+/// Makes the writers of the logs redact the home directory and the user name, see [`redact`].
+///
+/// NOTE the logs are pasted into public issues, with the messages of other crates too
+struct Redacting<M>(M);
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for Redacting<M> {
+    type Writer = Redacted<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        Redacted(self.0.make_writer())
+    }
+}
+
+/// Writer of [`Redacting`], a line of the logs is written at once.
+struct Redacted<W>(W);
+
+impl<W: io::Write> io::Write for Redacted<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let text = String::from_utf8_lossy(buf);
+        self.0.write_all(redact(&text).as_bytes())?;
+        Ok(buf.len())
     }
 
-    // 2. trying to guess it from path structure
-    let mut comps = path.components();
-    let root = comps.next();
-    let base = comps.next();
-    let user = comps.next();
-
-    match (root, base, user) {
-        (Some(Component::RootDir), Some(b), Some(_))
-            // NOTE prevent /etc/systemd/system becomes ~/system
-            if b.as_os_str() == "home" || b.as_os_str() == "Users" =>
-        {
-            let rest = comps.as_path();
-            if rest.as_os_str().is_empty() {
-                "~".to_string()
-            } else {
-                format!("~/{}", rest.display())
-            }
-        }
-        // 3. last resort: fallback to original
-        _ => path.to_string_lossy().into_owned(),
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
     }
 }
 
-/// Shows progress only if the effective level is `INFO` or quieter (no debug spam)
+/// Shows progress only on a terminal, if the effective level is `INFO` or quieter (no debug spam)
+/// and `--quiet` is not given.
 pub fn should_show_progress() -> bool {
-    let filter = EnvFilter::from_default_env();
-    filter
-        .max_level_hint()
-        .is_some_and(|lvl| lvl < tracing::Level::DEBUG)
+    SHOW_PROGRESS.load(Ordering::Relaxed)
 }
@@ -1,3 +1,5 @@
+use std::process::ExitCode;
+
 use anyhow::Context;
 use clap::Parser;
 use tracing::debug;
@@ -13,14 +15,25 @@ mod config;
 mod core;
 mod everest;
 mod log;
+mod platform;
 mod ui;
 mod utils;
 
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
+async fn main() -> ExitCode {
+    commands::completions::complete_if_requested();
     let args = Cli::parse();
 
-    log::init_logger(args.log_file.as_deref()).with_context(|| {
+    ui::style::init(args.color);
+    hultra_core::progress::set_reporter(ui::BarReporter);
+    match run(args).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => ui::report::report(&e),
+    }
+}
+
+async fn run(args: Cli) -> anyhow::Result<()> {
+    log::init_logger(args.log_file.as_deref(), args.log_format, args.quiet).with_context(|| {
         format!(
             "Failed to initialize logging system. Cannot create log file at {:?}",
             args.log_file.as_deref()
@@ -29,8 +42,14 @@ async fn main() -> anyhow::Result<()> {
 
     debug!("{} version {}", CARGO_PKG_NAME, CARGO_PKG_VERSION);
     debug!(?args);
+    core::network::cancel::listen();
 
-    let config = AppConfig::new(args.directory.as_deref())?;
+    let config = AppConfig::new(args.directory.as_deref(), args.config_profile.as_deref())?
+        .with_offline(args.offline)
+        .with_proxy(args.proxy)
+        .with_tls(args.tls)?
+        .with_output(args.output)
+        .with_assume_yes(args.yes);
     debug!(%config);
 
     cli::dispatch(args.commands, config).await
@@ -0,0 +1,304 @@
+//! Locations and system calls which differ between Linux, macOS, and Windows.
+//!
+//! `XDG_CONFIG_HOME` and `XDG_STATE_HOME` are respected on every platform if they are set.
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Name of the Everest installer in the game directory.
+#[cfg(target_os = "linux")]
+pub const MINI_INSTALLER: &str = "MiniInstaller-linux";
+#[cfg(target_os = "macos")]
+pub const MINI_INSTALLER: &str = "MiniInstaller-osx";
+#[cfg(windows)]
+pub const MINI_INSTALLER: &str = "MiniInstaller-win64.exe";
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+pub const MINI_INSTALLER: &str = "MiniInstaller-linux";
+
+/// Game directory relative to the `common` directory of the Steam library.
+///
+/// NOTE the game files are inside of the app bundle on macOS
+#[cfg(target_os = "macos")]
+const STEAM_GAME_DIRECTORY: &str = "Celeste/Celeste.app/Contents/Resources";
+#[cfg(not(target_os = "macos"))]
+const STEAM_GAME_DIRECTORY: &str = "Celeste";
+
+/// Manifests of the Epic Games Launcher, relative to `ProgramData` on Windows.
+pub const EPIC_MANIFESTS: &str = "Epic/EpicGamesLauncher/Data/Manifests";
+
+/// Returns the directory of the config file, without the app name.
+pub fn config_dir(home: &Path) -> PathBuf {
+    if let Some(dir) = env::var_os("XDG_CONFIG_HOME") {
+        return dir.into();
+    }
+    if cfg!(windows) {
+        env::var_os("APPDATA").map_or_else(|| home.join("AppData").join("Roaming"), Into::into)
+    } else if cfg!(target_os = "macos") {
+        home.join("Library").join("Application Support")
+    } else {
+        home.join(".config")
+    }
+}
+
+/// Returns the directory of the caches and the records, without the app name.
+pub fn state_dir(home: &Path) -> PathBuf {
+    if let Some(dir) = env::var_os("XDG_STATE_HOME") {
+        return dir.into();
+    }
+    if cfg!(windows) {
+        env::var_os("LOCALAPPDATA").map_or_else(|| home.join("AppData").join("Local"), Into::into)
+    } else if cfg!(target_os = "macos") {
+        home.join("Library").join("Application Support")
+    } else {
+        home.join(".local").join("state")
+    }
+}
+
+/// Reads hostname of the machine.
+pub fn hostname() -> Option<String> {
+    #[cfg(target_os = "linux")]
+    let hostname = fs::read_to_string("/proc/sys/kernel/hostname").ok()?;
+    #[cfg(windows)]
+    let hostname = env::var("COMPUTERNAME").ok()?;
+    #[cfg(not(any(target_os = "linux", windows)))]
+    let hostname = {
+        let output = std::process::Command::new("hostname").output().ok()?;
+        String::from_utf8(output.stdout).ok()?
+    };
+    let hostname = hostname.trim();
+    (!hostname.is_empty()).then(|| hostname.to_string())
+}
+
+/// Returns the roots of Steam, the primary one first.
+pub fn steam_roots(home: &Path) -> Vec<PathBuf> {
+    #[cfg(windows)]
+    {
+        let mut roots: Vec<PathBuf> = registry_steam_path().into_iter().collect();
+        roots.push(PathBuf::from(r"C:\Program Files (x86)\Steam"));
+        let _ = home;
+        roots
+    }
+    #[cfg(target_os = "macos")]
+    {
+        vec![home.join("Library/Application Support/Steam")]
+    }
+    #[cfg(not(any(windows, target_os = "macos")))]
+    {
+        [
+            ".local/share/Steam",
+            ".steam/steam",
+            ".var/app/com.valvesoftware.Steam/.local/share/Steam",
+            "snap/steam/common/.local/share/Steam",
+        ]
+        .iter()
+        .map(|root| home.join(root))
+        .collect()
+    }
+}
+
+/// Returns the game directory in the Steam library.
+pub fn steam_game_dir(library: &Path) -> PathBuf {
+    library
+        .join("steamapps")
+        .join("common")
+        .join(STEAM_GAME_DIRECTORY)
+}
+
+/// Returns the default locations of the installations by the other launchers.
+pub fn other_installs(home: &Path) -> Vec<PathBuf> {
+    #[cfg(windows)]
+    {
+        let mut dirs = vec![PathBuf::from(r"C:\Program Files\Epic Games\Celeste")];
+        if let Some(appdata) = env::var_os("APPDATA") {
+            dirs.push(PathBuf::from(appdata).join(r"itch\apps\celeste"));
+        }
+        let _ = home;
+        dirs
+    }
+    #[cfg(target_os = "macos")]
+    {
+        const BUNDLE: &str = "Celeste.app/Contents/Resources";
+        vec![
+            Path::new("/Applications").join(BUNDLE),
+            home.join("Applications").join(BUNDLE),
+            home.join("Library/Application Support/itch/apps/celeste")
+                .join(BUNDLE),
+        ]
+    }
+    #[cfg(not(any(windows, target_os = "macos")))]
+    {
+        [
+            // Heroic Games Launcher, for Epic Games Store
+            "Games/Heroic/Celeste",
+            // itch app
+            ".config/itch/apps/celeste",
+            "Games/Celeste",
+            ".local/share/Celeste",
+        ]
+        .iter()
+        .map(|dir| home.join(dir))
+        .collect()
+    }
+}
+
+/// Returns the directories where the itch app installs the games by default.
+pub fn itch_apps_dirs(home: &Path) -> Vec<PathBuf> {
+    #[cfg(windows)]
+    {
+        let _ = home;
+        env::var_os("APPDATA")
+            .map(|appdata| PathBuf::from(appdata).join(r"itch\apps"))
+            .into_iter()
+            .collect()
+    }
+    #[cfg(target_os = "macos")]
+    {
+        vec![home.join("Library/Application Support/itch/apps")]
+    }
+    #[cfg(not(any(windows, target_os = "macos")))]
+    {
+        [
+            ".config/itch/apps",
+            ".var/app/io.itch.itch/config/itch/apps",
+        ]
+        .iter()
+        .map(|dir| home.join(dir))
+        .collect()
+    }
+}
+
+/// Returns the directories of the manifests written by the Epic Games Launcher.
+///
+/// NOTE the launcher in a Wine prefix writes them under `drive_c`, see [`prefix_installs`]
+pub fn epic_manifest_dirs(home: &Path) -> Vec<PathBuf> {
+    #[cfg(windows)]
+    {
+        let _ = home;
+        let program_data =
+            env::var_os("PROGRAMDATA").map_or_else(|| PathBuf::from(r"C:\ProgramData"), Into::into);
+        vec![program_data.join(EPIC_MANIFESTS)]
+    }
+    #[cfg(target_os = "macos")]
+    {
+        vec![
+            home.join("Library/Application Support")
+                .join(EPIC_MANIFESTS),
+        ]
+    }
+    #[cfg(not(any(windows, target_os = "macos")))]
+    {
+        let _ = home;
+        Vec::new()
+    }
+}
+
+/// Returns `installed.json` of legendary, which Heroic uses for the Epic games on Linux.
+pub fn legendary_installed_files(home: &Path) -> Vec<PathBuf> {
+    if !cfg!(target_os = "linux") {
+        return Vec::new();
+    }
+    [
+        ".config/heroic/legendaryConfig/legendary/installed.json",
+        ".var/app/com.heroicgameslauncher.hgl/config/heroic/legendaryConfig/legendary/installed.json",
+        ".config/legendary/installed.json",
+    ]
+    .iter()
+    .map(|file| home.join(file))
+    .collect()
+}
+
+/// Returns the default locations of the Windows installations in `drive_c` of a Wine prefix.
+///
+/// NOTE only Linux has the Proton prefixes, and the Steam itself in a prefix is looked for too
+pub fn prefix_installs(drive_c: &Path) -> Vec<PathBuf> {
+    if !cfg!(target_os = "linux") {
+        return Vec::new();
+    }
+    [
+        "Program Files (x86)/Steam/steamapps/common/Celeste",
+        "Program Files/Epic Games/Celeste",
+        "Program Files (x86)/Celeste",
+        "Program Files/Celeste",
+        "users/steamuser/AppData/Roaming/itch/apps/celeste",
+    ]
+    .iter()
+    .map(|dir| drive_c.join(dir))
+    .collect()
+}
+
+/// Reads `SteamPath` of the registry written by the Steam client.
+#[cfg(windows)]
+fn registry_steam_path() -> Option<PathBuf> {
+    let output = std::process::Command::new("reg")
+        .args(["query", r"HKCU\Software\Valve\Steam", "/v", "SteamPath"])
+        .output()
+        .ok()?;
+    parse_reg_value(&String::from_utf8_lossy(&output.stdout), "SteamPath").map(PathBuf::from)
+}
+
+/// Reads the value from the output of `reg query`, e.g. `    SteamPath    REG_SZ    c:/steam`.
+#[cfg(any(windows, test))]
+fn parse_reg_value(output: &str, name: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let (key, value) = line.trim().split_once("REG_SZ")?;
+        (key.trim() == name).then(|| value.trim().to_string())
+    })
+}
+
+/// Makes the file executable by the user, Windows has no such permission.
+pub fn grant_execute_permission(path: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let user_exec_bit = 0o100;
+        let mut perms = fs::metadata(path)?.permissions();
+        let current_mode = perms.mode();
+        if (current_mode & user_exec_bit) != 0 {
+            return Ok(());
+        }
+        perms.set_mode(current_mode | user_exec_bit);
+        fs::set_permissions(path, perms)
+    }
+    #[cfg(not(unix))]
+    {
+        fs::metadata(path).map(|_| ())
+    }
+}
+
+/// Persists the entries of the directory, e.g. a rename into it.
+///
+/// NOTE directories cannot be opened as files on Windows, where renames are journaled anyway
+pub fn sync_dir(dir: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        fs::File::open(dir)?.sync_all()
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = dir;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_platform {
+    use super::*;
+
+    #[test]
+    fn test_parse_reg_value() {
+        let output = "\r\nHKEY_CURRENT_USER\\Software\\Valve\\Steam\r\n    SteamPath    REG_SZ    c:/program files (x86)/steam\r\n\r\n";
+        assert_eq!(
+            parse_reg_value(output, "SteamPath").as_deref(),
+            Some("c:/program files (x86)/steam")
+        );
+        assert_eq!(parse_reg_value(output, "SteamExe"), None);
+    }
+
+    #[test]
+    fn test_steam_game_dir() {
+        let dir = steam_game_dir(Path::new("/mnt/SteamLibrary"));
+        assert!(dir.starts_with("/mnt/SteamLibrary/steamapps/common/Celeste"));
+    }
+}
@@ -1,9 +1,58 @@
 //! Interface design
-use std::time::Duration;
+use std::{
+    io::{self, BufRead, Write},
+    sync::LazyLock,
+    time::{Duration, Instant},
+};
 
-use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use clap::ValueEnum;
+use console::StyledObject;
+use hultra_core::progress::{Progress, Reporter, Task};
+use indicatif::{BinaryBytes, MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use serde::Serialize;
 
-use crate::log;
+use crate::{core::network::downloader::DownloadReport, log};
+
+pub mod report;
+pub mod style;
+
+/// Format of the results printed to stdout.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable text.
+    #[default]
+    Text,
+    /// A single JSON document, the messages for humans are printed to stderr instead.
+    Json,
+}
+
+impl OutputFormat {
+    pub fn is_json(self) -> bool {
+        self == Self::Json
+    }
+
+    /// Colors the text for the stream [`Self::print_text`] prints to.
+    pub fn styled<D>(self, text: StyledObject<D>) -> StyledObject<D> {
+        match self {
+            Self::Text => text.for_stdout(),
+            Self::Json => text.for_stderr(),
+        }
+    }
+
+    /// Prints the message for humans, to stderr in JSON mode to keep stdout parsable.
+    pub fn print_text(self, text: &str) {
+        match self {
+            Self::Text => println!("{}", text),
+            Self::Json => eprintln!("{}", text),
+        }
+    }
+}
+
+/// Prints the value as pretty-printed JSON to stdout.
+pub fn print_json(value: &impl Serialize) -> serde_json::Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
 
 /// Create a progress bar for downloading a file.
 pub fn create_download_progress_bar(name: &str, size: u64) -> ProgressBar {
@@ -14,7 +63,7 @@ pub fn create_download_progress_bar(name: &str, size: u64) -> ProgressBar {
     };
     pb.set_style(
         ProgressStyle::with_template(
-            "{wide_msg} {total_bytes:>10.1.cyan/blue} {bytes_per_sec:>11.2} {elapsed_precise:>8} [{bar:>40}] {percent:>3}%"
+            "{wide_msg} {total_bytes:>10.1.cyan/blue} {prefix:>18} {elapsed_precise:>8} [{bar:>40}] {percent:>3}%"
         )
         .unwrap_or_else(|_| ProgressStyle::default_bar())
         .progress_chars("#>-")
@@ -23,10 +72,140 @@ pub fn create_download_progress_bar(name: &str, size: u64) -> ProgressBar {
     pb
 }
 
+/// Smoothed download speed, an exponentially weighted moving average.
+///
+/// Instantaneous speed jumps wildly with chunked responses, so the rate is sampled in windows
+/// and blended with the previous value.
+#[derive(Debug, Clone)]
+pub struct Throughput {
+    /// Bytes per second.
+    rate: Option<f64>,
+    window_start: Instant,
+    window_bytes: u64,
+}
+
+impl Throughput {
+    /// Minimum duration of a sample.
+    const WINDOW: Duration = Duration::from_millis(500);
+    /// Time constant of the average in seconds. Larger is smoother but slower to follow.
+    const TAU: f64 = 3.0;
+
+    pub fn new(now: Instant) -> Self {
+        Self {
+            rate: None,
+            window_start: now,
+            window_bytes: 0,
+        }
+    }
+
+    /// Records received bytes, returns `true` if the rate is updated.
+    pub fn record(&mut self, bytes: u64, now: Instant) -> bool {
+        self.window_bytes += bytes;
+        let elapsed = now.duration_since(self.window_start);
+        if elapsed < Self::WINDOW {
+            return false;
+        }
+
+        let secs = elapsed.as_secs_f64();
+        let sample = self.window_bytes as f64 / secs;
+        self.rate = Some(match self.rate {
+            Some(rate) => {
+                let alpha = 1.0 - (-secs / Self::TAU).exp();
+                rate + alpha * (sample - rate)
+            }
+            None => sample,
+        });
+        self.window_start = now;
+        self.window_bytes = 0;
+        true
+    }
+
+    /// Returns smoothed bytes per second.
+    pub fn rate(&self) -> Option<f64> {
+        self.rate
+    }
+
+    /// Estimates remaining time from the smoothed rate.
+    pub fn eta(&self, remaining: u64) -> Option<Duration> {
+        self.rate
+            .filter(|rate| *rate > 0.0)
+            .map(|rate| Duration::from_secs_f64(remaining as f64 / rate))
+    }
+}
+
+/// Shows the smoothed speed and remaining time on the progress bar.
+pub fn show_throughput(pb: &ProgressBar, throughput: &Throughput) {
+    let remaining = pb.length().unwrap_or(0).saturating_sub(pb.position());
+    if let (Some(rate), Some(eta)) = (throughput.rate(), throughput.eta(remaining)) {
+        let secs = eta.as_secs();
+        pb.set_prefix(format!(
+            "{}/s {:02}:{:02}",
+            BinaryBytes(rate as u64),
+            secs / 60,
+            secs % 60
+        ));
+    }
+}
+
+/// Shows that no bytes are received for a while.
+pub fn show_stalled(pb: &ProgressBar, stalled_for: Duration) {
+    pb.set_prefix(format!("stalled {}s", stalled_for.as_secs()));
+}
+
+/// Shows the retry waiting for the backoff.
+pub fn show_retrying(pb: &ProgressBar, retry: u8, delay: Duration) {
+    pb.set_prefix(format!("retry #{} in {:.1}s", retry, delay.as_secs_f64()));
+}
+
+/// Progress bars shown at the same time, e.g. hashing the mods while fetching the database.
+static BARS: LazyLock<MultiProgress> = LazyLock::new(MultiProgress::new);
+
+/// Draws the progress of hashing and scanning the installed mods in `hultra-core`.
+pub struct BarReporter;
+
+impl Reporter for BarReporter {
+    fn start(&self, task: Task, total: u64) -> Box<dyn Progress> {
+        let label = match task {
+            Task::Hashing => "hashing mods",
+            Task::Scanning => "scanning mods",
+        };
+        Box::new(CountBar(create_count_progress_bar(label, total)))
+    }
+}
+
+struct CountBar(ProgressBar);
+
+impl Progress for CountBar {
+    fn inc(&self, delta: u64) {
+        self.0.inc(delta);
+    }
+
+    fn finish(&self) {
+        self.0.finish_and_clear();
+    }
+}
+
+fn create_count_progress_bar(label: &str, len: u64) -> ProgressBar {
+    if log::should_show_progress() {
+        let pb = BARS.add(ProgressBar::new(len));
+        pb.set_style(
+            ProgressStyle::with_template(&format!(
+                "{} {{pos:>4}}/{{len:4}} [{{bar:40}}] {{elapsed}}",
+                label
+            ))
+            .unwrap_or_else(|_| ProgressStyle::default_bar())
+            .progress_chars("#>-"),
+        );
+        pb
+    } else {
+        ProgressBar::hidden()
+    }
+}
+
 /// Create a spinner progress bar for fetching online database.
 pub fn create_spinner() -> ProgressBar {
     if log::should_show_progress() {
-        let spinner = ProgressBar::new_spinner();
+        let spinner = BARS.add(ProgressBar::new_spinner());
         spinner.enable_steady_tick(Duration::from_millis(100));
         spinner.set_style(
             ProgressStyle::with_template("{spinner:.bold} {msg}")
@@ -38,3 +217,111 @@ pub fn create_spinner() -> ProgressBar {
         ProgressBar::hidden()
     }
 }
+
+/// Prints the outcome of each mod as a table, to stderr in JSON mode.
+pub fn print_download_report(report: &DownloadReport, output: OutputFormat) {
+    let succeeded: Vec<String> = report
+        .succeeded
+        .iter()
+        .map(|path| {
+            path.file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned()
+        })
+        .collect();
+    let width = succeeded
+        .iter()
+        .chain(report.failed.iter().map(|(name, _)| name))
+        .chain(report.skipped.iter().map(|(name, _)| name))
+        .chain(&report.canceled)
+        .map(String::len)
+        .max()
+        .unwrap_or_default();
+
+    for name in &succeeded {
+        let label = output.styled(style::success("Downloaded"));
+        output.print_text(&format!("{}  {}", label, name));
+    }
+    for (name, downloaded_as) in &report.skipped {
+        let label = output.styled(style::warning("Skipped   "));
+        output.print_text(&format!(
+            "{}  {:<width$}  same archive as {}",
+            label, name, downloaded_as
+        ));
+    }
+    for (name, e) in &report.failed {
+        for (i, reason) in e.reasons().iter().enumerate() {
+            let label = output.styled(style::error(if i == 0 { "Failed" } else { "" }));
+            let name = if i == 0 { name.as_str() } else { "" };
+            output.print_text(&format!("{:<10}  {:<width$}  {}", label, name, reason));
+        }
+    }
+    for name in &report.canceled {
+        let label = output.styled(style::warning("Canceled  "));
+        output.print_text(&format!(
+            "{}  {:<width$}  resumed on the next run",
+            label, name
+        ));
+    }
+}
+
+/// Asks the user for confirmation on stderr. An empty answer means yes.
+pub fn confirm(prompt: &str) -> io::Result<bool> {
+    let answer = ask(&format!("{} [Y/n]", prompt))?;
+    Ok(matches!(
+        answer.to_ascii_lowercase().as_str(),
+        "" | "y" | "yes"
+    ))
+}
+
+/// Asks the user for confirmation on stderr. An empty answer means no, for destructive changes.
+pub fn confirm_no(prompt: &str) -> io::Result<bool> {
+    let answer = ask(&format!("{} [y/N]", prompt))?;
+    Ok(matches!(answer.to_ascii_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Asks the user for a line of input on stderr, returns it trimmed.
+pub fn ask(prompt: &str) -> io::Result<String> {
+    let mut stderr = io::stderr().lock();
+    write!(stderr, "{} ", prompt)?;
+    stderr.flush()?;
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(answer.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests_throughput {
+    use super::*;
+
+    #[test]
+    fn test_throughput_is_smoothed() {
+        let start = Instant::now();
+        let mut throughput = Throughput::new(start);
+
+        // not enough time has passed for a sample
+        assert!(!throughput.record(1000, start + Duration::from_millis(100)));
+        assert_eq!(throughput.rate(), None);
+
+        // first sample is taken as is
+        assert!(throughput.record(0, start + Duration::from_secs(1)));
+        assert_eq!(throughput.rate(), Some(1000.0));
+
+        // a burst moves the rate only partially
+        assert!(throughput.record(10_000, start + Duration::from_secs(2)));
+        let rate = throughput.rate().expect("rate should be measured");
+        assert!(rate > 1000.0 && rate < 10_000.0, "rate: {}", rate);
+    }
+
+    #[test]
+    fn test_eta() {
+        let start = Instant::now();
+        let mut throughput = Throughput::new(start);
+        assert_eq!(throughput.eta(100), None);
+
+        throughput.record(1000, start + Duration::from_secs(1));
+        assert_eq!(throughput.eta(5000), Some(Duration::from_secs(5)));
+    }
+}
@@ -0,0 +1,151 @@
+//! Presentation of the error which stopped the command.
+//!
+//! The messages of the whole chain of contexts are printed, then a hint for the class of the
+//! error. The class also decides the exit status, so scripts can tell the failures apart.
+use std::{backtrace::BacktraceStatus, io, process::ExitCode};
+
+use crate::{core::network::downloader::DownloadFailure, ui::style};
+
+/// Class of the error, decided by the outermost cause of a known type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    Other,
+    /// None of the mods were downloaded.
+    DownloadFailed,
+    /// Some of the mods were downloaded, the others failed.
+    DownloadPartiallyFailed,
+    /// The downloads were canceled by Ctrl-C.
+    Canceled,
+    Network,
+    Permission,
+    /// Broken files, e.g. the config file or the responses of the APIs.
+    Parse,
+}
+
+impl ErrorClass {
+    pub fn of(e: &anyhow::Error) -> Self {
+        if let Some(failure) = e.downcast_ref::<DownloadFailure>() {
+            return match failure {
+                DownloadFailure::Canceled { .. } => Self::Canceled,
+                _ if failure.is_total() => Self::DownloadFailed,
+                _ => Self::DownloadPartiallyFailed,
+            };
+        }
+        e.chain().find_map(Self::of_cause).unwrap_or(Self::Other)
+    }
+
+    fn of_cause(cause: &(dyn std::error::Error + 'static)) -> Option<Self> {
+        if cause.is::<reqwest::Error>() {
+            return Some(Self::Network);
+        }
+        if cause.is::<serde_yaml_ng::Error>() {
+            return Some(Self::Parse);
+        }
+        // NOTE serde_json also reports the I/O errors of the reader
+        if let Some(e) = cause.downcast_ref::<serde_json::Error>() {
+            return (!e.is_io()).then_some(Self::Parse);
+        }
+        let e = cause.downcast_ref::<io::Error>()?;
+        match e.kind() {
+            io::ErrorKind::PermissionDenied | io::ErrorKind::ReadOnlyFilesystem => {
+                Some(Self::Permission)
+            }
+            io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::NotConnected
+            | io::ErrorKind::HostUnreachable
+            | io::ErrorKind::NetworkUnreachable
+            | io::ErrorKind::TimedOut => Some(Self::Network),
+            io::ErrorKind::InvalidData => Some(Self::Parse),
+            _ => None,
+        }
+    }
+
+    /// Exit status of the process, `2` is used by clap for the invalid arguments.
+    pub fn exit_code(self) -> u8 {
+        match self {
+            Self::Other => 1,
+            Self::DownloadFailed => 3,
+            Self::DownloadPartiallyFailed => 4,
+            Self::Network => 5,
+            Self::Permission => 6,
+            Self::Parse => 7,
+            // NOTE 128 + SIGINT like shells
+            Self::Canceled => 130,
+        }
+    }
+
+    fn hint(self) -> Option<&'static str> {
+        match self {
+            Self::Other => None,
+            Self::DownloadFailed | Self::DownloadPartiallyFailed => Some(
+                "run the command again to retry the failed mods, or try other mirrors with `--mirror-priority`",
+            ),
+            Self::Network => Some(
+                "check the network connection and `--proxy`, or use the cached databases with `--offline`",
+            ),
+            Self::Permission => Some(
+                "check the permissions of the directory above, or choose another one with `--directory`",
+            ),
+            Self::Parse => Some(
+                "the file or the response is broken, fix or remove the file, or retry later for a response",
+            ),
+            Self::Canceled => Some("run the command again to resume the canceled downloads"),
+        }
+    }
+}
+
+/// Prints the error with its causes and the hint to stderr, and returns the exit status.
+///
+/// The backtrace is printed only if it was captured, i.e. `RUST_BACKTRACE=1` is set.
+pub fn report(e: &anyhow::Error) -> ExitCode {
+    let class = ErrorClass::of(e);
+    eprintln!("{} {}", style::error("Error:").for_stderr(), e);
+    for cause in e.chain().skip(1) {
+        eprintln!("  {} {}", style::path("Caused by:").for_stderr(), cause);
+    }
+    if let Some(hint) = class.hint() {
+        eprintln!("{} {}", style::warning("hint:").for_stderr(), hint);
+    }
+    if e.backtrace().status() == BacktraceStatus::Captured {
+        eprintln!("\n{}", e.backtrace());
+    }
+    ExitCode::from(class.exit_code())
+}
+
+#[cfg(test)]
+mod tests_report {
+    use anyhow::{Context, anyhow};
+
+    use super::*;
+
+    #[test]
+    fn test_error_class() {
+        let denied: anyhow::Result<()> =
+            Err(io::Error::from(io::ErrorKind::PermissionDenied).into());
+        let e = denied
+            .context("Failed to write updaterblacklist.txt")
+            .unwrap_err();
+        assert_eq!(ErrorClass::of(&e), ErrorClass::Permission);
+        assert_eq!(ErrorClass::of(&e).exit_code(), 6);
+
+        let yaml = serde_yaml_ng::from_str::<Vec<u32>>("{").unwrap_err();
+        let e = anyhow::Error::new(yaml).context("Failed to load config file");
+        assert_eq!(ErrorClass::of(&e), ErrorClass::Parse);
+
+        let e = anyhow::Error::new(io::Error::from(io::ErrorKind::TimedOut)).context("Failed");
+        assert_eq!(ErrorClass::of(&e), ErrorClass::Network);
+
+        let canceled = DownloadFailure::Canceled {
+            canceled: 1,
+            total: 2,
+        };
+        assert_eq!(ErrorClass::of(&canceled.into()).exit_code(), 130);
+
+        // no hint for the others
+        let e = anyhow::Error::new(io::Error::from(io::ErrorKind::NotFound));
+        assert_eq!(ErrorClass::of(&e), ErrorClass::Other);
+        assert_eq!(ErrorClass::of(&anyhow!("no mods")).exit_code(), 1);
+    }
+}
@@ -0,0 +1,94 @@
+//! Colors of the terminal output, shared by the commands, the progress bars, and the logs.
+//!
+//! Colors are enabled for each of stdout and stderr separately, only if it is a terminal which
+//! supports them and `NO_COLOR` is not set, unless `--color always` or `--color never` is given.
+use std::{env, ffi::OsString, fmt::Display};
+
+use clap::ValueEnum;
+use console::{StyledObject, Term, style};
+
+/// When to use colors.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// Only on a terminal, and if `NO_COLOR` is not set.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// Enables or disables the colors of stdout and stderr, before anything is printed.
+pub fn init(choice: ColorChoice) {
+    let no_color = env::var_os("NO_COLOR");
+    let supported =
+        |term: Term| term.is_term() && env::var_os("TERM").is_none_or(|term| term != "dumb");
+    console::set_colors_enabled(should_color(
+        choice,
+        no_color.as_ref(),
+        supported(Term::stdout()),
+    ));
+    console::set_colors_enabled_stderr(should_color(
+        choice,
+        no_color.as_ref(),
+        supported(Term::stderr()),
+    ));
+}
+
+/// See <https://no-color.org>, an empty `NO_COLOR` does not disable the colors.
+fn should_color(choice: ColorChoice, no_color: Option<&OsString>, supported: bool) -> bool {
+    match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => no_color.is_none_or(|v| v.is_empty()) && supported,
+    }
+}
+
+/// Returns whether stderr is colored, for the logs.
+pub fn stderr_colored() -> bool {
+    console::colors_enabled_stderr()
+}
+
+/// Completed actions, e.g. `Downloaded`.
+pub fn success<D: Display>(text: D) -> StyledObject<D> {
+    style(text).green()
+}
+
+/// Skipped actions and the states to be noticed, e.g. `(disabled)`.
+pub fn warning<D: Display>(text: D) -> StyledObject<D> {
+    style(text).yellow()
+}
+
+/// Failures, e.g. `Error:`.
+pub fn error<D: Display>(text: D) -> StyledObject<D> {
+    style(text).red().bold()
+}
+
+pub fn version<D: Display>(text: D) -> StyledObject<D> {
+    style(text).cyan()
+}
+
+pub fn path<D: Display>(text: D) -> StyledObject<D> {
+    style(text).dim()
+}
+
+/// Names of the mods in the headings.
+pub fn name<D: Display>(text: D) -> StyledObject<D> {
+    style(text).bold()
+}
+
+#[cfg(test)]
+mod tests_style {
+    use super::*;
+
+    #[test]
+    fn test_should_color() {
+        let set = OsString::from("1");
+        let empty = OsString::new();
+        assert!(should_color(ColorChoice::Auto, None, true));
+        assert!(should_color(ColorChoice::Auto, Some(&empty), true));
+        assert!(!should_color(ColorChoice::Auto, Some(&set), true));
+        assert!(!should_color(ColorChoice::Auto, None, false));
+        assert!(should_color(ColorChoice::Always, Some(&set), false));
+        assert!(!should_color(ColorChoice::Never, None, true));
+    }
+}
@@ -1,99 +1,26 @@
-use std::num::ParseIntError;
+pub use hultra_core::utils::sanitize_stem;
 
-#[derive(Debug, thiserror::Error)]
-#[error("input string should contain only ASCII characters")]
-pub struct NonAsciiError;
-
-/// Sanitizes a mod name as file stem for Unix file systems.
-///
-/// # Rules
-/// - Trims leading/trailing whitespace.
-/// - Removes control characters.
-/// - Replaces characters not in the whitelist `[A-Za-z0-9 -_'()]` with `_`.
-/// - Truncates the result to 255 bytes.
+/// Parses the size like `2MiB`, `500K`, or `1.5G` in bytes, `None` if it is not positive.
 ///
-/// # Panics
-/// All characters in given string must be ASCII, otherwise it will panic.
-///
-/// # Notes
-/// Mod database only allows ASCII characters for the mod name. So the name should always valid UTF-8 and ASCII.
-pub fn sanitize_stem(input: &str) -> Result<String, NonAsciiError> {
-    let trimmed = input.trim();
-
-    if !trimmed.is_ascii() {
-        return Err(NonAsciiError);
-    }
-
-    let sanitized_bytes = trimmed
-        .bytes()
-        .filter(|c| !c.is_ascii_control())
-        .map(|c| {
-            if c.is_ascii_alphanumeric() || is_allowed_byte(c) {
-                c
-            } else {
-                b'_'
-            }
-        })
-        .take(u8::MAX as usize)
-        .collect();
-
-    // NOTE This is safe because `input` is always valid UFT-8 and ASCII
-    Ok(unsafe { String::from_utf8_unchecked(sanitized_bytes) })
-}
-
-/// Checks if a byte is allowed in the filename stem.
-#[inline(always)]
-fn is_allowed_byte(b: u8) -> bool {
-    matches!(
-        b,
-        b'A'..=b'Z' |            // Uppercase
-        b'a'..=b'z' |            // Lowercase
-        b'0'..=b'9' |            // Digits
-        b' ' | b'-' | b'_' |     // Separators
-        b'\'' | b'(' | b')' |    // Special allowed chars
-        b'+' | b','              // Special allowed chars 2 (common in mods name)
-    )
-}
-
-#[cfg(test)]
-mod test_sanitize_name {
-    use super::*;
-
-    #[tokio::test]
-    async fn test_no_change() {
-        let input = "valid-filename_123(final)";
-        let result = sanitize_stem(input).expect("should be sanitized");
-        assert_eq!(result, "valid-filename_123(final)");
-    }
-
-    #[tokio::test]
-    async fn test_replace_invalid_chars() {
-        let input = "file!?.txt";
-        let result = sanitize_stem(input).expect("should be sanitized");
-        assert_eq!(result, "file___txt");
-    }
-
-    #[tokio::test]
-    async fn test_remove_control_chars() {
-        // Control chars should be removed, not replaced
-        let input = "file\0name\n";
-        let result = sanitize_stem(input).expect("should be sanitized");
-        assert_eq!(result, "filename");
-    }
-
-    #[tokio::test]
-    async fn test_mixed_whitelist() {
-        // Ensure added whitelist chars ' and () are respected
-        let input = "  Spooooky's Asset Pack (WIP)  ";
-        let result = sanitize_stem(input).expect("should be sanitized");
-        assert_eq!(result, "Spooooky's Asset Pack (WIP)");
-    }
-
-    #[tokio::test]
-    async fn test_panic_on_non_ascii() {
-        let result = sanitize_stem("Error_日本語");
-        assert!(result.is_err_and(|err| matches!(err, NonAsciiError)))
-    }
+/// Units are binary like curl's, i.e. `K`, `KB`, and `KiB` are all 1024 bytes.
+pub fn parse_size(s: &str) -> Option<u64> {
+    let trimmed = s.trim();
+    let split = trimmed
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    let exponent = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 0,
+        "K" | "KB" | "KIB" => 1,
+        "M" | "MB" | "MIB" => 2,
+        "G" | "GB" | "GIB" => 3,
+        _ => return None,
+    };
+    let bytes = number * 1024f64.powi(exponent);
+    (1.0..=u64::MAX as f64)
+        .contains(&bytes)
+        .then_some(bytes as u64)
 }
 
 /// Gets first 19 characters from "2026-03-07T19:48:53.0343351Z", replace 'T' with ' '
@@ -135,38 +62,54 @@ mod test_format_date {
     }
 }
 
-pub fn from_str_digest(input: &str) -> Result<u64, ParseIntError> {
-    let clean_input = input.trim().strip_prefix("0x").unwrap_or(input.trim());
-    u64::from_str_radix(clean_input, 16)
+/// Formats UNIX time in seconds as the date in UTC, like "2026-03-07".
+pub fn format_unix_date(secs: u64) -> String {
+    // NOTE civil-from-days by Howard Hinnant, days are shifted to start from 0000-03-01
+    let days = (secs / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
 }
 
-#[cfg(test)]
-mod tests_from_str_digest {
-    use super::from_str_digest;
-
-    use anyhow::{Context, Result};
+/// Formats UNIX time in seconds as the date and time in UTC, like "2026-03-07 19:48:53".
+pub fn format_unix_time(secs: u64) -> String {
+    let time = secs % 86_400;
+    format!(
+        "{} {:02}:{:02}:{:02}",
+        format_unix_date(secs),
+        time / 3_600,
+        time % 3_600 / 60,
+        time % 60
+    )
+}
 
-    #[test]
-    fn parses_with_0x() -> Result<()> {
-        assert_eq!(
-            from_str_digest("0x7f4d96733b93c52c").context("should be converted")?,
-            9173153437688513836
-        );
-        Ok(())
-    }
+#[cfg(test)]
+mod tests_format_unix_date {
+    use super::{format_unix_date, format_unix_time};
 
     #[test]
-    fn parses_without_0x_and_spaces() -> Result<()> {
-        assert_eq!(
-            from_str_digest(" 7f4d96733b93c52c ").context("should be converted")?,
-            9173153437688513836
-        );
-        Ok(())
+    fn test_dates() {
+        assert_eq!(format_unix_date(0), "1970-01-01");
+        assert_eq!(format_unix_date(951_782_400), "2000-02-29");
+        assert_eq!(format_unix_date(1_758_235_322), "2025-09-18");
+        assert_eq!(format_unix_date(1_767_225_599), "2025-12-31");
     }
 
     #[test]
-    fn returns_error_on_invalid() {
-        let result = from_str_digest("not-hex");
-        assert!(result.is_err());
+    fn test_times() {
+        assert_eq!(format_unix_time(0), "1970-01-01 00:00:00");
+        assert_eq!(format_unix_time(1_758_235_322), "2025-09-18 22:42:02");
+        assert_eq!(format_unix_time(1_767_225_599), "2025-12-31 23:59:59");
     }
 }
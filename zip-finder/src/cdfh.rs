@@ -3,6 +3,8 @@
 //! This entry is an expanded form of the local header.
 //!
 //! <https://en.wikipedia.org/wiki/ZIP_(file_format)#Central_directory_file_header_(CDFH)>
+use std::io::{self, BufReader, Read};
+
 use crate::utils::{read_u16_le, read_u32_le};
 
 /// The fixed-size portion of the Central Directory File Header (CDFH).
@@ -24,9 +26,11 @@ pub enum CdfhError {
 }
 
 /// Represents the Central Directory File Header (CDFH) structure.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct CentralDirectoryFileHeader {
+    flags: u16,
     compression_method: u16,
+    crc32: u32,
     compressed_size: u32,
     uncompressed_size: u32,
     name_len: usize,
@@ -39,7 +43,9 @@ impl CentralDirectoryFileHeader {
     pub fn from_slice(buf: &[u8]) -> Self {
         assert_eq!(&buf[0..4], CDFH_SIGNATURE, "signature should match");
         Self {
+            flags: read_u16_le(&buf[8..]),
             compression_method: read_u16_le(&buf[10..]),
+            crc32: read_u32_le(&buf[16..]),
             compressed_size: read_u32_le(&buf[20..]),
             uncompressed_size: read_u32_le(&buf[24..]),
             name_len: read_u16_le(&buf[28..]) as usize,
@@ -55,10 +61,19 @@ impl CentralDirectoryFileHeader {
         CDFH_FIXED_SIZE + self.name_len + self.extra_len + self.comment_len
     }
 
+    /// General purpose bit flag.
+    pub fn flags(&self) -> u16 {
+        self.flags
+    }
+
     pub fn compression_method(&self) -> u16 {
         self.compression_method
     }
 
+    pub fn crc32(&self) -> u32 {
+        self.crc32
+    }
+
     pub fn compressed_size(&self) -> u32 {
         self.compressed_size
     }
@@ -82,9 +97,9 @@ impl CentralDirectoryFileHeader {
         filename: &[u8],
     ) -> Result<Self, CdfhError> {
         for _ in 0..total_entries {
-            // Ensure we have at least the fixed-size part of the CDFH
+            // NOTE a malformed record is an error like `parse_all` and `RecordScanner`, not a missing file
             if buffer.len() < CDFH_FIXED_SIZE || !buffer.starts_with(&CDFH_SIGNATURE) {
-                break;
+                return Err(CdfhError::InsufficientData);
             }
 
             let cdfh = Self::from_slice(buffer);
@@ -107,4 +122,249 @@ impl CentralDirectoryFileHeader {
 
         Err(CdfhError::TargetNotFound)
     }
+
+    /// Parses all records in CDFH, and returns them with their file names.
+    pub fn parse_all(
+        mut buffer: &[u8],
+        total_entries: u16,
+    ) -> Result<Vec<(Vec<u8>, Self)>, CdfhError> {
+        let mut records = Vec::with_capacity(total_entries as usize);
+
+        for _ in 0..total_entries {
+            if buffer.len() < CDFH_FIXED_SIZE || !buffer.starts_with(&CDFH_SIGNATURE) {
+                return Err(CdfhError::InsufficientData);
+            }
+
+            let cdfh = Self::from_slice(buffer);
+            let total_header_len = cdfh.total_len();
+
+            if buffer.len() < total_header_len {
+                return Err(CdfhError::InsufficientData);
+            }
+
+            let file_name = buffer[CDFH_FIXED_SIZE..(CDFH_FIXED_SIZE + cdfh.name_len())].to_vec();
+
+            // Advance the buffer slice to the start of the next CDFH
+            buffer = &buffer[total_header_len..];
+            records.push((file_name, cdfh));
+        }
+
+        Ok(records)
+    }
+}
+
+/// Streaming scanner over the records in CDFH.
+///
+/// Reads the central directory in chunks through a buffer of fixed capacity instead of
+/// loading it entirely, so peak memory stays flat even for archives with thousands of entries.
+pub struct RecordScanner<R: Read> {
+    reader: BufReader<R>,
+    remaining: u16,
+    /// Reusable buffer for the file name of the current record.
+    name: Vec<u8>,
+}
+
+impl<R: Read> RecordScanner<R> {
+    /// Creates a scanner reading `total_entries` records through a buffer of `capacity` bytes.
+    pub fn new(reader: R, total_entries: u16, capacity: usize) -> Self {
+        Self {
+            reader: BufReader::with_capacity(capacity.max(CDFH_FIXED_SIZE), reader),
+            remaining: total_entries,
+            name: Vec::new(),
+        }
+    }
+
+    /// Reads the next record, returns `None` when all records are consumed.
+    pub fn next_record(
+        &mut self,
+    ) -> Result<Option<(&[u8], CentralDirectoryFileHeader)>, CdfhError> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+
+        let mut fixed = [0u8; CDFH_FIXED_SIZE];
+        self.reader
+            .read_exact(&mut fixed)
+            .map_err(insufficient_on_eof)?;
+        if !fixed.starts_with(&CDFH_SIGNATURE) {
+            return Err(CdfhError::InsufficientData);
+        }
+
+        let cdfh = CentralDirectoryFileHeader::from_slice(&fixed);
+
+        self.name.resize(cdfh.name_len(), 0);
+        self.reader
+            .read_exact(&mut self.name)
+            .map_err(insufficient_on_eof)?;
+
+        // skip extra field and comment
+        let skip = (cdfh.extra_len + cdfh.comment_len) as u64;
+        let skipped = io::copy(&mut self.reader.by_ref().take(skip), &mut io::sink())?;
+        if skipped != skip {
+            return Err(CdfhError::InsufficientData);
+        }
+
+        Ok(Some((&self.name, cdfh)))
+    }
+
+    /// Scans records until `filename` is found. If it does not exist, returns the record of `alt_name`.
+    pub fn find_by_name(
+        self,
+        filename: &[u8],
+        alt_name: Option<&[u8]>,
+    ) -> Result<(Vec<u8>, CentralDirectoryFileHeader), CdfhError> {
+        self.find_best(filename, alt_name, false)
+    }
+
+    /// Same as [`Self::find_by_name`], but also matches nested entries whose basename equals the target.
+    ///
+    /// Priority: exact `filename` > exact `alt_name` > basename `filename` > basename `alt_name`.
+    /// The first record wins among the same priority.
+    pub fn find_by_suffix(
+        self,
+        filename: &[u8],
+        alt_name: Option<&[u8]>,
+    ) -> Result<(Vec<u8>, CentralDirectoryFileHeader), CdfhError> {
+        self.find_best(filename, alt_name, true)
+    }
+
+    fn find_best(
+        mut self,
+        filename: &[u8],
+        alt_name: Option<&[u8]>,
+        match_basename: bool,
+    ) -> Result<(Vec<u8>, CentralDirectoryFileHeader), CdfhError> {
+        // (priority, name, record), lower is better
+        let mut best: Option<(u8, Vec<u8>, CentralDirectoryFileHeader)> = None;
+
+        while let Some((name, cdfh)) = self.next_record()? {
+            let Some(rank) = rank(name, filename, alt_name, match_basename) else {
+                continue;
+            };
+            if rank == 0 {
+                return Ok((name.to_vec(), cdfh));
+            }
+            if best.as_ref().is_none_or(|(r, _, _)| rank < *r) {
+                best = Some((rank, name.to_vec(), cdfh));
+            }
+        }
+
+        best.map(|(_, name, cdfh)| (name, cdfh))
+            .ok_or(CdfhError::TargetNotFound)
+    }
+}
+
+/// Returns the priority of the entry name, or `None` if it does not match.
+fn rank(name: &[u8], filename: &[u8], alt_name: Option<&[u8]>, match_basename: bool) -> Option<u8> {
+    if name == filename {
+        return Some(0);
+    }
+    if alt_name.is_some_and(|alt| alt == name) {
+        return Some(1);
+    }
+    if !match_basename {
+        return None;
+    }
+    let base = basename(name);
+    if base == filename {
+        Some(2)
+    } else if alt_name.is_some_and(|alt| alt == base) {
+        Some(3)
+    } else {
+        None
+    }
+}
+
+/// Returns the last component of the entry name, e.g. `everest.yaml` of `Folder/everest.yaml`.
+///
+/// NOTE Some archivers on Windows write `\` as the separator against the spec.
+pub fn basename(name: &[u8]) -> &[u8] {
+    name.rsplit(|&b| b == b'/' || b == b'\\')
+        .next()
+        .unwrap_or(name)
+}
+
+/// Treats unexpected EOF as truncated central directory.
+fn insufficient_on_eof(err: io::Error) -> CdfhError {
+    if err.kind() == io::ErrorKind::UnexpectedEof {
+        CdfhError::InsufficientData
+    } else {
+        CdfhError::Io(err)
+    }
+}
+
+#[cfg(test)]
+mod tests_cdfh {
+    use std::io::{Cursor, Write};
+
+    use zip::{ZipWriter, write::SimpleFileOptions};
+
+    use super::*;
+    use crate::eocd::Eocd;
+
+    /// Returns the central directory and the number of its records.
+    fn central_directory(names: &[String]) -> (Vec<u8>, u16) {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for name in names {
+            writer
+                .start_file(name.as_str(), SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(name.as_bytes()).unwrap();
+        }
+        let bytes = writer.finish().unwrap().into_inner();
+        let eocd = Eocd::find(&mut Cursor::new(&bytes)).unwrap();
+        let start = eocd.central_directory_offset() as usize;
+        let end = start + eocd.central_directory_size() as usize;
+        (bytes[start..end].to_vec(), eocd.total_central_dir_records())
+    }
+
+    fn summary(name: &[u8], cdfh: &CentralDirectoryFileHeader) -> (Vec<u8>, u64, u32, u32) {
+        (
+            name.to_vec(),
+            cdfh.lfh_offset(),
+            cdfh.crc32(),
+            cdfh.compressed_size(),
+        )
+    }
+
+    #[test]
+    fn test_scan_across_chunks() {
+        // names of various lengths, so the records straddle the chunks at every offset
+        let names: Vec<String> = (0..100)
+            .map(|i| format!("Maps/{}/{}.bin", "x".repeat(i % 37), i))
+            .collect();
+        let (buffer, total) = central_directory(&names);
+
+        let expected: Vec<_> = CentralDirectoryFileHeader::parse_all(&buffer, total)
+            .unwrap()
+            .iter()
+            .map(|(name, cdfh)| summary(name, cdfh))
+            .collect();
+        assert_eq!(expected.len(), names.len());
+
+        for capacity in [1, CDFH_FIXED_SIZE + 1, 100, buffer.len()] {
+            let mut scanner = RecordScanner::new(buffer.as_slice(), total, capacity);
+            let mut scanned = Vec::new();
+            while let Some((name, cdfh)) = scanner.next_record().unwrap() {
+                scanned.push(summary(name, &cdfh));
+            }
+            assert_eq!(scanned, expected, "capacity {}", capacity);
+        }
+
+        let last = names.last().unwrap().as_bytes();
+        let (name, cdfh) = RecordScanner::new(buffer.as_slice(), total, 1)
+            .find_by_name(last, None)
+            .unwrap();
+        assert_eq!(summary(&name, &cdfh), expected[expected.len() - 1]);
+    }
+
+    #[test]
+    fn test_scan_truncated_central_directory() {
+        let names = ["everest.yaml".to_string(), "Maps/Foo.bin".to_string()];
+        let (buffer, total) = central_directory(&names);
+        let truncated = &buffer[..buffer.len() - 1];
+        let result = RecordScanner::new(truncated, total, 1).find_by_name(b"missing", None);
+        assert!(matches!(result, Err(CdfhError::InsufficientData)));
+    }
 }
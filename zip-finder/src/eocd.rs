@@ -1,10 +1,7 @@
 //! End Of Central Directory (EOCD)
 //!
 //! <https://en.wikipedia.org/wiki/ZIP_(file_format)#End_of_central_directory_record_(EOCD)>
-use std::{
-    fs::File,
-    io::{Read, Seek, SeekFrom},
-};
+use std::io::{Read, Seek, SeekFrom};
 
 use crate::utils::{read_u16_le, read_u32_le};
 
@@ -53,7 +50,7 @@ impl Eocd {
         self.central_directory_offset
     }
 
-    pub fn find(file: &mut File) -> Result<Self, EocdError> {
+    pub fn find<R: Read + Seek>(file: &mut R) -> Result<Self, EocdError> {
         // 1. trying to parse EOCD with minimal size
         file.seek(SeekFrom::End(-(EOCD_FIXED_SIZE as i64)))?;
 
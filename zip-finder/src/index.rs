@@ -0,0 +1,145 @@
+//! Index of the Central Directory
+//!
+//! Parses all records of the central directory at once, so multiple files can be extracted
+//! from the same archive without finding the EOCD and reading the central directory again.
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    ops::Range,
+    path::Path,
+};
+
+use crate::{
+    EntryInfo, Error,
+    cdfh::{CdfhError, CentralDirectoryFileHeader},
+    eocd::Eocd,
+    lfh::LocalFileHeader,
+};
+
+/// Index of the entries in the ZIP archive.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut index = ZipArchiveIndex::open("AchievementHelper.zip")?;
+/// let manifest = index.extract(b"everest.yaml")?;
+/// if index.contains(b"Dialog/English.txt") {
+///     let dialog = index.extract(b"Dialog/English.txt")?;
+/// }
+/// ```
+#[derive(Debug)]
+pub struct ZipArchiveIndex<R = File> {
+    reader: R,
+    /// Records in the order of the central directory.
+    records: Vec<(Vec<u8>, CentralDirectoryFileHeader)>,
+    /// Position of the record for the file name.
+    positions: HashMap<Vec<u8>, usize>,
+    /// Offset of the central directory, where the last entry ends.
+    central_directory_offset: u64,
+}
+
+impl ZipArchiveIndex<File> {
+    /// Opens the ZIP archive and parses its central directory.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let file = File::open(path)?;
+        Self::new(file)
+    }
+}
+
+impl<R: Read + Seek> ZipArchiveIndex<R> {
+    /// Parses the central directory of the archive from given reader.
+    pub fn new(mut reader: R) -> Result<Self, Error> {
+        let eocd = Eocd::find(&mut reader)?;
+
+        // move file pointer to the start of CDFH
+        reader.seek(SeekFrom::Start(eocd.central_directory_offset() as u64))?;
+
+        // read CDFH to the buffer
+        let mut buffer = vec![0u8; eocd.central_directory_size() as usize];
+        reader.read_exact(&mut buffer)?;
+
+        let records =
+            CentralDirectoryFileHeader::parse_all(&buffer, eocd.total_central_dir_records())?;
+        let positions = records
+            .iter()
+            .enumerate()
+            .map(|(i, (name, _))| (name.clone(), i))
+            .collect();
+
+        Ok(Self {
+            reader,
+            records,
+            positions,
+            central_directory_offset: eocd.central_directory_offset() as u64,
+        })
+    }
+
+    /// Returns `true` if the archive contains the file.
+    pub fn contains(&self, filename: &[u8]) -> bool {
+        self.positions.contains_key(filename)
+    }
+
+    /// Returns an iterator over the file names in the order of the central directory.
+    pub fn names(&self) -> impl Iterator<Item = &[u8]> {
+        self.records.iter().map(|(name, _)| name.as_slice())
+    }
+
+    /// Returns metadata of the entries in the order of the central directory.
+    pub fn entries(&self) -> impl Iterator<Item = EntryInfo> {
+        self.records
+            .iter()
+            .map(|(name, cdfh)| EntryInfo::new(name, cdfh))
+    }
+
+    /// Returns the offset of the central directory, which follows the last entry.
+    pub fn central_directory_offset(&self) -> u64 {
+        self.central_directory_offset
+    }
+
+    /// Returns the range of the raw data of the entry in the archive, without decoding it.
+    ///
+    /// Reads the local file header, since its extra field may differ from the central one.
+    pub fn data_range(&mut self, filename: &[u8]) -> Result<Range<u64>, Error> {
+        let position = *self
+            .positions
+            .get(filename)
+            .ok_or(CdfhError::TargetNotFound)?;
+        let range = LocalFileHeader::data_range(&mut self.reader, &self.records[position].1)?;
+        Ok(range)
+    }
+
+    /// Returns the number of entries in the archive.
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Returns `true` if the archive has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Returns metadata of the entry without extracting it.
+    pub fn info(&self, filename: &[u8]) -> Option<EntryInfo> {
+        let position = *self.positions.get(filename)?;
+        Some(EntryInfo::new(filename, &self.records[position].1))
+    }
+
+    /// Extracts the file as a byte vector, decoding its body if needed.
+    pub fn extract(&mut self, filename: &[u8]) -> Result<Vec<u8>, Error> {
+        let (bytes, _) = self.extract_with_info(filename)?;
+        Ok(bytes)
+    }
+
+    /// Extracts the file as a byte vector with metadata of the entry.
+    pub fn extract_with_info(&mut self, filename: &[u8]) -> Result<(Vec<u8>, EntryInfo), Error> {
+        let position = *self
+            .positions
+            .get(filename)
+            .ok_or(CdfhError::TargetNotFound)?;
+        let cdfh = self.records[position].1.clone();
+        let info = EntryInfo::new(filename, &cdfh);
+        let bytes = LocalFileHeader::extract_local_file(&mut self.reader, cdfh)?;
+        Ok((bytes, info))
+    }
+}
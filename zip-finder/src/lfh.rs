@@ -5,11 +5,11 @@
 //!
 //! <https://en.wikipedia.org/wiki/ZIP_(file_format)#Local_file_header>
 use std::{
-    fs::File,
-    io::{Read, Seek, SeekFrom},
+    io::{self, Read, Seek, SeekFrom},
+    ops::Range,
 };
 
-use flate2::read::DeflateDecoder;
+use flate2::{Crc, read::DeflateDecoder};
 
 use crate::{cdfh::CentralDirectoryFileHeader, utils::read_u16_le};
 
@@ -21,6 +21,34 @@ pub enum LfhError {
     Io(#[from] std::io::Error),
     #[error("Unsupported compression method: {0}")]
     UnsupportedCompression(u16),
+    #[error(transparent)]
+    Corrupted(#[from] Corruption),
+}
+
+/// Reason why the entry is considered as corrupted.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum Corruption {
+    #[error("CRC-32 mismatch: expected {expected:08x}, computed {computed:08x}")]
+    CrcMismatch { expected: u32, computed: u32 },
+    #[error("data ends before the expected size of {expected} bytes")]
+    Truncated { expected: u32 },
+    #[error("decompressed data is larger than the expected size of {expected} bytes")]
+    SizeMismatch { expected: u32 },
+    #[error("invalid compressed data: {0}")]
+    InvalidData(String),
+}
+
+impl Corruption {
+    /// Converts I/O errors caused by broken data into corruption, otherwise returns the original error.
+    fn from_io(err: io::Error, expected: u32) -> LfhError {
+        match err.kind() {
+            io::ErrorKind::UnexpectedEof => Self::Truncated { expected }.into(),
+            io::ErrorKind::InvalidInput | io::ErrorKind::InvalidData => {
+                Self::InvalidData(err.to_string()).into()
+            }
+            _ => LfhError::Io(err),
+        }
+    }
 }
 
 /// Represents the Local File Header (LFH) structure.
@@ -45,9 +73,25 @@ impl LocalFileHeader {
         self.name_len + self.extra_len
     }
 
+    /// Returns the range of the raw (compressed or stored) data of the entry in the file.
+    pub fn data_range<R: Read + Seek>(
+        file: &mut R,
+        cdfh: &CentralDirectoryFileHeader,
+    ) -> Result<Range<u64>, LfhError> {
+        file.seek(SeekFrom::Start(cdfh.lfh_offset()))?;
+        let mut buffer = [0u8; LFH_FIXED_SIZE];
+        file.read_exact(&mut buffer)?;
+        let lfh = LocalFileHeader::new(&buffer);
+
+        let start = cdfh.lfh_offset() + LFH_FIXED_SIZE as u64 + lfh.header_length();
+        Ok(start..start + cdfh.compressed_size() as u64)
+    }
+
     /// Seeks to Local File Header to get the slice of raw local file while decoding its body if needed.
-    pub fn extract_local_file(
-        file: &mut File,
+    ///
+    /// The decoded data is validated against the size and CRC-32 recorded in the central directory.
+    pub fn extract_local_file<R: Read + Seek>(
+        file: &mut R,
         cdfh: CentralDirectoryFileHeader,
     ) -> Result<Vec<u8>, LfhError> {
         file.seek(SeekFrom::Start(cdfh.lfh_offset()))?;
@@ -65,19 +109,141 @@ impl LocalFileHeader {
         // Limit the reader to only the compressed/stored size of this file
         let limited_reader = file.take(cdfh.compressed_size() as u64);
 
-        match cdfh.compression_method() {
+        let bytes = match cdfh.compression_method() {
             0 => {
                 let mut c_buf = vec![0u8; cdfh.compressed_size() as usize];
-                file.read_exact(&mut c_buf)?;
-                Ok(c_buf)
+                file.read_exact(&mut c_buf)
+                    .map_err(|e| Corruption::from_io(e, cdfh.compressed_size()))?;
+                c_buf
             }
             8 => {
                 let mut decoder = DeflateDecoder::new(limited_reader);
                 let mut u_buf = vec![0u8; cdfh.uncompressed_size() as usize];
-                decoder.read_exact(&mut u_buf)?;
-                Ok(u_buf)
+                decoder
+                    .read_exact(&mut u_buf)
+                    .map_err(|e| Corruption::from_io(e, cdfh.uncompressed_size()))?;
+
+                // the stream must end exactly at the expected size
+                let mut extra = [0u8; 1];
+                if decoder.read(&mut extra).unwrap_or(0) != 0 {
+                    return Err(Corruption::SizeMismatch {
+                        expected: cdfh.uncompressed_size(),
+                    }
+                    .into());
+                }
+                u_buf
             }
-            value => Err(LfhError::UnsupportedCompression(value)),
+            value => return Err(LfhError::UnsupportedCompression(value)),
+        };
+
+        let mut crc = Crc::new();
+        crc.update(&bytes);
+        if crc.sum() != cdfh.crc32() {
+            return Err(Corruption::CrcMismatch {
+                expected: cdfh.crc32(),
+                computed: crc.sum(),
+            }
+            .into());
+        }
+
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests_lfh {
+    use std::io::{Cursor, Write};
+
+    use zip::{CompressionMethod, ZipWriter, write::SimpleFileOptions};
+
+    use super::*;
+    use crate::eocd::Eocd;
+
+    const UNCOMPRESSED_SIZE_OFFSET: usize = 24;
+
+    /// Content which is not compressed into a few bytes.
+    fn content() -> Vec<u8> {
+        (0..20_000u32).map(|i| (i * i % 251) as u8).collect()
+    }
+
+    fn archive(method: CompressionMethod, content: &[u8]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = SimpleFileOptions::default().compression_method(method);
+        writer.start_file("everest.yaml", options).unwrap();
+        writer.write_all(content).unwrap();
+        writer.finish().unwrap().into_inner()
+    }
+
+    /// Returns the offset of the central directory and the record of the only entry.
+    fn record(bytes: &[u8]) -> (usize, CentralDirectoryFileHeader) {
+        let eocd = Eocd::find(&mut Cursor::new(bytes)).unwrap();
+        let offset = eocd.central_directory_offset() as usize;
+        let mut records = CentralDirectoryFileHeader::parse_all(
+            &bytes[offset..],
+            eocd.total_central_dir_records(),
+        )
+        .unwrap();
+        (offset, records.remove(0).1)
+    }
+
+    fn extract(bytes: &[u8], cdfh: CentralDirectoryFileHeader) -> Result<Vec<u8>, LfhError> {
+        LocalFileHeader::extract_local_file(&mut Cursor::new(bytes), cdfh)
+    }
+
+    #[test]
+    fn test_extract_valid_entries() {
+        let content = content();
+        for method in [CompressionMethod::Stored, CompressionMethod::Deflated] {
+            let bytes = archive(method, &content);
+            let (_, cdfh) = record(&bytes);
+            assert_eq!(extract(&bytes, cdfh).unwrap(), content);
         }
     }
+
+    #[test]
+    fn test_flipped_byte_is_crc_mismatch() {
+        let mut bytes = archive(CompressionMethod::Stored, &content());
+        let (_, cdfh) = record(&bytes);
+        let data = LocalFileHeader::data_range(&mut Cursor::new(&bytes), &cdfh).unwrap();
+        bytes[data.start as usize + 100] ^= 0xff;
+
+        let result = extract(&bytes, cdfh);
+        assert!(matches!(
+            result,
+            Err(LfhError::Corrupted(Corruption::CrcMismatch { .. }))
+        ));
+    }
+
+    #[test]
+    fn test_truncated_deflate_stream() {
+        let content = content();
+        let bytes = archive(CompressionMethod::Deflated, &content);
+        let (_, cdfh) = record(&bytes);
+        let data = LocalFileHeader::data_range(&mut Cursor::new(&bytes), &cdfh).unwrap();
+        // the archive ends in the middle of the compressed data
+        let truncated = &bytes[..(data.start + data.end) as usize / 2];
+
+        let result = extract(truncated, cdfh);
+        assert!(matches!(
+            result,
+            Err(LfhError::Corrupted(Corruption::Truncated { expected })) if expected as usize == content.len()
+        ));
+    }
+
+    #[test]
+    fn test_stream_longer_than_declared_size() {
+        let content = content();
+        let mut bytes = archive(CompressionMethod::Deflated, &content);
+        let (offset, _) = record(&bytes);
+        let declared = content.len() as u32 - 1;
+        let field = offset + UNCOMPRESSED_SIZE_OFFSET;
+        bytes[field..field + 4].copy_from_slice(&declared.to_le_bytes());
+        let (_, cdfh) = record(&bytes);
+
+        let result = extract(&bytes, cdfh);
+        assert!(matches!(
+            result,
+            Err(LfhError::Corrupted(Corruption::SizeMismatch { expected })) if expected == declared
+        ));
+    }
 }
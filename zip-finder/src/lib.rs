@@ -1,20 +1,27 @@
 use std::{
     fs::File,
     io::{Read, Seek, SeekFrom},
+    ops::Range,
     path::Path,
 };
 
 use crate::{
-    cdfh::{CdfhError, CentralDirectoryFileHeader},
+    cdfh::{CdfhError, CentralDirectoryFileHeader, RecordScanner},
     eocd::{Eocd, EocdError},
     lfh::{LfhError, LocalFileHeader},
 };
 
+pub use cdfh::basename;
+pub use lfh::Corruption;
+
 mod cdfh;
 mod eocd;
+mod index;
 mod lfh;
 mod utils;
 
+pub use index::ZipArchiveIndex;
+
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error(transparent)]
@@ -24,7 +31,35 @@ pub enum Error {
     #[error(transparent)]
     Cdfh(#[from] CdfhError),
     #[error(transparent)]
-    Lfh(#[from] LfhError),
+    Lfh(LfhError),
+    #[error("corrupted entry: {0}")]
+    CorruptedEntry(Corruption),
+}
+
+impl Error {
+    /// Checks if the archive is readable but has no such file.
+    pub fn is_target_not_found(&self) -> bool {
+        matches!(self, Self::Cdfh(CdfhError::TargetNotFound))
+    }
+}
+
+impl From<LfhError> for Error {
+    fn from(value: LfhError) -> Self {
+        match value {
+            LfhError::Corrupted(reason) => Self::CorruptedEntry(reason),
+            other => Self::Lfh(other),
+        }
+    }
+}
+
+/// Finds the range of the central directory in the archive from its EOCD.
+///
+/// Only the end of the archive is read, so it also works on a reader holding the tail of an
+/// archive, e.g. fetched with an HTTP `Range` request, to learn how much more to fetch.
+pub fn central_directory_range<R: Read + Seek>(reader: &mut R) -> Result<Range<u64>, Error> {
+    let eocd = Eocd::find(reader)?;
+    let start = eocd.central_directory_offset() as u64;
+    Ok(start..start + eocd.central_directory_size() as u64)
 }
 
 /// Extracts the specified file as a byte vector from the given ZIP archive.
@@ -64,10 +99,88 @@ pub fn extract_file_from_zip<P: AsRef<Path>>(
     filename: &[u8],
     alt_name: Option<&[u8]>,
 ) -> Result<Vec<u8>, Error> {
+    let (bytes, _) = extract_file_from_zip_with_info(path, filename, alt_name)?;
+    Ok(bytes)
+}
+
+/// Extracts the specified file from the given ZIP archive, with metadata of the entry.
+///
+/// Same as [`extract_file_from_zip`], but also returns [`EntryInfo`] so callers can
+/// log or validate what they read (e.g. which name was found, or its CRC-32).
+pub fn extract_file_from_zip_with_info<P: AsRef<Path>>(
+    path: P,
+    filename: &[u8],
+    alt_name: Option<&[u8]>,
+) -> Result<(Vec<u8>, EntryInfo), Error> {
+    extract_file_from_zip_with(path, filename, alt_name, &ExtractOptions::default())
+}
+
+/// Options for extracting a file from the ZIP archive.
+#[derive(Debug, Clone, Default)]
+pub struct ExtractOptions {
+    max_buffer: Option<usize>,
+    match_basename: bool,
+}
+
+impl ExtractOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Scans the central directory in chunks of at most `cap` bytes instead of reading it at once.
+    ///
+    /// Reading at once is faster for small archives, but the buffer grows with the number of entries.
+    /// Use this to keep peak memory flat when scanning many archives in parallel.
+    pub fn max_buffer(mut self, cap: usize) -> Self {
+        self.max_buffer = Some(cap);
+        self
+    }
+
+    /// Also matches nested entries such as `SomeFolder/everest.yaml` when the target is not at the root.
+    ///
+    /// Entries at the root are always preferred. Among nested ones, the first record in the
+    /// central directory wins. The name actually found is available from [`EntryInfo::name`].
+    pub fn match_basename(mut self, enabled: bool) -> Self {
+        self.match_basename = enabled;
+        self
+    }
+}
+
+/// Extracts the specified file from the given ZIP archive with options.
+///
+/// See [`extract_file_from_zip`] and [`ExtractOptions`] for details.
+pub fn extract_file_from_zip_with<P: AsRef<Path>>(
+    path: P,
+    filename: &[u8],
+    alt_name: Option<&[u8]>,
+    options: &ExtractOptions,
+) -> Result<(Vec<u8>, EntryInfo), Error> {
     let mut file = File::open(path)?;
 
     let eocd = Eocd::find(&mut file)?;
 
+    if let Some(cap) = options.max_buffer {
+        // move file pointer to the start of CDFH
+        file.seek(SeekFrom::Start(eocd.central_directory_offset() as u64))?;
+
+        let cd_size = eocd.central_directory_size() as u64;
+        let capacity = cap.min(cd_size as usize);
+        let scanner = RecordScanner::new(
+            (&mut file).take(cd_size),
+            eocd.total_central_dir_records(),
+            capacity,
+        );
+        let (name, cdfh) = if options.match_basename {
+            scanner.find_by_suffix(filename, alt_name)?
+        } else {
+            scanner.find_by_name(filename, alt_name)?
+        };
+
+        let info = EntryInfo::new(&name, &cdfh);
+        let bytes = LocalFileHeader::extract_local_file(&mut file, cdfh)?;
+        return Ok((bytes, info));
+    }
+
     // move file pointer to the start of CDFH
     file.seek(SeekFrom::Start(eocd.central_directory_offset() as u64))?;
 
@@ -77,16 +190,163 @@ pub fn extract_file_from_zip<P: AsRef<Path>>(
 
     // trying to find manifest
     let total_records = eocd.total_central_dir_records();
-    let cdfh = CentralDirectoryFileHeader::find_record_by_name(&buffer, total_records, filename)
-        .or_else(|err| {
-            alt_name
+    let (name, cdfh) =
+        match CentralDirectoryFileHeader::find_record_by_name(&buffer, total_records, filename) {
+            Ok(cdfh) => (filename.to_vec(), cdfh),
+            Err(err) => match alt_name
                 .map(|alt| {
                     CentralDirectoryFileHeader::find_record_by_name(&buffer, total_records, alt)
+                        .map(|cdfh| (alt.to_vec(), cdfh))
                 })
                 .unwrap_or(Err(err))
-        })?;
+            {
+                Ok(found) => found,
+                Err(CdfhError::TargetNotFound) if options.match_basename => {
+                    // NOTE Rare case, so scanning the buffer again is acceptable
+                    RecordScanner::new(buffer.as_slice(), total_records, buffer.len())
+                        .find_by_suffix(filename, alt_name)?
+                }
+                Err(err) => return Err(err.into()),
+            },
+        };
 
     // extract manifest bytes
+    let info = EntryInfo::new(&name, &cdfh);
     let yaml_slice = LocalFileHeader::extract_local_file(&mut file, cdfh)?;
-    Ok(yaml_slice)
+    Ok((yaml_slice, info))
+}
+
+/// Metadata of the extracted entry, taken from its central directory record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryInfo {
+    name: Vec<u8>,
+    flags: u16,
+    compression_method: u16,
+    compressed_size: u32,
+    uncompressed_size: u32,
+    crc32: u32,
+    offset: u64,
+}
+
+impl EntryInfo {
+    pub(crate) fn new(name: &[u8], cdfh: &CentralDirectoryFileHeader) -> Self {
+        Self {
+            name: name.to_vec(),
+            flags: cdfh.flags(),
+            compression_method: cdfh.compression_method(),
+            compressed_size: cdfh.compressed_size(),
+            uncompressed_size: cdfh.uncompressed_size(),
+            crc32: cdfh.crc32(),
+            offset: cdfh.lfh_offset(),
+        }
+    }
+
+    /// File name of the entry in bytes.
+    pub fn name(&self) -> &[u8] {
+        &self.name
+    }
+
+    /// Compression method of the entry. `0` is stored, `8` is deflate.
+    pub fn compression_method(&self) -> u16 {
+        self.compression_method
+    }
+
+    pub fn compressed_size(&self) -> u32 {
+        self.compressed_size
+    }
+
+    pub fn uncompressed_size(&self) -> u32 {
+        self.uncompressed_size
+    }
+
+    /// CRC-32 of the uncompressed data recorded in the archive.
+    pub fn crc32(&self) -> u32 {
+        self.crc32
+    }
+
+    /// Offset of the local file header of the entry in the archive.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Checks if a data descriptor follows the data of the entry, i.e. bit 3 of the flags.
+    pub fn has_data_descriptor(&self) -> bool {
+        self.flags & 0x0008 != 0
+    }
+}
+
+#[cfg(test)]
+mod tests_lib {
+    use std::io::Write;
+
+    use zip::{ZipWriter, write::SimpleFileOptions};
+
+    use super::*;
+
+    fn write_archive(path: &Path, files: &[(&str, &[u8])]) {
+        let mut writer = ZipWriter::new(File::create(path).unwrap());
+        for (name, content) in files {
+            writer
+                .start_file(*name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(content).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn test_match_basename() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Foo.zip");
+        write_archive(
+            &path,
+            &[
+                ("Maps/Foo.bin", b"map"),
+                ("Folder/everest.yaml", b"- Name: Foo\n"),
+            ],
+        );
+
+        let unbounded = ExtractOptions::new();
+        let bounded = ExtractOptions::new().max_buffer(64);
+        for options in [unbounded, bounded] {
+            let result =
+                extract_file_from_zip_with(&path, b"everest.yaml", Some(b"everest.yml"), &options);
+            assert!(result.is_err_and(|e| e.is_target_not_found()));
+
+            let options = options.match_basename(true);
+            let (bytes, info) =
+                extract_file_from_zip_with(&path, b"everest.yaml", Some(b"everest.yml"), &options)
+                    .unwrap();
+            assert_eq!(bytes, b"- Name: Foo\n");
+            assert_eq!(info.name(), b"Folder/everest.yaml");
+        }
+        assert_eq!(basename(b"Folder\\everest.yaml"), b"everest.yaml");
+        assert_eq!(basename(b"everest.yaml"), b"everest.yaml");
+    }
+
+    #[test]
+    fn test_malformed_central_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Foo.zip");
+        write_archive(&path, &[("Folder/everest.yaml", b"- Name: Foo\n")]);
+        // breaks the signature of the only record
+        let mut bytes = std::fs::read(&path).unwrap();
+        let range = central_directory_range(&mut std::io::Cursor::new(&bytes)).unwrap();
+        bytes[range.start as usize] = 0;
+        std::fs::write(&path, bytes).unwrap();
+
+        let unbounded = ExtractOptions::new();
+        let bounded = ExtractOptions::new().max_buffer(64);
+        for options in [unbounded, bounded] {
+            let result = extract_file_from_zip_with(&path, b"everest.yaml", None, &options);
+            assert!(matches!(
+                result,
+                Err(Error::Cdfh(CdfhError::InsufficientData))
+            ));
+        }
+        assert!(matches!(
+            ZipArchiveIndex::open(&path),
+            Err(Error::Cdfh(CdfhError::InsufficientData))
+        ));
+    }
 }
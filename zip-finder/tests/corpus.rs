@@ -0,0 +1,230 @@
+//! Corpus runner, compares the results of this crate with the `zip` crate as an oracle.
+//!
+//! Runs over every archive in the given directory and reports divergences.
+//!
+//! ```bash
+//! cargo test -p zip-finder --features corpus --test corpus -- --corpus ~/Mods
+//! # or
+//! ZIP_FINDER_CORPUS=~/Mods cargo test -p zip-finder --features corpus --test corpus
+//! ```
+use std::{
+    env,
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use zip_finder::{ExtractOptions, ZipArchiveIndex};
+
+const CORPUS_ENV: &str = "ZIP_FINDER_CORPUS";
+
+const MANIFEST: &[u8] = b"everest.yaml";
+const ALT_MANIFEST: &[u8] = b"everest.yml";
+
+/// Small enough to split records across reads.
+const SMALL_BUFFER: usize = 64;
+
+/// Entry metadata read by the oracle.
+struct OracleEntry {
+    name: Vec<u8>,
+    compression_method: u16,
+    compressed_size: u64,
+    uncompressed_size: u64,
+    crc32: u32,
+}
+
+fn main() -> ExitCode {
+    let Some(corpus) = corpus_dir() else {
+        eprintln!(
+            "no corpus given, pass `--corpus <DIR>` or set {}",
+            CORPUS_ENV
+        );
+        return ExitCode::SUCCESS;
+    };
+
+    let mut archives = Vec::new();
+    if let Err(e) = collect_archives(&corpus, &mut archives) {
+        eprintln!("failed to read corpus '{}': {}", corpus.display(), e);
+        return ExitCode::FAILURE;
+    }
+    archives.sort();
+
+    let mut skipped = 0;
+    let mut diverged = 0;
+    for path in &archives {
+        match check_archive(path) {
+            Ok(divergences) if divergences.is_empty() => {}
+            Ok(divergences) => {
+                diverged += 1;
+                println!("DIVERGED {}", path.display());
+                for divergence in divergences {
+                    println!("  {}", divergence);
+                }
+            }
+            Err(e) => {
+                skipped += 1;
+                println!("SKIPPED {}: oracle failed: {}", path.display(), e);
+            }
+        }
+    }
+
+    println!(
+        "corpus: {} archives, {} diverged, {} skipped",
+        archives.len(),
+        diverged,
+        skipped
+    );
+
+    if diverged == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// Reads the corpus directory from `--corpus <DIR>`, or the environment variable.
+fn corpus_dir() -> Option<PathBuf> {
+    let mut args = env::args().skip_while(|arg| arg != "--corpus").skip(1);
+    args.next()
+        .or_else(|| env::var(CORPUS_ENV).ok())
+        .map(PathBuf::from)
+}
+
+fn collect_archives(dir: &Path, archives: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_archives(&path, archives)?;
+        } else if path.extension().is_some_and(|ext| ext == "zip") {
+            archives.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Compares the archive with the oracle. Returns an error if the oracle cannot read it.
+fn check_archive(path: &Path) -> Result<Vec<String>, zip::result::ZipError> {
+    let mut oracle = zip::ZipArchive::new(File::open(path)?)?;
+    let mut entries = Vec::with_capacity(oracle.len());
+    for i in 0..oracle.len() {
+        let file = oracle.by_index_raw(i)?;
+        entries.push(OracleEntry {
+            name: file.name_raw().to_vec(),
+            compression_method: method_id(file.compression()),
+            compressed_size: file.compressed_size(),
+            uncompressed_size: file.size(),
+            crc32: file.crc32(),
+        });
+    }
+
+    let mut divergences = Vec::new();
+
+    // the expected manifest is `everest.yaml`, or `everest.yml` if it does not exist
+    let manifest = [MANIFEST, ALT_MANIFEST]
+        .iter()
+        .find_map(|target| entries.iter().position(|e| e.name == *target));
+    let expected = match manifest {
+        Some(i) => {
+            let mut bytes = Vec::new();
+            oracle.by_index(i)?.read_to_end(&mut bytes)?;
+            Some(bytes)
+        }
+        None => None,
+    };
+
+    let options = [
+        ("default", ExtractOptions::new()),
+        ("bounded", ExtractOptions::new().max_buffer(SMALL_BUFFER)),
+    ];
+    for (label, options) in &options {
+        let actual =
+            zip_finder::extract_file_from_zip_with(path, MANIFEST, Some(ALT_MANIFEST), options)
+                .map(|(bytes, _)| bytes);
+        compare_manifest(label, &expected, actual, &mut divergences);
+    }
+
+    let mut index = match ZipArchiveIndex::open(path) {
+        Ok(index) => index,
+        Err(e) => {
+            divergences.push(format!("index: failed to open: {}", e));
+            return Ok(divergences);
+        }
+    };
+
+    if index.len() != entries.len() {
+        divergences.push(format!(
+            "index: {} entries, oracle has {}",
+            index.len(),
+            entries.len()
+        ));
+    }
+
+    for entry in &entries {
+        let name = String::from_utf8_lossy(&entry.name);
+        let Some(info) = index.info(&entry.name) else {
+            divergences.push(format!("index: missing entry '{}'", name));
+            continue;
+        };
+        let actual = (
+            info.compression_method(),
+            info.compressed_size() as u64,
+            info.uncompressed_size() as u64,
+            info.crc32(),
+        );
+        let expected = (
+            // NOTE the oracle does not expose IDs of other methods
+            if entry.compression_method == u16::MAX {
+                info.compression_method()
+            } else {
+                entry.compression_method
+            },
+            entry.compressed_size,
+            entry.uncompressed_size,
+            entry.crc32,
+        );
+        if actual != expected {
+            divergences.push(format!(
+                "index: metadata of '{}' is {:?}, oracle has {:?}",
+                name, actual, expected
+            ));
+        }
+    }
+
+    if let Some(i) = manifest {
+        let actual = index.extract(&entries[i].name);
+        compare_manifest("index", &expected, actual, &mut divergences);
+    }
+
+    Ok(divergences)
+}
+
+/// Maps the compression method to its ID. Methods this crate does not support are `u16::MAX`.
+fn method_id(method: zip::CompressionMethod) -> u16 {
+    match method {
+        zip::CompressionMethod::Stored => 0,
+        zip::CompressionMethod::Deflated => 8,
+        _ => u16::MAX,
+    }
+}
+
+fn compare_manifest(
+    label: &str,
+    expected: &Option<Vec<u8>>,
+    actual: Result<Vec<u8>, zip_finder::Error>,
+    divergences: &mut Vec<String>,
+) {
+    match (expected, actual) {
+        (Some(expected), Ok(actual)) if *expected != actual => divergences.push(format!(
+            "{}: manifest differs ({} bytes, oracle has {} bytes)",
+            label,
+            actual.len(),
+            expected.len()
+        )),
+        (Some(_), Err(e)) => {
+            divergences.push(format!("{}: failed to extract manifest: {}", label, e))
+        }
+        (None, Ok(_)) => divergences.push(format!("{}: found manifest the oracle does not", label)),
+        _ => {}
+    }
+}